@@ -20,8 +20,8 @@ use thiserror::Error;
 use ::{
     cairo_vm::felt::felt_str,
     starknet_in_rust::{
-        definitions::constants::EXECUTE_ENTRY_POINT_SELECTOR,
-        transaction::{InvokeFunction, Transaction},
+        services::api::contract_classes::compiled_class::sierra_class_from_flattened,
+        transaction::{ApiTransaction, Transaction, TransactionClass},
     },
 };
 
@@ -223,92 +223,56 @@ impl RpcState {
         });
         let response: serde_json::Value = self.rpc_call(&params).unwrap();
 
-        match response["result"]["type"].as_str().unwrap() {
-            "INVOKE" => {
-                let sender_address = Address(felt_str!(
-                    response["result"]["sender_address"]
-                        .as_str()
-                        .unwrap()
-                        .strip_prefix("0x")
-                        .unwrap(),
-                    16
-                ));
+        let chain_id = StarknetChainId::from(self.chain).to_felt();
+        let mut tx = ApiTransaction::from_rpc_json(&response["result"], chain_id).unwrap();
 
-                let entry_point_selector = EXECUTE_ENTRY_POINT_SELECTOR.clone();
-                let max_fee = u128::from_str_radix(
-                    response["result"]["max_fee"]
-                        .as_str()
-                        .unwrap()
-                        .strip_prefix("0x")
-                        .unwrap(),
-                    16,
-                )
-                .unwrap();
-                let version = felt_str!(
-                    response["result"]["version"]
+        // Declares and deploys need their class, which might not exist yet at the block of the
+        // state, so it's requested at the latest block.
+        match response["result"]["type"].as_str() {
+            Some("DECLARE") | Some("DEPLOY") => {
+                let class_hash = felt_str!(
+                    response["result"]["class_hash"]
                         .as_str()
                         .unwrap()
                         .strip_prefix("0x")
                         .unwrap(),
                     16
                 );
-                let calldata = response["result"]["calldata"]
-                    .as_array()
-                    .unwrap()
-                    .iter()
-                    .map(|felt_as_value| {
-                        felt_str!(
-                            felt_as_value.as_str().unwrap().strip_prefix("0x").unwrap(),
-                            16
-                        )
-                    })
-                    .collect::<Vec<Felt252>>();
-                let signature = response["result"]["signature"]
-                    .as_array()
-                    .unwrap()
-                    .iter()
-                    .map(|felt_as_value| {
-                        felt_str!(
-                            felt_as_value.as_str().unwrap().strip_prefix("0x").unwrap(),
-                            16
-                        )
-                    })
-                    .collect::<Vec<Felt252>>();
-                let nonce = Some(felt_str!(
-                    response["result"]["nonce"]
-                        .as_str()
-                        .unwrap()
-                        .strip_prefix("0x")
-                        .unwrap(),
-                    16
-                ));
-
-                let hash_felt = felt_str!(format!("{}", hash), 16);
-                let tx = InvokeFunction::new_with_tx_hash(
-                    sender_address,
-                    entry_point_selector,
-                    max_fee,
-                    version,
-                    calldata,
-                    signature,
-                    nonce,
-                    hash_felt,
-                )
-                .unwrap();
-
-                // Note: we skip nonce checking because it can be increased twice in a single block
-                // and it leads to a buggy behaviour when that's the case because the get_nonce_at method
-                // returns a possibly higher nonce than the one we have in the transaction.
-                // Example: Block contains 2 transactions that execute the same entrypoint with nonce=20.
-                // - First tx has entrypoint with nonce=20
-                // - Second tx has nonce=21
-                // If we want to execute the first transaction the nonce check fails
-                // since get_nonce_at for that block returns 21 and the first tx has 20.
-                tx.create_for_simulation(false, false, false, false, true)
+                let params = ureq::json!({
+                    "jsonrpc": "2.0",
+                    "method": "starknet_getClass",
+                    "params": ["latest", format!("0x{}", class_hash.to_str_radix(16))],
+                    "id": 1
+                });
+                let class: RpcResponseProgram = self.rpc_call(&params).unwrap();
+                let class = match class.result {
+                    ContractClass::Sierra(flattened) => TransactionClass::Sierra(
+                        Box::new(sierra_class_from_flattened(&flattened)),
+                        None,
+                    ),
+                    legacy => match CompiledClass::from(legacy) {
+                        CompiledClass::Deprecated(class) => {
+                            TransactionClass::Deprecated((*class).clone())
+                        }
+                        CompiledClass::Casm(_) => unreachable!(),
+                    },
+                };
+                tx = tx.with_class(class);
             }
-
-            _ => unimplemented!(),
+            _ => {}
         }
+
+        // Note: we skip nonce checking because it can be increased twice in a single block
+        // and it leads to a buggy behaviour when that's the case because the get_nonce_at method
+        // returns a possibly higher nonce than the one we have in the transaction.
+        // Example: Block contains 2 transactions that execute the same entrypoint with nonce=20.
+        // - First tx has entrypoint with nonce=20
+        // - Second tx has nonce=21
+        // If we want to execute the first transaction the nonce check fails
+        // since get_nonce_at for that block returns 21 and the first tx has 20.
+        Transaction::try_from(tx)
+            .unwrap()
+            .create_for_simulation(false, false, false, false, true)
     }
 
     fn get_chain_name(&self) -> String {
@@ -322,6 +286,7 @@ impl RpcState {
     pub fn get_block_info(
         &self,
         starknet_os_config: starknet_in_rust::definitions::block_context::StarknetOsConfig,
+        gas_price: u128,
     ) -> starknet_in_rust::state::BlockInfo {
         let get_block_info_params = ureq::json!({
             "jsonrpc": "2.0",
@@ -341,8 +306,9 @@ impl RpcState {
                 .to_string()
                 .parse::<u64>()
                 .unwrap(),
-            gas_price: *starknet_os_config.gas_price(),
+            gas_price,
             sequencer_address: starknet_os_config.fee_token_address().clone(),
+            ..Default::default()
         }
     }
 }
@@ -544,6 +510,10 @@ mod tests {
             "049d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7",
             16
         ));
+        let strk_fee_token_address = Address(felt_str!(
+            "04718f5a0fc34cc1af16a1cdee98ffb20c31f5cd61d6ab07201858f4287c938d",
+            16
+        ));
 
         let get_block_info_params = ureq::json!({
             "jsonrpc": "2.0",
@@ -556,7 +526,7 @@ mod tests {
             starknet_in_rust::definitions::block_context::StarknetOsConfig::new(
                 network.to_felt(),
                 fee_token_address.clone(),
-                gas_price_u128,
+                strk_fee_token_address,
             );
         let block_info: serde_json::Value = rpc_state.rpc_call(&get_block_info_params).unwrap();
 
@@ -571,9 +541,13 @@ mod tests {
                 .unwrap(),
             gas_price: gas_price_u128,
             sequencer_address: fee_token_address,
+            ..Default::default()
         };
 
-        assert_eq!(rpc_state.get_block_info(starknet_os_config,), block_info);
+        assert_eq!(
+            rpc_state.get_block_info(starknet_os_config, gas_price_u128),
+            block_info
+        );
     }
 
     /// Tested with the following query to the Feeder Gateway API:
@@ -760,12 +734,16 @@ mod transaction_tests {
             "049d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7",
             16
         ));
+        let strk_fee_token_address = Address(felt_str!(
+            "04718f5a0fc34cc1af16a1cdee98ffb20c31f5cd61d6ab07201858f4287c938d",
+            16
+        ));
 
         let network: StarknetChainId = rpc_state.chain.into();
         let starknet_os_config =
-            StarknetOsConfig::new(network.to_felt(), fee_token_address, gas_price);
+            StarknetOsConfig::new(network.to_felt(), fee_token_address, strk_fee_token_address);
 
-        let block_info = rpc_state.get_block_info(starknet_os_config.clone());
+        let block_info = rpc_state.get_block_info(starknet_os_config.clone(), gas_price);
 
         let block_context = BlockContext::new(
             starknet_os_config,
@@ -11,8 +11,9 @@ use starknet_api::{
     core::EntryPointSelector,
     deprecated_contract_class::{EntryPoint, EntryPointOffset, EntryPointType},
     hash::{StarkFelt, StarkHash},
-    transaction::{InvokeTransaction, Transaction},
+    transaction::Transaction,
 };
+use starknet_in_rust::transaction::ApiTransaction;
 
 #[derive(Debug, Deserialize)]
 pub struct MiddleSierraContractClass {
@@ -67,23 +68,14 @@ pub fn decode_reader(bytes: Vec<u8>) -> io::Result<String> {
 pub fn deserialize_transaction_json(
     transaction: serde_json::Value,
 ) -> serde_json::Result<Transaction> {
-    let tx_type: String = serde_json::from_value(transaction["type"].clone())?;
-    let tx_version: String = serde_json::from_value(transaction["version"].clone())?;
-
-    match tx_type.as_str() {
-        "INVOKE" => match tx_version.as_str() {
-            "0x0" => Ok(Transaction::Invoke(InvokeTransaction::V0(
-                serde_json::from_value(transaction)?,
-            ))),
-            "0x1" => Ok(Transaction::Invoke(InvokeTransaction::V1(
-                serde_json::from_value(transaction)?,
-            ))),
-            x => Err(serde::de::Error::custom(format!(
-                "unimplemented invoke version: {x}"
-            ))),
-        },
-        x => Err(serde::de::Error::custom(format!(
-            "unimplemented transaction type deserialization: {x}"
-        ))),
+    // The chain is only needed to compute the hash of the transaction, which isn't done here.
+    let tx =
+        ApiTransaction::from_rpc_json(&transaction, 0.into()).map_err(serde::de::Error::custom)?;
+    match tx.v3_fields {
+        // The transactions of the Starknet API don't have the version 3 fields yet.
+        Some(_) => Err(serde::de::Error::custom(
+            "version 3 transactions aren't supported by the Starknet API",
+        )),
+        None => Ok(tx.transaction),
     }
 }
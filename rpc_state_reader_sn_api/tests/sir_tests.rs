@@ -24,11 +24,12 @@ use starknet_in_rust::{
     services::api::contract_classes::compiled_class::CompiledClass,
     state::{
         cached_state::{CachedState, ContractClassCache},
+        recording_state_reader::RecordingStateReader,
         state_api::StateReader,
         state_cache::StorageEntry,
         BlockInfo,
     },
-    transaction::{InvokeFunction, Transaction},
+    transaction::{ApiTransaction, InvokeFunction, Transaction},
     utils::{Address, ClassHash},
 };
 
@@ -41,7 +42,10 @@ pub struct RpcStateReader(RpcState);
 impl StateReader for RpcStateReader {
     fn get_contract_class(&self, class_hash: &ClassHash) -> Result<CompiledClass, StateError> {
         let hash = SNClassHash(StarkHash::new(*class_hash).unwrap());
-        Ok(CompiledClass::from(self.0.get_contract_class(&hash)))
+        // Keep the program of Cairo 0 classes, so they can be recorded in fixtures.
+        Ok(CompiledClass::from_starknet_rs_class_with_program_json(
+            self.0.get_contract_class(&hash),
+        ))
     }
 
     fn get_class_hash_at(&self, contract_address: &Address) -> Result<ClassHash, StateError> {
@@ -101,8 +105,13 @@ pub fn execute_tx(
         "049d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7",
         16
     ));
+    let strk_fee_token_address = Address(felt_str!(
+        "04718f5a0fc34cc1af16a1cdee98ffb20c31f5cd61d6ab07201858f4287c938d",
+        16
+    ));
 
     let tx_hash = tx_hash.strip_prefix("0x").unwrap();
+    let fixture_name = format!("0x{tx_hash}.json");
 
     // Instantiate the RPC StateReader and the CachedState
     let rpc_reader = RpcStateReader(RpcState::new_infura(network, block_number.into()));
@@ -114,8 +123,11 @@ pub fn execute_tx(
         RpcChain::TestNet => StarknetChainId::TestNet,
         RpcChain::TestNet2 => StarknetChainId::TestNet2,
     };
-    let starknet_os_config =
-        StarknetOsConfig::new(chain_id.to_felt(), fee_token_address, gas_price);
+    let starknet_os_config = StarknetOsConfig::new(
+        chain_id.to_felt(),
+        fee_token_address,
+        strk_fee_token_address,
+    );
     let block_info = {
         let RpcBlockInfo {
             block_number,
@@ -133,23 +145,26 @@ pub fn execute_tx(
             block_timestamp,
             gas_price,
             sequencer_address,
+            ..Default::default()
         }
     };
 
     // Get transaction before giving ownership of the reader
     let tx_hash = TransactionHash(stark_felt!(tx_hash));
-    let tx = match rpc_reader.0.get_transaction(&tx_hash) {
-        SNTransaction::Invoke(tx) => Transaction::InvokeFunction(
-            InvokeFunction::from_invoke_transaction(tx, chain_id).unwrap(),
-        ),
-        _ => unimplemented!(),
-    };
+    let tx = Transaction::try_from(ApiTransaction::new(
+        rpc_reader.0.get_transaction(&tx_hash),
+        chain_id.to_felt(),
+    ))
+    .unwrap();
 
     let trace = rpc_reader.0.get_transaction_trace(&tx_hash);
     let receipt = rpc_reader.0.get_transaction_receipt(&tx_hash);
 
+    // Record every value read from the network, so the transaction can be turned into an
+    // offline fixture (see `RecordingStateReader`) by setting `SIR_RECORD_FIXTURES` to a directory.
+    let recorder = Arc::new(RecordingStateReader::new(Arc::new(rpc_reader)));
     let class_cache = ContractClassCache::default();
-    let mut state = CachedState::new(Arc::new(rpc_reader), class_cache);
+    let mut state = CachedState::new(recorder.clone(), class_cache);
 
     let block_context = BlockContext::new(
         starknet_os_config,
@@ -163,11 +178,14 @@ pub fn execute_tx(
         true,
     );
 
-    (
-        tx.execute(&mut state, &block_context, u128::MAX).unwrap(),
-        trace,
-        receipt,
-    )
+    let execution_info = tx.execute(&mut state, &block_context, u128::MAX).unwrap();
+
+    if let Ok(fixtures_dir) = std::env::var("SIR_RECORD_FIXTURES") {
+        let fixture_path = std::path::Path::new(&fixtures_dir).join(fixture_name);
+        recorder.save_log(fixture_path).unwrap();
+    }
+
+    (execution_info, trace, receipt)
 }
 
 #[test]
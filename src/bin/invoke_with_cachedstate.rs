@@ -122,7 +122,7 @@ pub fn new_starknet_block_context_for_testing() -> BlockContext {
         StarknetOsConfig::new(
             StarknetChainId::TestNet.to_felt(),
             Address(Felt252::zero()),
-            0,
+            Address(Felt252::zero()),
         ),
         0,
         0,
@@ -1,14 +1,101 @@
 use crate::{
-    hash_utils::compute_hash_on_elements, syscalls::syscall_handler_errors::SyscallHandlerError,
+    core::{
+        errors::hash_errors::HashError,
+        state_commitment::patricia_trie::{PatriciaTrie, TrieHashFunction},
+    },
+    definitions::constants::BLOCK_HASH_CONTRACT_ADDRESS,
+    hash_utils::compute_hash_on_elements,
+    state::{state_api::State, state_cache::StorageEntry},
+    utils::Address,
 };
-use crate::core::errors::hash_errors::HashError;
 use cairo_vm::felt::Felt252;
 use starknet_crypto::{pedersen_hash, FieldElement};
 use std::iter::zip;
 
+/// The height of the Patricia trees of the transaction and event commitments.
+pub const COMMITMENT_TREE_HEIGHT: usize = 64;
+
+/// The fields of a block header that go into the block hash.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlockHeader {
+    pub block_number: u64,
+    /// The global state root after the block.
+    pub global_state_root: Felt252,
+    pub sequencer_address: Address,
+    pub block_timestamp: u64,
+    pub transaction_count: u64,
+    pub transaction_commitment: Felt252,
+    pub event_count: u64,
+    pub event_commitment: Felt252,
+    pub parent_block_hash: Felt252,
+}
+
+/// Calculates the hash of a block from its header, i.e.
+/// H(block_number, global_state_root, sequencer_address, block_timestamp, transaction_count,
+/// transaction_commitment, event_count, event_commitment, 0, 0, parent_block_hash), where the
+/// zeros stand for the protocol version and the extra data, which are not part of the hash yet.
+pub fn calculate_block_hash(header: &BlockHeader) -> Result<Felt252, HashError> {
+    compute_hash_on_elements(&[
+        header.block_number.into(),
+        header.global_state_root.clone(),
+        header.sequencer_address.0.clone(),
+        header.block_timestamp.into(),
+        header.transaction_count.into(),
+        header.transaction_commitment.clone(),
+        header.event_count.into(),
+        header.event_commitment.clone(),
+        0.into(),
+        0.into(),
+        header.parent_block_hash.clone(),
+    ])
+}
+
+/// Returns the storage entry of the block hash contract where the hash of the given block is
+/// kept, which is where the `get_block_hash` syscall reads it from.
+pub fn block_hash_storage_entry(block_number: u64) -> StorageEntry {
+    (
+        BLOCK_HASH_CONTRACT_ADDRESS.clone(),
+        Felt252::new(block_number).to_be_bytes(),
+    )
+}
+
+/// Stores the hash of a block in the block hash contract, so it can be queried with the
+/// `get_block_hash` syscall from the blocks that come 10 or more blocks after it.
+pub fn store_block_hash<S: State>(state: &mut S, block_number: u64, block_hash: Felt252) {
+    state.set_storage_at(&block_hash_storage_entry(block_number), block_hash);
+}
+
+/// Calculates the transaction commitment of a block: the root of a Patricia tree of height 64
+/// whose leaves are the hashes of the transactions with their signatures, by their index.
+pub fn calculate_tx_commitment(
+    tx_hashes: Vec<Felt252>,
+    tx_signatures: Vec<Vec<Felt252>>,
+) -> Result<Felt252, HashError> {
+    calculate_patricia_root(calculate_tx_hashes_with_signatures(
+        tx_hashes,
+        tx_signatures,
+    )?)
+}
+
+/// Calculates the event commitment of a block: the root of a Patricia tree of height 64 whose
+/// leaves are the hashes of the events emitted in the block (see [calculate_event_hash]), by
+/// their index.
+pub fn calculate_event_commitment(event_hashes: Vec<Felt252>) -> Result<Felt252, HashError> {
+    calculate_patricia_root(event_hashes)
+}
+
+/// Calculates the root of a Pedersen Patricia tree of height 64 with the given leaves, by their
+/// index.
+pub fn calculate_patricia_root(leaves: Vec<Felt252>) -> Result<Felt252, HashError> {
+    let mut trie = PatriciaTrie::new(TrieHashFunction::Pedersen, COMMITMENT_TREE_HEIGHT)?;
+    for (index, leaf) in leaves.iter().enumerate() {
+        trie.update(&index.into(), leaf)?;
+    }
+    Ok(trie.root())
+}
+
 /// Hashes the signatures with the given transaction hashes, to get hashes that take into account the
 /// entire transactions, as the original hashes do not include the signatures.
-
 pub fn calculate_tx_hashes_with_signatures(
     tx_hashes: Vec<Felt252>,
     tx_signatures: Vec<Vec<Felt252>>,
@@ -20,18 +107,17 @@ pub fn calculate_tx_hashes_with_signatures(
 
 /// Hashes the signature with the given transaction hash, to get a hash that takes into account the
 /// entire transaction, as the original hash does not include the signature.
-
 pub fn calculate_single_tx_hash_with_signature(
     tx_hash: Felt252,
     tx_signature: Vec<Felt252>,
-) -> Result<Felt252, > {
+) -> Result<Felt252, HashError> {
     let signature_hash = compute_hash_on_elements(&tx_signature)?;
     let signature_str = signature_hash.to_str_radix(10);
     let tx_hash_str = tx_hash.to_str_radix(10);
     let hash = FieldElement::from_dec_str(&tx_hash_str)
-        .map_err(|_| HashError::FailToComputeHash)?;
+        .map_err(|e| HashError::FailedToComputeHash(e.to_string()))?;
     let signature = FieldElement::from_dec_str(&signature_str)
-        .map_err(|_| HashError::FailToComputeHash)?;
+        .map_err(|e| HashError::FailedToComputeHash(e.to_string()))?;
     let new_hash = pedersen_hash(&hash, &signature);
     Ok(Felt252::from_bytes_be(&new_hash.to_bytes_be()))
 }
@@ -52,7 +138,17 @@ pub fn calculate_event_hash(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        state::{
+            cached_state::CachedState, in_memory_state_reader::InMemoryStateReader,
+            state_api::StateReader,
+        },
+        utils::felt_from_hex,
+    };
     use coverage_helper::test;
+    use num_traits::Zero;
+    use serde::Deserialize;
+    use std::{collections::HashMap, fs::File, io::BufReader, sync::Arc};
 
     #[test]
     fn calculate_event_hash_test() {
@@ -82,4 +178,128 @@ mod tests {
 
         assert!(calculate_tx_hashes_with_signatures(tx_hash, signatures).is_ok());
     }
+
+    #[test]
+    fn calculate_commitments_of_empty_block_test() {
+        assert_eq!(
+            calculate_tx_commitment(vec![], vec![]).unwrap(),
+            Felt252::zero()
+        );
+        assert_eq!(calculate_event_commitment(vec![]).unwrap(), Felt252::zero());
+    }
+
+    /// Mainnet block 867, with its transaction hashes and signatures and its only event.
+    #[derive(Deserialize)]
+    struct Block {
+        block_number: u64,
+        block_hash: String,
+        parent_block_hash: String,
+        global_state_root: String,
+        sequencer_address: String,
+        block_timestamp: u64,
+        transaction_commitment: String,
+        event_commitment: String,
+        transactions: Vec<Transaction>,
+        events: Vec<Event>,
+    }
+
+    #[derive(Deserialize)]
+    struct Transaction {
+        transaction_hash: String,
+        signature: Vec<String>,
+    }
+
+    #[derive(Deserialize)]
+    struct Event {
+        from_address: String,
+        keys: Vec<String>,
+        data: Vec<String>,
+    }
+
+    fn felt(value: &str) -> Felt252 {
+        felt_from_hex(value).unwrap()
+    }
+
+    fn felts(values: &[String]) -> Vec<Felt252> {
+        values.iter().map(|value| felt(value)).collect()
+    }
+
+    fn mainnet_block_867() -> Block {
+        let file = File::open("tests/fixtures/mainnet_block_867.json").unwrap();
+        serde_json::from_reader(BufReader::new(file)).unwrap()
+    }
+
+    #[test]
+    fn calculate_tx_commitment_of_mainnet_block() {
+        let block = mainnet_block_867();
+
+        let (tx_hashes, tx_signatures) = block
+            .transactions
+            .iter()
+            .map(|tx| (felt(&tx.transaction_hash), felts(&tx.signature)))
+            .unzip();
+        assert_eq!(
+            calculate_tx_commitment(tx_hashes, tx_signatures).unwrap(),
+            felt(&block.transaction_commitment)
+        );
+    }
+
+    #[test]
+    fn calculate_event_commitment_of_mainnet_block() {
+        let block = mainnet_block_867();
+
+        let event_hashes = block
+            .events
+            .iter()
+            .map(|event| {
+                calculate_event_hash(
+                    felt(&event.from_address),
+                    felts(&event.keys),
+                    felts(&event.data),
+                )
+                .unwrap()
+            })
+            .collect();
+        assert_eq!(
+            calculate_event_commitment(event_hashes).unwrap(),
+            felt(&block.event_commitment)
+        );
+    }
+
+    #[test]
+    fn calculate_block_hash_of_mainnet_block() {
+        let block = mainnet_block_867();
+
+        let header = BlockHeader {
+            block_number: block.block_number,
+            global_state_root: felt(&block.global_state_root),
+            sequencer_address: Address(felt(&block.sequencer_address)),
+            block_timestamp: block.block_timestamp,
+            transaction_count: block.transactions.len() as u64,
+            transaction_commitment: felt(&block.transaction_commitment),
+            event_count: block.events.len() as u64,
+            event_commitment: felt(&block.event_commitment),
+            parent_block_hash: felt(&block.parent_block_hash),
+        };
+        assert_eq!(
+            calculate_block_hash(&header).unwrap(),
+            felt(&block.block_hash)
+        );
+    }
+
+    #[test]
+    fn stored_block_hashes_are_read_from_the_block_hash_contract() {
+        let mut state = CachedState::new(Arc::new(InMemoryStateReader::default()), HashMap::new());
+        store_block_hash(&mut state, 867, 42.into());
+
+        assert_eq!(
+            state
+                .get_storage_at(&(
+                    BLOCK_HASH_CONTRACT_ADDRESS.clone(),
+                    Felt252::new(867).to_be_bytes()
+                ))
+                .unwrap(),
+            Felt252::new(42)
+        );
+    }
 }
@@ -4,4 +4,6 @@ use thiserror::Error;
 pub enum HashError {
     #[error("Failed to compute hash {0}")]
     FailedToComputeHash(String),
+    #[error("Trie height {0} is higher than the maximum height of 251")]
+    InvalidTrieHeight(usize),
 }
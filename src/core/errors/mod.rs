@@ -1,3 +1,4 @@
 pub mod contract_address_errors;
 pub mod hash_errors;
+pub mod proof_errors;
 pub mod state_errors;
@@ -0,0 +1,25 @@
+use crate::core::errors::hash_errors::HashError;
+use cairo_vm::felt::Felt252;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ProofError {
+    #[error("Key {0} doesn't fit in a trie of height {1}")]
+    KeyOutOfRange(Felt252, usize),
+    #[error("Proof node hashes to {actual}, expected {expected}")]
+    NodeHashMismatch { expected: Felt252, actual: Felt252 },
+    #[error("Invalid edge node with path {path} and length {length}")]
+    InvalidEdge { path: Felt252, length: usize },
+    #[error("Proof doesn't reach the leaf of the key nor leaves its path")]
+    IncompleteProof,
+    #[error("Proof has nodes past the end of the path of the key")]
+    UnexpectedNodes,
+    #[error("Global state root is {actual}, expected {expected}")]
+    GlobalRootMismatch { expected: Felt252, actual: Felt252 },
+    #[error("Contract state hash is {actual}, the contracts trie has {expected}")]
+    ContractStateMismatch { expected: Felt252, actual: Felt252 },
+    #[error("Expected {expected} storage proofs, got {actual}")]
+    StorageProofCount { expected: usize, actual: usize },
+    #[error(transparent)]
+    Hash(#[from] HashError),
+}
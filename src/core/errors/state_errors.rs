@@ -56,4 +56,10 @@ pub enum StateError {
     CustomError(String),
     #[error(transparent)]
     ByteArray(#[from] FromByteArrayError),
+    #[error("The checkpoint was already reverted or committed")]
+    InvalidCheckpoint,
+    #[error("Corrupted persistent state: {0}")]
+    CorruptedPersistentState(String),
+    #[error("The program of the deprecated class {0:?} must be stored before committing it")]
+    MissingDeprecatedClassProgram(ClassHash),
 }
@@ -1,3 +1,5 @@
+pub mod block_hash;
 pub mod contract_address;
 pub mod errors;
+pub mod state_commitment;
 pub mod transaction_hash;
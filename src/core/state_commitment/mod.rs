@@ -0,0 +1,358 @@
+pub mod patricia_trie;
+pub mod storage_proof;
+
+use self::{
+    patricia_trie::{PatriciaTrie, TrieHashFunction, DEFAULT_TRIE_HEIGHT},
+    storage_proof::{ContractData, StorageProof},
+};
+use crate::{
+    core::errors::hash_errors::HashError,
+    definitions::block_context::BlockContext,
+    state::StateDiff,
+    utils::{felt_to_field_element, field_element_to_felt, Address, ClassHash},
+};
+use cairo_vm::felt::Felt252;
+use lazy_static::lazy_static;
+use num_traits::Zero;
+use starknet_crypto::{pedersen_hash, poseidon_hash, poseidon_hash_many, FieldElement};
+use std::collections::{HashMap, HashSet};
+
+/// The version of the contract state hash.
+const CONTRACT_STATE_HASH_VERSION: u64 = 0;
+
+lazy_static! {
+    /// "CONTRACT_CLASS_LEAF_V0", the prefix of the leaves of the classes trie.
+    static ref CONTRACT_CLASS_LEAF_V0: FieldElement =
+        FieldElement::from_byte_slice_be(b"CONTRACT_CLASS_LEAF_V0").unwrap_or(FieldElement::ZERO);
+    /// "STARKNET_STATE_V0", the prefix of the global state root.
+    static ref STARKNET_STATE_V0: FieldElement =
+        FieldElement::from_byte_slice_be(b"STARKNET_STATE_V0").unwrap_or(FieldElement::ZERO);
+}
+
+/// The commitment to the whole Starknet state, built from the state diffs applied to it.
+///
+/// It keeps a storage trie per contract (Pedersen), the contracts trie (Pedersen), whose leaves
+/// are the contract state hashes `H(H(H(class_hash, storage_root), nonce), 0)`, and the classes
+/// trie (Poseidon), whose leaves are `Poseidon("CONTRACT_CLASS_LEAF_V0", compiled_class_hash)`.
+/// The tries are updated incrementally, so applying a diff only rehashes what it touched.
+#[derive(Clone, Debug)]
+pub struct StateCommitment {
+    /// An empty storage trie, cloned for each new contract.
+    empty_storage: PatriciaTrie,
+    contracts: HashMap<Address, ContractCommitment>,
+    contracts_trie: PatriciaTrie,
+    classes_trie: PatriciaTrie,
+    contracts_root: Felt252,
+    classes_root: Felt252,
+}
+
+/// The state of a contract that goes into its leaf of the contracts trie.
+#[derive(Clone, Debug)]
+struct ContractCommitment {
+    class_hash: Felt252,
+    nonce: Felt252,
+    storage: PatriciaTrie,
+    storage_root: Felt252,
+}
+
+impl StateCommitment {
+    /// Creates the commitment of an empty state.
+    ///
+    /// # Parameters
+    /// - `contract_storage_tree_height` - The height of the storage trie of each contract.
+    /// - `global_state_tree_height` - The height of the contracts and classes tries.
+    ///
+    /// Fails if a height is higher than [DEFAULT_TRIE_HEIGHT].
+    pub fn new(
+        contract_storage_tree_height: usize,
+        global_state_tree_height: usize,
+    ) -> Result<Self, HashError> {
+        Ok(Self {
+            empty_storage: PatriciaTrie::new(
+                TrieHashFunction::Pedersen,
+                contract_storage_tree_height,
+            )?,
+            contracts: HashMap::new(),
+            contracts_trie: PatriciaTrie::new(
+                TrieHashFunction::Pedersen,
+                global_state_tree_height,
+            )?,
+            classes_trie: PatriciaTrie::new(TrieHashFunction::Poseidon, global_state_tree_height)?,
+            contracts_root: Felt252::zero(),
+            classes_root: Felt252::zero(),
+        })
+    }
+
+    /// Creates the commitment of an empty state, with the tree heights of `block_context`.
+    pub fn from_block_context(block_context: &BlockContext) -> Result<Self, HashError> {
+        Self::new(
+            block_context.contract_storage_commitment_tree_height() as usize,
+            block_context.global_state_commitment_tree_height() as usize,
+        )
+    }
+
+    /// Applies a [StateDiff] to the committed state, updating every root it affects.
+    pub fn apply_state_diff(&mut self, state_diff: &StateDiff) -> Result<(), HashError> {
+        let mut touched_contracts: HashSet<&Address> = HashSet::new();
+
+        for (address, updates) in state_diff.storage_updates() {
+            let contract = self.contract_mut(address);
+            for (key, value) in updates {
+                contract.storage.update(key, value)?;
+            }
+            touched_contracts.insert(address);
+        }
+        for (address, class_hash) in state_diff.address_to_class_hash() {
+            self.contract_mut(address).class_hash = Felt252::from_bytes_be(class_hash);
+            touched_contracts.insert(address);
+        }
+        for (address, nonce) in state_diff.address_to_nonce() {
+            self.contract_mut(address).nonce = nonce.clone();
+            touched_contracts.insert(address);
+        }
+
+        for address in touched_contracts {
+            let contract = self.contract_mut(address);
+            contract.storage_root = contract.storage.root();
+            let contract_state_hash = contract.state_hash()?;
+            self.contracts_trie
+                .update(&address.0, &contract_state_hash)?;
+        }
+
+        for (class_hash, compiled_class_hash) in state_diff.class_hash_to_compiled_class_hash() {
+            self.classes_trie.update(
+                &Felt252::from_bytes_be(class_hash),
+                &class_leaf(&Felt252::from_bytes_be(compiled_class_hash))?,
+            )?;
+        }
+
+        self.contracts_root = self.contracts_trie.root();
+        self.classes_root = self.classes_trie.root();
+        Ok(())
+    }
+
+    /// Returns the root of the storage trie of the contract at `address`.
+    pub fn storage_root(&self, address: &Address) -> Felt252 {
+        self.contracts
+            .get(address)
+            .map(|contract| contract.storage_root.clone())
+            .unwrap_or_default()
+    }
+
+    /// Returns the root of the contracts trie.
+    pub fn contracts_root(&self) -> &Felt252 {
+        &self.contracts_root
+    }
+
+    /// Returns the root of the classes trie.
+    pub fn classes_root(&self) -> &Felt252 {
+        &self.classes_root
+    }
+
+    /// Returns the global state root. While there are no Cairo 1 classes it is the contracts
+    /// root, and afterwards it is `Poseidon("STARKNET_STATE_V0", contracts_root, classes_root)`.
+    pub fn global_root(&self) -> Result<Felt252, HashError> {
+        global_state_root(&self.contracts_root, &self.classes_root)
+    }
+
+    /// Returns the proof of the state of the contract at `address` and of the values of its
+    /// storage at `keys`, which can be checked against the global root with
+    /// [verify_storage_proof](storage_proof::verify_storage_proof).
+    pub fn get_proof(
+        &mut self,
+        address: &Address,
+        keys: &[Felt252],
+    ) -> Result<StorageProof, HashError> {
+        let contract_proof = self.contracts_trie.proof(&address.0)?;
+        let contract_data = match self.contracts.get_mut(address) {
+            Some(contract) => Some(ContractData {
+                class_hash: contract.class_hash.clone(),
+                nonce: contract.nonce.clone(),
+                root: contract.storage_root.clone(),
+                contract_state_hash_version: CONTRACT_STATE_HASH_VERSION.into(),
+                storage_proofs: keys
+                    .iter()
+                    .map(|key| contract.storage.proof(key))
+                    .collect::<Result<_, _>>()?,
+            }),
+            None => None,
+        };
+
+        Ok(StorageProof {
+            state_commitment: self.global_root()?,
+            class_commitment: self.classes_root.clone(),
+            contract_proof,
+            contract_data,
+        })
+    }
+
+    /// Returns the class hash committed for the contract at `address`.
+    pub fn class_hash_at(&self, address: &Address) -> ClassHash {
+        self.contracts
+            .get(address)
+            .map(|contract| contract.class_hash.to_be_bytes())
+            .unwrap_or_default()
+    }
+
+    fn contract_mut(&mut self, address: &Address) -> &mut ContractCommitment {
+        let empty_storage = &self.empty_storage;
+        self.contracts
+            .entry(address.clone())
+            .or_insert_with(|| ContractCommitment {
+                class_hash: Felt252::zero(),
+                nonce: Felt252::zero(),
+                storage: empty_storage.clone(),
+                storage_root: Felt252::zero(),
+            })
+    }
+}
+
+impl Default for StateCommitment {
+    fn default() -> Self {
+        Self::new(DEFAULT_TRIE_HEIGHT, DEFAULT_TRIE_HEIGHT)
+            .expect("the default trie height is valid")
+    }
+}
+
+impl ContractCommitment {
+    /// Returns `H(H(H(class_hash, storage_root), nonce), 0)`.
+    fn state_hash(&self) -> Result<Felt252, HashError> {
+        contract_state_hash(
+            &self.class_hash,
+            &self.storage_root,
+            &self.nonce,
+            &CONTRACT_STATE_HASH_VERSION.into(),
+        )
+    }
+}
+
+/// Returns the leaf of the contracts trie for a contract,
+/// `H(H(H(class_hash, storage_root), nonce), version)`.
+pub(crate) fn contract_state_hash(
+    class_hash: &Felt252,
+    storage_root: &Felt252,
+    nonce: &Felt252,
+    version: &Felt252,
+) -> Result<Felt252, HashError> {
+    let hash = pedersen_hash(
+        &felt_to_field_element(class_hash)?,
+        &felt_to_field_element(storage_root)?,
+    );
+    let hash = pedersen_hash(&hash, &felt_to_field_element(nonce)?);
+    let hash = pedersen_hash(&hash, &felt_to_field_element(version)?);
+    Ok(field_element_to_felt(&hash))
+}
+
+/// Returns the global state root for the given roots of the contracts and classes tries.
+pub(crate) fn global_state_root(
+    contracts_root: &Felt252,
+    classes_root: &Felt252,
+) -> Result<Felt252, HashError> {
+    if classes_root.is_zero() {
+        return Ok(contracts_root.clone());
+    }
+    Ok(field_element_to_felt(&poseidon_hash_many(&[
+        *STARKNET_STATE_V0,
+        felt_to_field_element(contracts_root)?,
+        felt_to_field_element(classes_root)?,
+    ])))
+}
+
+/// Returns the leaf of the classes trie for a class with the given compiled class hash.
+fn class_leaf(compiled_class_hash: &Felt252) -> Result<Felt252, HashError> {
+    Ok(field_element_to_felt(&poseidon_hash(
+        *CONTRACT_CLASS_LEAF_V0,
+        felt_to_field_element(compiled_class_hash)?,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_state_has_zero_root() {
+        let state_commitment = StateCommitment::default();
+        assert_eq!(state_commitment.global_root().unwrap(), Felt252::zero());
+    }
+
+    #[test]
+    fn contract_leaf_commits_to_class_hash_storage_and_nonce() {
+        let address = Address(0x10.into());
+        let mut state_diff = StateDiff::default();
+        state_diff
+            .address_to_class_hash
+            .insert(address.clone(), [1; 32]);
+        state_diff
+            .address_to_nonce
+            .insert(address.clone(), 2.into());
+        state_diff.storage_updates.insert(
+            address.clone(),
+            HashMap::from([(Felt252::from(3), Felt252::from(4))]),
+        );
+
+        let mut state_commitment = StateCommitment::default();
+        state_commitment.apply_state_diff(&state_diff).unwrap();
+
+        let mut storage =
+            PatriciaTrie::new(TrieHashFunction::Pedersen, DEFAULT_TRIE_HEIGHT).unwrap();
+        storage.update(&3.into(), &4.into()).unwrap();
+        assert_eq!(state_commitment.storage_root(&address), storage.root());
+
+        let contract = ContractCommitment {
+            class_hash: Felt252::from_bytes_be(&[1; 32]),
+            nonce: 2.into(),
+            storage_root: storage.root(),
+            storage,
+        };
+        let mut contracts =
+            PatriciaTrie::new(TrieHashFunction::Pedersen, DEFAULT_TRIE_HEIGHT).unwrap();
+        contracts
+            .update(&address.0, &contract.state_hash().unwrap())
+            .unwrap();
+        assert_eq!(state_commitment.contracts_root(), &contracts.root());
+        // Without Cairo 1 classes, the global root is the contracts root.
+        assert_eq!(
+            &state_commitment.global_root().unwrap(),
+            state_commitment.contracts_root()
+        );
+    }
+
+    #[test]
+    fn applying_diffs_one_by_one_matches_applying_them_composed() {
+        let address = Address(0x10.into());
+        let mut first = StateDiff::default();
+        first.address_to_class_hash.insert(address.clone(), [1; 32]);
+        first.storage_updates.insert(
+            address.clone(),
+            HashMap::from([(Felt252::from(3), Felt252::from(4))]),
+        );
+        first
+            .class_hash_to_compiled_class_hash
+            .insert([5; 32], [6; 32]);
+        let mut second = StateDiff::default();
+        second.address_to_nonce.insert(address.clone(), 1.into());
+        second.storage_updates.insert(
+            address,
+            HashMap::from([
+                (Felt252::from(3), Felt252::zero()),
+                (Felt252::from(7), Felt252::from(8)),
+            ]),
+        );
+
+        let mut one_by_one = StateCommitment::default();
+        one_by_one.apply_state_diff(&first).unwrap();
+        let root_after_first = one_by_one.global_root().unwrap();
+        one_by_one.apply_state_diff(&second).unwrap();
+
+        let mut composed = StateCommitment::default();
+        composed.apply_state_diff(&first.compose(&second)).unwrap();
+
+        assert_ne!(root_after_first, one_by_one.global_root().unwrap());
+        assert_eq!(
+            one_by_one.global_root().unwrap(),
+            composed.global_root().unwrap()
+        );
+        assert_ne!(one_by_one.classes_root(), &Felt252::zero());
+    }
+}
@@ -0,0 +1,617 @@
+use crate::{
+    core::errors::{hash_errors::HashError, proof_errors::ProofError},
+    utils::{felt_to_field_element, field_element_to_felt},
+};
+use cairo_vm::felt::Felt252;
+use num_traits::Zero;
+use starknet_crypto::{pedersen_hash, poseidon_hash, FieldElement};
+use std::collections::{BTreeMap, HashMap};
+
+/// The height of the tries used by Starknet.
+pub const DEFAULT_TRIE_HEIGHT: usize = 251;
+
+/// The hash function used to hash the nodes of a [PatriciaTrie].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TrieHashFunction {
+    Pedersen,
+    Poseidon,
+}
+
+impl TrieHashFunction {
+    pub fn hash(&self, left: &FieldElement, right: &FieldElement) -> FieldElement {
+        match self {
+            TrieHashFunction::Pedersen => pedersen_hash(left, right),
+            TrieHashFunction::Poseidon => poseidon_hash(*left, *right),
+        }
+    }
+}
+
+/// A node of a [PatriciaTrie], as it appears in a proof.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TrieNode {
+    /// A node with two children, given by their hashes.
+    Binary { left: Felt252, right: Felt252 },
+    /// A path of `length` bits from a node to its only child with two children, or to its
+    /// only leaf.
+    Edge {
+        child: Felt252,
+        path: Felt252,
+        length: usize,
+    },
+}
+
+impl TrieNode {
+    /// Returns the hash of the node.
+    pub fn hash(&self, hash_function: TrieHashFunction) -> Result<Felt252, HashError> {
+        let hash = match self {
+            TrieNode::Binary { left, right } => hash_function.hash(
+                &felt_to_field_element(left)?,
+                &felt_to_field_element(right)?,
+            ),
+            TrieNode::Edge {
+                child,
+                path,
+                length,
+            } => {
+                hash_function.hash(
+                    &felt_to_field_element(child)?,
+                    &felt_to_field_element(path)?,
+                ) + FieldElement::from(*length as u64)
+            }
+        };
+        Ok(field_element_to_felt(&hash))
+    }
+}
+
+/// A key of a trie, as the big-endian bytes of the felt.
+pub(crate) type TrieKey = [u8; 32];
+
+/// A binary Merkle-Patricia trie, as used by Starknet for the contract storage, the contracts
+/// and the classes commitments.
+///
+/// The trie has a fixed height, and every leaf is at the bottom. A node with a single child is
+/// compressed with its descendants with a single child into an edge node, whose hash is
+/// `H(child, path) + length`. The hash of a binary node is `H(left, right)`, and the hash of an
+/// empty trie is zero.
+///
+/// The hashes of the nodes are kept between updates, so computing the root after an update
+/// only hashes the nodes on the paths of the updated leaves.
+#[derive(Clone, Debug)]
+pub struct PatriciaTrie {
+    hash_function: TrieHashFunction,
+    height: usize,
+    leaves: BTreeMap<TrieKey, FieldElement>,
+    /// Hashes of the subtries rooted at a depth, by their depth and key prefix.
+    node_hashes: HashMap<(usize, TrieKey), FieldElement>,
+}
+
+impl PatriciaTrie {
+    /// Creates an empty trie. Fails if `height` is higher than [DEFAULT_TRIE_HEIGHT], as keys
+    /// are felts.
+    ///
+    /// # Parameters
+    /// - `hash_function` - The hash function used to hash the nodes.
+    /// - `height` - The height of the trie. Keys must be lower than `2^height`.
+    pub fn new(hash_function: TrieHashFunction, height: usize) -> Result<Self, HashError> {
+        if height > DEFAULT_TRIE_HEIGHT {
+            return Err(HashError::InvalidTrieHeight(height));
+        }
+        Ok(Self {
+            hash_function,
+            height,
+            leaves: BTreeMap::new(),
+            node_hashes: HashMap::new(),
+        })
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn hash_function(&self) -> TrieHashFunction {
+        self.hash_function
+    }
+
+    /// Returns the amount of leaves in the trie.
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Returns the value of the leaf at `key`, or zero if there is none.
+    pub fn get(&self, key: &Felt252) -> Felt252 {
+        self.leaves
+            .get(&key.to_be_bytes())
+            .map(field_element_to_felt)
+            .unwrap_or_default()
+    }
+
+    /// Sets the value of the leaf at `key`. Setting it to zero removes the leaf.
+    pub fn update(&mut self, key: &Felt252, value: &Felt252) -> Result<(), HashError> {
+        let key_bytes = self.key_bytes(key)?;
+
+        if *value == Felt252::zero() {
+            if self.leaves.remove(&key_bytes).is_none() {
+                return Ok(());
+            }
+        } else {
+            self.leaves.insert(key_bytes, felt_to_field_element(value)?);
+        }
+
+        for depth in 0..=self.height {
+            self.node_hashes
+                .remove(&(depth, self.prefix(&key_bytes, depth)));
+        }
+        Ok(())
+    }
+
+    /// Returns the root of the trie.
+    pub fn root(&mut self) -> Felt252 {
+        if self.leaves.is_empty() {
+            return Felt252::zero();
+        }
+        field_element_to_felt(&self.subtrie_hash(0, [0; 32]))
+    }
+
+    /// Returns the nodes on the path from the root to the leaf at `key`, which prove its value.
+    /// If there is no leaf at `key`, the proof ends with the edge node where the path of `key`
+    /// leaves the trie, which proves that its value is zero.
+    pub fn proof(&mut self, key: &Felt252) -> Result<Vec<TrieNode>, HashError> {
+        let key_bytes = self.key_bytes(key)?;
+        let mut proof = Vec::new();
+        if self.leaves.is_empty() {
+            return Ok(proof);
+        }
+
+        let (mut depth, mut prefix) = (0, [0; 32]);
+        while depth < self.height {
+            let (first, last) = self.first_and_last_leaves(depth, &prefix);
+            let common = self.common_prefix_length(&first, &last, depth);
+            if common == 0 {
+                let right_prefix = self.with_bit(&prefix, depth);
+                let left = self.subtrie_hash(depth + 1, prefix);
+                let right = self.subtrie_hash(depth + 1, right_prefix);
+                proof.push(TrieNode::Binary {
+                    left: field_element_to_felt(&left),
+                    right: field_element_to_felt(&right),
+                });
+                if get_bit(&key_bytes, self.bit_index(depth)) {
+                    prefix = right_prefix;
+                }
+                depth += 1;
+            } else {
+                let child_depth = depth + common;
+                let child_prefix = self.prefix(&first, child_depth);
+                let child = self.subtrie_hash(child_depth, child_prefix);
+                proof.push(TrieNode::Edge {
+                    child: field_element_to_felt(&child),
+                    path: field_element_to_felt(&self.path(&first, depth, child_depth)),
+                    length: common,
+                });
+                if self.prefix(&key_bytes, child_depth) != child_prefix {
+                    break;
+                }
+                depth = child_depth;
+                prefix = child_prefix;
+            }
+        }
+        Ok(proof)
+    }
+
+    /// Returns the hash of the subtrie of the leaves starting with `prefix`, rooted at `depth`.
+    /// If all those leaves share the next bits, the subtrie starts with an edge node.
+    fn subtrie_hash(&mut self, depth: usize, prefix: TrieKey) -> FieldElement {
+        if let Some(hash) = self.node_hashes.get(&(depth, prefix)) {
+            return *hash;
+        }
+
+        let (first, last) = self.first_and_last_leaves(depth, &prefix);
+        let hash = if depth == self.height {
+            self.leaves[&first]
+        } else {
+            let common = self.common_prefix_length(&first, &last, depth);
+            if common == 0 {
+                let right_prefix = self.with_bit(&prefix, depth);
+                let left = self.subtrie_hash(depth + 1, prefix);
+                let right = self.subtrie_hash(depth + 1, right_prefix);
+                self.hash_function.hash(&left, &right)
+            } else {
+                let child_depth = depth + common;
+                let child = self.subtrie_hash(child_depth, self.prefix(&first, child_depth));
+                let path = self.path(&first, depth, child_depth);
+                self.hash_function.hash(&child, &path) + FieldElement::from(common as u64)
+            }
+        };
+
+        self.node_hashes.insert((depth, prefix), hash);
+        hash
+    }
+
+    /// Returns the lowest and highest keys starting with `prefix`. There must be at least one.
+    fn first_and_last_leaves(&self, depth: usize, prefix: &TrieKey) -> (TrieKey, TrieKey) {
+        let mut end = *prefix;
+        for index in 0..self.height - depth {
+            set_bit(&mut end, index);
+        }
+        let mut range = self.leaves.range(*prefix..=end).map(|(key, _)| *key);
+        let first = range.next().unwrap_or(*prefix);
+        let last = range.next_back().unwrap_or(first);
+        (first, last)
+    }
+
+    /// Returns how many bits, starting from `depth`, the two keys have in common.
+    fn common_prefix_length(&self, first: &TrieKey, last: &TrieKey, depth: usize) -> usize {
+        (depth..self.height)
+            .take_while(|depth| {
+                get_bit(first, self.bit_index(*depth)) == get_bit(last, self.bit_index(*depth))
+            })
+            .count()
+    }
+
+    /// Returns the bits of `key` from `from_depth` to `to_depth`, as a number.
+    fn path(&self, key: &TrieKey, from_depth: usize, to_depth: usize) -> FieldElement {
+        FieldElement::from_bytes_be(&key_path(key, self.height, from_depth, to_depth))
+            .unwrap_or(FieldElement::ZERO)
+    }
+
+    fn key_bytes(&self, key: &Felt252) -> Result<TrieKey, HashError> {
+        let key_bytes = key.to_be_bytes();
+        if !fits_in_height(&key_bytes, self.height) {
+            return Err(HashError::FailedToComputeHash(format!(
+                "key {key} doesn't fit in a trie of height {}",
+                self.height
+            )));
+        }
+        Ok(key_bytes)
+    }
+
+    /// Returns `key` with only its first `depth` bits.
+    fn prefix(&self, key: &TrieKey, depth: usize) -> TrieKey {
+        let mut prefix = *key;
+        for index in 0..self.height - depth {
+            clear_bit(&mut prefix, index);
+        }
+        prefix
+    }
+
+    fn with_bit(&self, key: &TrieKey, depth: usize) -> TrieKey {
+        let mut key = *key;
+        set_bit(&mut key, self.bit_index(depth));
+        key
+    }
+
+    /// Returns the index, from the least significant bit, of the bit of a key that decides the
+    /// child of a node at `depth`.
+    fn bit_index(&self, depth: usize) -> usize {
+        self.height - 1 - depth
+    }
+}
+
+/// Verifies a proof of the value at `key`, as returned by [PatriciaTrie::proof], against the
+/// root of the trie, and returns the proven value. The value is zero if the proof shows that
+/// there is no leaf at `key`.
+///
+/// # Parameters
+/// - `root` - The trusted root of the trie.
+/// - `key` - The key whose value is proven.
+/// - `proof` - The nodes on the path from the root to the key.
+/// - `hash_function` - The hash function of the trie.
+/// - `height` - The height of the trie.
+pub fn verify_proof(
+    root: &Felt252,
+    key: &Felt252,
+    proof: &[TrieNode],
+    hash_function: TrieHashFunction,
+    height: usize,
+) -> Result<Felt252, ProofError> {
+    let key_bytes = key.to_be_bytes();
+    if height > DEFAULT_TRIE_HEIGHT || !fits_in_height(&key_bytes, height) {
+        return Err(ProofError::KeyOutOfRange(key.clone(), height));
+    }
+    if proof.is_empty() {
+        if root.is_zero() {
+            return Ok(Felt252::zero());
+        }
+        return Err(ProofError::IncompleteProof);
+    }
+
+    let mut expected = root.clone();
+    let mut depth = 0;
+    for (index, node) in proof.iter().enumerate() {
+        let actual = node.hash(hash_function)?;
+        if actual != expected {
+            return Err(ProofError::NodeHashMismatch { expected, actual });
+        }
+        if depth == height {
+            return Err(ProofError::UnexpectedNodes);
+        }
+
+        match node {
+            TrieNode::Binary { left, right } => {
+                expected = if get_bit(&key_bytes, height - 1 - depth) {
+                    right.clone()
+                } else {
+                    left.clone()
+                };
+                depth += 1;
+            }
+            TrieNode::Edge {
+                child,
+                path,
+                length,
+            } => {
+                if *length == 0
+                    || depth + length > height
+                    || !fits_in_height(&path.to_be_bytes(), *length)
+                {
+                    return Err(ProofError::InvalidEdge {
+                        path: path.clone(),
+                        length: *length,
+                    });
+                }
+                if key_path(&key_bytes, height, depth, depth + length) != path.to_be_bytes() {
+                    // The path of the key leaves the trie here, so there is no leaf at the key.
+                    if index + 1 != proof.len() {
+                        return Err(ProofError::UnexpectedNodes);
+                    }
+                    return Ok(Felt252::zero());
+                }
+                expected = child.clone();
+                depth += length;
+            }
+        }
+    }
+
+    if depth != height {
+        return Err(ProofError::IncompleteProof);
+    }
+    Ok(expected)
+}
+
+/// Returns whether `key` is lower than `2^height`.
+fn fits_in_height(key: &TrieKey, height: usize) -> bool {
+    !(height..256).any(|index| get_bit(key, index))
+}
+
+/// Returns the bits of `key` from `from_depth` to `to_depth` in a trie of the given height.
+fn key_path(key: &TrieKey, height: usize, from_depth: usize, to_depth: usize) -> TrieKey {
+    let mut path = [0; 32];
+    for depth in from_depth..to_depth {
+        if get_bit(key, height - 1 - depth) {
+            set_bit(&mut path, to_depth - 1 - depth);
+        }
+    }
+    path
+}
+
+fn get_bit(key: &TrieKey, index: usize) -> bool {
+    key[31 - index / 8] & (1 << (index % 8)) != 0
+}
+
+fn set_bit(key: &mut TrieKey, index: usize) {
+    key[31 - index / 8] |= 1 << (index % 8);
+}
+
+fn clear_bit(key: &mut TrieKey, index: usize) {
+    key[31 - index / 8] &= !(1 << (index % 8));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cairo_vm::felt::felt_str;
+
+    /// Computes the root of a trie from its leaves, without any caching.
+    fn naive_root(
+        hash_function: TrieHashFunction,
+        height: usize,
+        leaves: &[(u64, u64)],
+    ) -> Felt252 {
+        fn node(
+            hash_function: TrieHashFunction,
+            height: usize,
+            depth: usize,
+            leaves: &[(u64, FieldElement)],
+        ) -> (FieldElement, Option<(FieldElement, u64, usize)>) {
+            // Returns the hash of the node, and if it's an edge, its child, path and length.
+            if depth == height {
+                return (leaves[0].1, None);
+            }
+            let bit = height - 1 - depth;
+            let (left, right): (Vec<_>, Vec<_>) = leaves
+                .iter()
+                .copied()
+                .partition(|(key, _)| key & (1 << bit) == 0);
+            let child_hash = |leaves: &[(u64, FieldElement)]| {
+                let (hash, edge) = node(hash_function, height, depth + 1, leaves);
+                (hash, edge)
+            };
+            match (left.is_empty(), right.is_empty()) {
+                (false, false) => {
+                    let (left, _) = child_hash(&left);
+                    let (right, _) = child_hash(&right);
+                    (hash_function.hash(&left, &right), None)
+                }
+                (left_empty, _) => {
+                    let direction = u64::from(left_empty);
+                    let child = if left_empty { &right } else { &left };
+                    let (hash, edge) = child_hash(child);
+                    let (child, path, length) = match edge {
+                        Some((child, path, length)) => {
+                            (child, (direction << length) | path, length + 1)
+                        }
+                        None => (hash, direction, 1),
+                    };
+                    (
+                        hash_function.hash(&child, &FieldElement::from(path))
+                            + FieldElement::from(length as u64),
+                        Some((child, path, length)),
+                    )
+                }
+            }
+        }
+
+        if leaves.is_empty() {
+            return Felt252::zero();
+        }
+        let leaves: Vec<_> = leaves
+            .iter()
+            .map(|(key, value)| (*key, FieldElement::from(*value)))
+            .collect();
+        field_element_to_felt(&node(hash_function, height, 0, &leaves).0)
+    }
+
+    #[test]
+    fn empty_trie_has_zero_root() {
+        let mut trie = PatriciaTrie::new(TrieHashFunction::Pedersen, DEFAULT_TRIE_HEIGHT).unwrap();
+        assert_eq!(trie.root(), Felt252::zero());
+    }
+
+    #[test]
+    fn single_leaf_root_is_an_edge_to_the_leaf() {
+        let mut trie = PatriciaTrie::new(TrieHashFunction::Pedersen, DEFAULT_TRIE_HEIGHT).unwrap();
+        trie.update(&5.into(), &7.into()).unwrap();
+
+        let expected = pedersen_hash(&FieldElement::from(7u64), &FieldElement::from(5u64))
+            + FieldElement::from(DEFAULT_TRIE_HEIGHT as u64);
+        assert_eq!(trie.root(), field_element_to_felt(&expected));
+    }
+
+    #[test]
+    fn incremental_updates_match_the_trie_built_from_scratch() {
+        let height = 8;
+        for hash_function in [TrieHashFunction::Pedersen, TrieHashFunction::Poseidon] {
+            let mut trie = PatriciaTrie::new(hash_function, height).unwrap();
+            let mut leaves = BTreeMap::new();
+            let mut seed: u64 = 7;
+            for _ in 0..60 {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                let key = (seed >> 33) % 256;
+                let value = (seed >> 20) % 4;
+                trie.update(&key.into(), &value.into()).unwrap();
+                if value == 0 {
+                    leaves.remove(&key);
+                } else {
+                    leaves.insert(key, value);
+                }
+
+                let leaves: Vec<_> = leaves.iter().map(|(k, v)| (*k, *v)).collect();
+                assert_eq!(trie.root(), naive_root(hash_function, height, &leaves));
+            }
+        }
+    }
+
+    #[test]
+    fn proofs_verify_against_the_root() {
+        let height = 8;
+        let mut trie = PatriciaTrie::new(TrieHashFunction::Pedersen, height).unwrap();
+        for (key, value) in [(3u64, 30u64), (4, 40), (5, 50), (200, 7)] {
+            trie.update(&key.into(), &value.into()).unwrap();
+        }
+        let root = trie.root();
+
+        // Every key, whether it's in the trie or not, gets a proof of its value.
+        for key in 0..256u64 {
+            let key = Felt252::from(key);
+            let proof = trie.proof(&key).unwrap();
+            assert_eq!(
+                verify_proof(&root, &key, &proof, TrieHashFunction::Pedersen, height).unwrap(),
+                trie.get(&key)
+            );
+        }
+    }
+
+    #[test]
+    fn tampered_proofs_are_rejected() {
+        let height = 8;
+        let mut trie = PatriciaTrie::new(TrieHashFunction::Poseidon, height).unwrap();
+        for (key, value) in [(3u64, 30u64), (4, 40), (200, 7)] {
+            trie.update(&key.into(), &value.into()).unwrap();
+        }
+        let root = trie.root();
+        let key = Felt252::from(4);
+        let proof = trie.proof(&key).unwrap();
+
+        let mut tampered = proof.clone();
+        match tampered.last_mut().unwrap() {
+            TrieNode::Binary { left, .. } => *left = left.clone() + Felt252::from(1),
+            TrieNode::Edge { child, .. } => *child = child.clone() + Felt252::from(1),
+        }
+        assert_matches!(
+            verify_proof(&root, &key, &tampered, TrieHashFunction::Poseidon, height),
+            Err(ProofError::NodeHashMismatch { .. })
+        );
+        assert_matches!(
+            verify_proof(
+                &root,
+                &key,
+                &proof[..proof.len() - 1],
+                TrieHashFunction::Poseidon,
+                height
+            ),
+            Err(ProofError::IncompleteProof)
+        );
+        assert_matches!(
+            verify_proof(&root, &key, &[], TrieHashFunction::Poseidon, height),
+            Err(ProofError::IncompleteProof)
+        );
+        assert_matches!(
+            verify_proof(&root, &key, &proof, TrieHashFunction::Pedersen, height),
+            Err(ProofError::NodeHashMismatch { .. })
+        );
+    }
+
+    #[test]
+    fn tries_cant_be_higher_than_felts() {
+        assert!(PatriciaTrie::new(TrieHashFunction::Pedersen, DEFAULT_TRIE_HEIGHT).is_ok());
+        assert_matches!(
+            PatriciaTrie::new(TrieHashFunction::Pedersen, DEFAULT_TRIE_HEIGHT + 1),
+            Err(HashError::InvalidTrieHeight(252))
+        );
+    }
+
+    /// Regression vectors of the Poseidon Patricia tries of height 64 of Starknet (used for the
+    /// commitments of the blocks since 0.13.2), from `starknet_api`.
+    #[test]
+    fn poseidon_roots_match_the_starknet_ones() {
+        let root = |leaves: &[u64]| {
+            let mut trie = PatriciaTrie::new(TrieHashFunction::Poseidon, 64).unwrap();
+            for (index, leaf) in leaves.iter().enumerate() {
+                trie.update(&index.into(), &(*leaf).into()).unwrap();
+            }
+            trie.root()
+        };
+
+        assert_eq!(
+            root(&[1]),
+            felt_str!(
+                "7752582c54a42fe0fa35c40f07293bb7d8efe90e21d8d2c06a7db52d7d9b7e1",
+                16
+            )
+        );
+        assert_eq!(
+            root(&[1, 2]),
+            felt_str!(
+                "1c1ba983ee0a0de87d87d67ea3cbee7023aa65f6b7bcf71259f122ea3af80bf",
+                16
+            )
+        );
+        assert_eq!(
+            root(&[1, 2, 3]),
+            felt_str!(
+                "3b5cc7f1292eb3847c3f902d048a7e5dc7702d1c191ccd17c2d33f797e6fc32",
+                16
+            )
+        );
+    }
+
+    #[test]
+    fn keys_must_fit_in_the_trie() {
+        let mut trie = PatriciaTrie::new(TrieHashFunction::Pedersen, 8).unwrap();
+        assert!(trie.update(&255.into(), &1.into()).is_ok());
+        assert!(trie.update(&256.into(), &1.into()).is_err());
+    }
+}
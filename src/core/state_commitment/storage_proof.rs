@@ -0,0 +1,210 @@
+use super::{
+    contract_state_hash, global_state_root,
+    patricia_trie::{verify_proof, TrieHashFunction, TrieNode, DEFAULT_TRIE_HEIGHT},
+};
+use crate::{core::errors::proof_errors::ProofError, utils::Address};
+use cairo_vm::felt::Felt252;
+use num_traits::Zero;
+
+/// A proof of the state of a contract and of some of its storage values, like the one returned
+/// by `pathfinder_getProof`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StorageProof {
+    /// The global state root the proof was built for.
+    pub state_commitment: Felt252,
+    /// The root of the classes trie.
+    pub class_commitment: Felt252,
+    /// The path to the leaf of the contract in the contracts trie.
+    pub contract_proof: Vec<TrieNode>,
+    /// The state of the contract, or `None` if the contract doesn't exist.
+    pub contract_data: Option<ContractData>,
+}
+
+/// The state of a contract, with the paths to the requested keys in its storage trie.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ContractData {
+    pub class_hash: Felt252,
+    pub nonce: Felt252,
+    /// The root of the storage trie of the contract.
+    pub root: Felt252,
+    pub contract_state_hash_version: Felt252,
+    /// The paths to the requested keys in the storage trie, in the same order as the keys.
+    pub storage_proofs: Vec<Vec<TrieNode>>,
+}
+
+/// Verifies a [StorageProof] against a trusted global state root, and returns the proven
+/// values of the storage of the contract at `keys`. The values of keys that aren't in the
+/// storage, or of a contract that doesn't exist, are proven to be zero.
+///
+/// The tries are expected to have the height used by Starknet.
+///
+/// # Parameters
+/// - `proof` - The proof to verify.
+/// - `global_root` - The trusted global state root.
+/// - `address` - The address of the contract.
+/// - `keys` - The storage keys, in the order they were requested.
+pub fn verify_storage_proof(
+    proof: &StorageProof,
+    global_root: &Felt252,
+    address: &Address,
+    keys: &[Felt252],
+) -> Result<Vec<Felt252>, ProofError> {
+    let contracts_root = match proof.contract_proof.first() {
+        Some(node) => node.hash(TrieHashFunction::Pedersen)?,
+        None => Felt252::zero(),
+    };
+    let actual_root = global_state_root(&contracts_root, &proof.class_commitment)?;
+    if actual_root != *global_root {
+        return Err(ProofError::GlobalRootMismatch {
+            expected: global_root.clone(),
+            actual: actual_root,
+        });
+    }
+
+    let contract_leaf = verify_proof(
+        &contracts_root,
+        &address.0,
+        &proof.contract_proof,
+        TrieHashFunction::Pedersen,
+        DEFAULT_TRIE_HEIGHT,
+    )?;
+    let contract_data = match &proof.contract_data {
+        Some(contract_data) => contract_data,
+        None if contract_leaf.is_zero() => return Ok(vec![Felt252::zero(); keys.len()]),
+        None => {
+            return Err(ProofError::ContractStateMismatch {
+                expected: contract_leaf,
+                actual: Felt252::zero(),
+            })
+        }
+    };
+
+    let contract_hash = contract_state_hash(
+        &contract_data.class_hash,
+        &contract_data.root,
+        &contract_data.nonce,
+        &contract_data.contract_state_hash_version,
+    )?;
+    if contract_hash != contract_leaf {
+        return Err(ProofError::ContractStateMismatch {
+            expected: contract_leaf,
+            actual: contract_hash,
+        });
+    }
+
+    if contract_data.storage_proofs.len() != keys.len() {
+        return Err(ProofError::StorageProofCount {
+            expected: keys.len(),
+            actual: contract_data.storage_proofs.len(),
+        });
+    }
+    keys.iter()
+        .zip(&contract_data.storage_proofs)
+        .map(|(key, storage_proof)| {
+            verify_proof(
+                &contract_data.root,
+                key,
+                storage_proof,
+                TrieHashFunction::Pedersen,
+                DEFAULT_TRIE_HEIGHT,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{core::state_commitment::StateCommitment, state::StateDiff};
+    use std::collections::HashMap;
+
+    fn state_commitment() -> StateCommitment {
+        let mut state_diff = StateDiff::default();
+        for i in 1..4u64 {
+            let address = Address((0x100 * i).into());
+            state_diff
+                .address_to_class_hash
+                .insert(address.clone(), [i as u8; 32]);
+            state_diff
+                .address_to_nonce
+                .insert(address.clone(), i.into());
+            state_diff.storage_updates.insert(
+                address,
+                HashMap::from([
+                    (Felt252::from(i), Felt252::from(10 * i)),
+                    (Felt252::from(i + 1), Felt252::from(20 * i)),
+                ]),
+            );
+        }
+        state_diff
+            .class_hash_to_compiled_class_hash
+            .insert([1; 32], [2; 32]);
+
+        let mut state_commitment = StateCommitment::default();
+        state_commitment.apply_state_diff(&state_diff).unwrap();
+        state_commitment
+    }
+
+    #[test]
+    fn storage_proofs_verify_against_the_global_root() {
+        let mut state_commitment = state_commitment();
+        let global_root = state_commitment.global_root().unwrap();
+        let address = Address(0x200.into());
+        let keys = [2.into(), 3.into(), 4.into()];
+
+        let proof = state_commitment.get_proof(&address, &keys).unwrap();
+        assert_eq!(proof.state_commitment, global_root);
+        assert_eq!(
+            verify_storage_proof(&proof, &global_root, &address, &keys).unwrap(),
+            vec![20.into(), 40.into(), Felt252::zero()]
+        );
+    }
+
+    #[test]
+    fn missing_contracts_are_proven_to_be_empty() {
+        let mut state_commitment = state_commitment();
+        let global_root = state_commitment.global_root().unwrap();
+        let address = Address(0x250.into());
+        let keys = [2.into()];
+
+        let proof = state_commitment.get_proof(&address, &keys).unwrap();
+        assert!(proof.contract_data.is_none());
+        assert_eq!(
+            verify_storage_proof(&proof, &global_root, &address, &keys).unwrap(),
+            vec![Felt252::zero()]
+        );
+    }
+
+    #[test]
+    fn forged_storage_proofs_are_rejected() {
+        let mut state_commitment = state_commitment();
+        let global_root = state_commitment.global_root().unwrap();
+        let address = Address(0x200.into());
+        let keys = [2.into()];
+        let proof = state_commitment.get_proof(&address, &keys).unwrap();
+
+        assert_matches!(
+            verify_storage_proof(&proof, &Felt252::from(1), &address, &keys),
+            Err(ProofError::GlobalRootMismatch { .. })
+        );
+
+        let mut forged = proof.clone();
+        if let Some(contract_data) = forged.contract_data.as_mut() {
+            contract_data.nonce = 7.into();
+        }
+        assert_matches!(
+            verify_storage_proof(&forged, &global_root, &address, &keys),
+            Err(ProofError::ContractStateMismatch { .. })
+        );
+
+        let mut forged = proof.clone();
+        forged.contract_data = None;
+        assert_matches!(
+            verify_storage_proof(&forged, &global_root, &address, &keys),
+            Err(ProofError::ContractStateMismatch { .. })
+        );
+
+        // The proof of another contract doesn't prove the storage of this one.
+        assert!(verify_storage_proof(&proof, &global_root, &Address(0x300.into()), &keys).is_err());
+    }
+}
@@ -1,11 +1,15 @@
 use crate::core::errors::hash_errors::HashError;
 use crate::{
     core::contract_address::compute_deprecated_class_hash,
-    definitions::constants::CONSTRUCTOR_ENTRY_POINT_SELECTOR, hash_utils::compute_hash_on_elements,
-    services::api::contract_classes::deprecated_contract_class::ContractClass, utils::Address,
+    definitions::constants::CONSTRUCTOR_ENTRY_POINT_SELECTOR,
+    hash_utils::compute_hash_on_elements,
+    services::api::contract_classes::deprecated_contract_class::ContractClass,
+    transaction::V3TransactionFields,
+    utils::{felt_to_field_element, field_element_to_felt, Address},
 };
 use cairo_vm::felt::{felt_str, Felt252};
 use num_traits::Zero;
+use starknet_crypto::{poseidon_hash_many, FieldElement};
 
 #[derive(Debug)]
 /// Enum representing the different types of transaction hash prefixes.
@@ -177,6 +181,151 @@ pub fn calculate_declare_v2_transaction_hash(
     )
 }
 
+// ----------------------------
+//      V3 Hash Functions
+// ----------------------------
+
+/// Calculates the hash of a version 3 transaction, which is a Poseidon hash of:
+///    1. A prefix that depends on the transaction type.
+///    2. The transaction's version.
+///    3. The sender address (the deployed address for `DeployAccount`).
+///    4. A hash of the tip and the resource bounds.
+///    5. A hash of the paymaster data.
+///    6. The network's chain ID.
+///    7. The transaction's nonce.
+///    8. The data availability modes of the nonce and the fee.
+///    9. The data that depends on the transaction type.
+pub fn calculate_transaction_hash_v3_common(
+    tx_hash_prefix: TransactionHashPrefix,
+    version: Felt252,
+    sender_address: &Address,
+    chain_id: Felt252,
+    nonce: Felt252,
+    v3_fields: &V3TransactionFields,
+    additional_data: &[Felt252],
+) -> Result<Felt252, HashError> {
+    let mut fee_fields = vec![FieldElement::from(v3_fields.tip)];
+    for (resource, bounds) in v3_fields.resource_bounds() {
+        // The resource name, the max amount and the max price per unit are packed as
+        // resource (48 bits) | max_amount (64 bits) | max_price_per_unit (128 bits).
+        let mut packed_bounds = [0; 32];
+        packed_bounds[8 - resource.len()..8].copy_from_slice(resource);
+        packed_bounds[8..16].copy_from_slice(&bounds.max_amount.to_be_bytes());
+        packed_bounds[16..].copy_from_slice(&bounds.max_price_per_unit.to_be_bytes());
+        fee_fields.push(
+            FieldElement::from_bytes_be(&packed_bounds).map_err(|_| {
+                HashError::FailedToComputeHash("Invalid resource bounds".to_string())
+            })?,
+        );
+    }
+    let data_availability_modes = (u64::from(u32::from(v3_fields.nonce_data_availability_mode))
+        << 32)
+        + u64::from(u32::from(v3_fields.fee_data_availability_mode));
+
+    let mut data_to_hash = vec![
+        felt_to_field_element(&tx_hash_prefix.get_prefix())?,
+        felt_to_field_element(&version)?,
+        felt_to_field_element(&sender_address.0)?,
+        poseidon_hash_many(&fee_fields),
+        poseidon_hash_on_elements(&v3_fields.paymaster_data)?,
+        felt_to_field_element(&chain_id)?,
+        felt_to_field_element(&nonce)?,
+        FieldElement::from(data_availability_modes),
+    ];
+    for data in additional_data {
+        data_to_hash.push(felt_to_field_element(data)?);
+    }
+
+    Ok(field_element_to_felt(&poseidon_hash_many(&data_to_hash)))
+}
+
+/// Calculates the hash of a version 3 `InvokeFunction` transaction.
+pub fn calculate_invoke_v3_transaction_hash(
+    version: Felt252,
+    sender_address: &Address,
+    calldata: &[Felt252],
+    chain_id: Felt252,
+    nonce: Felt252,
+    v3_fields: &V3TransactionFields,
+) -> Result<Felt252, HashError> {
+    calculate_transaction_hash_v3_common(
+        TransactionHashPrefix::Invoke,
+        version,
+        sender_address,
+        chain_id,
+        nonce,
+        v3_fields,
+        &[
+            field_element_to_felt(&poseidon_hash_on_elements(
+                &v3_fields.account_deployment_data,
+            )?),
+            field_element_to_felt(&poseidon_hash_on_elements(calldata)?),
+        ],
+    )
+}
+
+/// Calculates the hash of a version 3 `Declare` transaction.
+pub fn calculate_declare_v3_transaction_hash(
+    version: Felt252,
+    sierra_class_hash: Felt252,
+    compiled_class_hash: Felt252,
+    sender_address: &Address,
+    chain_id: Felt252,
+    nonce: Felt252,
+    v3_fields: &V3TransactionFields,
+) -> Result<Felt252, HashError> {
+    calculate_transaction_hash_v3_common(
+        TransactionHashPrefix::Declare,
+        version,
+        sender_address,
+        chain_id,
+        nonce,
+        v3_fields,
+        &[
+            field_element_to_felt(&poseidon_hash_on_elements(
+                &v3_fields.account_deployment_data,
+            )?),
+            sierra_class_hash,
+            compiled_class_hash,
+        ],
+    )
+}
+
+/// Calculates the hash of a version 3 `DeployAccount` transaction.
+#[allow(clippy::too_many_arguments)]
+pub fn calculate_deploy_account_v3_transaction_hash(
+    version: Felt252,
+    contract_address: &Address,
+    class_hash: Felt252,
+    constructor_calldata: &[Felt252],
+    salt: Felt252,
+    chain_id: Felt252,
+    nonce: Felt252,
+    v3_fields: &V3TransactionFields,
+) -> Result<Felt252, HashError> {
+    calculate_transaction_hash_v3_common(
+        TransactionHashPrefix::DeployAccount,
+        version,
+        contract_address,
+        chain_id,
+        nonce,
+        v3_fields,
+        &[
+            field_element_to_felt(&poseidon_hash_on_elements(constructor_calldata)?),
+            class_hash,
+            salt,
+        ],
+    )
+}
+
+fn poseidon_hash_on_elements(elements: &[Felt252]) -> Result<FieldElement, HashError> {
+    let elements = elements
+        .iter()
+        .map(felt_to_field_element)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(poseidon_hash_many(&elements))
+}
+
 #[cfg(test)]
 mod tests {
     use cairo_vm::felt::felt_str;
@@ -251,4 +400,161 @@ mod tests {
             )
         )
     }
+
+    #[test]
+    fn calculate_invoke_v3_hash_test() {
+        let sender_address = Address(1234.into());
+        let calldata = vec![1.into(), 2.into()];
+        let v3_fields = V3TransactionFields {
+            l1_resource_bounds: crate::transaction::ResourceBounds {
+                max_amount: 0x186a0,
+                max_price_per_unit: 0x5af3107a4000,
+            },
+            tip: 5,
+            paymaster_data: vec![7.into()],
+            ..Default::default()
+        };
+        let hash = |version: Felt252, v3_fields: &V3TransactionFields| {
+            calculate_invoke_v3_transaction_hash(
+                version,
+                &sender_address,
+                &calldata,
+                StarknetChainId::TestNet.to_felt(),
+                8.into(),
+                v3_fields,
+            )
+            .unwrap()
+        };
+
+        let resource_bounds = |name: &[u8], max_amount: u64, max_price_per_unit: u128| {
+            let name = Felt252::from_bytes_be(name);
+            let bounds = (name << 192u32)
+                + (Felt252::from(max_amount) << 128u32)
+                + Felt252::from(max_price_per_unit);
+            felt_to_field_element(&bounds).unwrap()
+        };
+        let felts = |values: &[Felt252]| {
+            values
+                .iter()
+                .map(|value| felt_to_field_element(value).unwrap())
+                .collect::<Vec<_>>()
+        };
+        let expected = poseidon_hash_many(&[
+            felt_to_field_element(&felt_str!("115923154332517")).unwrap(),
+            FieldElement::from(3u64),
+            FieldElement::from(1234u64),
+            poseidon_hash_many(&[
+                FieldElement::from(5u64),
+                resource_bounds(b"L1_GAS", 0x186a0, 0x5af3107a4000),
+                resource_bounds(b"L2_GAS", 0, 0),
+            ]),
+            poseidon_hash_many(&[FieldElement::from(7u64)]),
+            felt_to_field_element(&StarknetChainId::TestNet.to_felt()).unwrap(),
+            FieldElement::from(8u64),
+            FieldElement::ZERO,
+            poseidon_hash_many(&[]),
+            poseidon_hash_many(&felts(&calldata)),
+        ]);
+        assert_eq!(hash(3.into(), &v3_fields), field_element_to_felt(&expected));
+
+        // Every V3 field is committed to by the hash.
+        let with_tip = V3TransactionFields {
+            tip: 6,
+            ..v3_fields.clone()
+        };
+        assert_ne!(hash(3.into(), &with_tip), hash(3.into(), &v3_fields));
+        let with_l2_da = V3TransactionFields {
+            fee_data_availability_mode: crate::transaction::DataAvailabilityMode::L2,
+            ..v3_fields.clone()
+        };
+        assert_ne!(hash(3.into(), &with_l2_da), hash(3.into(), &v3_fields));
+    }
+
+    #[test]
+    fn calculate_invoke_v3_hash_of_mainnet_transaction() {
+        // Transaction 0x1d4735f4ba73a67be2f648d9b21cab3783383b8c229566b46b027c46012219 of mainnet
+        // block 636864.
+        let v3_fields = V3TransactionFields {
+            l1_resource_bounds: crate::transaction::ResourceBounds {
+                max_amount: 0xa9e,
+                max_price_per_unit: 0x7f2a1ad4f2f1,
+            },
+            ..Default::default()
+        };
+        let calldata = vec![
+            1.into(),
+            felt_str!(
+                "4c0a5193d58f74fbace4b74dcf65481e734ed1714121bdc571da345540efa05",
+                16
+            ),
+            felt_str!(
+                "3943907ef0ef6f9d2e2408b05e520a66daaf74293dbf665e5a20b117676170e",
+                16
+            ),
+            2.into(),
+            felt_str!(
+                "49d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7",
+                16
+            ),
+            felt_str!("16345785d8a0000", 16),
+        ];
+
+        assert_eq!(
+            calculate_invoke_v3_transaction_hash(
+                3.into(),
+                &Address(felt_str!(
+                    "69c0f9bcd79697bdceaf7748e3ff8f34aa39e4063ce44896af664c0c96f6c10",
+                    16
+                )),
+                &calldata,
+                StarknetChainId::MainNet.to_felt(),
+                0x9d.into(),
+                &v3_fields,
+            )
+            .unwrap(),
+            felt_str!(
+                "1d4735f4ba73a67be2f648d9b21cab3783383b8c229566b46b027c46012219",
+                16
+            )
+        );
+    }
+
+    #[test]
+    fn calculate_declare_v3_hash_of_mainnet_transaction() {
+        // Transaction 0x50ca961fd1b5f7f1ea1e8620ac980cc3c3fb4e434f0661c3a2d50893082b9fb of
+        // mainnet block 630721.
+        let v3_fields = V3TransactionFields {
+            l1_resource_bounds: crate::transaction::ResourceBounds {
+                max_amount: 0xe38c2,
+                max_price_per_unit: 0x24e2649cc098,
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            calculate_declare_v3_transaction_hash(
+                3.into(),
+                felt_str!(
+                    "7a9d1cd5dcf3d47b10e638eb1330d101f7d471f08eff9664b42dfa90f6973bf",
+                    16
+                ),
+                felt_str!(
+                    "4fc6444f59a2fb0b2c67e1e22caba0edc3b48727e8a9cc19e59e5c3e5dc8270",
+                    16
+                ),
+                &Address(felt_str!(
+                    "46d3a562c606077c14c3479946e4145b7a372538875eb4e635e758fcd1d2c80",
+                    16
+                )),
+                StarknetChainId::MainNet.to_felt(),
+                3.into(),
+                &v3_fields,
+            )
+            .unwrap(),
+            felt_str!(
+                "50ca961fd1b5f7f1ea1e8620ac980cc3c3fb4e434f0661c3a2d50893082b9fb",
+                16
+            )
+        );
+    }
 }
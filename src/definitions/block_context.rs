@@ -2,17 +2,25 @@
 //!
 //! This module contains structs representing the context of a specific Starknet block.
 
-use crate::{state::BlockInfo, utils::Address};
+use crate::{
+    execution::bouncer::BouncerWeights,
+    state::{shared_class_cache::SharedClassCache, BlockInfo},
+    utils::Address,
+};
 use cairo_vm::felt::Felt252;
 use core::fmt;
 use getset::{CopyGetters, Getters, MutGetters};
 use starknet_api::block::Block;
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
-use super::constants::{
-    DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS, DEFAULT_CONTRACT_STORAGE_COMMITMENT_TREE_HEIGHT,
-    DEFAULT_GLOBAL_STATE_COMMITMENT_TREE_HEIGHT, DEFAULT_INVOKE_TX_MAX_N_STEPS,
-    DEFAULT_SEQUENCER_ADDRESS, DEFAULT_STARKNET_OS_CONFIG, DEFAULT_VALIDATE_MAX_N_STEPS,
+use super::{
+    constants::{
+        DEFAULT_CONTRACT_STORAGE_COMMITMENT_TREE_HEIGHT,
+        DEFAULT_GLOBAL_STATE_COMMITMENT_TREE_HEIGHT, DEFAULT_SEQUENCER_ADDRESS,
+        DEFAULT_STARKNET_OS_CONFIG, DEFAULT_STARKNET_VERSION, QUERY_VERSION_BASE,
+        V3_TRANSACTION_VERSION,
+    },
+    versioned_constants::{StarknetVersion, VersionedConstants, VersionedConstantsError},
 };
 
 /// Unique identifier of a Starknet chain.
@@ -63,18 +71,41 @@ impl StarknetChainId {
     }
 }
 
+/// The token in which the fee of a transaction is paid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FeeType {
+    /// The fee of transactions before version 3, bounded by their `max_fee`.
+    Eth,
+    /// The fee of version 3 transactions, bounded by their resource bounds.
+    Strk,
+}
+
+impl FeeType {
+    /// Returns the token used to pay the fee of a transaction of the given version. Query
+    /// versions pay in the same token as the version they query.
+    pub fn from_version(version: &Felt252) -> Self {
+        if *version == *V3_TRANSACTION_VERSION
+            || *version == &*V3_TRANSACTION_VERSION | &*QUERY_VERSION_BASE
+        {
+            FeeType::Strk
+        } else {
+            FeeType::Eth
+        }
+    }
+}
+
 #[derive(Debug, Clone, Getters, MutGetters)]
 /// Starknet OS configuration.
 pub struct StarknetOsConfig {
     /// ID of the configured chain
     #[getset(get = "pub", get_mut = "pub")]
     pub(crate) chain_id: Felt252,
-    /// Address of the token used when paying fees
+    /// Address of the ETH token, used when paying fees of transactions before version 3
     #[get = "pub"]
     pub(crate) fee_token_address: Address,
-    /// Price of gas
+    /// Address of the STRK token, used when paying fees of version 3 transactions
     #[get = "pub"]
-    pub(crate) gas_price: u128,
+    pub(crate) strk_fee_token_address: Address,
 }
 
 impl StarknetOsConfig {
@@ -83,13 +114,27 @@ impl StarknetOsConfig {
     /// # Arguments
     ///
     /// * `chain_id` - [`Felt252`] of the configured chain.
-    /// * `fee_token_address` - Address of the token used when paying fees.
-    /// * `gas_price` - Price of gas.
-    pub const fn new(chain_id: Felt252, fee_token_address: Address, gas_price: u128) -> Self {
+    /// * `fee_token_address` - Address of the ETH token used when paying fees.
+    /// * `strk_fee_token_address` - Address of the STRK token used when paying fees.
+    ///
+    /// The gas prices are those of each block, see [`BlockInfo`].
+    pub const fn new(
+        chain_id: Felt252,
+        fee_token_address: Address,
+        strk_fee_token_address: Address,
+    ) -> Self {
         StarknetOsConfig {
             chain_id,
             fee_token_address,
-            gas_price,
+            strk_fee_token_address,
+        }
+    }
+
+    /// Returns the address of the token the fees of the given type are paid with.
+    pub fn fee_token_address_by_type(&self, fee_type: FeeType) -> &Address {
+        match fee_type {
+            FeeType::Eth => &self.fee_token_address,
+            FeeType::Strk => &self.strk_fee_token_address,
         }
     }
 }
@@ -121,6 +166,20 @@ pub struct BlockContext {
     #[getset(get = "pub", get_mut = "pub")]
     pub(crate) blocks: HashMap<u64, Block>,
     pub(crate) enforce_l1_handler_fee: bool,
+    /// The version of the Starknet protocol the block is executed with.
+    #[get_copy = "pub"]
+    pub(crate) starknet_version: StarknetVersion,
+    /// The constants of the Starknet version, such as the resources used by the OS and the gas
+    /// costs of the syscalls.
+    #[get = "pub"]
+    pub(crate) versioned_constants: Arc<VersionedConstants>,
+    /// The capacity of the block, which closes before a transaction takes it over its limits.
+    #[get = "pub"]
+    pub(crate) bouncer_limits: BouncerWeights,
+    /// The cache of the classes read by the transactions of the block, shared with the other
+    /// blocks executed with clones of this context.
+    #[get = "pub"]
+    pub(crate) class_cache: Option<Arc<SharedClassCache>>,
 }
 
 impl BlockContext {
@@ -138,8 +197,12 @@ impl BlockContext {
     /// * `blocks` - Blocks in the range [ current_block - 1024, current_block - 10 ].
     ///     Example: for block number 6351, this includes the blocks 5327, 5328, ..., 6340, 6341.
     /// * `enforce_l1_handler_fee` - Whether to enforce the L1 handler fee.
+    ///
+    /// The block is executed with the constants of [`DEFAULT_STARKNET_VERSION`]. See
+    /// [`BlockContext::with_starknet_version`]. Its capacity has no limits, see
+    /// [`BlockContext::with_bouncer_limits`].
     #[allow(clippy::too_many_arguments)]
-    pub const fn new(
+    pub fn new(
         starknet_os_config: StarknetOsConfig,
         contract_storage_commitment_tree_height: u64,
         global_state_commitment_tree_height: u64,
@@ -150,6 +213,9 @@ impl BlockContext {
         blocks: HashMap<u64, Block>,
         enforce_l1_handler_fee: bool,
     ) -> Self {
+        let starknet_version = DEFAULT_STARKNET_VERSION;
+        let versioned_constants = VersionedConstants::for_starknet_version(starknet_version)
+            .expect("There are bundled constants for the default Starknet version");
         Self {
             starknet_os_config,
             contract_storage_commitment_tree_height,
@@ -160,23 +226,92 @@ impl BlockContext {
             block_info,
             blocks,
             enforce_l1_handler_fee,
+            starknet_version,
+            versioned_constants,
+            bouncer_limits: BouncerWeights::max(),
+            class_cache: None,
         }
     }
+
+    /// Executes the block with the bundled constants of the given Starknet version. This
+    /// replaces the fee weights and the step limits with those of the version.
+    ///
+    /// # Arguments
+    ///
+    /// * `starknet_version` - The version of the Starknet protocol of the block.
+    pub fn with_starknet_version(
+        self,
+        starknet_version: StarknetVersion,
+    ) -> Result<Self, VersionedConstantsError> {
+        let versioned_constants = VersionedConstants::for_starknet_version(starknet_version)?;
+        Ok(Self {
+            starknet_version,
+            ..self.with_versioned_constants(versioned_constants)
+        })
+    }
+
+    /// Executes the block with the given constants, e.g. loaded with
+    /// [`VersionedConstants::from_path`]. This replaces the fee weights and the step limits with
+    /// those of the constants.
+    ///
+    /// # Arguments
+    ///
+    /// * `versioned_constants` - The constants of the Starknet protocol.
+    pub fn with_versioned_constants(
+        mut self,
+        versioned_constants: Arc<VersionedConstants>,
+    ) -> Self {
+        self.cairo_resource_fee_weights = versioned_constants.cairo_resource_fee_weights.clone();
+        self.invoke_tx_max_n_steps = versioned_constants.invoke_tx_max_n_steps;
+        self.validate_max_n_steps = versioned_constants.validate_max_n_steps;
+        self.versioned_constants = versioned_constants;
+        self
+    }
+
+    /// Limits the capacity of the block. A transaction that would take the block over the
+    /// limits is left for the next block.
+    ///
+    /// # Arguments
+    ///
+    /// * `bouncer_limits` - The maximum weights of the transactions of the block.
+    pub fn with_bouncer_limits(mut self, bouncer_limits: BouncerWeights) -> Self {
+        self.bouncer_limits = bouncer_limits;
+        self
+    }
+
+    /// Keeps the classes read by the transactions executed with this context in `class_cache`,
+    /// so that each class is fetched from the state reader and parsed only once. The cache
+    /// should only be shared between contexts of the same chain, see [SharedClassCache].
+    ///
+    /// # Arguments
+    ///
+    /// * `class_cache` - The cache of the classes read from the state.
+    pub fn with_class_cache(mut self, class_cache: Arc<SharedClassCache>) -> Self {
+        self.class_cache = Some(class_cache);
+        self
+    }
 }
 
 impl Default for BlockContext {
     fn default() -> Self {
+        let starknet_version = DEFAULT_STARKNET_VERSION;
+        let versioned_constants = VersionedConstants::for_starknet_version(starknet_version)
+            .expect("There are bundled constants for the default Starknet version");
         Self {
             starknet_os_config: Default::default(),
             contract_storage_commitment_tree_height:
                 DEFAULT_CONTRACT_STORAGE_COMMITMENT_TREE_HEIGHT,
             global_state_commitment_tree_height: DEFAULT_GLOBAL_STATE_COMMITMENT_TREE_HEIGHT,
-            invoke_tx_max_n_steps: DEFAULT_INVOKE_TX_MAX_N_STEPS,
-            cairo_resource_fee_weights: DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS.clone(),
-            validate_max_n_steps: DEFAULT_VALIDATE_MAX_N_STEPS,
+            invoke_tx_max_n_steps: versioned_constants.invoke_tx_max_n_steps,
+            cairo_resource_fee_weights: versioned_constants.cairo_resource_fee_weights.clone(),
+            validate_max_n_steps: versioned_constants.validate_max_n_steps,
             block_info: BlockInfo::empty(DEFAULT_SEQUENCER_ADDRESS.clone()),
             blocks: HashMap::default(),
             enforce_l1_handler_fee: true,
+            starknet_version,
+            versioned_constants,
+            bouncer_limits: BouncerWeights::max(),
+            class_cache: None,
         }
     }
 }
@@ -202,4 +337,78 @@ mod tests {
             felt_str!("393402129659245999442226"),
         );
     }
+
+    #[test]
+    fn fee_type_from_version() {
+        assert_eq!(FeeType::from_version(&0.into()), FeeType::Eth);
+        assert_eq!(FeeType::from_version(&1.into()), FeeType::Eth);
+        assert_eq!(
+            FeeType::from_version(&(&Felt252::from(2) | &*QUERY_VERSION_BASE)),
+            FeeType::Eth
+        );
+        assert_eq!(FeeType::from_version(&3.into()), FeeType::Strk);
+        assert_eq!(
+            FeeType::from_version(&(&Felt252::from(3) | &*QUERY_VERSION_BASE)),
+            FeeType::Strk
+        );
+    }
+
+    #[test]
+    fn fee_token_and_gas_price_by_type() {
+        let config = StarknetOsConfig::new(
+            StarknetChainId::TestNet.to_felt(),
+            Address(1.into()),
+            Address(2.into()),
+        );
+        assert_eq!(
+            config.fee_token_address_by_type(FeeType::Eth),
+            &Address(1.into())
+        );
+        assert_eq!(
+            config.fee_token_address_by_type(FeeType::Strk),
+            &Address(2.into())
+        );
+
+        let block_info = BlockInfo {
+            gas_price: 10,
+            strk_gas_price: 20,
+            data_gas_price: 1,
+            strk_data_gas_price: 2,
+            ..Default::default()
+        };
+        assert_eq!(block_info.gas_price_by_type(FeeType::Eth), 10);
+        assert_eq!(block_info.gas_price_by_type(FeeType::Strk), 20);
+        assert_eq!(block_info.data_gas_price_by_type(FeeType::Eth), 1);
+        assert_eq!(block_info.data_gas_price_by_type(FeeType::Strk), 2);
+    }
+
+    #[test]
+    fn block_context_with_versioned_constants() {
+        let mut block_context = BlockContext::default();
+        block_context.invoke_tx_max_n_steps = 10;
+
+        let block_context = block_context
+            .with_starknet_version("0.12.2".parse().unwrap())
+            .unwrap();
+        assert_eq!(
+            block_context.starknet_version(),
+            StarknetVersion::new(0, 12, 2)
+        );
+        assert_eq!(
+            block_context.invoke_tx_max_n_steps(),
+            block_context.versioned_constants().invoke_tx_max_n_steps()
+        );
+
+        let block_context = block_context
+            .with_starknet_version(StarknetVersion::new(0, 13, 1))
+            .unwrap();
+        assert_eq!(
+            block_context.cairo_resource_fee_weights(),
+            VersionedConstants::latest().1.cairo_resource_fee_weights()
+        );
+
+        assert!(BlockContext::default()
+            .with_starknet_version(StarknetVersion::new(0, 10, 3))
+            .is_err());
+    }
 }
@@ -1,4 +1,7 @@
-use super::block_context::{StarknetChainId, StarknetOsConfig};
+use super::{
+    block_context::{StarknetChainId, StarknetOsConfig},
+    versioned_constants::{StarknetVersion, VersionedConstants},
+};
 use crate::utils::Address;
 use cairo_vm::felt::{felt_str, Felt252};
 use lazy_static::lazy_static;
@@ -26,38 +29,29 @@ pub(crate) const LOG_MSG_TO_L1_ENCODED_DATA_SIZE: usize =
 /// actual_fee = min(max_fee, consumed_resources) * FEE_FACTOR
 pub(crate) const FEE_FACTOR: u128 = 1;
 
-/// The (empirical) L1 gas cost of each Cairo step.
-pub(crate) const N_STEPS_FEE_WEIGHT: f64 = 0.01;
-
 /// The version is considered 0 for L1-Handler transaction hash calculation purposes.
 pub(crate) const L1_HANDLER_VERSION: u64 = 0;
 
 lazy_static! {
-    pub static ref SUPPORTED_VERSIONS: [Felt252; 6] = [
+    pub static ref SUPPORTED_VERSIONS: [Felt252; 8] = [
         0.into(),
         1.into(),
         2.into(),
+        3.into(),
         &0.into() | &QUERY_VERSION_BASE.clone(),
         &1.into() | &QUERY_VERSION_BASE.clone(),
         &2.into() | &QUERY_VERSION_BASE.clone(),
+        &3.into() | &QUERY_VERSION_BASE.clone(),
     ];
 }
 
 lazy_static! {
-    // Ratios are taken from the `starknet_instance` CairoLayout object in cairo-lang.
+    /// The fee weights of the Cairo resources in [DEFAULT_STARKNET_VERSION].
     pub static ref DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS: HashMap<String, f64> =
-        HashMap::from([
-            ("n_steps".to_string(), N_STEPS_FEE_WEIGHT),
-            ("output_builtin".to_string(), 0.0),
-            ("pedersen_builtin".to_string(), N_STEPS_FEE_WEIGHT * 32.0),
-            ("range_check_builtin".to_string(), N_STEPS_FEE_WEIGHT * 16.0),
-            ("ecdsa_builtin".to_string(), N_STEPS_FEE_WEIGHT * 2048.0),
-            ("bitwise_builtin".to_string(), N_STEPS_FEE_WEIGHT * 64.0),
-            ("ec_op_builtin".to_string(), N_STEPS_FEE_WEIGHT * 1024.0),
-            ("poseidon_builtin".to_string(), N_STEPS_FEE_WEIGHT * 32.0),
-            ("segment_arena_builtin".to_string(), N_STEPS_FEE_WEIGHT * 10.0),
-            ("keccak_builtin".to_string(), N_STEPS_FEE_WEIGHT * 2048.0), // 2**11
-    ]);
+        VersionedConstants::for_starknet_version(DEFAULT_STARKNET_VERSION)
+            .expect("There are bundled constants for the default Starknet version")
+            .cairo_resource_fee_weights()
+            .clone();
     pub static ref DEFAULT_SEQUENCER_ADDRESS: Address = Address(felt_str!(
         "3711666a3506c99c9d78c4d4013409a87a962b7a0880a1c24af9fe193dafc01",
         16
@@ -68,11 +62,16 @@ lazy_static! {
             "4c07059285c2607d528a4c5220ef1f64d8f01273c23cfd9dec68759f61b544",
             16
         )),
-        gas_price: 0,
+        strk_fee_token_address: Address(felt_str!(
+            "4718f5a0fc34cc1af16a1cdee98ffb20c31f5cd61d6ab07201858f4287c938d",
+            16
+        )),
     };
 
 pub static ref DECLARE_VERSION: Felt252 = 2.into();
 pub static ref TRANSACTION_VERSION: Felt252 = 1.into();
+/// The version of the transactions that pay their fees in STRK, with resource bounds.
+pub static ref V3_TRANSACTION_VERSION: Felt252 = 3.into();
 }
 
 pub const DEFAULT_GAS_PRICE: u128 = 100_000_000_000; // 100 * 10**9
@@ -80,6 +79,10 @@ pub const DEFAULT_CONTRACT_STORAGE_COMMITMENT_TREE_HEIGHT: u64 = 251;
 pub const DEFAULT_GLOBAL_STATE_COMMITMENT_TREE_HEIGHT: u64 = 251;
 pub const DEFAULT_INVOKE_TX_MAX_N_STEPS: u64 = 1000000;
 pub const DEFAULT_VALIDATE_MAX_N_STEPS: u64 = 1000000;
+/// The Starknet version whose bundled constants `BlockContext::default` and the defaults above
+/// are taken from. Blocks of other versions are executed with
+/// `BlockContext::with_starknet_version`.
+pub const DEFAULT_STARKNET_VERSION: StarknetVersion = StarknetVersion::new(0, 12, 0);
 
 // Gas Cost.
 // From cairo_programs/constants.cairo.
@@ -1,3 +1,4 @@
 pub mod block_context;
 pub mod constants;
 pub mod transaction_type;
+pub mod versioned_constants;
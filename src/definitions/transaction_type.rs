@@ -1,7 +1,9 @@
+use serde::Deserialize;
+
 /// TransactionType is an enum that represents the type of transaction.
 ///
 /// It is used in the transaction header and in the transaction execution info.
-#[derive(Debug, PartialEq, Copy, Clone, Eq, Hash)]
+#[derive(Debug, PartialEq, Copy, Clone, Eq, Hash, Deserialize)]
 pub enum TransactionType {
     Declare,
     Deploy,
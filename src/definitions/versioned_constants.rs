@@ -0,0 +1,251 @@
+//! # Versioned Constants
+//!
+//! This module contains the constants of the Starknet protocol that change between its
+//! versions: the fee weights of the Cairo resources, the step limits, the gas costs of the
+//! syscalls and the resources used by the OS to run transactions and syscalls.
+
+use crate::execution::os_usage::OsResources;
+use getset::{CopyGetters, Getters};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use std::{collections::HashMap, fmt, fs, path::Path, str::FromStr, sync::Arc};
+use thiserror::Error;
+
+/// A version of the Starknet protocol, e.g. `0.12.3`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StarknetVersion {
+    major: u32,
+    minor: u32,
+    patch: u32,
+}
+
+impl StarknetVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        StarknetVersion {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl FromStr for StarknetVersion {
+    type Err = VersionedConstantsError;
+
+    /// Parses a version such as `0.12` or `0.12.3`. Components after the patch, like the
+    /// fourth one of `0.13.1.1`, don't change the constants and are ignored.
+    fn from_str(version: &str) -> Result<Self, Self::Err> {
+        let invalid = || VersionedConstantsError::InvalidStarknetVersion(version.to_string());
+        let components = version
+            .split('.')
+            .map(|component| component.parse::<u32>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+        match components[..] {
+            [major, minor] => Ok(StarknetVersion::new(major, minor, 0)),
+            [major, minor, patch, ..] => Ok(StarknetVersion::new(major, minor, patch)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for StarknetVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum VersionedConstantsError {
+    #[error("Invalid Starknet version: {0}")]
+    InvalidStarknetVersion(String),
+    #[error("No versioned constants for Starknet version {0}")]
+    UnsupportedStarknetVersion(StarknetVersion),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// The constants of a version of the Starknet protocol.
+#[derive(Clone, Debug, PartialEq, Deserialize, Getters, CopyGetters)]
+pub struct VersionedConstants {
+    /// Weights used when calculating transaction fees.
+    #[get = "pub"]
+    pub(crate) cairo_resource_fee_weights: HashMap<String, f64>,
+    /// Maximum number of steps allowed when executing transactions.
+    #[get_copy = "pub"]
+    pub(crate) invoke_tx_max_n_steps: u64,
+    /// Maximum number of steps allowed when validating transactions.
+    #[get_copy = "pub"]
+    pub(crate) validate_max_n_steps: u64,
+    /// The gas cost of each syscall, including the base cost of a syscall.
+    #[get = "pub"]
+    pub(crate) syscall_gas_costs: HashMap<String, u128>,
+    /// The resources used by the OS to run each type of transaction and syscall.
+    #[get = "pub"]
+    pub(crate) os_resources: OsResources,
+}
+
+/// Parses the bundled constants of a Starknet version.
+fn preset(version: StarknetVersion, json: &str) -> (StarknetVersion, Arc<VersionedConstants>) {
+    let constants = VersionedConstants::from_json(json).unwrap_or_else(|err| {
+        panic!("The bundled constants of Starknet {version} are invalid: {err}")
+    });
+    (version, Arc::new(constants))
+}
+
+lazy_static! {
+    /// The bundled constants, sorted by the version they were introduced in. Each preset
+    /// applies to its version and the ones after it, up to the next preset: the constants of
+    /// 0.11.0 apply to all of 0.11.x and 0.12.x.
+    static ref PRESETS: Vec<(StarknetVersion, Arc<VersionedConstants>)> = vec![
+        preset(
+            StarknetVersion::new(0, 11, 0),
+            include_str!("versioned_constants/0_11_0.json"),
+        ),
+        preset(
+            StarknetVersion::new(0, 13, 0),
+            include_str!("versioned_constants/0_13_0.json"),
+        ),
+        preset(
+            StarknetVersion::new(0, 13, 1),
+            include_str!("versioned_constants/0_13_1.json"),
+        ),
+    ];
+}
+
+impl VersionedConstants {
+    /// Parses constants from their JSON representation, in the format of the bundled presets.
+    pub fn from_json(json: &str) -> Result<Self, VersionedConstantsError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Reads constants from a JSON file, in the format of the bundled presets.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, VersionedConstantsError> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Returns the bundled constants of the given Starknet version.
+    pub fn for_starknet_version(
+        version: StarknetVersion,
+    ) -> Result<Arc<Self>, VersionedConstantsError> {
+        PRESETS
+            .iter()
+            .rev()
+            .find(|(preset_version, _)| *preset_version <= version)
+            .map(|(_, constants)| constants.clone())
+            .ok_or(VersionedConstantsError::UnsupportedStarknetVersion(version))
+    }
+
+    /// Returns the latest supported Starknet version with its bundled constants.
+    pub fn latest() -> (StarknetVersion, Arc<Self>) {
+        let (version, constants) = PRESETS.last().expect("There is a bundled preset");
+        (*version, constants.clone())
+    }
+
+    /// Returns the gas cost of the given syscall, if it has one.
+    pub fn syscall_gas_cost(&self, syscall_name: &str) -> Option<u128> {
+        self.syscall_gas_costs.get(syscall_name).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definitions::constants::{
+        DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS, DEFAULT_INVOKE_TX_MAX_N_STEPS,
+        DEFAULT_STARKNET_VERSION, DEFAULT_VALIDATE_MAX_N_STEPS,
+    };
+    use coverage_helper::test;
+
+    #[test]
+    fn parse_starknet_version() {
+        assert_eq!(
+            "0.12".parse::<StarknetVersion>().unwrap(),
+            StarknetVersion::new(0, 12, 0)
+        );
+        assert_eq!(
+            "0.13.1.1".parse::<StarknetVersion>().unwrap(),
+            StarknetVersion::new(0, 13, 1)
+        );
+        assert_eq!(StarknetVersion::new(0, 12, 3).to_string(), "0.12.3");
+        assert_matches!(
+            "0.x".parse::<StarknetVersion>(),
+            Err(VersionedConstantsError::InvalidStarknetVersion(_))
+        );
+    }
+
+    #[test]
+    fn constants_are_selected_by_starknet_version() {
+        let constants = VersionedConstants::for_starknet_version(DEFAULT_STARKNET_VERSION).unwrap();
+        assert_eq!(
+            constants.cairo_resource_fee_weights(),
+            &*DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS
+        );
+        assert_eq!(
+            constants.invoke_tx_max_n_steps(),
+            DEFAULT_INVOKE_TX_MAX_N_STEPS
+        );
+        assert_eq!(
+            constants.validate_max_n_steps(),
+            DEFAULT_VALIDATE_MAX_N_STEPS
+        );
+        assert_eq!(constants.syscall_gas_cost("storage_read"), Some(15000));
+        assert_eq!(constants.cairo_resource_fee_weights()["n_steps"], 0.01);
+        assert_eq!(
+            VersionedConstants::for_starknet_version(StarknetVersion::new(0, 11, 2)).unwrap(),
+            constants
+        );
+        assert_eq!(
+            VersionedConstants::for_starknet_version(StarknetVersion::new(0, 12, 3)).unwrap(),
+            constants
+        );
+
+        let constants =
+            VersionedConstants::for_starknet_version(StarknetVersion::new(0, 13, 0)).unwrap();
+        assert_eq!(constants.cairo_resource_fee_weights()["n_steps"], 0.005);
+        assert_eq!(constants.invoke_tx_max_n_steps(), 3000000);
+        assert_eq!(constants.syscall_gas_cost("storage_read"), Some(15000));
+
+        let constants =
+            VersionedConstants::for_starknet_version(StarknetVersion::new(0, 13, 1)).unwrap();
+        assert_eq!(constants.cairo_resource_fee_weights()["n_steps"], 0.0025);
+        assert_eq!(constants.invoke_tx_max_n_steps(), 4000000);
+        assert_eq!(
+            VersionedConstants::latest(),
+            (StarknetVersion::new(0, 13, 1), constants)
+        );
+
+        assert_matches!(
+            VersionedConstants::for_starknet_version(StarknetVersion::new(0, 10, 3)),
+            Err(VersionedConstantsError::UnsupportedStarknetVersion(_))
+        );
+    }
+
+    #[test]
+    fn constants_are_loaded_from_json() {
+        let json = r#"{
+            "cairo_resource_fee_weights": { "n_steps": 0.005 },
+            "invoke_tx_max_n_steps": 3000000,
+            "validate_max_n_steps": 1000000,
+            "syscall_gas_costs": { "storage_read": 100 },
+            "os_resources": {
+                "execute_syscalls": {
+                    "storage_read": { "n_steps": 87, "builtin_instance_counter": { "range_check_builtin": 1 } }
+                },
+                "execute_txs_inner": {
+                    "Declare": { "n_steps": 2703 },
+                    "InvokeFunction": {
+                        "constant": { "n_steps": 3546 },
+                        "calldata_factor": { "n_steps": 8 }
+                    }
+                }
+            }
+        }"#;
+        let constants = VersionedConstants::from_json(json).unwrap();
+        assert_eq!(constants.invoke_tx_max_n_steps(), 3000000);
+        assert_eq!(constants.cairo_resource_fee_weights()["n_steps"], 0.005);
+        assert_eq!(constants.syscall_gas_cost("storage_read"), Some(100));
+        assert_eq!(constants.syscall_gas_cost("storage_write"), None);
+    }
+}
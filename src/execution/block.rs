@@ -0,0 +1,186 @@
+use super::{
+    bouncer::{Bouncer, BouncerWeights, TransactionFit},
+    Event, L2toL1MessageInfo, TransactionExecutionInfo,
+};
+use crate::{
+    core::errors::state_errors::StateError,
+    definitions::{
+        block_context::{BlockContext, FeeType},
+        constants::INITIAL_GAS_COST,
+    },
+    state::{
+        cached_state::{CachedState, ContractClassCache},
+        shared_class_cache::SharedClassCache,
+        state_api::{State, StateReader},
+        StateDiff,
+    },
+    transaction::{error::TransactionError, Transaction},
+};
+use cairo_vm::felt::Felt252;
+use std::{collections::HashMap, sync::Arc};
+
+/// The outputs of executing a block with [crate::execute_block] or
+/// [crate::execute_block_parallel].
+#[derive(Debug, Default)]
+pub struct BlockExecutionOutput {
+    /// The execution infos of the transactions included in the block, either succeeded or
+    /// reverted, with the index of each transaction in the block.
+    pub execution_infos: Vec<(usize, TransactionExecutionInfo)>,
+    /// The transactions left out of the block, in the order they were executed.
+    pub rejected_transactions: Vec<RejectedTransaction>,
+    /// The changes made to the state by the block.
+    pub state_diff: StateDiff,
+    /// The sum of the resources used by the included transactions.
+    pub total_resources: HashMap<String, usize>,
+    /// The sum of the fees charged to the included transactions, by the token they were paid in.
+    pub total_fees: HashMap<FeeType, u128>,
+    /// The events emitted by the included transactions, in the order they were emitted.
+    pub events: Vec<Event>,
+    /// The messages sent to L1 by the included transactions, in the order they were sent.
+    pub l2_to_l1_messages: Vec<L2toL1MessageInfo>,
+    /// The sum of the weights of the included transactions, as tracked by the [Bouncer].
+    pub block_weights: BouncerWeights,
+    /// The index of the transaction that didn't fit in the block, which closed it. Neither it nor
+    /// the transactions after it were included or rejected, so they can go in the next block.
+    pub overflowing_transaction: Option<usize>,
+}
+
+/// A transaction that can't be included in a block, because its execution failed before it
+/// could be charged, e.g. because of an invalid nonce or a failed validation.
+#[derive(Debug)]
+pub struct RejectedTransaction {
+    /// The index of the transaction in the block.
+    pub index: usize,
+    pub transaction_hash: Felt252,
+    pub error: TransactionError,
+}
+
+impl BlockExecutionOutput {
+    /// Adds the result of a transaction to the outputs of the block. The writes of an included
+    /// transaction are applied to `state`, and the transactions after it are executed over them.
+    /// A transaction that doesn't fit in the block according to `bouncer` is not included, and
+    /// its writes are discarded.
+    ///
+    /// Returns whether the transaction was rejected as too large for the block after its
+    /// execution, in which case the transactions after it may have read its discarded writes.
+    pub(crate) fn add<S: StateReader>(
+        &mut self,
+        index: usize,
+        transaction: &Transaction,
+        result: IsolatedResult,
+        state: &mut CachedState<S>,
+        bouncer: &mut Bouncer,
+    ) -> Result<bool, TransactionError> {
+        let (execution_info, writes) = match result {
+            Ok(included) => included,
+            Err(error) => {
+                self.reject(index, transaction, error);
+                return Ok(false);
+            }
+        };
+        let tx_weights =
+            BouncerWeights::of_transaction(transaction, &execution_info, &writes.state_diff)?;
+        match bouncer.try_add(&tx_weights, &writes.state_diff) {
+            TransactionFit::Fits => {}
+            TransactionFit::BlockFull => {
+                self.overflowing_transaction = Some(index);
+                return Ok(false);
+            }
+            TransactionFit::TooLarge(limit) => {
+                self.reject(
+                    index,
+                    transaction,
+                    TransactionError::TransactionTooLargeForBlock(limit),
+                );
+                return Ok(true);
+            }
+        }
+        writes.apply(state)?;
+
+        self.events.extend(execution_info.get_sorted_events()?);
+        self.l2_to_l1_messages
+            .extend(execution_info.get_sorted_l2_to_l1_messages()?);
+        for (resource, amount) in &execution_info.actual_resources {
+            *self.total_resources.entry(resource.clone()).or_default() += amount;
+        }
+        *self
+            .total_fees
+            .entry(FeeType::from_version(&transaction.version()))
+            .or_default() += execution_info.actual_fee;
+        self.execution_infos.push((index, execution_info));
+        Ok(false)
+    }
+
+    /// Computes the state diff of the block, once the writes of every included transaction have
+    /// been applied to `state`.
+    pub(crate) fn finish<S: StateReader>(
+        &mut self,
+        state: &mut CachedState<S>,
+        bouncer: &Bouncer,
+    ) -> Result<(), StateError> {
+        // Transactions read the block state without recording its initial values.
+        state.update_initial_values_of_write_only_accesses()?;
+        self.state_diff = StateDiff::from_cached_state_ref(state)?;
+        self.block_weights = bouncer.block_weights().clone();
+        Ok(())
+    }
+
+    fn reject(&mut self, index: usize, transaction: &Transaction, error: TransactionError) {
+        self.rejected_transactions.push(RejectedTransaction {
+            index,
+            transaction_hash: transaction.hash_value(),
+            error,
+        });
+    }
+}
+
+/// The result of executing a transaction with [execute_isolated].
+pub(crate) type IsolatedResult =
+    Result<(TransactionExecutionInfo, TransactionWrites), TransactionError>;
+
+/// The changes a transaction made to the state it was executed on.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TransactionWrites {
+    pub(crate) state_diff: StateDiff,
+    /// The classes declared by the transaction.
+    pub(crate) contract_classes: ContractClassCache,
+}
+
+impl TransactionWrites {
+    /// Applies the writes to `state`.
+    pub(crate) fn apply<S: StateReader>(
+        &self,
+        state: &mut CachedState<S>,
+    ) -> Result<(), StateError> {
+        state.apply_state_update(&self.state_diff)?;
+        for (class_hash, contract_class) in &self.contract_classes {
+            state.set_contract_class(class_hash, contract_class)?;
+        }
+        Ok(())
+    }
+}
+
+/// Executes a transaction on a cached state of its own over `state_reader`, which holds the
+/// state left by the transactions before it in the block. Returns the execution info of the
+/// transaction together with its writes, so that every transaction is charged for its own state
+/// changes only.
+pub(crate) fn execute_isolated<R: StateReader>(
+    transaction: &Transaction,
+    block_context: &BlockContext,
+    state_reader: R,
+) -> IsolatedResult {
+    // The classes read are kept out of `contract_classes`, which then only holds the declared
+    // ones. Without a class cache in the block context, they're cached for this transaction only.
+    let class_cache = block_context
+        .class_cache()
+        .clone()
+        .unwrap_or_else(|| Arc::new(SharedClassCache::with_limits(None, None)));
+    let mut state = CachedState::new_with_class_cache(Arc::new(state_reader), class_cache);
+    let execution_info = transaction.execute(&mut state, block_context, INITIAL_GAS_COST)?;
+    let contract_classes = std::mem::take(&mut state.contract_classes);
+    let writes = TransactionWrites {
+        state_diff: StateDiff::from_cached_state(state)?,
+        contract_classes,
+    };
+    Ok((execution_info, writes))
+}
@@ -0,0 +1,377 @@
+use super::{gas_usage::get_message_segment_lenght, TransactionExecutionInfo};
+use crate::{
+    state::StateDiff,
+    transaction::{error::TransactionError, Transaction},
+    utils::{Address, ClassHash},
+};
+use cairo_vm::felt::Felt252;
+use std::collections::{HashMap, HashSet};
+
+/// The weights of a transaction or a block, i.e. how much of the capacity of a block they take.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BouncerWeights {
+    pub n_steps: usize,
+    /// The instances of each builtin. A builtin missing from the limits of a block has no limit.
+    pub builtin_count: HashMap<String, usize>,
+    /// The L1 gas used besides the Cairo resources, as in the `l1_gas_usage` resource.
+    pub l1_gas: usize,
+    /// The L1 data gas used by the blobs of the block, as in the `l1_data_gas_usage` resource.
+    pub l1_data_gas: usize,
+    /// The amount of words added to the on-chain data by the state diff. The weights of a block
+    /// count each contract, storage cell and class once, however many of its transactions
+    /// changed it.
+    pub state_diff_size: usize,
+    /// The amount of words added to the messages segment, as in [get_message_segment_lenght].
+    pub message_segment_length: usize,
+    pub n_events: usize,
+}
+
+impl BouncerWeights {
+    /// Returns weights that work as limits that no block reaches.
+    pub fn max() -> Self {
+        BouncerWeights {
+            n_steps: usize::MAX,
+            builtin_count: HashMap::new(),
+            l1_gas: usize::MAX,
+            l1_data_gas: usize::MAX,
+            state_diff_size: usize::MAX,
+            message_segment_length: usize::MAX,
+            n_events: usize::MAX,
+        }
+    }
+
+    /// Computes the weights of an executed transaction.
+    ///
+    /// ## Parameters
+    /// - transaction: The executed transaction.
+    /// - execution_info: The execution info of the transaction.
+    /// - state_diff: The changes made to the state by the transaction alone.
+    pub fn of_transaction(
+        transaction: &Transaction,
+        execution_info: &TransactionExecutionInfo,
+        state_diff: &StateDiff,
+    ) -> Result<Self, TransactionError> {
+        let builtin_count = execution_info
+            .actual_resources
+            .iter()
+            .filter(|(resource, _)| {
+                !["n_steps", "l1_gas_usage", "l1_data_gas_usage"].contains(&resource.as_str())
+            })
+            .map(|(builtin, count)| (builtin.clone(), *count))
+            .collect();
+        let l1_handler_payload_size = match transaction {
+            Transaction::L1Handler(l1_handler) => Some(l1_handler.get_payload_size()),
+            _ => None,
+        };
+
+        Ok(BouncerWeights {
+            n_steps: get_resource(execution_info, "n_steps"),
+            builtin_count,
+            l1_gas: get_resource(execution_info, "l1_gas_usage"),
+            l1_data_gas: get_resource(execution_info, "l1_data_gas_usage"),
+            state_diff_size: StateDiffKeys::of(state_diff).size(),
+            message_segment_length: get_message_segment_lenght(
+                &execution_info.get_sorted_l2_to_l1_messages()?,
+                l1_handler_payload_size,
+            ),
+            n_events: execution_info.get_sorted_events()?.len(),
+        })
+    }
+
+    /// Returns the sum of both weights. The state diff sizes are summed too, see [Bouncer] for
+    /// the size of the state diff of a block.
+    pub fn add(&self, other: &BouncerWeights) -> BouncerWeights {
+        let mut builtin_count = self.builtin_count.clone();
+        for (builtin, count) in &other.builtin_count {
+            let total = builtin_count.entry(builtin.clone()).or_default();
+            *total = total.saturating_add(*count);
+        }
+        BouncerWeights {
+            n_steps: self.n_steps.saturating_add(other.n_steps),
+            builtin_count,
+            l1_gas: self.l1_gas.saturating_add(other.l1_gas),
+            l1_data_gas: self.l1_data_gas.saturating_add(other.l1_data_gas),
+            state_diff_size: self.state_diff_size.saturating_add(other.state_diff_size),
+            message_segment_length: self
+                .message_segment_length
+                .saturating_add(other.message_segment_length),
+            n_events: self.n_events.saturating_add(other.n_events),
+        }
+    }
+
+    /// Returns the name of a weight above its limit in `limits`, if there is any.
+    pub fn exceeded_limit(&self, limits: &BouncerWeights) -> Option<String> {
+        let weights = [
+            ("n_steps", self.n_steps, limits.n_steps),
+            ("l1_gas", self.l1_gas, limits.l1_gas),
+            ("l1_data_gas", self.l1_data_gas, limits.l1_data_gas),
+            (
+                "state_diff_size",
+                self.state_diff_size,
+                limits.state_diff_size,
+            ),
+            (
+                "message_segment_length",
+                self.message_segment_length,
+                limits.message_segment_length,
+            ),
+            ("n_events", self.n_events, limits.n_events),
+        ];
+        if let Some((name, _, _)) = weights.iter().find(|(_, weight, limit)| weight > limit) {
+            return Some(name.to_string());
+        }
+        self.builtin_count
+            .iter()
+            .find(|(builtin, count)| {
+                limits
+                    .builtin_count
+                    .get(*builtin)
+                    .map_or(false, |limit| *count > limit)
+            })
+            .map(|(builtin, _)| builtin.clone())
+    }
+}
+
+/// Whether a transaction fits in the block of a [Bouncer].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TransactionFit {
+    /// The transaction fits, and its weights were added to the block.
+    Fits,
+    /// The transaction doesn't fit in what is left of the block, which should be closed. The
+    /// transaction can go in the next block.
+    BlockFull,
+    /// The transaction exceeds the named limit on its own, so it can't go in any block.
+    TooLarge(String),
+}
+
+/// Keeps track of the weights of the transactions added to a block, so that the block is closed
+/// before going over its limits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bouncer {
+    limits: BouncerWeights,
+    block_weights: BouncerWeights,
+    /// The parts of the state changed by the transactions added to the block.
+    state_diff_keys: StateDiffKeys,
+}
+
+impl Bouncer {
+    /// Creates a bouncer for an empty block with the given limits.
+    pub fn new(limits: BouncerWeights) -> Self {
+        Bouncer {
+            limits,
+            block_weights: BouncerWeights::default(),
+            state_diff_keys: StateDiffKeys::default(),
+        }
+    }
+
+    /// Returns the limits of the block.
+    pub fn limits(&self) -> &BouncerWeights {
+        &self.limits
+    }
+
+    /// Returns the sum of the weights of the transactions added to the block.
+    pub fn block_weights(&self) -> &BouncerWeights {
+        &self.block_weights
+    }
+
+    /// Checks whether a transaction fits in the block, and adds its weights to the block if it
+    /// does. Only the parts of the state that no transaction of the block changed before add to
+    /// the size of the state diff of the block.
+    ///
+    /// ## Parameters
+    /// - tx_weights: The weights of the transaction, see [BouncerWeights::of_transaction].
+    /// - tx_state_diff: The changes made to the state by the transaction alone.
+    pub fn try_add(
+        &mut self,
+        tx_weights: &BouncerWeights,
+        tx_state_diff: &StateDiff,
+    ) -> TransactionFit {
+        if let Some(limit) = tx_weights.exceeded_limit(&self.limits) {
+            return TransactionFit::TooLarge(limit);
+        }
+        let new_state_diff_keys =
+            StateDiffKeys::of(tx_state_diff).difference(&self.state_diff_keys);
+        let block_weights = BouncerWeights {
+            state_diff_size: self.block_weights.state_diff_size + new_state_diff_keys.size(),
+            ..self.block_weights.add(tx_weights)
+        };
+        if block_weights.exceeded_limit(&self.limits).is_some() {
+            return TransactionFit::BlockFull;
+        }
+        self.block_weights = block_weights;
+        self.state_diff_keys.extend(new_state_diff_keys);
+        TransactionFit::Fits
+    }
+}
+
+/// The parts of the state changed by one or more transactions, which make up the size of their
+/// state diff.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct StateDiffKeys {
+    modified_contracts: HashSet<Address>,
+    storage_cells: HashSet<(Address, Felt252)>,
+    declared_classes: HashSet<ClassHash>,
+}
+
+impl StateDiffKeys {
+    fn of(state_diff: &StateDiff) -> Self {
+        let modified_contracts = state_diff
+            .address_to_class_hash
+            .keys()
+            .chain(state_diff.address_to_nonce.keys())
+            .chain(state_diff.storage_updates.keys())
+            .cloned()
+            .collect();
+        let storage_cells = state_diff
+            .storage_updates
+            .iter()
+            .flat_map(|(address, updates)| updates.keys().map(|key| (address.clone(), key.clone())))
+            .collect();
+        let declared_classes = state_diff
+            .class_hash_to_compiled_class_hash
+            .keys()
+            .copied()
+            .collect();
+        StateDiffKeys {
+            modified_contracts,
+            storage_cells,
+            declared_classes,
+        }
+    }
+
+    /// Returns the amount of words the changes add to the on-chain data: two for each modified
+    /// contract, storage cell and declared class.
+    fn size(&self) -> usize {
+        2 * (self.modified_contracts.len() + self.storage_cells.len() + self.declared_classes.len())
+    }
+
+    /// Returns the keys that are not in `other`.
+    fn difference(self, other: &StateDiffKeys) -> StateDiffKeys {
+        StateDiffKeys {
+            modified_contracts: self
+                .modified_contracts
+                .into_iter()
+                .filter(|address| !other.modified_contracts.contains(address))
+                .collect(),
+            storage_cells: self
+                .storage_cells
+                .into_iter()
+                .filter(|cell| !other.storage_cells.contains(cell))
+                .collect(),
+            declared_classes: self
+                .declared_classes
+                .into_iter()
+                .filter(|class_hash| !other.declared_classes.contains(class_hash))
+                .collect(),
+        }
+    }
+
+    fn extend(&mut self, other: StateDiffKeys) {
+        self.modified_contracts.extend(other.modified_contracts);
+        self.storage_cells.extend(other.storage_cells);
+        self.declared_classes.extend(other.declared_classes);
+    }
+}
+
+fn get_resource(execution_info: &TransactionExecutionInfo, name: &str) -> usize {
+    execution_info
+        .actual_resources
+        .get(name)
+        .copied()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coverage_helper::test;
+
+    #[test]
+    fn bouncer_closes_the_block_before_its_limits() {
+        let mut bouncer = Bouncer::new(BouncerWeights {
+            n_steps: 100,
+            builtin_count: HashMap::from([("pedersen_builtin".to_string(), 10)]),
+            ..BouncerWeights::max()
+        });
+        let tx_weights = BouncerWeights {
+            n_steps: 40,
+            builtin_count: HashMap::from([
+                ("pedersen_builtin".to_string(), 2),
+                ("range_check_builtin".to_string(), 1000),
+            ]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            bouncer.try_add(&tx_weights, &StateDiff::default()),
+            TransactionFit::Fits
+        );
+        assert_eq!(
+            bouncer.try_add(&tx_weights, &StateDiff::default()),
+            TransactionFit::Fits
+        );
+        assert_eq!(
+            bouncer.try_add(&tx_weights, &StateDiff::default()),
+            TransactionFit::BlockFull
+        );
+        assert_eq!(bouncer.block_weights().n_steps, 80);
+        assert_eq!(bouncer.block_weights().builtin_count["pedersen_builtin"], 4);
+
+        let large_tx_weights = BouncerWeights {
+            builtin_count: HashMap::from([("pedersen_builtin".to_string(), 11)]),
+            ..Default::default()
+        };
+        assert_eq!(
+            bouncer.try_add(&large_tx_weights, &StateDiff::default()),
+            TransactionFit::TooLarge("pedersen_builtin".to_string())
+        );
+    }
+
+    #[test]
+    fn bouncer_counts_each_change_of_the_block_once() {
+        let mut bouncer = Bouncer::new(BouncerWeights {
+            state_diff_size: 10,
+            ..BouncerWeights::max()
+        });
+        let state_diff = |address: u64, key: u64| StateDiff {
+            address_to_nonce: HashMap::from([(Address(address.into()), 1.into())]),
+            storage_updates: HashMap::from([(
+                Address(address.into()),
+                HashMap::from([(key.into(), 1.into())]),
+            )]),
+            ..Default::default()
+        };
+        let tx_weights = BouncerWeights {
+            state_diff_size: 4,
+            ..Default::default()
+        };
+
+        // The second transaction changes the same contract and storage cell as the first one.
+        assert_eq!(
+            bouncer.try_add(&tx_weights, &state_diff(1, 1)),
+            TransactionFit::Fits
+        );
+        assert_eq!(
+            bouncer.try_add(&tx_weights, &state_diff(1, 1)),
+            TransactionFit::Fits
+        );
+        assert_eq!(bouncer.block_weights().state_diff_size, 4);
+        // A new storage cell of the same contract.
+        assert_eq!(
+            bouncer.try_add(&tx_weights, &state_diff(1, 2)),
+            TransactionFit::Fits
+        );
+        assert_eq!(bouncer.block_weights().state_diff_size, 6);
+        // A new contract and storage cell would take the block to 10 words.
+        assert_eq!(
+            bouncer.try_add(&tx_weights, &state_diff(2, 1)),
+            TransactionFit::Fits
+        );
+        assert_eq!(bouncer.block_weights().state_diff_size, 10);
+        assert_eq!(
+            bouncer.try_add(&tx_weights, &state_diff(3, 1)),
+            TransactionFit::BlockFull
+        );
+        assert_eq!(bouncer.block_weights().state_diff_size, 10);
+    }
+}
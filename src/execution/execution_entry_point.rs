@@ -4,7 +4,6 @@ use crate::services::api::contract_classes::deprecated_contract_class::{
     ContractEntryPoint, EntryPointType,
 };
 use crate::state::cached_state::CachedState;
-use crate::state::StateDiff;
 use crate::{
     definitions::{block_context::BlockContext, constants::DEFAULT_ENTRY_POINT_SELECTOR},
     runner::StarknetRunner,
@@ -34,7 +33,9 @@ use cairo_vm::{
         relocatable::{MaybeRelocatable, Relocatable},
     },
     vm::{
-        runners::cairo_runner::{CairoArg, CairoRunner, ExecutionResources, RunResources},
+        runners::cairo_runner::{
+            CairoArg, CairoRunner, ExecutionResources, ResourceTracker, RunResources,
+        },
         vm_core::VirtualMachine,
     },
 };
@@ -94,6 +95,11 @@ impl ExecutionEntryPoint {
     /// The information collected from this run (number of steps required, modifications to the
     /// contract storage, etc.) is saved on the resources manager.
     /// Returns a CallInfo object that represents the execution.
+    ///
+    /// The execution is stopped once it runs `max_steps` steps. If the execution fails, its
+    /// changes to the state are discarded. When `support_reverted` is set, the failure is returned
+    /// as a reverted [ExecutionResult] with the number of steps consumed before it, instead of an
+    /// error.
     pub fn execute<T>(
         &self,
         state: &mut CachedState<T>,
@@ -111,59 +117,59 @@ impl ExecutionEntryPoint {
         let contract_class = state
             .get_contract_class(&class_hash)
             .map_err(|_| TransactionError::MissingCompiledClass)?;
-        match contract_class {
-            CompiledClass::Deprecated(contract_class) => {
-                let call_info = self._execute_version0_class(
-                    state,
-                    resources_manager,
-                    block_context,
-                    tx_execution_context,
-                    contract_class,
-                    class_hash,
-                )?;
+
+        let previous_resources_manager = resources_manager.clone();
+        let checkpoint = state.checkpoint();
+        let result = match contract_class {
+            CompiledClass::Deprecated(contract_class) => self._execute_version0_class(
+                state,
+                resources_manager,
+                block_context,
+                tx_execution_context,
+                contract_class,
+                class_hash,
+                max_steps,
+            ),
+            CompiledClass::Casm(contract_class) => self._execute(
+                state,
+                resources_manager,
+                block_context,
+                tx_execution_context,
+                contract_class,
+                class_hash,
+                support_reverted,
+                max_steps,
+            ),
+        };
+
+        match result {
+            Ok(call_info) => {
+                state.commit(checkpoint)?;
                 Ok(ExecutionResult {
                     call_info: Some(call_info),
                     revert_error: None,
                     n_reverted_steps: 0,
                 })
             }
-            CompiledClass::Casm(contract_class) => {
-                let mut tmp_state =
-                    CachedState::new(state.state_reader.clone(), state.contract_classes.clone());
-                tmp_state.cache = state.cache.clone();
-
-                match self._execute(
-                    &mut tmp_state,
-                    resources_manager,
-                    block_context,
-                    tx_execution_context,
-                    contract_class,
-                    class_hash,
-                    support_reverted,
-                ) {
-                    Ok(call_info) => {
-                        let state_diff = StateDiff::from_cached_state(tmp_state)?;
-                        state.apply_state_update(&state_diff)?;
-                        Ok(ExecutionResult {
-                            call_info: Some(call_info),
-                            revert_error: None,
-                            n_reverted_steps: 0,
-                        })
-                    }
-                    Err(e) => {
-                        if !support_reverted {
-                            return Err(e);
-                        }
-
-                        let n_reverted_steps =
-                            (max_steps as usize) - resources_manager.cairo_usage.n_steps;
-                        Ok(ExecutionResult {
-                            call_info: None,
-                            revert_error: Some(e.to_string()),
-                            n_reverted_steps,
-                        })
-                    }
+            Err(e) => {
+                state.revert_to(checkpoint)?;
+                if !support_reverted {
+                    return Err(e);
                 }
+
+                // The reverted execution leaves no trace besides the steps it consumed before
+                // failing, which are still charged up to the step limit.
+                let n_reverted_steps = resources_manager
+                    .cairo_usage
+                    .n_steps
+                    .saturating_sub(previous_resources_manager.cairo_usage.n_steps)
+                    .min(max_steps as usize);
+                *resources_manager = previous_resources_manager;
+                Ok(ExecutionResult {
+                    call_info: None,
+                    revert_error: Some(e.to_string()),
+                    n_reverted_steps,
+                })
             }
         }
     }
@@ -332,6 +338,7 @@ impl ExecutionEntryPoint {
         tx_execution_context: &mut TransactionExecutionContext,
         contract_class: Arc<ContractClass>,
         class_hash: [u8; 32],
+        max_steps: u64,
     ) -> Result<CallInfo, TransactionError> {
         let previous_cairo_usage = resources_manager.cairo_usage.clone();
         // fetch selected entry point
@@ -367,8 +374,10 @@ impl ExecutionEntryPoint {
             block_context.clone(),
             initial_syscall_ptr,
         );
-        let hint_processor =
-            DeprecatedSyscallHintProcessor::new(syscall_handler, RunResources::default());
+        let hint_processor = DeprecatedSyscallHintProcessor::new(
+            syscall_handler,
+            RunResources::new(max_steps as usize),
+        );
         let mut runner = StarknetRunner::new(cairo_runner, vm, hint_processor);
 
         // Positional arguments are passed to *args in the 'run_from_entrypoint' function.
@@ -387,7 +396,22 @@ impl ExecutionEntryPoint {
         ];
 
         // cairo runner entry point
-        runner.run_from_entrypoint(entry_point.offset(), &entry_point_args, None)?;
+        if let Err(error) =
+            runner.run_from_entrypoint(entry_point.offset(), &entry_point_args, None)
+        {
+            // Keep the resources consumed until the failure, so that a revert can charge them.
+            *resources_manager = runner
+                .hint_processor
+                .syscall_handler
+                .resources_manager
+                .clone();
+            return Err(run_failure(
+                error,
+                &runner.hint_processor,
+                resources_manager,
+                max_steps,
+            ));
+        }
         runner.validate_and_process_os_context_for_version0_class(os_context)?;
 
         // When execution starts the stack holds entry_points_args + [ret_fp, ret_pc].
@@ -438,6 +462,7 @@ impl ExecutionEntryPoint {
         contract_class: Arc<CasmContractClass>,
         class_hash: [u8; 32],
         support_reverted: bool,
+        max_steps: u64,
     ) -> Result<CallInfo, TransactionError> {
         let previous_cairo_usage = resources_manager.cairo_usage.clone();
 
@@ -484,7 +509,7 @@ impl ExecutionEntryPoint {
         let hint_processor = SyscallHintProcessor::new(
             syscall_handler,
             &contract_class.hints,
-            RunResources::default(),
+            RunResources::new(max_steps as usize),
         );
         let mut runner = StarknetRunner::new(cairo_runner, vm, hint_processor);
 
@@ -528,11 +553,24 @@ impl ExecutionEntryPoint {
         let ref_vec: Vec<&CairoArg> = entrypoint_args.iter().collect();
 
         // run the Cairo1 entrypoint
-        runner.run_from_entrypoint(
+        if let Err(error) = runner.run_from_entrypoint(
             entry_point.offset,
             &ref_vec,
             Some(program.data_len() + program_extra_data.len()),
-        )?;
+        ) {
+            // Keep the resources consumed until the failure, so that a revert can charge them.
+            *resources_manager = runner
+                .hint_processor
+                .syscall_handler
+                .resources_manager
+                .clone();
+            return Err(run_failure(
+                error,
+                &runner.hint_processor,
+                resources_manager,
+                max_steps,
+            ));
+        }
 
         runner
             .vm
@@ -579,3 +617,20 @@ impl ExecutionEntryPoint {
         )
     }
 }
+
+/// Adds the steps run before a failure to the resources, and turns the failure into
+/// [TransactionError::StepsLimitExceeded] if the execution ran out of steps.
+fn run_failure(
+    error: TransactionError,
+    tracker: &impl ResourceTracker,
+    resources_manager: &mut ExecutionResourcesManager,
+    max_steps: u64,
+) -> TransactionError {
+    let remaining_steps = tracker.get_n_steps().unwrap_or_default();
+    resources_manager.cairo_usage.n_steps += (max_steps as usize).saturating_sub(remaining_steps);
+    if tracker.consumed() {
+        TransactionError::StepsLimitExceeded(max_steps)
+    } else {
+        error
+    }
+}
@@ -1,6 +1,47 @@
 use crate::definitions::constants::*;
 use crate::execution::L2toL1MessageInfo;
 use crate::services::eth_definitions::eth_gas_constants::*;
+use crate::state::L1DataAvailabilityMode;
+
+/// The L1 gas used by a transaction, split by what it pays for.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct L1GasUsage {
+    /// The gas of publishing the modified contracts and storage cells. It's L1 data gas when
+    /// the state diff is published in blobs.
+    pub storage_diffs: usize,
+    /// The gas of publishing the deployed contracts. It's L1 data gas when the state diff is
+    /// published in blobs.
+    pub contract_deployments: usize,
+    /// The gas of sending the messages to L1.
+    pub l2_to_l1_messages: usize,
+    /// The gas of consuming the message from L1 handled by an L1 handler.
+    pub l1_handler_payload: usize,
+    /// How the state diff is published.
+    pub l1_da_mode: L1DataAvailabilityMode,
+}
+
+impl L1GasUsage {
+    /// Returns the L1 gas used by the transaction.
+    pub fn l1_gas(&self) -> usize {
+        let data_availability_gas = match self.l1_da_mode {
+            L1DataAvailabilityMode::Calldata => self.data_availability(),
+            L1DataAvailabilityMode::Blob => 0,
+        };
+        self.l2_to_l1_messages + self.l1_handler_payload + data_availability_gas
+    }
+
+    /// Returns the L1 data gas used by the transaction.
+    pub fn l1_data_gas(&self) -> usize {
+        match self.l1_da_mode {
+            L1DataAvailabilityMode::Calldata => 0,
+            L1DataAvailabilityMode::Blob => self.data_availability(),
+        }
+    }
+
+    const fn data_availability(&self) -> usize {
+        self.storage_diffs + self.contract_deployments
+    }
+}
 
 /// Estimates L1 gas usage by Starknet's update state and the verifier
 ///
@@ -16,7 +57,7 @@ use crate::services::eth_definitions::eth_gas_constants::*;
 ///
 /// # Returns:
 ///
-/// The estimation of L1 gas usage as a `usize` value.
+/// The estimation of L1 gas usage as a `usize` value, with the state diff published in calldata.
 pub fn calculate_tx_gas_usage(
     l2_to_l1_messages: Vec<L2toL1MessageInfo>,
     n_modified_contracts: usize,
@@ -24,53 +65,70 @@ pub fn calculate_tx_gas_usage(
     l1_handler_payload_size: Option<usize>,
     n_deployments: usize,
 ) -> usize {
-    let residual_message_segment_length =
-        get_message_segment_lenght(&l2_to_l1_messages, l1_handler_payload_size);
+    calculate_tx_gas_usage_breakdown(
+        &l2_to_l1_messages,
+        n_modified_contracts,
+        n_storage_changes,
+        l1_handler_payload_size,
+        n_deployments,
+        L1DataAvailabilityMode::Calldata,
+    )
+    .l1_gas()
+}
+
+/// Estimates L1 gas usage by Starknet's update state and the verifier, split by what it pays
+/// for. The parameters are those of [calculate_tx_gas_usage], and:
+/// - `l1_da_mode`: How the state diff is published. In blobs, each of its words costs
+/// L1 data gas, as much as a field element of a blob, instead of L1 gas.
+///
+/// # Returns:
+///
+/// The estimation of L1 gas usage as an [`L1GasUsage`].
+pub fn calculate_tx_gas_usage_breakdown(
+    l2_to_l1_messages: &[L2toL1MessageInfo],
+    n_modified_contracts: usize,
+    n_storage_changes: usize,
+    l1_handler_payload_size: Option<usize>,
+    n_deployments: usize,
+    l1_da_mode: L1DataAvailabilityMode,
+) -> L1GasUsage {
+    // Each word of the message segment is paid both by Starknet and by the verifier.
+    let gas_per_message_word = GAS_PER_MEMORY_WORD + SHARP_GAS_PER_MEMORY_WORD;
 
-    let residual_onchain_data_segment_length =
-        get_onchain_data_segment_length(n_modified_contracts, n_storage_changes, n_deployments);
+    let l2_to_l1_messages_gas = get_message_segment_lenght(l2_to_l1_messages, None)
+        * gas_per_message_word
+        + l2_to_l1_messages.len() * GAS_PER_ZERO_TO_NONZERO_STORAGE_SET
+        + get_log_message_to_l1_emissions_cost(l2_to_l1_messages);
 
-    let n_l2_to_l1_messages = l2_to_l1_messages.len();
-    let n_l1_to_l2_messages = match l1_handler_payload_size {
-        Some(_size) => 1,
+    let l1_handler_payload_gas = match l1_handler_payload_size {
+        Some(size) => {
+            (L1_TO_L2_MSG_HEADER_SIZE + size) * gas_per_message_word
+                + GAS_PER_COUNTER_DECREASE
+                + get_consumed_message_to_l2_emissions_cost(l1_handler_payload_size)
+        }
         None => 0,
     };
 
-    let starknet_gas_usage = starknet_gas_usage(
-        residual_message_segment_length,
-        n_l2_to_l1_messages,
-        n_l1_to_l2_messages,
-        l1_handler_payload_size,
-        &l2_to_l1_messages,
-    );
-
-    let sharp_gas_usage = (residual_message_segment_length * SHARP_GAS_PER_MEMORY_WORD)
-        + (residual_onchain_data_segment_length * SHARP_GAS_PER_MEMORY_WORD);
+    let gas_per_data_word = match l1_da_mode {
+        L1DataAvailabilityMode::Calldata => SHARP_GAS_PER_MEMORY_WORD,
+        L1DataAvailabilityMode::Blob => DATA_GAS_PER_FIELD_ELEMENT,
+    };
 
-    starknet_gas_usage + sharp_gas_usage
+    L1GasUsage {
+        storage_diffs: get_onchain_data_segment_length(n_modified_contracts, n_storage_changes, 0)
+            * gas_per_data_word,
+        contract_deployments: get_onchain_data_segment_length(0, 0, n_deployments)
+            * gas_per_data_word,
+        l2_to_l1_messages: l2_to_l1_messages_gas,
+        l1_handler_payload: l1_handler_payload_gas,
+        l1_da_mode,
+    }
 }
 
 // ~~~~~~~~~~~~~~~~
 // Helper function
 // ~~~~~~~~~~~~~~~~
 
-fn starknet_gas_usage(
-    residual_msg_lenght: usize,
-    l2_to_l1_msg_len: usize,
-    l1_to_l2_msg_len: usize,
-    l1_handler: Option<usize>,
-    l2_to_l1_messages: &[L2toL1MessageInfo],
-) -> usize {
-    let l2_emissions_cost = get_consumed_message_to_l2_emissions_cost(l1_handler);
-    let l1_log_emissions_cost = get_log_message_to_l1_emissions_cost(l2_to_l1_messages);
-
-    (residual_msg_lenght * GAS_PER_MEMORY_WORD)
-        + (l2_to_l1_msg_len * GAS_PER_ZERO_TO_NONZERO_STORAGE_SET)
-        + (l1_to_l2_msg_len * GAS_PER_COUNTER_DECREASE)
-        + l2_emissions_cost
-        + l1_log_emissions_cost
-}
-
 /// Calculates the amount of `felt252` added to the output message's segment by the given messages.
 ///
 /// # Parameters:
@@ -265,4 +323,45 @@ mod test {
             77051
         )
     }
+
+    #[test]
+    fn transaction_gas_usage_breakdown_test() {
+        let ord_ev1 = OrderedL2ToL1Message::new(1, Address(1235.into()), vec![4.into()]);
+        let ord_ev2 = OrderedL2ToL1Message::new(2, Address(35.into()), vec![5.into(), 6.into()]);
+        let message1 = L2toL1MessageInfo::new(ord_ev1, Address(1234.into()));
+        let message2 = L2toL1MessageInfo::new(ord_ev2, Address(1235.into()));
+
+        let l1_gas_usage = calculate_tx_gas_usage_breakdown(
+            &[message1.clone(), message2.clone()],
+            2,
+            2,
+            Some(2),
+            1,
+            L1DataAvailabilityMode::Calldata,
+        );
+
+        // SHARP_GAS_PER_MEMORY_WORD = 612
+        // storage diffs: (2 * 2 + 2 * 2) * 612
+        assert_eq!(l1_gas_usage.storage_diffs, 4896);
+        // DEPLOYMENT_INFO_SIZE = 2
+        assert_eq!(l1_gas_usage.contract_deployments, 1224);
+        // message segment: (3 + 1) + (3 + 2) words
+        assert_eq!(l1_gas_usage.l2_to_l1_messages, 54908);
+        // message segment: 5 + 2 words
+        assert_eq!(l1_gas_usage.l1_handler_payload, 16023);
+        assert_eq!(l1_gas_usage.l1_gas(), 77051);
+        assert_eq!(l1_gas_usage.l1_data_gas(), 0);
+
+        // In blobs, the state diff costs DATA_GAS_PER_FIELD_ELEMENT = 32 data gas per word.
+        let l1_gas_usage = calculate_tx_gas_usage_breakdown(
+            &[message1, message2],
+            2,
+            2,
+            Some(2),
+            1,
+            L1DataAvailabilityMode::Blob,
+        );
+        assert_eq!(l1_gas_usage.l1_gas(), 54908 + 16023);
+        assert_eq!(l1_gas_usage.l1_data_gas(), (8 + 2) * 32);
+    }
 }
@@ -1,6 +1,12 @@
+pub mod block;
+pub mod bouncer;
 pub mod execution_entry_point;
 pub mod gas_usage;
 pub mod os_usage;
+pub(crate) mod parallel;
+pub mod receipt;
+pub mod simulation;
+pub mod trace;
 
 use crate::definitions::constants::QUERY_VERSION_BASE;
 use crate::services::api::contract_classes::deprecated_contract_class::EntryPointType;
@@ -9,7 +15,7 @@ use crate::{
     definitions::{constants::CONSTRUCTOR_ENTRY_POINT_SELECTOR, transaction_type::TransactionType},
     state::state_cache::StorageEntry,
     syscalls::syscall_handler_errors::SyscallHandlerError,
-    transaction::error::TransactionError,
+    transaction::{error::TransactionError, fee::FeeBreakdown, V3TransactionFields},
     utils::{get_big_int, get_integer, get_relocatable, Address, ClassHash},
 };
 use cairo_vm::felt::Felt252;
@@ -354,6 +360,9 @@ pub struct TransactionExecutionContext {
     pub(crate) nonce: Felt252,
     pub(crate) n_sent_messages: usize,
     pub(crate) _n_steps: u64,
+    /// The fields of version 3 transactions, or `None` for the previous versions.
+    #[get = "pub"]
+    pub(crate) v3_fields: Option<V3TransactionFields>,
 }
 
 impl TransactionExecutionContext {
@@ -382,9 +391,16 @@ impl TransactionExecutionContext {
             version,
             n_sent_messages: 0,
             _n_steps: n_steps,
+            v3_fields: None,
         }
     }
 
+    /// Sets the fields of the version 3 transaction the context belongs to.
+    pub fn with_v3_fields(mut self, v3_fields: Option<V3TransactionFields>) -> Self {
+        self.v3_fields = v3_fields;
+        self
+    }
+
     pub fn create_for_testing(
         account_contract_address: Address,
         _max_fee: u128,
@@ -402,6 +418,7 @@ impl TransactionExecutionContext {
             nonce,
             n_sent_messages: 0,
             _n_steps: n_steps,
+            v3_fields: None,
         }
     }
 }
@@ -491,6 +508,8 @@ pub struct TransactionExecutionInfo {
     pub actual_fee: u128,
     pub actual_resources: HashMap<String, usize>,
     pub tx_type: Option<TransactionType>,
+    /// How the fee of the transaction was calculated from `actual_resources`.
+    pub fee_breakdown: Option<FeeBreakdown>,
 }
 
 impl TransactionExecutionInfo {
@@ -511,6 +530,7 @@ impl TransactionExecutionInfo {
             actual_fee,
             actual_resources,
             tx_type,
+            fee_breakdown: None,
         }
     }
 
@@ -550,6 +570,7 @@ impl TransactionExecutionInfo {
             actual_fee: 0,
             actual_resources: HashMap::new(),
             tx_type,
+            fee_breakdown: None,
         }
     }
 
@@ -568,9 +589,15 @@ impl TransactionExecutionInfo {
             actual_fee: 0,
             actual_resources,
             tx_type,
+            fee_breakdown: None,
         }
     }
 
+    pub fn with_fee_breakdown(mut self, fee_breakdown: FeeBreakdown) -> Self {
+        self.fee_breakdown = Some(fee_breakdown);
+        self
+    }
+
     pub fn set_fee_info(&mut self, actual_fee: u128, fee_transfer_call_info: Option<CallInfo>) {
         self.actual_fee = actual_fee;
         self.fee_transfer_info = fee_transfer_call_info;
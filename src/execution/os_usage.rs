@@ -1,266 +1,124 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, hash::Hash};
 
 use cairo_vm::vm::runners::cairo_runner::ExecutionResources;
-
-use crate::{definitions::transaction_type::TransactionType, transaction::error::TransactionError};
-
-#[derive(Debug, Clone)]
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    definitions::{
+        constants::DEFAULT_STARKNET_VERSION, transaction_type::TransactionType,
+        versioned_constants::VersionedConstants,
+    },
+    transaction::error::TransactionError,
+};
+
+/// The resources used by the OS to run each type of transaction and syscall, on top of the
+/// resources used by the contracts themselves.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct OsResources {
+    #[serde(deserialize_with = "deserialize_resources")]
     execute_syscalls: HashMap<String, ExecutionResources>,
-    execute_txs_inner: HashMap<TransactionType, ExecutionResources>,
+    execute_txs_inner: HashMap<TransactionType, TransactionOsResources>,
 }
 
 impl Default for OsResources {
+    /// Returns the resources of the default Starknet version, see [DEFAULT_STARKNET_VERSION].
     fn default() -> Self {
-        let execute_txs_inner: HashMap<TransactionType, ExecutionResources> = HashMap::from([
-            (
-                TransactionType::InvokeFunction,
-                ExecutionResources {
-                    n_steps: 3363,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::from([
-                        ("pedersen_builtin".to_string(), 16),
-                        ("range_check_builtin".to_string(), 80),
-                    ]),
-                },
-            ),
-            (
-                TransactionType::Declare,
-                ExecutionResources {
-                    n_steps: 2703,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::from([
-                        ("pedersen_builtin".to_string(), 15),
-                        ("range_check_builtin".to_string(), 63),
-                    ]),
-                },
-            ),
-            (
-                TransactionType::Deploy,
-                ExecutionResources {
-                    n_steps: 0,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::new(),
-                },
-            ),
-            (
-                TransactionType::DeployAccount,
-                ExecutionResources {
-                    n_steps: 3612,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::from([
-                        ("pedersen_builtin".to_string(), 23),
-                        ("range_check_builtin".to_string(), 83),
-                    ]),
-                },
-            ),
-            (
-                TransactionType::L1Handler,
-                ExecutionResources {
-                    n_steps: 1068,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::from([
-                        ("pedersen_builtin".to_string(), 11),
-                        ("range_check_builtin".to_string(), 17),
-                    ]),
-                },
-            ),
-        ]);
+        VersionedConstants::for_starknet_version(DEFAULT_STARKNET_VERSION)
+            .expect("There are bundled constants for the default Starknet version")
+            .os_resources
+            .clone()
+    }
+}
+
+/// The resources used by the OS to run a transaction: a constant part, and since Starknet
+/// 0.13.1 a part that grows with the length of its calldata.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(from = "TransactionOsResourcesJson")]
+pub struct TransactionOsResources {
+    constant: ExecutionResources,
+    calldata_factor: ExecutionResources,
+}
+
+impl TransactionOsResources {
+    /// Returns the resources used by the OS to run a transaction with `calldata_length`
+    /// calldata felts.
+    pub fn for_calldata_length(&self, calldata_length: usize) -> ExecutionResources {
+        &self.constant + &(&self.calldata_factor * calldata_length)
+    }
+}
+
+/// The JSON representation of [ExecutionResources].
+#[derive(Deserialize)]
+struct ExecutionResourcesJson {
+    n_steps: usize,
+    #[serde(default)]
+    n_memory_holes: usize,
+    #[serde(default)]
+    builtin_instance_counter: HashMap<String, usize>,
+}
 
-        let execute_syscalls = HashMap::from([
-            (
-                "call_contract".to_string(),
-                ExecutionResources {
-                    n_steps: 690,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::from([(
-                        "range_check_builtin".to_string(),
-                        19,
-                    )]),
-                },
-            ),
-            (
-                "delegate_call".to_string(),
-                ExecutionResources {
-                    n_steps: 712,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::from([(
-                        "range_check_builtin".to_string(),
-                        19,
-                    )]),
-                },
-            ),
-            (
-                "delegate_l1_handler".to_string(),
-                ExecutionResources {
-                    n_steps: 691,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::from([(
-                        "range_check_builtin".to_string(),
-                        15,
-                    )]),
-                },
-            ),
-            (
-                "deploy".to_string(),
-                ExecutionResources {
-                    n_steps: 936,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::from([
-                        ("range_check_builtin".to_string(), 18),
-                        ("pedersen_builtin".to_string(), 7),
-                    ]),
-                },
-            ),
-            (
-                "library_call".to_string(),
-                ExecutionResources {
-                    n_steps: 679,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::from([(
-                        "range_check_builtin".to_string(),
-                        19,
-                    )]),
-                },
-            ),
-            (
-                "emit_event".to_string(),
-                ExecutionResources {
-                    n_steps: 19,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::new(),
-                },
-            ),
-            (
-                "get_block_hash".to_string(),
-                ExecutionResources {
-                    n_steps: 44,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::new(),
-                },
-            ),
-            (
-                "get_block_number".to_string(),
-                ExecutionResources {
-                    n_steps: 40,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::new(),
-                },
-            ),
-            (
-                "get_block_timestamp".to_string(),
-                ExecutionResources {
-                    n_steps: 38,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::new(),
-                },
-            ),
-            (
-                "get_caller_address".to_string(),
-                ExecutionResources {
-                    n_steps: 32,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::new(),
-                },
-            ),
-            (
-                "get_contract_address".to_string(),
-                ExecutionResources {
-                    n_steps: 36,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::new(),
-                },
-            ),
-            (
-                "get_execution_info".to_string(),
-                ExecutionResources {
-                    n_steps: 29,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::new(),
-                },
-            ),
-            (
-                "get_sequencer_address".to_string(),
-                ExecutionResources {
-                    n_steps: 34,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::new(),
-                },
-            ),
-            (
-                "get_tx_info".to_string(),
-                ExecutionResources {
-                    n_steps: 29,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::new(),
-                },
-            ),
-            (
-                "get_tx_signature".to_string(),
-                ExecutionResources {
-                    n_steps: 44,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::new(),
-                },
-            ),
-            (
-                "library_call_l1_handler".to_string(),
-                ExecutionResources {
-                    n_steps: 658,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::from([(
-                        "range_check_builtin".to_string(),
-                        15,
-                    )]),
-                },
-            ),
-            (
-                "replace_class".to_string(),
-                ExecutionResources {
-                    n_steps: 73,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::new(),
-                },
-            ),
-            (
-                "send_message_to_l1".to_string(),
-                ExecutionResources {
-                    n_steps: 84,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::new(),
-                },
-            ),
-            (
-                "storage_read".to_string(),
-                ExecutionResources {
-                    n_steps: 44,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::new(),
-                },
-            ),
-            (
-                "storage_write".to_string(),
-                ExecutionResources {
-                    n_steps: 46,
-                    n_memory_holes: 0,
-                    builtin_instance_counter: HashMap::new(),
-                },
-            ),
-        ]);
+/// The JSON representation of [TransactionOsResources]: either the constant resources alone, or
+/// both parts.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TransactionOsResourcesJson {
+    WithCalldataFactor {
+        constant: ExecutionResourcesJson,
+        calldata_factor: ExecutionResourcesJson,
+    },
+    Constant(ExecutionResourcesJson),
+}
 
-        OsResources {
-            execute_syscalls,
-            execute_txs_inner,
+impl From<ExecutionResourcesJson> for ExecutionResources {
+    fn from(resources: ExecutionResourcesJson) -> Self {
+        ExecutionResources {
+            n_steps: resources.n_steps,
+            n_memory_holes: resources.n_memory_holes,
+            builtin_instance_counter: resources.builtin_instance_counter,
         }
     }
 }
 
+impl From<TransactionOsResourcesJson> for TransactionOsResources {
+    fn from(resources: TransactionOsResourcesJson) -> Self {
+        match resources {
+            TransactionOsResourcesJson::WithCalldataFactor {
+                constant,
+                calldata_factor,
+            } => TransactionOsResources {
+                constant: constant.into(),
+                calldata_factor: calldata_factor.into(),
+            },
+            TransactionOsResourcesJson::Constant(constant) => TransactionOsResources {
+                constant: constant.into(),
+                calldata_factor: ExecutionResources::default(),
+            },
+        }
+    }
+}
+
+fn deserialize_resources<'de, D, K>(
+    deserializer: D,
+) -> Result<HashMap<K, ExecutionResources>, D::Error>
+where
+    D: Deserializer<'de>,
+    K: Deserialize<'de> + Eq + Hash,
+{
+    let resources = HashMap::<K, ExecutionResourcesJson>::deserialize(deserializer)?;
+    Ok(resources
+        .into_iter()
+        .map(|(key, resources)| (key, resources.into()))
+        .collect())
+}
+
+/// Returns the resources used by the OS to run a transaction of type `tx_type` with
+/// `calldata_length` calldata felts, and the syscalls it made, as counted in `syscall_counter`.
 pub fn get_additional_os_resources(
     syscall_counter: HashMap<String, u64>,
     tx_type: &TransactionType,
+    calldata_length: usize,
+    os_resources: &OsResources,
 ) -> Result<ExecutionResources, TransactionError> {
-    let os_resources = OsResources::default();
-
     let mut additional_os_resources = ExecutionResources::default();
 
     for (syscall, count) in syscall_counter {
@@ -278,7 +136,7 @@ pub fn get_additional_os_resources(
         .execute_txs_inner
         .get(tx_type)
         .ok_or_else(|| TransactionError::NoneTransactionType(*tx_type, os_resources.clone()))?
-        .clone();
+        .for_calldata_length(calldata_length);
 
     Ok(additional_os_resources)
 }
@@ -289,7 +147,8 @@ fn get_additional_os_resources_test() {
 
     let tx_type = TransactionType::InvokeFunction;
 
-    let additional_os_resources = get_additional_os_resources(syscall_counter, &tx_type).unwrap();
+    let additional_os_resources =
+        get_additional_os_resources(syscall_counter, &tx_type, 3, &OsResources::default()).unwrap();
     let expected_additional_os_resources = ExecutionResources {
         n_steps: 3589,
         n_memory_holes: 0,
@@ -301,3 +160,30 @@ fn get_additional_os_resources_test() {
 
     assert_eq!(additional_os_resources, expected_additional_os_resources);
 }
+
+#[test]
+fn get_additional_os_resources_grow_with_calldata_since_starknet_0_13_1() {
+    let syscall_counter = HashMap::from([("storage_read".into(), 2), ("storage_write".into(), 3)]);
+    let versioned_constants = VersionedConstants::for_starknet_version(
+        crate::definitions::versioned_constants::StarknetVersion::new(0, 13, 1),
+    )
+    .unwrap();
+
+    let additional_os_resources = get_additional_os_resources(
+        syscall_counter,
+        &TransactionType::InvokeFunction,
+        3,
+        versioned_constants.os_resources(),
+    )
+    .unwrap();
+    let expected_additional_os_resources = ExecutionResources {
+        n_steps: 4011,
+        n_memory_holes: 0,
+        builtin_instance_counter: HashMap::from([
+            ("range_check_builtin".to_string(), 85),
+            ("pedersen_builtin".to_string(), 17),
+        ]),
+    };
+
+    assert_eq!(additional_os_resources, expected_additional_os_resources);
+}
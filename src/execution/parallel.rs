@@ -0,0 +1,288 @@
+//! Optimistic concurrent execution of the transactions of a block, following Block-STM.
+//!
+//! Transactions are executed speculatively by worker threads over a [VersionedState], where
+//! each one reads the writes of the transactions before it that were executed so far. Every
+//! execution is then validated by checking that the versions it read are still the ones a new
+//! execution would read; an execution that fails validation is discarded and the transaction is
+//! executed again. Once every transaction has a validated execution, their results are those of
+//! executing them one after the other.
+
+use super::block::{execute_isolated, IsolatedResult};
+use crate::{
+    core::errors::state_errors::StateError,
+    definitions::block_context::BlockContext,
+    state::{
+        state_api::StateReader,
+        versioned_state::{StateKey, Version, VersionedState, VersionedStateReader},
+    },
+    transaction::{error::TransactionError, Transaction},
+};
+use std::{
+    panic,
+    sync::{Condvar, Mutex, MutexGuard},
+    thread,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Task {
+    /// Executes the transaction at the index, for the given incarnation.
+    Execute(usize, usize),
+    /// Validates the given incarnation of the transaction at the index.
+    Validate(usize, usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Status {
+    ReadyToExecute,
+    Executing,
+    Executed,
+}
+
+/// Hands out execution and validation tasks to the workers, lower transactions first.
+struct Scheduler {
+    state: Mutex<SchedulerState>,
+    task_finished: Condvar,
+}
+
+struct SchedulerState {
+    /// The lowest transaction that may need to be executed.
+    execution_idx: usize,
+    /// The lowest transaction that may need to be validated.
+    validation_idx: usize,
+    statuses: Vec<Status>,
+    incarnations: Vec<usize>,
+    /// The amount of tasks being run.
+    n_active_tasks: usize,
+    /// Whether a worker failed, and the others should stop.
+    halted: bool,
+}
+
+impl Scheduler {
+    fn new(n_txs: usize) -> Self {
+        Self {
+            state: Mutex::new(SchedulerState {
+                execution_idx: 0,
+                validation_idx: 0,
+                statuses: vec![Status::ReadyToExecute; n_txs],
+                incarnations: vec![0; n_txs],
+                n_active_tasks: 0,
+                halted: false,
+            }),
+            task_finished: Condvar::new(),
+        }
+    }
+
+    /// Returns the next task to run, waiting for the running ones if there is none yet, or
+    /// `None` once every transaction is executed and validated.
+    fn next_task(&self) -> Result<Option<Task>, StateError> {
+        let mut state = self.lock()?;
+        loop {
+            if state.halted {
+                return Ok(None);
+            }
+            let n_txs = state.statuses.len();
+            if state.validation_idx < state.execution_idx {
+                let index = state.validation_idx;
+                state.validation_idx += 1;
+                // A transaction that isn't executed yet is validated after its execution.
+                if state.statuses[index] == Status::Executed {
+                    state.n_active_tasks += 1;
+                    return Ok(Some(Task::Validate(index, state.incarnations[index])));
+                }
+            } else if state.execution_idx < n_txs {
+                let index = state.execution_idx;
+                state.execution_idx += 1;
+                if state.statuses[index] == Status::ReadyToExecute {
+                    state.statuses[index] = Status::Executing;
+                    state.n_active_tasks += 1;
+                    return Ok(Some(Task::Execute(index, state.incarnations[index])));
+                }
+            } else if state.n_active_tasks == 0 {
+                self.task_finished.notify_all();
+                return Ok(None);
+            } else {
+                state = self
+                    .task_finished
+                    .wait(state)
+                    .map_err(|_| lock_poisoned())?;
+            }
+        }
+    }
+
+    /// Records the end of an execution, whose writes are already published. The transaction and
+    /// the ones after it are validated again, as they may have read its previous writes.
+    fn finish_execution(&self, index: usize) -> Result<(), StateError> {
+        let mut state = self.lock()?;
+        state.statuses[index] = Status::Executed;
+        state.validation_idx = state.validation_idx.min(index);
+        self.finish_task(state);
+        Ok(())
+    }
+
+    /// Records the end of a validation. An execution that read outdated versions is aborted,
+    /// and the transaction is executed again.
+    fn finish_validation(
+        &self,
+        index: usize,
+        incarnation: usize,
+        valid: bool,
+    ) -> Result<(), StateError> {
+        let mut state = self.lock()?;
+        if !valid
+            && state.incarnations[index] == incarnation
+            && state.statuses[index] == Status::Executed
+        {
+            state.statuses[index] = Status::ReadyToExecute;
+            state.incarnations[index] += 1;
+            state.execution_idx = state.execution_idx.min(index);
+        }
+        self.finish_task(state);
+        Ok(())
+    }
+
+    fn finish_task(&self, mut state: MutexGuard<SchedulerState>) {
+        state.n_active_tasks -= 1;
+        self.task_finished.notify_all();
+    }
+
+    /// Stops every worker.
+    fn halt(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.halted = true;
+        }
+        self.task_finished.notify_all();
+    }
+
+    fn lock(&self) -> Result<MutexGuard<SchedulerState>, StateError> {
+        self.state.lock().map_err(|_| lock_poisoned())
+    }
+}
+
+fn lock_poisoned() -> StateError {
+    StateError::CustomError("Parallel execution lock poisoned".to_string())
+}
+
+/// The last execution of a transaction.
+struct Execution {
+    incarnation: usize,
+    reads: Vec<(StateKey, Option<Version>)>,
+    result: IsolatedResult,
+}
+
+/// Halts the scheduler if the worker holding it panics, so that the other workers don't wait
+/// for its task forever.
+struct HaltOnPanic<'a>(&'a Scheduler);
+
+impl Drop for HaltOnPanic<'_> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            self.0.halt();
+        }
+    }
+}
+
+/// Executes the transactions of a block over `state_reader` on `n_workers` threads. Returns the
+/// result of each transaction, the same as executing them in order with [execute_isolated] and
+/// applying the writes of each one before executing the next.
+pub(crate) fn execute_concurrently<S: StateReader + Sync>(
+    txs: &[Transaction],
+    block_context: &BlockContext,
+    state_reader: S,
+    n_workers: usize,
+) -> Result<Vec<IsolatedResult>, TransactionError> {
+    let state = VersionedState::new(state_reader);
+    let scheduler = Scheduler::new(txs.len());
+    let executions: Vec<Mutex<Option<Execution>>> = txs.iter().map(|_| Mutex::new(None)).collect();
+
+    let worker = || -> Result<(), TransactionError> {
+        let _halt_on_panic = HaltOnPanic(&scheduler);
+        let result = run_worker(txs, block_context, &state, &scheduler, &executions);
+        if result.is_err() {
+            scheduler.halt();
+        }
+        result
+    };
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..n_workers.max(1)).map(|_| scope.spawn(worker)).collect();
+        workers
+            .into_iter()
+            .map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| panic::resume_unwind(panic))
+            })
+            .collect::<Result<Vec<()>, TransactionError>>()
+    })?;
+
+    executions
+        .into_iter()
+        .map(|execution| -> Result<IsolatedResult, TransactionError> {
+            execution
+                .into_inner()
+                .map_err(|_| lock_poisoned())?
+                .map(|execution| execution.result)
+                .ok_or_else(|| {
+                    StateError::CustomError("Transaction left unexecuted".to_string()).into()
+                })
+        })
+        .collect()
+}
+
+fn run_worker<S: StateReader>(
+    txs: &[Transaction],
+    block_context: &BlockContext,
+    state: &VersionedState<S>,
+    scheduler: &Scheduler,
+    executions: &[Mutex<Option<Execution>>],
+) -> Result<(), TransactionError> {
+    while let Some(task) = scheduler.next_task()? {
+        match task {
+            Task::Execute(index, incarnation) => {
+                let reader = VersionedStateReader::new(state, index);
+                let result = execute_isolated(&txs[index], block_context, &reader);
+                let reads = reader.into_reads();
+                // The writes of a rejected transaction are discarded.
+                let writes = result.as_ref().ok().map(|(_, writes)| writes);
+                state.publish(index, incarnation, writes)?;
+                *lock_execution(&executions[index])? = Some(Execution {
+                    incarnation,
+                    reads,
+                    result,
+                });
+                scheduler.finish_execution(index)?;
+            }
+            Task::Validate(index, incarnation) => {
+                let valid = match &*lock_execution(&executions[index])? {
+                    Some(execution) if execution.incarnation == incarnation => {
+                        reads_are_current(state, index, &execution.reads)?
+                    }
+                    // A newer execution is validated on its own.
+                    _ => true,
+                };
+                scheduler.finish_validation(index, incarnation, valid)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn lock_execution(
+    execution: &Mutex<Option<Execution>>,
+) -> Result<MutexGuard<Option<Execution>>, StateError> {
+    execution.lock().map_err(|_| lock_poisoned())
+}
+
+/// Checks whether an execution of the transaction at `index` would read the same versions
+/// again.
+fn reads_are_current<S: StateReader>(
+    state: &VersionedState<S>,
+    index: usize,
+    reads: &[(StateKey, Option<Version>)],
+) -> Result<bool, StateError> {
+    for (key, version) in reads {
+        if state.version_of(key, index)? != *version {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
@@ -0,0 +1,325 @@
+use super::{trace::rpc_builtin_name, Event, L2toL1MessageInfo, TransactionExecutionInfo};
+use crate::{
+    definitions::{block_context::FeeType, transaction_type::TransactionType},
+    services::api::messages::StarknetMessageToL1,
+    transaction::{error::TransactionError, Transaction},
+    utils::{felt_to_hex, felts_to_hex, Address},
+};
+use cairo_vm::felt::Felt252;
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+
+/// The receipt of an executed transaction, which serializes to the `TRANSACTION_RECEIPT` of the
+/// JSON-RPC, without the fields of the block that includes the transaction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransactionReceipt {
+    pub transaction_hash: Felt252,
+    pub tx_type: TransactionType,
+    /// The fee charged to the transaction.
+    pub actual_fee: u128,
+    /// The token the fee was paid in.
+    pub fee_unit: FeeType,
+    pub execution_status: ExecutionStatus,
+    /// The events emitted by the transaction, in the order they were emitted.
+    pub events: Vec<Event>,
+    /// The messages sent to L1 by the transaction, in the order they were sent.
+    pub messages_sent: Vec<MessageToL1>,
+    /// The address of the deployed contract, for `Deploy` and `DeployAccount` transactions.
+    pub contract_address: Option<Address>,
+    /// The resources used by the transaction, as in
+    /// [TransactionExecutionInfo::actual_resources].
+    pub execution_resources: HashMap<String, usize>,
+}
+
+/// Whether the execution of a transaction succeeded or was reverted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExecutionStatus {
+    Succeeded,
+    /// The execution was reverted, for the given reason.
+    Reverted(String),
+}
+
+/// A message sent from L2 to L1, with its hash as computed by [StarknetMessageToL1::get_hash].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MessageToL1 {
+    pub message: L2toL1MessageInfo,
+    pub hash: Vec<u8>,
+}
+
+impl From<L2toL1MessageInfo> for MessageToL1 {
+    fn from(message: L2toL1MessageInfo) -> Self {
+        let hash = StarknetMessageToL1::new(
+            message.from_address.clone(),
+            message.to_address.clone(),
+            message.payload.clone(),
+        )
+        .get_hash();
+        MessageToL1 { message, hash }
+    }
+}
+
+impl TransactionReceipt {
+    /// Builds the receipt of a transaction from the result of its execution.
+    ///
+    /// ## Parameters
+    /// - transaction: The executed transaction.
+    /// - execution_info: The result of the execution of the transaction.
+    pub fn new(
+        transaction: &Transaction,
+        execution_info: &TransactionExecutionInfo,
+    ) -> Result<Self, TransactionError> {
+        let tx_type = transaction.tx_type();
+        let contract_address = match tx_type {
+            TransactionType::Deploy | TransactionType::DeployAccount => {
+                Some(transaction.contract_address())
+            }
+            _ => None,
+        };
+        let execution_status = match &execution_info.revert_error {
+            Some(revert_reason) => ExecutionStatus::Reverted(revert_reason.clone()),
+            None => ExecutionStatus::Succeeded,
+        };
+
+        Ok(TransactionReceipt {
+            transaction_hash: transaction.hash_value(),
+            tx_type,
+            actual_fee: execution_info.actual_fee,
+            fee_unit: FeeType::from_version(&transaction.version()),
+            execution_status,
+            events: execution_info.get_sorted_events()?,
+            messages_sent: execution_info
+                .get_sorted_l2_to_l1_messages()?
+                .into_iter()
+                .map(MessageToL1::from)
+                .collect(),
+            contract_address,
+            execution_resources: execution_info.actual_resources.clone(),
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct RpcTransactionReceipt<'a> {
+    #[serde(rename = "type")]
+    tx_type: &'static str,
+    transaction_hash: String,
+    actual_fee: RpcFeePayment,
+    execution_status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    revert_reason: Option<&'a str>,
+    messages_sent: Vec<RpcMessageToL1>,
+    events: Vec<RpcEvent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contract_address: Option<String>,
+    execution_resources: BTreeMap<&'static str, usize>,
+}
+
+#[derive(Serialize)]
+struct RpcFeePayment {
+    amount: String,
+    unit: &'static str,
+}
+
+#[derive(Serialize)]
+struct RpcMessageToL1 {
+    from_address: String,
+    to_address: String,
+    payload: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct RpcEvent {
+    from_address: String,
+    keys: Vec<String>,
+    data: Vec<String>,
+}
+
+impl Serialize for TransactionReceipt {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (execution_status, revert_reason) = match &self.execution_status {
+            ExecutionStatus::Succeeded => ("SUCCEEDED", None),
+            ExecutionStatus::Reverted(revert_reason) => ("REVERTED", Some(revert_reason.as_str())),
+        };
+
+        // The steps already include the memory holes.
+        let mut execution_resources = BTreeMap::from([(
+            "steps",
+            self.execution_resources
+                .get("n_steps")
+                .copied()
+                .unwrap_or_default(),
+        )]);
+        for (resource, count) in &self.execution_resources {
+            match rpc_builtin_name(resource) {
+                Some(name) if *count != 0 => {
+                    execution_resources.insert(name, *count);
+                }
+                _ => {}
+            }
+        }
+
+        RpcTransactionReceipt {
+            tx_type: match self.tx_type {
+                TransactionType::Declare => "DECLARE",
+                TransactionType::Deploy => "DEPLOY",
+                TransactionType::DeployAccount => "DEPLOY_ACCOUNT",
+                TransactionType::L1Handler => "L1_HANDLER",
+                TransactionType::InvokeFunction | TransactionType::InitializeBlockInfo => "INVOKE",
+            },
+            transaction_hash: felt_to_hex(&self.transaction_hash),
+            actual_fee: RpcFeePayment {
+                amount: format!("{:#x}", self.actual_fee),
+                unit: match self.fee_unit {
+                    FeeType::Eth => "WEI",
+                    FeeType::Strk => "FRI",
+                },
+            },
+            execution_status,
+            revert_reason,
+            messages_sent: self
+                .messages_sent
+                .iter()
+                .map(|MessageToL1 { message, .. }| RpcMessageToL1 {
+                    from_address: felt_to_hex(&message.from_address.0),
+                    to_address: felt_to_hex(&message.to_address.0),
+                    payload: felts_to_hex(&message.payload),
+                })
+                .collect(),
+            events: self
+                .events
+                .iter()
+                .map(|event| RpcEvent {
+                    from_address: felt_to_hex(&event.from_address.0),
+                    keys: felts_to_hex(&event.keys),
+                    data: felts_to_hex(&event.data),
+                })
+                .collect(),
+            contract_address: self
+                .contract_address
+                .as_ref()
+                .map(|address| felt_to_hex(&address.0)),
+            execution_resources,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        definitions::block_context::StarknetChainId,
+        execution::{CallInfo, OrderedEvent, OrderedL2ToL1Message},
+        transaction::InvokeFunction,
+    };
+    use coverage_helper::test;
+    use serde_json::json;
+
+    fn invoke() -> Transaction {
+        Transaction::InvokeFunction(
+            InvokeFunction::new_with_tx_hash(
+                Address(0x10.into()),
+                0x20.into(),
+                1000,
+                1.into(),
+                vec![],
+                vec![],
+                Some(0.into()),
+                0x99.into(),
+            )
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn invoke_receipt_serializes_to_the_spec() {
+        let execution_info = TransactionExecutionInfo {
+            call_info: Some(CallInfo {
+                contract_address: Address(0x10.into()),
+                caller_address: Address(0x10.into()),
+                events: vec![
+                    OrderedEvent::new(2, vec![2.into()], vec![]),
+                    OrderedEvent::new(1, vec![1.into()], vec![3.into()]),
+                ],
+                l2_to_l1_messages: vec![OrderedL2ToL1Message::new(
+                    0,
+                    Address(0x30.into()),
+                    vec![7.into()],
+                )],
+                ..Default::default()
+            }),
+            actual_fee: 0x1234,
+            actual_resources: HashMap::from([
+                ("l1_gas_usage".to_string(), 10),
+                ("n_steps".to_string(), 100),
+                ("range_check_builtin".to_string(), 2),
+                ("pedersen_builtin".to_string(), 0),
+            ]),
+            tx_type: Some(TransactionType::InvokeFunction),
+            ..Default::default()
+        };
+        let receipt = TransactionReceipt::new(&invoke(), &execution_info).unwrap();
+
+        assert_eq!(receipt.execution_status, ExecutionStatus::Succeeded);
+        assert_eq!(receipt.events[0].keys, vec![1.into()]);
+        assert_eq!(receipt.messages_sent[0].hash.len(), 32);
+        assert_eq!(
+            serde_json::to_value(&receipt).unwrap(),
+            json!({
+                "type": "INVOKE",
+                "transaction_hash": "0x99",
+                "actual_fee": { "amount": "0x1234", "unit": "WEI" },
+                "execution_status": "SUCCEEDED",
+                "messages_sent": [{ "from_address": "0x10", "to_address": "0x30", "payload": ["0x7"] }],
+                "events": [
+                    { "from_address": "0x10", "keys": ["0x1"], "data": ["0x3"] },
+                    { "from_address": "0x10", "keys": ["0x2"], "data": [] },
+                ],
+                "execution_resources": { "steps": 100, "range_check_builtin_applications": 2 },
+            })
+        );
+    }
+
+    #[test]
+    fn reverted_receipt_has_the_revert_reason() {
+        let execution_info = TransactionExecutionInfo {
+            revert_error: Some("Out of gas".to_string()),
+            tx_type: Some(TransactionType::InvokeFunction),
+            ..Default::default()
+        };
+        let receipt =
+            serde_json::to_value(TransactionReceipt::new(&invoke(), &execution_info).unwrap())
+                .unwrap();
+
+        assert_eq!(receipt["execution_status"], json!("REVERTED"));
+        assert_eq!(receipt["revert_reason"], json!("Out of gas"));
+        assert!(receipt.get("contract_address").is_none());
+    }
+
+    #[test]
+    fn deploy_account_receipt_has_the_contract_address() {
+        let deploy_account = crate::transaction::DeployAccount::new(
+            [1; 32],
+            0,
+            1.into(),
+            0.into(),
+            vec![],
+            vec![],
+            0.into(),
+            StarknetChainId::TestNet.to_felt(),
+        )
+        .unwrap();
+        let contract_address = deploy_account.contract_address().clone();
+        let receipt = TransactionReceipt::new(
+            &Transaction::DeployAccount(deploy_account),
+            &TransactionExecutionInfo::default(),
+        )
+        .unwrap();
+
+        assert_eq!(receipt.contract_address, Some(contract_address));
+        assert_eq!(
+            serde_json::to_value(receipt).unwrap()["type"],
+            json!("DEPLOY_ACCOUNT")
+        );
+    }
+}
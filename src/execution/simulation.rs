@@ -0,0 +1,261 @@
+use super::TransactionExecutionInfo;
+use crate::{
+    core::errors::state_errors::StateError,
+    definitions::block_context::{BlockContext, FeeType},
+    state::{
+        forked_state_reader::ForkedStateReader, state_api::StateReader, state_cache::StorageEntry,
+    },
+    transaction::{
+        error::TransactionError,
+        fee::{calculate_tx_l1_data_gas_usage, calculate_tx_l1_gas_usage},
+    },
+    utils::{Address, ClassHash},
+};
+use cairo_vm::felt::Felt252;
+use num_traits::Zero;
+use std::collections::HashMap;
+
+/// The options of [crate::simulate_transaction].
+#[derive(Clone, Debug, Default)]
+pub struct SimulationFlags {
+    pub skip_validate: bool,
+    pub skip_execute: bool,
+    pub skip_fee_transfer: bool,
+    /// Whether to execute the transactions as if their max fee was unlimited.
+    pub ignore_max_fee: bool,
+    pub skip_nonce_check: bool,
+    /// Whether to go on with the next transactions when one fails. When it's not set, the
+    /// simulation stops with the error of the first transaction that fails.
+    pub continue_on_failure: bool,
+    /// Changes to the block the transactions are simulated in.
+    pub block_overrides: BlockOverrides,
+    /// Changes to the state the transactions are simulated on.
+    pub state_overrides: StateOverrides,
+}
+
+/// Values of the block that replace those of the [BlockContext] of a simulation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlockOverrides {
+    pub block_number: Option<u64>,
+    pub block_timestamp: Option<u64>,
+    /// The price of the L1 gas in Wei, used to charge fees in ETH.
+    pub gas_price: Option<u128>,
+    /// The price of the L1 gas in Fri, used to charge fees in STRK.
+    pub strk_gas_price: Option<u128>,
+    /// The price of the L1 data gas in Wei, used to charge fees in ETH.
+    pub data_gas_price: Option<u128>,
+    /// The price of the L1 data gas in Fri, used to charge fees in STRK.
+    pub strk_data_gas_price: Option<u128>,
+    pub sequencer_address: Option<Address>,
+}
+
+/// Values of the state that replace those of the state reader of a simulation, without
+/// changing the reader itself.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateOverrides {
+    pub storage: HashMap<StorageEntry, Felt252>,
+    pub nonces: HashMap<Address, Felt252>,
+    pub class_hashes: HashMap<Address, ClassHash>,
+    /// The balances of accounts, in both the ETH and the STRK fee tokens.
+    pub balances: HashMap<Address, u128>,
+}
+
+/// The result of simulating a transaction.
+#[derive(Debug)]
+pub enum SimulationResult {
+    Succeeded(TransactionExecutionInfo),
+    /// The execution of the transaction was reverted, and only its fee was charged.
+    Reverted(TransactionExecutionInfo),
+    /// The transaction failed before it could be charged, e.g. because its validation failed.
+    /// Its changes to the state are discarded.
+    Failed(TransactionError),
+}
+
+/// The estimate of the fee of a transaction, as in the `FEE_ESTIMATE` of the JSON-RPC.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeeEstimate {
+    /// The L1 gas consumed by the transaction, including the gas its computation costs.
+    pub gas_consumed: u128,
+    /// The price of the L1 gas, in the unit of the fee.
+    pub gas_price: u128,
+    /// The L1 data gas consumed by the transaction, when its block publishes the state diffs in
+    /// blobs.
+    pub data_gas_consumed: u128,
+    /// The price of the L1 data gas, in the unit of the fee.
+    pub data_gas_price: u128,
+    /// The fee of the transaction,
+    /// `gas_consumed * gas_price + data_gas_consumed * data_gas_price`.
+    pub overall_fee: u128,
+    /// The token the fee is paid in.
+    pub unit: FeeType,
+}
+
+impl FeeEstimate {
+    /// Estimates the fee of a transaction from the resources used by its execution.
+    ///
+    /// ## Parameters
+    /// - actual_resources: The resources used by the transaction.
+    /// - unit: The token the fee is paid in.
+    /// - block_context: The context holding the gas prices and the fee weights.
+    pub(crate) fn new(
+        actual_resources: &HashMap<String, usize>,
+        unit: FeeType,
+        block_context: &BlockContext,
+    ) -> Result<Self, TransactionError> {
+        let gas_consumed = calculate_tx_l1_gas_usage(actual_resources, block_context)?;
+        let gas_price = block_context.block_info.gas_price_by_type(unit);
+        let data_gas_consumed = calculate_tx_l1_data_gas_usage(actual_resources);
+        let data_gas_price = block_context.block_info.data_gas_price_by_type(unit);
+        Ok(FeeEstimate {
+            gas_consumed,
+            gas_price,
+            data_gas_consumed,
+            data_gas_price,
+            overall_fee: gas_consumed * gas_price + data_gas_consumed * data_gas_price,
+            unit,
+        })
+    }
+}
+
+impl BlockOverrides {
+    /// Returns `block_context` with the overridden values.
+    pub fn apply(&self, block_context: &BlockContext) -> BlockContext {
+        let mut block_context = block_context.clone();
+        let block_info = &mut block_context.block_info;
+        if let Some(block_number) = self.block_number {
+            block_info.block_number = block_number;
+        }
+        if let Some(block_timestamp) = self.block_timestamp {
+            block_info.block_timestamp = block_timestamp;
+        }
+        if let Some(sequencer_address) = &self.sequencer_address {
+            block_info.sequencer_address = sequencer_address.clone();
+        }
+        if let Some(gas_price) = self.gas_price {
+            block_info.gas_price = gas_price;
+        }
+        if let Some(strk_gas_price) = self.strk_gas_price {
+            block_info.strk_gas_price = strk_gas_price;
+        }
+        if let Some(data_gas_price) = self.data_gas_price {
+            block_info.data_gas_price = data_gas_price;
+        }
+        if let Some(strk_data_gas_price) = self.strk_data_gas_price {
+            block_info.strk_data_gas_price = strk_data_gas_price;
+        }
+        block_context
+    }
+}
+
+impl StateOverrides {
+    /// Sets the overridden values in `state_reader`.
+    ///
+    /// ## Parameters
+    /// - state_reader: The reader the overrides are set in.
+    /// - block_context: The context holding the addresses of the fee tokens.
+    pub fn apply<T: StateReader>(
+        &self,
+        state_reader: &mut ForkedStateReader<T>,
+        block_context: &BlockContext,
+    ) -> Result<(), StateError> {
+        state_reader
+            .address_to_storage_mut()
+            .extend(self.storage.clone());
+        state_reader
+            .address_to_nonce_mut()
+            .extend(self.nonces.clone());
+        state_reader
+            .address_to_class_hash_mut()
+            .extend(self.class_hashes.clone());
+        for (account_address, balance) in &self.balances {
+            for fee_type in [FeeType::Eth, FeeType::Strk] {
+                state_reader.set_erc20_balance(
+                    block_context
+                        .starknet_os_config
+                        .fee_token_address_by_type(fee_type),
+                    account_address,
+                    (Felt252::from(*balance), Felt252::zero()),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SimulationResult {
+    /// Returns the execution info of the transaction, unless it failed.
+    pub fn execution_info(&self) -> Option<&TransactionExecutionInfo> {
+        match self {
+            SimulationResult::Succeeded(execution_info)
+            | SimulationResult::Reverted(execution_info) => Some(execution_info),
+            SimulationResult::Failed(_) => None,
+        }
+    }
+}
+
+impl From<TransactionExecutionInfo> for SimulationResult {
+    fn from(execution_info: TransactionExecutionInfo) -> Self {
+        if execution_info.revert_error.is_some() {
+            SimulationResult::Reverted(execution_info)
+        } else {
+            SimulationResult::Succeeded(execution_info)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::in_memory_state_reader::InMemoryStateReader;
+    use coverage_helper::test;
+    use std::sync::Arc;
+
+    #[test]
+    fn overrides_replace_the_block_and_state_values() {
+        let block_context = BlockOverrides {
+            block_number: Some(7),
+            gas_price: Some(3),
+            strk_gas_price: Some(30),
+            data_gas_price: Some(2),
+            sequencer_address: Some(Address(5.into())),
+            ..Default::default()
+        }
+        .apply(&BlockContext::default());
+        assert_eq!(block_context.block_info.block_number, 7);
+        assert_eq!(block_context.block_info.block_timestamp, 0);
+        assert_eq!(block_context.block_info.gas_price, 3);
+        assert_eq!(block_context.block_info.strk_gas_price, 30);
+        assert_eq!(block_context.block_info.data_gas_price, 2);
+        assert_eq!(
+            block_context.block_info.sequencer_address,
+            Address(5.into())
+        );
+
+        let account_address = Address(10.into());
+        let mut state_reader = ForkedStateReader::new(Arc::new(InMemoryStateReader::default()));
+        StateOverrides {
+            nonces: HashMap::from([(account_address.clone(), 4.into())]),
+            balances: HashMap::from([(account_address.clone(), 1000)]),
+            ..Default::default()
+        }
+        .apply(&mut state_reader, &block_context)
+        .unwrap();
+        assert_eq!(
+            state_reader.get_nonce_at(&account_address).unwrap(),
+            4.into()
+        );
+        for fee_type in [FeeType::Eth, FeeType::Strk] {
+            assert_eq!(
+                state_reader
+                    .get_erc20_balance(
+                        block_context
+                            .starknet_os_config
+                            .fee_token_address_by_type(fee_type),
+                        &account_address
+                    )
+                    .unwrap(),
+                (1000.into(), Felt252::zero())
+            );
+        }
+    }
+}
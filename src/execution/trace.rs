@@ -0,0 +1,345 @@
+use super::{CallInfo, CallType, TransactionExecutionInfo};
+use crate::{
+    definitions::transaction_type::TransactionType,
+    services::api::contract_classes::deprecated_contract_class::EntryPointType,
+    state::StateDiff,
+    transaction::error::TransactionError,
+    utils::{felt_to_hex, felts_to_hex, hash_to_hex},
+};
+use cairo_vm::{felt::Felt252, vm::runners::cairo_runner::ExecutionResources};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// The trace of an executed transaction, which serializes to the `TRANSACTION_TRACE` of the
+/// JSON-RPC returned by `starknet_traceTransaction` and `starknet_simulateTransactions`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TransactionTrace {
+    #[serde(rename = "type")]
+    tx_type: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    validate_invocation: Option<FunctionInvocation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    execute_invocation: Option<ExecuteInvocation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    constructor_invocation: Option<FunctionInvocation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    function_invocation: Option<FunctionInvocation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fee_transfer_invocation: Option<FunctionInvocation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state_diff: Option<StateDiff>,
+}
+
+impl TransactionTrace {
+    /// Builds the trace of a transaction from the result of its execution.
+    ///
+    /// ## Parameters
+    /// - execution_info: The result of the execution of the transaction.
+    /// - state_diff: The changes the transaction made to the state, if they're to be traced.
+    pub fn new(
+        execution_info: &TransactionExecutionInfo,
+        state_diff: Option<StateDiff>,
+    ) -> Result<Self, TransactionError> {
+        let invocation = |call_info: &Option<CallInfo>| -> Option<FunctionInvocation> {
+            call_info.as_ref().map(FunctionInvocation::from)
+        };
+        let mut trace = TransactionTrace {
+            tx_type: "",
+            validate_invocation: invocation(&execution_info.validate_info),
+            execute_invocation: None,
+            constructor_invocation: None,
+            function_invocation: None,
+            fee_transfer_invocation: invocation(&execution_info.fee_transfer_info),
+            state_diff,
+        };
+
+        match execution_info.tx_type {
+            Some(TransactionType::InvokeFunction) => {
+                trace.tx_type = "INVOKE";
+                trace.execute_invocation = match &execution_info.revert_error {
+                    Some(revert_reason) => Some(ExecuteInvocation::Reverted {
+                        revert_reason: revert_reason.clone(),
+                    }),
+                    None => invocation(&execution_info.call_info).map(ExecuteInvocation::Invoked),
+                };
+            }
+            Some(TransactionType::Declare) => trace.tx_type = "DECLARE",
+            Some(TransactionType::DeployAccount) => {
+                trace.tx_type = "DEPLOY_ACCOUNT";
+                trace.constructor_invocation = invocation(&execution_info.call_info);
+            }
+            Some(TransactionType::L1Handler) => {
+                trace.tx_type = "L1_HANDLER";
+                trace.validate_invocation = None;
+                trace.fee_transfer_invocation = None;
+                trace.function_invocation = invocation(&execution_info.call_info);
+            }
+            tx_type => return Err(TransactionError::NoRpcTrace(tx_type)),
+        }
+
+        Ok(trace)
+    }
+}
+
+/// The execution of the `__execute__` entry point of an invoke, or why it was reverted.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+enum ExecuteInvocation {
+    Invoked(FunctionInvocation),
+    Reverted { revert_reason: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct FunctionInvocation {
+    contract_address: String,
+    entry_point_selector: String,
+    calldata: Vec<String>,
+    caller_address: String,
+    class_hash: String,
+    entry_point_type: &'static str,
+    call_type: &'static str,
+    result: Vec<String>,
+    calls: Vec<FunctionInvocation>,
+    events: Vec<OrderedEvent>,
+    messages: Vec<OrderedMessage>,
+    execution_resources: BTreeMap<&'static str, usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct OrderedEvent {
+    order: u64,
+    keys: Vec<String>,
+    data: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct OrderedMessage {
+    order: usize,
+    from_address: String,
+    to_address: String,
+    payload: Vec<String>,
+}
+
+impl From<&CallInfo> for FunctionInvocation {
+    fn from(call_info: &CallInfo) -> Self {
+        FunctionInvocation {
+            contract_address: felt_to_hex(&call_info.contract_address.0),
+            entry_point_selector: felt_to_hex(
+                call_info
+                    .entry_point_selector
+                    .as_ref()
+                    .unwrap_or(&Felt252::default()),
+            ),
+            calldata: felts_to_hex(&call_info.calldata),
+            caller_address: felt_to_hex(&call_info.caller_address.0),
+            class_hash: hash_to_hex(&call_info.class_hash.unwrap_or_default()),
+            entry_point_type: match call_info.entry_point_type {
+                Some(EntryPointType::L1Handler) => "L1_HANDLER",
+                Some(EntryPointType::Constructor) => "CONSTRUCTOR",
+                Some(EntryPointType::External) | None => "EXTERNAL",
+            },
+            call_type: match call_info.call_type {
+                Some(CallType::Delegate) => "LIBRARY_CALL",
+                Some(CallType::Call) | None => "CALL",
+            },
+            result: felts_to_hex(&call_info.retdata),
+            calls: call_info.internal_calls.iter().map(Into::into).collect(),
+            events: call_info
+                .events
+                .iter()
+                .map(|event| OrderedEvent {
+                    order: event.order,
+                    keys: felts_to_hex(&event.keys),
+                    data: felts_to_hex(&event.data),
+                })
+                .collect(),
+            messages: call_info
+                .l2_to_l1_messages
+                .iter()
+                .map(|message| OrderedMessage {
+                    order: message.order,
+                    from_address: felt_to_hex(&call_info.contract_address.0),
+                    to_address: felt_to_hex(&message.to_address.0),
+                    payload: felts_to_hex(&message.payload),
+                })
+                .collect(),
+            execution_resources: computation_resources(&call_info.execution_resources),
+        }
+    }
+}
+
+/// Returns the execution resources as the `COMPUTATION_RESOURCES` of the JSON-RPC, which
+/// leaves out the resources that weren't used.
+fn computation_resources(resources: &ExecutionResources) -> BTreeMap<&'static str, usize> {
+    let mut computation_resources = BTreeMap::from([("steps", resources.n_steps)]);
+    if resources.n_memory_holes != 0 {
+        computation_resources.insert("memory_holes", resources.n_memory_holes);
+    }
+    for (builtin, count) in &resources.builtin_instance_counter {
+        match rpc_builtin_name(builtin) {
+            Some(name) if *count != 0 => {
+                computation_resources.insert(name, *count);
+            }
+            _ => {}
+        }
+    }
+    computation_resources
+}
+
+/// Returns the name of the resource of a builtin in the JSON-RPC, or `None` for the builtins
+/// that aren't part of the spec.
+pub(super) fn rpc_builtin_name(builtin: &str) -> Option<&'static str> {
+    match builtin {
+        "range_check_builtin" => Some("range_check_builtin_applications"),
+        "pedersen_builtin" => Some("pedersen_builtin_applications"),
+        "poseidon_builtin" => Some("poseidon_builtin_applications"),
+        "ec_op_builtin" => Some("ec_op_builtin_applications"),
+        "ecdsa_builtin" => Some("ecdsa_builtin_applications"),
+        "bitwise_builtin" => Some("bitwise_builtin_applications"),
+        "keccak_builtin" => Some("keccak_builtin_applications"),
+        "segment_arena_builtin" => Some("segment_arena_builtin"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        execution::{OrderedEvent as CallEvent, OrderedL2ToL1Message},
+        utils::Address,
+    };
+    use coverage_helper::test;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn call_info() -> CallInfo {
+        CallInfo {
+            caller_address: Address(0.into()),
+            call_type: Some(CallType::Call),
+            contract_address: Address(0x10.into()),
+            class_hash: Some([1; 32]),
+            entry_point_selector: Some(0x20.into()),
+            entry_point_type: Some(EntryPointType::External),
+            calldata: vec![1.into(), 2.into()],
+            retdata: vec![3.into()],
+            execution_resources: ExecutionResources {
+                n_steps: 100,
+                n_memory_holes: 0,
+                builtin_instance_counter: HashMap::from([
+                    ("range_check_builtin".to_string(), 4),
+                    ("pedersen_builtin".to_string(), 0),
+                    ("output_builtin".to_string(), 1),
+                ]),
+            },
+            events: vec![CallEvent::new(0, vec![5.into()], vec![6.into()])],
+            l2_to_l1_messages: vec![OrderedL2ToL1Message::new(
+                1,
+                Address(0x30.into()),
+                vec![7.into()],
+            )],
+            internal_calls: vec![CallInfo {
+                call_type: Some(CallType::Delegate),
+                ..CallInfo::empty_constructor_call(
+                    Address(0x11.into()),
+                    Address(0x10.into()),
+                    // Class hashes above the field prime are kept as they are.
+                    Some([0xff; 32]),
+                )
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn invoke_trace_serializes_to_the_spec() {
+        let execution_info = TransactionExecutionInfo {
+            call_info: Some(call_info()),
+            tx_type: Some(TransactionType::InvokeFunction),
+            ..Default::default()
+        };
+        let trace = TransactionTrace::new(&execution_info, Some(StateDiff::default())).unwrap();
+        let trace = serde_json::to_value(trace).unwrap();
+
+        assert_eq!(trace["type"], json!("INVOKE"));
+        assert!(trace.get("validate_invocation").is_none());
+        assert_eq!(
+            trace["execute_invocation"]["execution_resources"],
+            json!({ "steps": 100, "range_check_builtin_applications": 4 })
+        );
+        assert_eq!(
+            trace["execute_invocation"]["messages"],
+            json!([{ "order": 1, "from_address": "0x10", "to_address": "0x30", "payload": ["0x7"] }])
+        );
+        assert_eq!(
+            trace["execute_invocation"]["events"],
+            json!([{ "order": 0, "keys": ["0x5"], "data": ["0x6"] }])
+        );
+        assert_eq!(
+            trace["execute_invocation"]["class_hash"],
+            json!(format!("0x1{}", "01".repeat(31)))
+        );
+        let inner_call = &trace["execute_invocation"]["calls"][0];
+        assert_eq!(
+            inner_call["class_hash"],
+            json!(format!("0x{}", "ff".repeat(32)))
+        );
+        assert_eq!(inner_call["call_type"], json!("LIBRARY_CALL"));
+        assert_eq!(inner_call["entry_point_type"], json!("CONSTRUCTOR"));
+        assert_eq!(trace["state_diff"]["storage_diffs"], json!([]));
+    }
+
+    #[test]
+    fn reverted_invoke_trace_has_the_revert_reason() {
+        let execution_info = TransactionExecutionInfo {
+            validate_info: Some(call_info()),
+            revert_error: Some("Out of gas".to_string()),
+            tx_type: Some(TransactionType::InvokeFunction),
+            ..Default::default()
+        };
+        let trace =
+            serde_json::to_value(TransactionTrace::new(&execution_info, None).unwrap()).unwrap();
+
+        assert_eq!(
+            trace["execute_invocation"],
+            json!({ "revert_reason": "Out of gas" })
+        );
+        assert_eq!(
+            trace["validate_invocation"]["contract_address"],
+            json!("0x10")
+        );
+        assert!(trace.get("state_diff").is_none());
+    }
+
+    #[test]
+    fn deploy_account_and_l1_handler_traces() {
+        let execution_info = TransactionExecutionInfo {
+            call_info: Some(call_info()),
+            tx_type: Some(TransactionType::DeployAccount),
+            ..Default::default()
+        };
+        let trace =
+            serde_json::to_value(TransactionTrace::new(&execution_info, None).unwrap()).unwrap();
+        assert_eq!(trace["type"], json!("DEPLOY_ACCOUNT"));
+        assert!(trace.get("constructor_invocation").is_some());
+        assert!(trace.get("execute_invocation").is_none());
+
+        let execution_info = TransactionExecutionInfo {
+            tx_type: Some(TransactionType::L1Handler),
+            ..execution_info
+        };
+        let trace =
+            serde_json::to_value(TransactionTrace::new(&execution_info, None).unwrap()).unwrap();
+        assert_eq!(trace["type"], json!("L1_HANDLER"));
+        assert!(trace.get("function_invocation").is_some());
+
+        let execution_info = TransactionExecutionInfo {
+            tx_type: Some(TransactionType::Deploy),
+            ..execution_info
+        };
+        assert_matches!(
+            TransactionTrace::new(&execution_info, None),
+            Err(TransactionError::NoRpcTrace(Some(TransactionType::Deploy)))
+        );
+    }
+}
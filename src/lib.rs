@@ -1,25 +1,36 @@
 #![deny(warnings)]
 #![forbid(unsafe_code)]
 #![cfg_attr(coverage_nightly, feature(no_coverage))]
-use std::{collections::HashMap, sync::Arc};
+use std::sync::Arc;
 
 use crate::{
     execution::{
-        execution_entry_point::ExecutionEntryPoint, CallType, TransactionExecutionContext,
-        TransactionExecutionInfo,
+        block::{execute_isolated, BlockExecutionOutput},
+        bouncer::Bouncer,
+        execution_entry_point::ExecutionEntryPoint,
+        parallel::execute_concurrently,
+        simulation::{BlockOverrides, FeeEstimate, SimulationFlags, SimulationResult},
+        CallInfo, CallType, TransactionExecutionContext, TransactionExecutionInfo,
     },
     state::{
+        forked_state_reader::ForkedStateReader,
         state_api::{State, StateReader},
         ExecutionResourcesManager,
     },
-    transaction::{error::TransactionError, Transaction},
+    transaction::{
+        error::{TransactionError, TransactionRejection},
+        Transaction,
+    },
 };
 
 use cairo_vm::felt::Felt252;
-use definitions::block_context::BlockContext;
+use definitions::{
+    block_context::{BlockContext, FeeType},
+    constants::INITIAL_GAS_COST,
+};
 use execution::execution_entry_point::ExecutionResult;
 use state::cached_state::CachedState;
-use transaction::{fee::calculate_tx_fee, L1Handler};
+use transaction::L1Handler;
 use utils::Address;
 
 #[cfg(test)]
@@ -49,66 +60,124 @@ pub mod testing;
 pub mod transaction;
 pub mod utils;
 
-#[allow(clippy::too_many_arguments)]
+/// Simulates the execution of transactions one after the other, over a copy of `state` with
+/// the overrides of `flags` applied. The reader itself is left untouched.
+///
+/// ## Parameters
+/// - transactions: The transactions to simulate, in order.
+/// - state: The reader of the state the transactions are simulated on.
+/// - block_context: The context of the block the transactions are simulated in.
+/// - remaining_gas: The gas available to each transaction.
+/// - flags: The steps of the execution to skip and the overrides of the block and the state.
+///
+/// Returns the result of each transaction. Unless `flags.continue_on_failure` is set, the
+/// simulation stops with the error of the first transaction that fails.
 pub fn simulate_transaction<S: StateReader>(
     transactions: &[&Transaction],
     state: S,
     block_context: &BlockContext,
     remaining_gas: u128,
-    skip_validate: bool,
-    skip_execute: bool,
-    skip_fee_transfer: bool,
-    ignore_max_fee: bool,
-    skip_nonce_check: bool,
-) -> Result<Vec<TransactionExecutionInfo>, TransactionError> {
-    let mut cache_state = CachedState::new(Arc::new(state), HashMap::new());
+    flags: &SimulationFlags,
+) -> Result<Vec<SimulationResult>, TransactionError> {
+    let block_context = flags.block_overrides.apply(block_context);
+    let mut state_reader = ForkedStateReader::new(Arc::new(state));
+    flags
+        .state_overrides
+        .apply(&mut state_reader, &block_context)?;
+
+    let mut cache_state = CachedState::new_for_block(Arc::new(state_reader), &block_context);
     let mut result = Vec::with_capacity(transactions.len());
     for transaction in transactions {
         let tx_for_simulation = transaction.create_for_simulation(
-            skip_validate,
-            skip_execute,
-            skip_fee_transfer,
-            ignore_max_fee,
-            skip_nonce_check,
+            flags.skip_validate,
+            flags.skip_execute,
+            flags.skip_fee_transfer,
+            flags.ignore_max_fee,
+            flags.skip_nonce_check,
         );
-        let tx_result =
-            tx_for_simulation.execute(&mut cache_state, block_context, remaining_gas)?;
-        result.push(tx_result);
+        let checkpoint = cache_state.checkpoint();
+        match tx_for_simulation.execute(&mut cache_state, &block_context, remaining_gas) {
+            Ok(execution_info) => {
+                cache_state.commit(checkpoint)?;
+                result.push(SimulationResult::from(execution_info));
+            }
+            Err(error) if flags.continue_on_failure => {
+                // The next transactions are simulated as if the failed one wasn't there.
+                cache_state.revert_to(checkpoint)?;
+                result.push(SimulationResult::Failed(error));
+            }
+            Err(error) => return Err(error),
+        }
     }
 
     Ok(result)
 }
 
-/// Estimate the fee associated with transaction
-pub fn estimate_fee<T>(
-    transactions: &[Transaction],
-    mut cached_state: CachedState<T>,
+/// Checks whether a transaction can be admitted to the mempool, running only its validation
+/// and not its execution. Returns the call info of the validation, or why the transaction is
+/// rejected. See [Transaction::validate].
+pub fn validate_transaction<S: StateReader>(
+    transaction: &Transaction,
+    state: S,
     block_context: &BlockContext,
-) -> Result<Vec<(u128, usize)>, TransactionError>
-where
-    T: StateReader,
-{
-    let mut result = Vec::with_capacity(transactions.len());
-    for transaction in transactions {
-        // Check if the contract is deployed.
-        cached_state.get_class_hash_at(&transaction.contract_address())?;
-        // execute the transaction with the fake state.
-
-        // This is important, since we're interested in the fee estimation even if the account does not currently have sufficient funds.
-        let tx_for_simulation = transaction.create_for_simulation(false, false, true, true, false);
-
-        let transaction_result =
-            tx_for_simulation.execute(&mut cached_state, block_context, 100_000_000)?;
-        if let Some(gas_usage) = transaction_result.actual_resources.get("l1_gas_usage") {
-            result.push((transaction_result.actual_fee, *gas_usage));
-        } else {
-            return Err(TransactionError::ResourcesError);
-        };
+    max_nonce_gap: u64,
+) -> Result<Option<CallInfo>, TransactionRejection> {
+    let mut cache_state = CachedState::new_for_block(Arc::new(state), block_context);
+    transaction.validate(&mut cache_state, block_context, max_nonce_gap)
+}
 
-        cached_state.cache.update_initial_values();
-    }
+/// Estimates the fee of transactions simulated one after the other with
+/// [simulate_transaction]. Fees are estimated even if the accounts can't currently pay them, so
+/// the fee transfer is skipped and the max fee ignored whatever the flags are. The estimation
+/// always continues after the transactions that fail, which get an error as their estimate.
+///
+/// ## Parameters
+/// - transactions: The transactions to estimate, in order.
+/// - state: The reader of the state the transactions are simulated on.
+/// - block_context: The context of the block the transactions are simulated in.
+/// - flags: The options of the simulation, e.g. `skip_validate` to estimate unsigned
+///   transactions.
+///
+/// Returns the estimate of each transaction, or why it couldn't be estimated. A reverted
+/// transaction can't be estimated, as it would fail the same way once sent.
+pub fn estimate_fee<S: StateReader>(
+    transactions: &[Transaction],
+    state: S,
+    block_context: &BlockContext,
+    flags: &SimulationFlags,
+) -> Result<Vec<Result<FeeEstimate, TransactionError>>, TransactionError> {
+    let block_context = flags.block_overrides.apply(block_context);
+    let flags = SimulationFlags {
+        skip_fee_transfer: true,
+        ignore_max_fee: true,
+        continue_on_failure: true,
+        block_overrides: BlockOverrides::default(),
+        ..flags.clone()
+    };
+    let transactions: Vec<&Transaction> = transactions.iter().collect();
+    let results = simulate_transaction(
+        &transactions,
+        state,
+        &block_context,
+        INITIAL_GAS_COST,
+        &flags,
+    )?;
 
-    Ok(result)
+    Ok(transactions
+        .iter()
+        .zip(results)
+        .map(|(transaction, result)| match result {
+            SimulationResult::Succeeded(execution_info) => FeeEstimate::new(
+                &execution_info.actual_resources,
+                FeeType::from_version(&transaction.version()),
+                &block_context,
+            ),
+            SimulationResult::Reverted(execution_info) => Err(TransactionError::ExecutionReverted(
+                execution_info.revert_error.unwrap_or_default(),
+            )),
+            SimulationResult::Failed(error) => Err(error),
+        })
+        .collect())
 }
 
 pub fn call_contract<T: StateReader>(
@@ -164,44 +233,123 @@ pub fn call_contract<T: StateReader>(
     Ok(call_info.retdata)
 }
 
-/// Estimate the fee associated with L1Handler
+/// Estimates the fee of an L1Handler transaction, i.e. the fee to pay on L1 for the message
+/// that triggers it. See [estimate_fee].
 pub fn estimate_message_fee<T>(
     l1_handler: &L1Handler,
     state: T,
     block_context: &BlockContext,
-) -> Result<(u128, usize), TransactionError>
+) -> Result<FeeEstimate, TransactionError>
 where
     T: StateReader,
 {
-    // This is used as a copy of the original state, we can update this cached state freely.
-    let mut cached_state = CachedState::<T>::new(Arc::new(state), HashMap::new());
-
-    // Check if the contract is deployed.
-    cached_state.get_class_hash_at(l1_handler.contract_address())?;
-
-    // execute the transaction with the fake state.
-    let transaction_result = l1_handler.execute(&mut cached_state, block_context, 1_000_000)?;
-    let tx_fee = calculate_tx_fee(
-        &transaction_result.actual_resources,
-        block_context.starknet_os_config.gas_price,
+    let transaction = Transaction::L1Handler(l1_handler.clone());
+    estimate_fee(
+        &[transaction],
+        state,
         block_context,
-    )?;
-    if let Some(gas_usage) = transaction_result.actual_resources.get("l1_gas_usage") {
-        Ok((tx_fee, *gas_usage))
-    } else {
-        Err(TransactionError::ResourcesError)
-    }
+        &SimulationFlags::default(),
+    )?
+    .remove(0)
 }
 
+/// Executes a transaction on `state`. If `state` doesn't cache the classes it reads in a
+/// [SharedClassCache](state::shared_class_cache::SharedClassCache) of its own, it starts using
+/// the one of the block context, see [BlockContext::with_class_cache].
 pub fn execute_transaction<S: StateReader>(
     tx: Transaction,
     state: &mut CachedState<S>,
     block_context: BlockContext,
     remaining_gas: u128,
 ) -> Result<TransactionExecutionInfo, TransactionError> {
+    if state.class_cache.is_none() {
+        state.class_cache = block_context.class_cache().clone();
+    }
     tx.execute(state, &block_context, remaining_gas)
 }
 
+/// Executes the transactions of a block in order. Each transaction is either included in the
+/// block, whether it succeeds or is reverted, or rejected when it fails before it can be charged
+/// (e.g. because of an invalid nonce or a failed validation). The changes of a rejected
+/// transaction are discarded, and the execution goes on with the next one.
+///
+/// The block closes before the first transaction that would take it over the limits of the
+/// block context, see [BlockContext::with_bouncer_limits]. The changes of that transaction are
+/// discarded, and it's left for the next block together with the ones after it.
+///
+/// Every transaction is executed on a cached state of its own over the state left by the
+/// transactions before it, so it's only charged for its own state changes.
+///
+/// ## Parameters
+/// - txs: The transactions of the block.
+/// - block_context: The context of the block.
+/// - state: The state the block is executed on. The state diff of the block is computed from
+///   all the writes in its cache, so it should hold no writes from before the block.
+pub fn execute_block<S: StateReader>(
+    txs: &[Transaction],
+    block_context: &BlockContext,
+    state: &mut CachedState<S>,
+) -> Result<BlockExecutionOutput, TransactionError> {
+    let mut output = BlockExecutionOutput::default();
+    let mut bouncer = Bouncer::new(block_context.bouncer_limits().clone());
+    for (index, tx) in txs.iter().enumerate() {
+        let result = execute_isolated(tx, block_context, &*state);
+        output.add(index, tx, result, state, &mut bouncer)?;
+        if output.overflowing_transaction.is_some() {
+            break;
+        }
+    }
+    output.finish(state, &bouncer)?;
+
+    Ok(output)
+}
+
+/// Executes the transactions of a block like [execute_block], but concurrently on `n_workers`
+/// threads. Transactions are executed optimistically, and executed again when they read a value
+/// that a transaction before them changed afterwards, so the outputs are the same as those of
+/// [execute_block].
+///
+/// Transactions that touch the same parts of the state, like the balance of the sequencer that
+/// collects the fees, are executed again more often, and gain less from the concurrency.
+/// The transactions after the one that closes the block are executed too, but their results are
+/// discarded. A transaction rejected as too large for the block is only known after its
+/// execution, so the transactions after it are executed again without its writes.
+///
+/// ## Parameters
+/// - txs: The transactions of the block.
+/// - block_context: The context of the block.
+/// - state: The state the block is executed on, as in [execute_block].
+/// - n_workers: The amount of threads executing transactions.
+pub fn execute_block_parallel<S: StateReader + Send + Sync>(
+    txs: &[Transaction],
+    block_context: &BlockContext,
+    state: &mut CachedState<S>,
+    n_workers: usize,
+) -> Result<BlockExecutionOutput, TransactionError> {
+    let mut output = BlockExecutionOutput::default();
+    let mut bouncer = Bouncer::new(block_context.bouncer_limits().clone());
+    let mut start = 0;
+    'block: while start < txs.len() {
+        let results = execute_concurrently(&txs[start..], block_context, &*state, n_workers)?;
+        for (offset, (tx, result)) in txs[start..].iter().zip(results).enumerate() {
+            let index = start + offset;
+            let too_large = output.add(index, tx, result, state, &mut bouncer)?;
+            if output.overflowing_transaction.is_some() {
+                break 'block;
+            }
+            // The transactions after it were executed over its discarded writes.
+            if too_large {
+                start = index + 1;
+                continue 'block;
+            }
+        }
+        break;
+    }
+    output.finish(state, &bouncer)?;
+
+    Ok(output)
+}
+
 #[cfg(test)]
 mod test {
     use std::collections::HashMap;
@@ -225,13 +373,15 @@ mod test {
     use crate::services::api::contract_classes::deprecated_contract_class::EntryPointType;
     use crate::state::state_api::State;
     use crate::testing::{
-        create_account_tx_test_state, TEST_ACCOUNT_CONTRACT_ADDRESS, TEST_CONTRACT_ADDRESS,
-        TEST_CONTRACT_PATH, TEST_FIB_COMPILED_CONTRACT_CLASS_HASH,
+        create_account_tx_test_state, create_test_contract_invoke, TEST_ACCOUNT_CONTRACT_ADDRESS,
+        TEST_ACCOUNT_CONTRACT_CLASS_HASH, TEST_CONTRACT_ADDRESS, TEST_CONTRACT_PATH,
+        TEST_FIB_COMPILED_CONTRACT_CLASS_HASH,
     };
     use crate::transaction::{
-        Declare, DeclareV2, Deploy, DeployAccount, InvokeFunction, L1Handler, Transaction,
+        fee::calculate_tx_l1_gas_usage, Declare, DeclareV2, Deploy, DeployAccount, InvokeFunction,
+        L1Handler, Transaction,
     };
-    use crate::utils::felt_to_hash;
+    use crate::utils::{calculate_sn_keccak, felt_to_hash};
     use cairo_lang_starknet::casm_contract_class::CasmContractClass;
     use cairo_lang_starknet::contract_class::ContractClass as SierraContractClass;
     use cairo_vm::felt::{felt_str, Felt252};
@@ -239,13 +389,23 @@ mod test {
 
     use crate::{
         call_contract,
-        definitions::block_context::BlockContext,
+        definitions::block_context::{BlockContext, FeeType},
+        execute_block, execute_block_parallel,
+        execution::{
+            bouncer::BouncerWeights,
+            simulation::{
+                BlockOverrides, FeeEstimate, SimulationFlags, SimulationResult, StateOverrides,
+            },
+        },
         simulate_transaction,
         state::{
-            cached_state::CachedState, in_memory_state_reader::InMemoryStateReader,
+            cached_state::CachedState, forked_state_reader::ForkedStateReader,
+            in_memory_state_reader::InMemoryStateReader, shared_class_cache::SharedClassCache,
             ExecutionResourcesManager,
         },
+        transaction::error::{TransactionError, TransactionRejection},
         utils::{Address, ClassHash},
+        validate_transaction,
     };
 
     use crate::services::api::contract_classes::compiled_class::CompiledClass;
@@ -294,8 +454,56 @@ mod test {
         .unwrap();
         let transaction = Transaction::InvokeFunction(invoke_function);
 
-        let estimated_fee = estimate_fee(&[transaction], state, &block_context).unwrap();
-        assert_eq!(estimated_fee[0], (2483, 2448));
+        let estimated_fee = estimate_fee(
+            &[transaction],
+            state,
+            &block_context,
+            &SimulationFlags::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            estimated_fee[0].as_ref().unwrap(),
+            &FeeEstimate {
+                gas_consumed: 2483,
+                gas_price: 1,
+                data_gas_consumed: 0,
+                data_gas_price: 0,
+                overall_fee: 2483,
+                unit: FeeType::Eth,
+            }
+        );
+    }
+
+    #[test]
+    fn estimate_fee_reports_a_result_per_transaction() {
+        let (block_context, state) = create_account_tx_test_state().unwrap();
+        let invoke =
+            |nonce: u64, selector: &[u8]| create_test_contract_invoke(selector, 2.into(), nonce, 0);
+        let txs = [
+            invoke(0, b"return_result"),
+            invoke(0, b"return_result"),
+            invoke(1, b"missing_entry_point"),
+        ];
+        // The estimation continues after the failed transactions even without
+        // `continue_on_failure`.
+        let flags = SimulationFlags {
+            skip_validate: true,
+            ..Default::default()
+        };
+
+        let estimates = estimate_fee(&txs, state, &block_context, &flags).unwrap();
+        assert_eq!(estimates.len(), 3);
+
+        let estimate = estimates[0].as_ref().unwrap();
+        assert_eq!(
+            estimate.overall_fee,
+            estimate.gas_consumed * estimate.gas_price
+        );
+        assert_matches!(
+            estimates[1],
+            Err(TransactionError::InvalidTransactionNonce(..))
+        );
+        assert_matches!(estimates[2], Err(TransactionError::ExecutionReverted(_)));
     }
 
     #[test]
@@ -340,6 +548,42 @@ mod test {
         assert_eq!(retdata, vec![89.into()]);
     }
 
+    #[test]
+    fn call_contract_on_forked_state_with_replaced_class() {
+        #[cfg(not(feature = "cairo_1_tests"))]
+        let program_data = include_bytes!("../starknet_programs/cairo2/fibonacci.casm");
+        #[cfg(feature = "cairo_1_tests")]
+        let program_data = include_bytes!("../starknet_programs/cairo1/fibonacci.casm");
+
+        let contract_class: CasmContractClass = serde_json::from_slice(program_data).unwrap();
+        let entrypoints = contract_class.clone().entry_points_by_type;
+        let entrypoint_selector = &entrypoints.external.get(0).unwrap().selector;
+
+        // The contract doesn't exist in the backing state, it only exists in the fork.
+        let address = Address(1111.into());
+        let mut state_reader = ForkedStateReader::new(Arc::new(InMemoryStateReader::default()));
+        state_reader.replace_class(
+            address.clone(),
+            [1; 32],
+            CompiledClass::Casm(Arc::new(contract_class)),
+        );
+
+        let mut state = CachedState::new(Arc::new(state_reader), HashMap::new());
+        let calldata = [1.into(), 1.into(), 10.into()].to_vec();
+
+        let retdata = call_contract(
+            address.0,
+            entrypoint_selector.into(),
+            calldata,
+            &mut state,
+            BlockContext::default(),
+            Address(0.into()),
+        )
+        .unwrap();
+
+        assert_eq!(retdata, vec![89.into()]);
+    }
+
     #[test]
     fn test_estimate_message_fee() {
         let l1_handler = L1Handler::new(
@@ -386,10 +630,20 @@ mod test {
         state.set_contract_classes(contract_classes).unwrap();
 
         let mut block_context = BlockContext::default();
-        block_context.starknet_os_config.gas_price = 1;
+        block_context.block_info.gas_price = 1;
 
         let estimated_fee = estimate_message_fee(&l1_handler, state, &block_context).unwrap();
-        assert_eq!(estimated_fee, (18485, 18471));
+        assert_eq!(
+            estimated_fee,
+            FeeEstimate {
+                gas_consumed: 18485,
+                gas_price: 1,
+                data_gas_consumed: 0,
+                data_gas_price: 0,
+                overall_fee: 18485,
+                unit: FeeType::Eth,
+            }
+        );
     }
 
     #[test]
@@ -560,13 +814,17 @@ mod test {
             state_reader,
             &block_context,
             1000,
-            false,
-            true,
-            true,
-            false,
-            false,
+            &SimulationFlags {
+                skip_execute: true,
+                skip_fee_transfer: true,
+                ..Default::default()
+            },
         )
         .unwrap();
+        let context: Vec<_> = context
+            .iter()
+            .map(|result| result.execution_info().unwrap())
+            .collect();
 
         assert!(context[0].validate_info.is_some());
         assert!(context[0].call_info.is_none());
@@ -662,13 +920,18 @@ mod test {
             state_reader,
             &block_context,
             1000,
-            true,
-            true,
-            true,
-            false,
-            false,
+            &SimulationFlags {
+                skip_validate: true,
+                skip_execute: true,
+                skip_fee_transfer: true,
+                ..Default::default()
+            },
         )
         .unwrap();
+        let context: Vec<_> = context
+            .iter()
+            .map(|result| result.execution_info().unwrap())
+            .collect();
 
         assert!(context[0].validate_info.is_none());
         assert!(context[0].call_info.is_none());
@@ -708,11 +971,7 @@ mod test {
             state,
             block_context,
             100_000_000,
-            false,
-            false,
-            false,
-            false,
-            false,
+            &SimulationFlags::default(),
         )
         .unwrap();
     }
@@ -745,11 +1004,7 @@ mod test {
             state,
             block_context,
             100_000_000,
-            false,
-            false,
-            false,
-            false,
-            false,
+            &SimulationFlags::default(),
         )
         .unwrap();
     }
@@ -809,15 +1064,445 @@ mod test {
             state,
             &block_context,
             100_000_000,
-            false,
-            false,
-            false,
-            false,
-            false,
+            &SimulationFlags::default(),
         )
         .unwrap();
     }
 
+    #[test]
+    fn validate_transaction_checks_the_admission_of_transactions() {
+        let (mut block_context, state) = create_account_tx_test_state().unwrap();
+        block_context.validate_max_n_steps = 1_000_000;
+        let invoke = |version: u64, nonce: u64, max_fee: u128| {
+            Transaction::InvokeFunction(
+                InvokeFunction::new(
+                    TEST_ACCOUNT_CONTRACT_ADDRESS.clone(),
+                    EXECUTE_ENTRY_POINT_SELECTOR.clone(),
+                    max_fee,
+                    version.into(),
+                    vec![
+                        TEST_CONTRACT_ADDRESS.0.clone(),
+                        Felt252::zero(),
+                        Felt252::zero(),
+                    ],
+                    vec![],
+                    StarknetChainId::TestNet.to_felt(),
+                    Some(nonce.into()),
+                )
+                .unwrap(),
+            )
+        };
+
+        let validate_info =
+            validate_transaction(&invoke(1, 0, 1000), state.clone(), &block_context, 2)
+                .unwrap()
+                .unwrap();
+        assert_eq!(
+            validate_info.entry_point_selector,
+            Some(VALIDATE_ENTRY_POINT_SELECTOR.clone())
+        );
+        assert!(
+            validate_transaction(&invoke(1, 2, 1000), state.clone(), &block_context, 2).is_ok()
+        );
+
+        assert_matches!(
+            validate_transaction(&invoke(4, 0, 1000), state.clone(), &block_context, 2),
+            Err(TransactionRejection::UnsupportedVersion(_))
+        );
+        assert_matches!(
+            validate_transaction(&invoke(1, 3, 1000), state.clone(), &block_context, 2),
+            Err(TransactionRejection::NonceTooFarAhead { .. })
+        );
+        // The account has a balance of 10000000.
+        assert_matches!(
+            validate_transaction(&invoke(1, 0, 10000001), state.clone(), &block_context, 2),
+            Err(TransactionRejection::InsufficientBalance {
+                max_fee: 10000001,
+                ..
+            })
+        );
+
+        block_context.validate_max_n_steps = 1;
+        assert_matches!(
+            validate_transaction(&invoke(1, 0, 1000), state, &block_context, 2),
+            Err(TransactionRejection::ValidateStepsExceeded { max_n_steps: 1, .. })
+        );
+    }
+
+    #[test]
+    fn validate_transaction_stops_a_validation_that_never_ends() {
+        let (mut block_context, mut state) = create_account_tx_test_state().unwrap();
+        block_context.validate_max_n_steps = 10_000;
+        let account_class =
+            ContractClass::from_path("starknet_programs/account_with_endless_validation.json")
+                .unwrap();
+        state
+            .set_contract_class(
+                &felt_to_hash(&TEST_ACCOUNT_CONTRACT_CLASS_HASH),
+                &CompiledClass::Deprecated(Arc::new(account_class)),
+            )
+            .unwrap();
+        let invoke = Transaction::InvokeFunction(
+            InvokeFunction::new(
+                TEST_ACCOUNT_CONTRACT_ADDRESS.clone(),
+                EXECUTE_ENTRY_POINT_SELECTOR.clone(),
+                1000,
+                1.into(),
+                vec![
+                    TEST_CONTRACT_ADDRESS.0.clone(),
+                    Felt252::zero(),
+                    Felt252::zero(),
+                ],
+                vec![],
+                StarknetChainId::TestNet.to_felt(),
+                Some(0.into()),
+            )
+            .unwrap(),
+        );
+
+        assert_matches!(
+            validate_transaction(&invoke, state, &block_context, 0),
+            Err(TransactionRejection::ValidateStepsExceeded {
+                n_steps: 10_001,
+                max_n_steps: 10_000,
+            })
+        );
+    }
+
+    #[test]
+    fn execute_block_includes_reverted_and_rejects_invalid_transactions() {
+        let (block_context, mut state) = create_account_tx_test_state().unwrap();
+        let invoke = |nonce: u64, selector: &[u8]| {
+            create_test_contract_invoke(selector, 2.into(), nonce, 1000000)
+        };
+        let txs = [
+            invoke(0, b"return_result"),
+            // Reuses the nonce of the first transaction.
+            invoke(0, b"missing_entry_point"),
+            invoke(1, b"missing_entry_point"),
+        ];
+
+        let output = execute_block(&txs, &block_context, &mut state).unwrap();
+
+        assert_eq!(
+            output
+                .execution_infos
+                .iter()
+                .map(|(index, info)| (*index, info.revert_error.is_some()))
+                .collect::<Vec<_>>(),
+            vec![(0, false), (2, true)]
+        );
+        assert_eq!(output.rejected_transactions.len(), 1);
+        assert_eq!(output.rejected_transactions[0].index, 1);
+        assert_eq!(
+            output.rejected_transactions[0].transaction_hash,
+            txs[1].hash_value()
+        );
+        assert_matches!(
+            output.rejected_transactions[0].error,
+            TransactionError::InvalidTransactionNonce(..)
+        );
+
+        let fees: u128 = output
+            .execution_infos
+            .iter()
+            .map(|(_, info)| info.actual_fee)
+            .sum();
+        assert_eq!(output.total_fees, HashMap::from([(FeeType::Eth, fees)]));
+        let n_steps: usize = output
+            .execution_infos
+            .iter()
+            .map(|(_, info)| info.actual_resources["n_steps"])
+            .sum();
+        assert_eq!(output.total_resources["n_steps"], n_steps);
+        assert_eq!(
+            output.state_diff.address_to_nonce()[&TEST_ACCOUNT_CONTRACT_ADDRESS],
+            2.into()
+        );
+        assert_eq!(
+            state.get_nonce_at(&TEST_ACCOUNT_CONTRACT_ADDRESS).unwrap(),
+            2.into()
+        );
+    }
+
+    #[test]
+    fn execute_block_charges_independent_transactions_the_same_fee() {
+        let (mut block_context, mut state) = create_account_tx_test_state().unwrap();
+        block_context.block_info.gas_price = 1;
+        let txs = [
+            create_test_contract_invoke(b"return_result", 2.into(), 0, 1000000),
+            create_test_contract_invoke(b"return_result", 2.into(), 1, 1000000),
+        ];
+
+        let output = execute_block(&txs, &block_context, &mut state).unwrap();
+
+        // Each transaction is charged for its own changes, not for the ones of the transactions
+        // before it in the block.
+        let fees: Vec<u128> = output
+            .execution_infos
+            .iter()
+            .map(|(_, info)| info.actual_fee)
+            .collect();
+        assert_eq!(fees.len(), 2);
+        assert!(fees[0] > 0);
+        assert_eq!(fees[0], fees[1]);
+        assert_eq!(
+            output.total_fees,
+            HashMap::from([(FeeType::Eth, fees[0] + fees[1])])
+        );
+    }
+
+    #[test]
+    fn fee_breakdown_adds_up_to_the_actual_fee() {
+        let (mut block_context, mut state) = create_account_tx_test_state().unwrap();
+        block_context.block_info.gas_price = 7;
+        let tx = create_test_contract_invoke(b"return_result", 2.into(), 0, 1000000);
+
+        let info = tx
+            .execute(&mut state, &block_context, INITIAL_GAS_COST)
+            .unwrap();
+
+        let fee_breakdown = info.fee_breakdown.unwrap();
+        assert!(info.actual_fee > 0);
+        assert_eq!(fee_breakdown.total_l1_gas * 7, info.actual_fee);
+    }
+
+    #[test]
+    fn execute_block_closes_before_the_bouncer_limits() {
+        let (block_context, state) = create_account_tx_test_state().unwrap();
+        let invoke =
+            |nonce: u64| create_test_contract_invoke(b"return_result", 2.into(), nonce, 1000000);
+        let txs: Vec<Transaction> = (0..3).map(invoke).collect();
+
+        let unlimited = execute_block(&txs, &block_context, &mut state.clone()).unwrap();
+        assert_eq!(unlimited.overflowing_transaction, None);
+        assert_eq!(
+            unlimited.block_weights.n_steps,
+            unlimited.total_resources["n_steps"]
+        );
+
+        // Only the first two transactions fit in the block.
+        let n_steps: usize = unlimited.execution_infos[..2]
+            .iter()
+            .map(|(_, info)| info.actual_resources["n_steps"])
+            .sum();
+        let block_context = block_context.with_bouncer_limits(BouncerWeights {
+            n_steps,
+            ..BouncerWeights::max()
+        });
+        let mut state = state;
+        let output = execute_block(&txs, &block_context, &mut state).unwrap();
+
+        assert_eq!(output.execution_infos.len(), 2);
+        assert!(output.rejected_transactions.is_empty());
+        assert_eq!(output.overflowing_transaction, Some(2));
+        assert_eq!(output.block_weights.n_steps, n_steps);
+        // The changes of the transaction that overflowed the block are discarded.
+        assert_eq!(
+            state.get_nonce_at(&TEST_ACCOUNT_CONTRACT_ADDRESS).unwrap(),
+            2.into()
+        );
+    }
+
+    #[test]
+    fn simulate_transaction_continues_after_failures_with_overrides() {
+        let (block_context, state) = create_account_tx_test_state().unwrap();
+        let invoke = |nonce: u64, selector: &[u8]| {
+            create_test_contract_invoke(selector, 2.into(), nonce, 1000000)
+        };
+        let txs = [
+            invoke(3, b"return_result"),
+            // Reuses the nonce of the first transaction.
+            invoke(3, b"return_result"),
+            invoke(4, b"missing_entry_point"),
+        ];
+        let txs: Vec<&Transaction> = txs.iter().collect();
+        let mut flags = SimulationFlags {
+            block_overrides: BlockOverrides {
+                gas_price: Some(0),
+                ..Default::default()
+            },
+            state_overrides: StateOverrides {
+                nonces: HashMap::from([(TEST_ACCOUNT_CONTRACT_ADDRESS.clone(), 3.into())]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_matches!(
+            simulate_transaction(&txs, &state, &block_context, 100_000_000, &flags),
+            Err(TransactionError::InvalidTransactionNonce(..))
+        );
+
+        flags.continue_on_failure = true;
+        let results =
+            simulate_transaction(&txs, &state, &block_context, 100_000_000, &flags).unwrap();
+        assert_matches!(&results[0], SimulationResult::Succeeded(info) if info.actual_fee == 0);
+        assert_matches!(
+            &results[1],
+            SimulationResult::Failed(TransactionError::InvalidTransactionNonce(..))
+        );
+        assert_matches!(&results[2], SimulationResult::Reverted(info) if info.actual_fee == 0);
+
+        // The overrides don't change the simulated state.
+        assert_eq!(
+            state.get_nonce_at(&TEST_ACCOUNT_CONTRACT_ADDRESS).unwrap(),
+            0.into()
+        );
+    }
+
+    #[test]
+    fn simulate_transaction_reads_each_class_once_with_a_class_cache() {
+        let (block_context, state) = create_account_tx_test_state().unwrap();
+        let class_cache = Arc::new(SharedClassCache::with_limits(None, None));
+        let block_context = block_context.with_class_cache(class_cache.clone());
+        let invoke = create_test_contract_invoke(b"return_result", 2.into(), 0, 1000000);
+        let flags = SimulationFlags {
+            block_overrides: BlockOverrides {
+                gas_price: Some(0),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        simulate_transaction(&[&invoke], &state, &block_context, 100_000_000, &flags).unwrap();
+        let misses = class_cache.stats().misses;
+        assert!(misses > 0);
+
+        // The classes read by the first simulation are reused by the second one.
+        simulate_transaction(&[&invoke], &state, &block_context, 100_000_000, &flags).unwrap();
+        assert_eq!(class_cache.stats().misses, misses);
+        assert!(class_cache.stats().hits > 0);
+    }
+
+    #[test]
+    fn execute_block_parallel_matches_the_sequential_execution() {
+        let (block_context, state) = create_account_tx_test_state().unwrap();
+        let invoke = |nonce: u64, value: u64| {
+            create_test_contract_invoke(b"return_result", value.into(), nonce, 1000000)
+        };
+        // Every transaction depends on the ones before it, through the nonce of the account
+        // and the fee balances.
+        let txs: Vec<Transaction> = [(0, 1), (1, 2), (1, 3), (2, 4), (3, 5), (5, 6), (4, 7)]
+            .into_iter()
+            .map(|(nonce, value)| invoke(nonce, value))
+            .collect();
+
+        let mut sequential_state = state.clone();
+        let sequential = execute_block(&txs, &block_context, &mut sequential_state).unwrap();
+        let mut parallel_state = state;
+        let parallel =
+            execute_block_parallel(&txs, &block_context, &mut parallel_state, 4).unwrap();
+
+        assert_eq!(parallel.execution_infos, sequential.execution_infos);
+        assert_eq!(
+            parallel
+                .rejected_transactions
+                .iter()
+                .map(|rejected| rejected.index)
+                .collect::<Vec<_>>(),
+            vec![2, 5]
+        );
+        assert_eq!(
+            sequential
+                .rejected_transactions
+                .iter()
+                .map(|rejected| rejected.index)
+                .collect::<Vec<_>>(),
+            vec![2, 5]
+        );
+        assert_eq!(parallel.state_diff, sequential.state_diff);
+        assert_eq!(parallel.total_fees, sequential.total_fees);
+        assert_eq!(parallel.total_resources, sequential.total_resources);
+        assert_eq!(parallel.events, sequential.events);
+        assert_eq!(
+            parallel_state
+                .get_nonce_at(&TEST_ACCOUNT_CONTRACT_ADDRESS)
+                .unwrap(),
+            5.into()
+        );
+    }
+
+    #[test]
+    fn execute_block_parallel_executes_again_the_transactions_after_one_too_large() {
+        let (block_context, state) = create_account_tx_test_state().unwrap();
+        let invoke =
+            |nonce: u64| create_test_contract_invoke(b"return_result", 2.into(), nonce, 1000000);
+        // Computing the 10000th Fibonacci number takes more steps than the other transactions
+        // together.
+        let too_large = Transaction::InvokeFunction(
+            InvokeFunction::new(
+                TEST_ACCOUNT_CONTRACT_ADDRESS.clone(),
+                EXECUTE_ENTRY_POINT_SELECTOR.clone(),
+                1000000,
+                1.into(),
+                vec![
+                    TEST_CONTRACT_ADDRESS.0.clone(),
+                    Felt252::from_bytes_be(&calculate_sn_keccak(b"fib")),
+                    3.into(),
+                    1.into(),
+                    1.into(),
+                    10000.into(),
+                ],
+                vec![],
+                StarknetChainId::TestNet.to_felt(),
+                Some(1.into()),
+            )
+            .unwrap(),
+        );
+        // The transactions after the one that is too large reuse its nonce.
+        let txs = vec![invoke(0), too_large, invoke(1), invoke(2)];
+
+        let unlimited = execute_block(&txs, &block_context, &mut state.clone()).unwrap();
+        let (_, too_large_info) = &unlimited.execution_infos[1];
+        let block_context = block_context.with_bouncer_limits(BouncerWeights {
+            n_steps: too_large_info.actual_resources["n_steps"] - 1,
+            ..BouncerWeights::max()
+        });
+
+        let mut sequential_state = state.clone();
+        let sequential = execute_block(&txs, &block_context, &mut sequential_state).unwrap();
+        let mut parallel_state = state;
+        let parallel =
+            execute_block_parallel(&txs, &block_context, &mut parallel_state, 4).unwrap();
+
+        assert_eq!(
+            sequential
+                .execution_infos
+                .iter()
+                .map(|(index, _)| *index)
+                .collect::<Vec<_>>(),
+            vec![0, 2, 3]
+        );
+        assert_matches!(
+            sequential.rejected_transactions.as_slice(),
+            [rejected] if rejected.index == 1
+                && matches!(rejected.error, TransactionError::TransactionTooLargeForBlock(_))
+        );
+        assert_matches!(
+            parallel.rejected_transactions.as_slice(),
+            [rejected] if rejected.index == 1
+                && matches!(rejected.error, TransactionError::TransactionTooLargeForBlock(_))
+        );
+        assert_eq!(parallel.execution_infos, sequential.execution_infos);
+        assert_eq!(
+            parallel.overflowing_transaction,
+            sequential.overflowing_transaction
+        );
+        assert_eq!(parallel.state_diff, sequential.state_diff);
+        assert_eq!(parallel.total_fees, sequential.total_fees);
+        assert_eq!(parallel.total_resources, sequential.total_resources);
+        assert_eq!(parallel.block_weights, sequential.block_weights);
+        assert_eq!(parallel.events, sequential.events);
+        assert_eq!(parallel_state.cache(), sequential_state.cache());
+        assert_eq!(
+            parallel_state
+                .get_nonce_at(&TEST_ACCOUNT_CONTRACT_ADDRESS)
+                .unwrap(),
+            3.into()
+        );
+    }
+
     #[test]
     fn test_simulate_deploy_account() {
         let state_reader = Arc::new(InMemoryStateReader::default());
@@ -852,11 +1537,7 @@ mod test {
             state,
             block_context,
             100_000_000,
-            false,
-            false,
-            false,
-            false,
-            false,
+            &SimulationFlags::default(),
         )
         .unwrap();
     }
@@ -884,6 +1565,7 @@ mod test {
             skip_execute: false,
             skip_fee_transfer: false,
             skip_validate: false,
+            v3_fields: None,
         }
     }
 
@@ -897,11 +1579,10 @@ mod test {
             state,
             &block_context,
             100_000_000,
-            false,
-            false,
-            true,
-            false,
-            false,
+            &SimulationFlags {
+                skip_fee_transfer: true,
+                ..Default::default()
+            },
         )
         .unwrap();
     }
@@ -958,18 +1639,14 @@ mod test {
             .unwrap();
 
         let mut block_context = BlockContext::default();
-        block_context.starknet_os_config.gas_price = 1;
+        block_context.block_info.gas_price = 1;
 
         simulate_transaction(
             &[&l1_handler_tx],
             state,
             &block_context,
             100_000_000,
-            false,
-            false,
-            false,
-            false, // won't have any effect
-            false,
+            &SimulationFlags::default(),
         )
         .unwrap();
     }
@@ -1022,18 +1699,51 @@ mod test {
             state.clone(),
             block_context,
             100_000_000,
-            false,
-            false,
-            false,
-            false,
-            false,
+            &SimulationFlags::default(),
         )
         .unwrap();
 
-        assert_eq!(
-            estimate_fee(&[deploy, invoke_tx], state, block_context,).unwrap(),
-            [(0, 2448), (0, 2448)]
-        );
+        // The estimation skips the fee transfer and ignores the max fee.
+        let results = simulate_transaction(
+            &[&deploy, &invoke_tx],
+            state.clone(),
+            block_context,
+            100_000_000,
+            &SimulationFlags {
+                skip_fee_transfer: true,
+                ignore_max_fee: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let estimates = estimate_fee(
+            &[deploy, invoke_tx],
+            state,
+            block_context,
+            &SimulationFlags::default(),
+        )
+        .unwrap();
+        assert_eq!(estimates.len(), 2);
+        for (result, estimate) in results.iter().zip(estimates) {
+            let execution_info = match result {
+                SimulationResult::Succeeded(execution_info) => execution_info,
+                _ => panic!("the simulation failed"),
+            };
+            let estimate = estimate.unwrap();
+            // The gas price of the default block context is 0.
+            assert_eq!(
+                (
+                    estimate.overall_fee,
+                    execution_info.actual_resources["l1_gas_usage"]
+                ),
+                (0, 2448)
+            );
+            // The L1 gas of the state diff plus the one of the Cairo resources.
+            assert_eq!(
+                estimate.gas_consumed,
+                calculate_tx_l1_gas_usage(&execution_info.actual_resources, block_context).unwrap()
+            );
+        }
     }
 
     #[test]
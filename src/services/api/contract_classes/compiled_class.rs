@@ -5,9 +5,10 @@ use std::sync::Arc;
 use crate::core::contract_address::{compute_hinted_class_hash, CairoProgramToHash};
 use crate::services::api::contract_class_errors::ContractClassError;
 use crate::services::api::contract_classes::deprecated_contract_class::AbiType;
+use crate::utils::{felt_from_hex, felt_to_hex};
 use crate::{ContractEntryPoint, EntryPointType};
 
-use super::deprecated_contract_class::ContractClass;
+use super::deprecated_contract_class::{to_cairo_runner_program, ContractClass};
 use cairo_lang_starknet::abi::Contract;
 use cairo_lang_starknet::casm_contract_class::CasmContractClass;
 use cairo_lang_starknet::contract_class::{
@@ -19,6 +20,7 @@ use cairo_vm::types::program::Program;
 use serde::{Deserialize, Serialize};
 use starknet::core::types::ContractClass as StarknetRsContractClass;
 use starknet::core::types::ContractClass::{Legacy, Sierra};
+use starknet::core::types::FlattenedSierraClass;
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub enum CompiledClass {
@@ -58,23 +60,46 @@ struct MiddleSierraContractClass {
     #[allow(dead_code)]
     abi: serde_json::Value,
 }
+/// Converts a Sierra class in the format of the JSON-RPC into a [SierraContractClass].
+pub fn sierra_class_from_flattened(
+    flattened_sierra_contract_class: &FlattenedSierraClass,
+) -> SierraContractClass {
+    let abi: Option<Contract> = serde_json::from_str(&flattened_sierra_contract_class.abi).unwrap();
+
+    let v = serde_json::to_value(flattened_sierra_contract_class).unwrap();
+    let middle_sierra: MiddleSierraContractClass = serde_json::from_value(v).unwrap();
+
+    SierraContractClass {
+        sierra_program: middle_sierra.sierra_program,
+        sierra_program_debug_info: middle_sierra.sierra_program_debug_info,
+        contract_class_version: middle_sierra.contract_class_version,
+        entry_points_by_type: middle_sierra.entry_points_by_type,
+        abi,
+    }
+}
+
 impl From<StarknetRsContractClass> for CompiledClass {
     fn from(starknet_rs_contract_class: StarknetRsContractClass) -> Self {
-        match starknet_rs_contract_class {
-            Sierra(flattened_sierra_contract_class) => {
-                let abi: Option<Contract> =
-                    serde_json::from_str(&flattened_sierra_contract_class.abi).unwrap();
+        CompiledClass::from_starknet_rs_class(starknet_rs_contract_class, false)
+    }
+}
 
-                let v = serde_json::to_value(&flattened_sierra_contract_class).unwrap();
-                let middle_sierra: MiddleSierraContractClass = serde_json::from_value(v).unwrap();
+impl CompiledClass {
+    /// Like the [From] conversion, but Cairo 0 classes keep the JSON of their program so they
+    /// can be serialized (see [ContractClass::from_str_with_program_json]).
+    pub fn from_starknet_rs_class_with_program_json(
+        starknet_rs_contract_class: StarknetRsContractClass,
+    ) -> Self {
+        CompiledClass::from_starknet_rs_class(starknet_rs_contract_class, true)
+    }
 
-                let sierra_cc = SierraContractClass {
-                    sierra_program: middle_sierra.sierra_program,
-                    sierra_program_debug_info: middle_sierra.sierra_program_debug_info,
-                    contract_class_version: middle_sierra.contract_class_version,
-                    entry_points_by_type: middle_sierra.entry_points_by_type,
-                    abi,
-                };
+    fn from_starknet_rs_class(
+        starknet_rs_contract_class: StarknetRsContractClass,
+        keep_program_json: bool,
+    ) -> Self {
+        match starknet_rs_contract_class {
+            Sierra(flattened_sierra_contract_class) => {
+                let sierra_cc = sierra_class_from_flattened(&flattened_sierra_contract_class);
 
                 let casm_cc = CasmContractClass::from_contract_class(sierra_cc, true).unwrap();
 
@@ -154,6 +179,7 @@ impl From<StarknetRsContractClass> for CompiledClass {
                     entry_points_by_type,
                     abi,
                     hinted_class_hash,
+                    program_json: keep_program_json.then(|| as_str.into()),
                 }))
             }
         }
@@ -178,3 +204,190 @@ fn decode_reader(bytes: Vec<u8>) -> io::Result<String> {
     gz.read_to_string(&mut s)?;
     Ok(s)
 }
+
+// -------------------------------
+//         Serialization
+// -------------------------------
+
+/// Serialized form of a [ContractClass]. The program is kept as the JSON it was parsed from.
+#[derive(Serialize, Deserialize)]
+struct SerializedDeprecatedClass {
+    program: String,
+    entry_points_by_type: HashMap<String, Vec<SerializedEntryPoint>>,
+    abi: Option<AbiType>,
+    hinted_class_hash: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializedEntryPoint {
+    selector: String,
+    offset: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum SerializedCompiledClassRef<'a> {
+    Deprecated(SerializedDeprecatedClass),
+    Casm(&'a CasmContractClass),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum SerializedCompiledClass {
+    Deprecated(SerializedDeprecatedClass),
+    Casm(CasmContractClass),
+}
+
+const fn entry_point_type_name(entry_point_type: &EntryPointType) -> &'static str {
+    match entry_point_type {
+        EntryPointType::External => "EXTERNAL",
+        EntryPointType::L1Handler => "L1_HANDLER",
+        EntryPointType::Constructor => "CONSTRUCTOR",
+    }
+}
+
+impl Serialize for CompiledClass {
+    /// Serializes the class. Cairo 0 classes can only be serialized if the JSON of their
+    /// program was kept when they were parsed, see [ContractClass::from_str_with_program_json].
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            CompiledClass::Casm(casm_class) => {
+                SerializedCompiledClassRef::Casm(casm_class).serialize(serializer)
+            }
+            CompiledClass::Deprecated(contract_class) => {
+                let program = contract_class.program_json().ok_or_else(|| {
+                    serde::ser::Error::custom("the program of the class is not available")
+                })?;
+                let entry_points_by_type = contract_class
+                    .entry_points_by_type()
+                    .iter()
+                    .map(|(entry_point_type, entry_points)| {
+                        (
+                            entry_point_type_name(entry_point_type).to_string(),
+                            entry_points
+                                .iter()
+                                .map(|entry_point| SerializedEntryPoint {
+                                    selector: felt_to_hex(entry_point.selector()),
+                                    offset: entry_point.offset(),
+                                })
+                                .collect(),
+                        )
+                    })
+                    .collect();
+
+                SerializedCompiledClassRef::Deprecated(SerializedDeprecatedClass {
+                    program: program.to_string(),
+                    entry_points_by_type,
+                    abi: contract_class.abi().clone(),
+                    hinted_class_hash: felt_to_hex(contract_class.hinted_class_hash()),
+                })
+                .serialize(serializer)
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CompiledClass {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let parse_felt = |value: &str| {
+            felt_from_hex(value).ok_or_else(|| D::Error::custom(format!("invalid felt: {value}")))
+        };
+
+        match SerializedCompiledClass::deserialize(deserializer)? {
+            SerializedCompiledClass::Casm(casm_class) => {
+                Ok(CompiledClass::Casm(Arc::new(casm_class)))
+            }
+            SerializedCompiledClass::Deprecated(serialized_class) => {
+                let program: starknet_api::deprecated_contract_class::Program =
+                    serde_json::from_str(&serialized_class.program).map_err(D::Error::custom)?;
+                let program = to_cairo_runner_program(program).map_err(D::Error::custom)?;
+
+                let mut entry_points_by_type = HashMap::new();
+                for (entry_point_type, entry_points) in serialized_class.entry_points_by_type {
+                    let entry_point_type = match entry_point_type.as_str() {
+                        "EXTERNAL" => EntryPointType::External,
+                        "L1_HANDLER" => EntryPointType::L1Handler,
+                        "CONSTRUCTOR" => EntryPointType::Constructor,
+                        other => {
+                            return Err(D::Error::custom(format!(
+                                "invalid entry point type: {other}"
+                            )))
+                        }
+                    };
+                    let entry_points = entry_points
+                        .into_iter()
+                        .map(|entry_point| {
+                            Ok(ContractEntryPoint::new(
+                                parse_felt(&entry_point.selector)?,
+                                entry_point.offset,
+                            ))
+                        })
+                        .collect::<Result<Vec<_>, D::Error>>()?;
+                    entry_points_by_type.insert(entry_point_type, entry_points);
+                }
+
+                let mut contract_class = ContractClass::new_with_hinted_class_hash(
+                    parse_felt(&serialized_class.hinted_class_hash)?,
+                    program,
+                    entry_points_by_type,
+                    serialized_class.abi,
+                )
+                .map_err(|e| D::Error::custom(format!("{e:?}")))?;
+                contract_class.program_json = Some(serialized_class.program.into());
+
+                Ok(CompiledClass::Deprecated(Arc::new(contract_class)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deprecated_class_serialization_roundtrip() {
+        let contract_class = ContractClass::from_str_with_program_json(
+            &std::fs::read_to_string("starknet_programs/raw_contract_classes/class_with_abi.json")
+                .unwrap(),
+        )
+        .unwrap();
+        let compiled_class = CompiledClass::Deprecated(Arc::new(contract_class));
+
+        let serialized = serde_json::to_string(&compiled_class).unwrap();
+        let deserialized: CompiledClass = serde_json::from_str(&serialized).unwrap();
+
+        assert_eq!(deserialized, compiled_class);
+    }
+
+    #[test]
+    fn casm_class_serialization_roundtrip() {
+        #[cfg(not(feature = "cairo_1_tests"))]
+        let program_data = include_bytes!("../../../../starknet_programs/cairo2/fibonacci.casm");
+        #[cfg(feature = "cairo_1_tests")]
+        let program_data = include_bytes!("../../../../starknet_programs/cairo1/fibonacci.casm");
+
+        let casm_class: CasmContractClass = serde_json::from_slice(program_data).unwrap();
+        let compiled_class = CompiledClass::Casm(Arc::new(casm_class));
+
+        let serialized = serde_json::to_string(&compiled_class).unwrap();
+        let deserialized: CompiledClass = serde_json::from_str(&serialized).unwrap();
+
+        assert_eq!(deserialized, compiled_class);
+    }
+
+    #[test]
+    fn deprecated_class_without_program_json_cant_be_serialized() {
+        // Classes don't keep their program's JSON unless asked to.
+        let contract_class =
+            ContractClass::from_path("starknet_programs/raw_contract_classes/class_with_abi.json")
+                .unwrap();
+        assert_eq!(contract_class.program_json(), None);
+
+        assert!(
+            serde_json::to_string(&CompiledClass::Deprecated(Arc::new(contract_class))).is_err()
+        );
+    }
+}
@@ -13,6 +13,7 @@ use serde_json::Value;
 use starknet_api::deprecated_contract_class::{ContractClassAbiEntry, EntryPoint};
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::Arc;
 
 pub type AbiType = Vec<ContractClassAbiEntry>;
 
@@ -71,7 +72,7 @@ impl From<starknet_api::deprecated_contract_class::EntryPointType> for EntryPoin
 //         Contract Class
 // -------------------------------
 
-#[derive(Clone, Debug, Eq, Getters, PartialEq)]
+#[derive(Clone, Debug, Getters)]
 pub struct ContractClass {
     #[getset(get = "pub")]
     pub(crate) program: Program,
@@ -81,8 +82,24 @@ pub struct ContractClass {
     pub(crate) entry_points_by_type: HashMap<EntryPointType, Vec<ContractEntryPoint>>,
     #[getset(get = "pub")]
     pub(crate) abi: Option<AbiType>,
+    /// The JSON of the program, only kept for classes parsed with
+    /// [ContractClass::from_str_with_program_json]. The parsed [Program] can't be turned back
+    /// into JSON, so this is what gets serialized.
+    pub(crate) program_json: Option<Arc<str>>,
 }
 
+impl PartialEq for ContractClass {
+    fn eq(&self, other: &Self) -> bool {
+        // The program's JSON is only kept for serialization, it's not part of the class.
+        self.program == other.program
+            && self.hinted_class_hash == other.hinted_class_hash
+            && self.entry_points_by_type == other.entry_points_by_type
+            && self.abi == other.abi
+    }
+}
+
+impl Eq for ContractClass {}
+
 impl ContractClass {
     pub fn new(
         program_json: Value,
@@ -103,6 +120,7 @@ impl ContractClass {
             program,
             entry_points_by_type,
             abi,
+            program_json: None,
         })
     }
 
@@ -125,6 +143,7 @@ impl ContractClass {
             program,
             entry_points_by_type,
             abi,
+            program_json: None,
         })
     }
 
@@ -155,9 +174,23 @@ impl ContractClass {
             program,
             entry_points_by_type,
             abi: contract_class.abi,
+            program_json: None,
         })
     }
 
+    /// Parses a [`ContractClass`] from a compiled Cairo 0 program's JSON, keeping the JSON of
+    /// its program so the class can be serialized (for example, to record it in a fixture).
+    ///
+    /// Classes parsed with [`FromStr`] don't keep it, as it's a second copy of the program.
+    pub fn from_str_with_program_json(program_json: &str) -> Result<Self, ProgramError> {
+        Self::parse(program_json, true)
+    }
+
+    /// Returns the JSON of the program the class was parsed from, if it was kept.
+    pub fn program_json(&self) -> Option<&str> {
+        self.program_json.as_deref()
+    }
+
     /// Parses a [`ContractClass`] from a compiled Cairo 0 program's JSON
     /// at the given file path.
     pub fn from_path<F>(path: F) -> Result<Self, ProgramError>
@@ -177,19 +210,13 @@ impl ContractClass {
         reader.read_to_string(&mut s)?;
         Self::from_str(s.as_str())
     }
-}
-
-// -------------------------------
-//         From traits
-// -------------------------------
 
-impl FromStr for ContractClass {
-    type Err = ProgramError;
-
-    /// Parses a [`ContractClass`] from a compiled Cairo 0 program's JSON.
-    fn from_str(program_json: &str) -> Result<Self, ProgramError> {
+    fn parse(program_json: &str, keep_program_json: bool) -> Result<Self, ProgramError> {
         let contract_class: starknet_api::deprecated_contract_class::ContractClass =
             serde_json::from_str(program_json)?;
+        let serialized_program = keep_program_json
+            .then(|| serde_json::to_string(&contract_class.program))
+            .transpose()?;
         let program = to_cairo_runner_program(contract_class.program)?;
         let entry_points_by_type = convert_entry_points(contract_class.entry_points_by_type);
         let hinted_class_hash =
@@ -199,10 +226,24 @@ impl FromStr for ContractClass {
             program,
             entry_points_by_type,
             abi: contract_class.abi,
+            program_json: serialized_program.map(Into::into),
         })
     }
 }
 
+// -------------------------------
+//         From traits
+// -------------------------------
+
+impl FromStr for ContractClass {
+    type Err = ProgramError;
+
+    /// Parses a [`ContractClass`] from a compiled Cairo 0 program's JSON.
+    fn from_str(program_json: &str) -> Result<Self, ProgramError> {
+        Self::parse(program_json, false)
+    }
+}
+
 // -------------------
 //  Helper Functions
 // -------------------
@@ -1,5 +1,4 @@
 use cairo_vm::felt::Felt252;
-use num_traits::ToPrimitive;
 use sha3::{Digest, Keccak256};
 
 use crate::utils::Address;
@@ -31,14 +30,13 @@ impl StarknetMessageToL1 {
         encoding
     }
 
+    /// Returns the hash of the message as computed on L1: the keccak of its encoding, with each
+    /// element as a 32 bytes word.
     pub fn get_hash(&self) -> Vec<u8> {
-        let data = self
-            .encode()
-            .iter()
-            .map(|elem| elem.to_usize().unwrap() as u8)
-            .collect::<Vec<u8>>();
         let mut hasher = Keccak256::new();
-        hasher.update(data);
+        for elem in self.encode() {
+            hasher.update(elem.to_be_bytes());
+        }
         let finalized_hash = hasher.finalize();
         finalized_hash.as_slice().to_vec()
     }
@@ -91,8 +89,8 @@ fn get_hash_for_starknet_message_to_l1() {
     assert_eq!(
         message.get_hash(),
         Vec::from([
-            35, 146, 105, 229, 123, 197, 150, 164, 71, 161, 100, 157, 18, 54, 233, 219, 32, 150,
-            155, 238, 74, 8, 254, 114, 153, 144, 74, 32, 110, 104, 86, 42
+            167, 136, 113, 216, 253, 103, 186, 175, 18, 220, 46, 76, 223, 13, 159, 1, 39, 10, 16,
+            199, 0, 12, 66, 204, 54, 152, 75, 18, 225, 196, 153, 246
         ])
     )
 }
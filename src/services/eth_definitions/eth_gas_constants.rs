@@ -16,3 +16,8 @@ pub(crate) const GAS_PER_COUNTER_DECREASE: usize =
 pub(crate) const SHARP_ADDITIONAL_GAS_PER_MEMORY_WORD: usize = 100; //This value is not accurate.
 pub(crate) const SHARP_GAS_PER_MEMORY_WORD: usize =
     GAS_PER_MEMORY_WORD + SHARP_ADDITIONAL_GAS_PER_MEMORY_WORD;
+
+// Blob constants, see https://eips.ethereum.org/EIPS/eip-4844.
+pub(crate) const DATA_GAS_PER_BLOB: usize = 1 << 17;
+pub(crate) const FIELD_ELEMENTS_PER_BLOB: usize = 1 << 12;
+pub(crate) const DATA_GAS_PER_FIELD_ELEMENT: usize = DATA_GAS_PER_BLOB / FIELD_ELEMENTS_PER_BLOB;
@@ -1,14 +1,16 @@
 use super::{
+    shared_class_cache::SharedClassCache,
     state_api::{State, StateReader},
     state_cache::{StateCache, StorageEntry},
 };
 use crate::{
     core::errors::state_errors::StateError,
+    definitions::block_context::BlockContext,
     services::api::contract_classes::compiled_class::CompiledClass,
     state::StateDiff,
     utils::{
         get_erc20_balance_var_addresses, subtract_mappings, to_cache_state_storage_mapping,
-        Address, ClassHash,
+        Address, ClassHash, CompiledClassHash,
     },
 };
 use cairo_vm::felt::Felt252;
@@ -16,6 +18,7 @@ use getset::{Getters, MutGetters};
 use num_traits::Zero;
 use std::{
     collections::{HashMap, HashSet},
+    hash::Hash,
     sync::Arc,
 };
 
@@ -23,6 +26,30 @@ pub type ContractClassCache = HashMap<ClassHash, CompiledClass>;
 
 pub const UNINITIALIZED_CLASS_HASH: &ClassHash = &[0u8; 32];
 
+/// A savepoint in the writes made to a [CachedState], created with [CachedState::checkpoint].
+///
+/// Checkpoints nest: reverting or committing a checkpoint also discards the ones created after
+/// it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Checkpoint {
+    depth: usize,
+    journal_len: usize,
+}
+
+/// A write made to a [CachedState] while a [Checkpoint] was open, together with the value it
+/// replaced (`None` if there was none), so that it can be undone.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum JournalEntry {
+    ClassHash(Address, Option<ClassHash>),
+    CompiledClass(ClassHash, Option<CompiledClass>),
+    Nonce(Address, Option<Felt252>),
+    Storage(StorageEntry, Option<Felt252>),
+    CompiledClassHash(ClassHash, Option<CompiledClassHash>),
+    ContractClass(ClassHash, Option<CompiledClass>),
+    /// A Cairo 0 class declaration, and whether the class was already declared.
+    DeprecatedDeclaredClass(ClassHash, bool),
+}
+
 /// Represents a cached state of contract classes with optional caches.
 #[derive(Default, Clone, Debug, Eq, Getters, MutGetters, PartialEq)]
 pub struct CachedState<T: StateReader> {
@@ -31,6 +58,12 @@ pub struct CachedState<T: StateReader> {
     pub(crate) cache: StateCache,
     #[get = "pub"]
     pub(crate) contract_classes: ContractClassCache,
+    /// Classes read from the state reader. When set, they are cached here instead of in
+    /// `contract_classes`.
+    #[get = "pub"]
+    pub(crate) class_cache: Option<Arc<SharedClassCache>>,
+    journal: Vec<JournalEntry>,
+    checkpoints: Vec<usize>,
 }
 
 impl<T: StateReader> CachedState<T> {
@@ -40,6 +73,28 @@ impl<T: StateReader> CachedState<T> {
             cache: StateCache::default(),
             state_reader,
             contract_classes,
+            class_cache: None,
+            journal: Vec::new(),
+            checkpoints: Vec::new(),
+        }
+    }
+
+    /// Creates a new cached state that keeps the classes it reads from `state_reader` in
+    /// `class_cache`, which can be shared with other states over the same reader.
+    pub fn new_with_class_cache(state_reader: Arc<T>, class_cache: Arc<SharedClassCache>) -> Self {
+        Self {
+            class_cache: Some(class_cache),
+            ..Self::new(state_reader, HashMap::new())
+        }
+    }
+
+    /// Creates a new cached state to execute transactions of the block of `block_context` on.
+    /// The classes it reads are kept in the class cache of the block context, if it has one
+    /// (see [BlockContext::with_class_cache]).
+    pub fn new_for_block(state_reader: Arc<T>, block_context: &BlockContext) -> Self {
+        match block_context.class_cache() {
+            Some(class_cache) => Self::new_with_class_cache(state_reader, class_cache.clone()),
+            None => Self::new(state_reader, HashMap::new()),
         }
     }
 
@@ -53,6 +108,9 @@ impl<T: StateReader> CachedState<T> {
             cache,
             contract_classes,
             state_reader,
+            class_cache: None,
+            journal: Vec::new(),
+            checkpoints: Vec::new(),
         }
     }
 
@@ -67,6 +125,140 @@ impl<T: StateReader> CachedState<T> {
         self.contract_classes = contract_classes;
         Ok(())
     }
+
+    /// Opens a new savepoint. Every write made from now on is journaled, so that it can be
+    /// undone with [CachedState::revert_to] or kept with [CachedState::commit].
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        self.checkpoints.push(self.journal.len());
+        Checkpoint {
+            depth: self.checkpoints.len() - 1,
+            journal_len: self.journal.len(),
+        }
+    }
+
+    /// Undoes every write made since `checkpoint` was opened, and closes it together with
+    /// the checkpoints nested inside it.
+    ///
+    /// This takes time proportional to the number of writes being undone.
+    pub fn revert_to(&mut self, checkpoint: Checkpoint) -> Result<(), StateError> {
+        self.validate_checkpoint(&checkpoint)?;
+
+        for entry in self.journal.drain(checkpoint.journal_len..).rev() {
+            match entry {
+                JournalEntry::ClassHash(address, previous) => {
+                    restore(&mut self.cache.class_hash_writes, address, previous)
+                }
+                JournalEntry::CompiledClass(class_hash, previous) => restore(
+                    &mut self.cache.compiled_class_hash_writes,
+                    class_hash,
+                    previous,
+                ),
+                JournalEntry::Nonce(address, previous) => {
+                    restore(&mut self.cache.nonce_writes, address, previous)
+                }
+                JournalEntry::Storage(storage_entry, previous) => {
+                    restore(&mut self.cache.storage_writes, storage_entry, previous)
+                }
+                JournalEntry::CompiledClassHash(class_hash, previous) => restore(
+                    &mut self.cache.class_hash_to_compiled_class_hash,
+                    class_hash,
+                    previous,
+                ),
+                JournalEntry::ContractClass(class_hash, previous) => {
+                    restore(&mut self.contract_classes, class_hash, previous)
+                }
+                JournalEntry::DeprecatedDeclaredClass(class_hash, already_declared) => {
+                    if !already_declared {
+                        self.cache.deprecated_declared_classes.remove(&class_hash);
+                    }
+                }
+            }
+        }
+        self.checkpoints.truncate(checkpoint.depth);
+        Ok(())
+    }
+
+    /// Keeps every write made since `checkpoint` was opened, and closes it together with the
+    /// checkpoints nested inside it. The writes can still be undone by reverting an enclosing
+    /// checkpoint.
+    pub fn commit(&mut self, checkpoint: Checkpoint) -> Result<(), StateError> {
+        self.validate_checkpoint(&checkpoint)?;
+
+        self.checkpoints.truncate(checkpoint.depth);
+        if self.checkpoints.is_empty() {
+            self.journal.clear();
+        }
+        Ok(())
+    }
+
+    fn validate_checkpoint(&self, checkpoint: &Checkpoint) -> Result<(), StateError> {
+        match self.checkpoints.get(checkpoint.depth) {
+            Some(journal_len) if *journal_len == checkpoint.journal_len => Ok(()),
+            _ => Err(StateError::InvalidCheckpoint),
+        }
+    }
+
+    /// Reads a class from the state reader, going through the shared class cache if there is
+    /// one.
+    fn read_contract_class(&self, class_hash: &ClassHash) -> Result<CompiledClass, StateError> {
+        match &self.class_cache {
+            Some(class_cache) => class_cache.get_or_fetch(class_hash, || {
+                self.state_reader.get_contract_class(class_hash)
+            }),
+            None => self.state_reader.get_contract_class(class_hash),
+        }
+    }
+
+    /// Records a write in the journal, if there is an open checkpoint.
+    fn record_write(&mut self, entry: JournalEntry) {
+        if !self.checkpoints.is_empty() {
+            self.journal.push(entry);
+        }
+    }
+
+    fn write_class_hash(&mut self, address: Address, class_hash: ClassHash) {
+        let previous = self
+            .cache
+            .class_hash_writes
+            .insert(address.clone(), class_hash);
+        self.record_write(JournalEntry::ClassHash(address, previous));
+    }
+
+    fn write_compiled_class(&mut self, class_hash: ClassHash, compiled_class: CompiledClass) {
+        let previous = self
+            .cache
+            .compiled_class_hash_writes
+            .insert(class_hash, compiled_class);
+        self.record_write(JournalEntry::CompiledClass(class_hash, previous));
+    }
+
+    fn write_nonce(&mut self, address: Address, nonce: Felt252) {
+        let previous = self.cache.nonce_writes.insert(address.clone(), nonce);
+        self.record_write(JournalEntry::Nonce(address, previous));
+    }
+
+    fn write_storage(&mut self, storage_entry: StorageEntry, value: Felt252) {
+        let previous = self
+            .cache
+            .storage_writes
+            .insert(storage_entry.clone(), value);
+        self.record_write(JournalEntry::Storage(storage_entry, previous));
+    }
+
+    fn write_deprecated_declared_class(&mut self, class_hash: ClassHash) {
+        let already_declared = !self.cache.deprecated_declared_classes.insert(class_hash);
+        self.record_write(JournalEntry::DeprecatedDeclaredClass(
+            class_hash,
+            already_declared,
+        ));
+    }
+}
+
+fn restore<K: Eq + Hash, V>(map: &mut HashMap<K, V>, key: K, previous: Option<V>) {
+    match previous {
+        Some(value) => map.insert(key, value),
+        None => map.remove(&key),
+    };
 }
 
 impl<T: StateReader> StateReader for CachedState<T> {
@@ -140,7 +332,7 @@ impl<T: StateReader> StateReader for CachedState<T> {
         }
 
         // II: FETCHING FROM STATE_READER
-        self.state_reader.get_contract_class(class_hash)
+        self.read_contract_class(class_hash)
     }
 }
 
@@ -151,8 +343,10 @@ impl<T: StateReader> State for CachedState<T> {
         class_hash: &ClassHash,
         contract_class: &CompiledClass,
     ) -> Result<(), StateError> {
-        self.contract_classes
+        let previous = self
+            .contract_classes
             .insert(*class_hash, contract_class.clone());
+        self.record_write(JournalEntry::ContractClass(*class_hash, previous));
 
         Ok(())
     }
@@ -179,24 +373,18 @@ impl<T: StateReader> State for CachedState<T> {
             _ => {}
         }
 
-        self.cache
-            .class_hash_writes
-            .insert(deploy_contract_address.clone(), class_hash);
+        self.write_class_hash(deploy_contract_address, class_hash);
         Ok(())
     }
 
     fn increment_nonce(&mut self, contract_address: &Address) -> Result<(), StateError> {
         let new_nonce = self.get_nonce_at(contract_address)? + Felt252::from(1);
-        self.cache
-            .nonce_writes
-            .insert(contract_address.clone(), new_nonce);
+        self.write_nonce(contract_address.clone(), new_nonce);
         Ok(())
     }
 
     fn set_storage_at(&mut self, storage_entry: &StorageEntry, value: Felt252) {
-        self.cache
-            .storage_writes
-            .insert(storage_entry.clone(), value);
+        self.write_storage(storage_entry.clone(), value);
     }
 
     fn set_class_hash_at(
@@ -210,9 +398,7 @@ impl<T: StateReader> State for CachedState<T> {
             ));
         }
 
-        self.cache
-            .class_hash_writes
-            .insert(deploy_contract_address, class_hash);
+        self.write_class_hash(deploy_contract_address, class_hash);
         Ok(())
     }
 
@@ -224,21 +410,43 @@ impl<T: StateReader> State for CachedState<T> {
         let class_hash = class_hash.to_be_bytes();
         let compiled_class_hash = compiled_class_hash.to_be_bytes();
 
-        self.cache
+        let previous = self
+            .cache
             .class_hash_to_compiled_class_hash
             .insert(class_hash, compiled_class_hash);
+        self.record_write(JournalEntry::CompiledClassHash(class_hash, previous));
         Ok(())
     }
 
-    fn apply_state_update(&mut self, state_updates: &StateDiff) -> Result<(), StateError> {
-        let storage_updates = to_cache_state_storage_mapping(&state_updates.storage_updates);
+    fn declare_deprecated_class(&mut self, class_hash: &ClassHash) -> Result<(), StateError> {
+        self.write_deprecated_declared_class(*class_hash);
+        Ok(())
+    }
 
-        self.cache.update_writes(
-            &state_updates.address_to_class_hash,
-            &state_updates.class_hash_to_compiled_class,
-            &state_updates.address_to_nonce,
-            &storage_updates,
-        );
+    fn apply_state_update(&mut self, state_updates: &StateDiff) -> Result<(), StateError> {
+        for (address, class_hash) in &state_updates.address_to_class_hash {
+            self.write_class_hash(address.clone(), *class_hash);
+        }
+        for (class_hash, compiled_class) in &state_updates.class_hash_to_compiled_class {
+            self.write_compiled_class(*class_hash, compiled_class.clone());
+        }
+        for (class_hash, compiled_class_hash) in &state_updates.class_hash_to_compiled_class_hash {
+            let previous = self
+                .cache
+                .class_hash_to_compiled_class_hash
+                .insert(*class_hash, *compiled_class_hash);
+            self.record_write(JournalEntry::CompiledClassHash(*class_hash, previous));
+        }
+        for class_hash in &state_updates.deprecated_declared_classes {
+            self.write_deprecated_declared_class(*class_hash);
+        }
+        for (address, nonce) in &state_updates.address_to_nonce {
+            self.write_nonce(address.clone(), nonce.clone());
+        }
+        for (storage_entry, value) in to_cache_state_storage_mapping(&state_updates.storage_updates)
+        {
+            self.write_storage(storage_entry, value);
+        }
         Ok(())
     }
 
@@ -374,18 +582,22 @@ impl<T: StateReader> State for CachedState<T> {
         }
 
         // II: FETCHING FROM STATE_READER
-        let contract = self.state_reader.get_contract_class(class_hash)?;
+        let contract = self.read_contract_class(class_hash)?;
         match contract {
             CompiledClass::Casm(ref casm_class) => {
                 // We call this method instead of state_reader's in order to update the cache's class_hash_initial_values map
                 let compiled_class_hash = self.get_compiled_class_hash(class_hash)?;
-                self.set_contract_class(
-                    &compiled_class_hash,
-                    &CompiledClass::Casm(casm_class.clone()),
-                )?;
+                // Classes read from the state reader are not writes, so they aren't journaled.
+                if self.class_cache.is_none() {
+                    self.contract_classes
+                        .insert(compiled_class_hash, CompiledClass::Casm(casm_class.clone()));
+                }
             }
             CompiledClass::Deprecated(ref contract) => {
-                self.set_contract_class(class_hash, &CompiledClass::Deprecated(contract.clone()))?
+                if self.class_cache.is_none() {
+                    self.contract_classes
+                        .insert(*class_hash, CompiledClass::Deprecated(contract.clone()));
+                }
             }
         }
         Ok(contract)
@@ -397,7 +609,9 @@ impl<T: StateReader> CachedState<T> {
     // If a key is present in the storage_writes but not in storage_initial_values,
     // the initial value for that key will be fetched from the state_reader and inserted into the cache's storage_initial_values
     // The same process is applied to class hash and nonce values.
-    fn update_initial_values_of_write_only_accesses(&mut self) -> Result<(), StateError> {
+    pub(crate) fn update_initial_values_of_write_only_accesses(
+        &mut self,
+    ) -> Result<(), StateError> {
         // Update storage_initial_values with keys in storage_writes
         for storage_entry in self.cache.storage_writes.keys() {
             if !self
@@ -522,6 +736,39 @@ mod tests {
         );
     }
 
+    /// This test checks that states sharing a class cache only read each class once.
+    #[test]
+    fn get_contract_class_through_shared_class_cache() {
+        let mut state_reader = InMemoryStateReader::default();
+        let contract_class =
+            ContractClass::from_path("starknet_programs/raw_contract_classes/class_with_abi.json")
+                .unwrap();
+        state_reader
+            .class_hash_to_compiled_class
+            .insert([1; 32], CompiledClass::Deprecated(Arc::new(contract_class)));
+        let state_reader = Arc::new(state_reader);
+        let class_cache = Arc::new(SharedClassCache::new(10));
+
+        let mut first_state =
+            CachedState::new_with_class_cache(state_reader.clone(), class_cache.clone());
+        let second_state = CachedState::new_with_class_cache(state_reader.clone(), class_cache);
+
+        assert_eq!(
+            State::get_contract_class(&mut first_state, &[1; 32]).unwrap(),
+            state_reader.get_contract_class(&[1; 32]).unwrap()
+        );
+        assert_eq!(
+            StateReader::get_contract_class(&second_state, &[1; 32]).unwrap(),
+            state_reader.get_contract_class(&[1; 32]).unwrap()
+        );
+        // Classes missing from the reader are still missing.
+        assert!(StateReader::get_contract_class(&second_state, &[2; 32]).is_err());
+
+        let stats = second_state.class_cache().as_ref().unwrap().stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 1));
+        assert!(first_state.contract_classes().is_empty());
+    }
+
     /// This test verifies the correct handling of storage in the cached state.
     #[test]
     fn cached_state_storage_test() {
@@ -687,6 +934,9 @@ mod tests {
             address_to_nonce: HashMap::from([(address_one.clone(), Felt252::one())]),
             class_hash_to_compiled_class: HashMap::new(),
             storage_updates: HashMap::new(),
+            class_hash_to_compiled_class_hash: HashMap::new(),
+            deprecated_declared_classes: HashSet::new(),
+            replaced_classes: HashSet::new(),
         };
         assert!(cached_state.apply_state_update(&state_diff).is_ok());
         assert!(cached_state
@@ -755,4 +1005,101 @@ mod tests {
             ])
         )
     }
+
+    /// This test checks that reverting a checkpoint undoes only the writes made after it,
+    /// including the ones made inside nested checkpoints.
+    #[test]
+    fn revert_to_checkpoint_undoes_writes() {
+        let mut cached_state =
+            CachedState::new(Arc::new(InMemoryStateReader::default()), HashMap::new());
+
+        let address = Address(1.into());
+        let storage_entry = (address.clone(), [1; 32]);
+        cached_state.set_storage_at(&storage_entry, 1.into());
+
+        let outer = cached_state.checkpoint();
+        cached_state.set_storage_at(&storage_entry, 2.into());
+        cached_state.increment_nonce(&address).unwrap();
+
+        let inner = cached_state.checkpoint();
+        cached_state
+            .set_class_hash_at(address.clone(), [2; 32])
+            .unwrap();
+        cached_state.set_storage_at(&(address.clone(), [2; 32]), 3.into());
+
+        cached_state.revert_to(inner).unwrap();
+        assert!(cached_state.cache.class_hash_writes.is_empty());
+        assert_eq!(
+            cached_state.cache.storage_writes,
+            HashMap::from([(storage_entry.clone(), Felt252::from(2))])
+        );
+        assert!(cached_state.cache.nonce_writes[&address].is_one());
+
+        cached_state.revert_to(outer).unwrap();
+        assert_eq!(
+            cached_state.cache.storage_writes,
+            HashMap::from([(storage_entry, Felt252::from(1))])
+        );
+        assert!(cached_state.cache.nonce_writes.is_empty());
+        assert!(cached_state.journal.is_empty());
+    }
+
+    /// This test checks that committed writes are kept, but can still be undone by reverting
+    /// an enclosing checkpoint.
+    #[test]
+    fn commit_checkpoint_keeps_writes() {
+        let mut cached_state =
+            CachedState::new(Arc::new(InMemoryStateReader::default()), HashMap::new());
+
+        let storage_entry = (Address(1.into()), [1; 32]);
+
+        let outer = cached_state.checkpoint();
+        let inner = cached_state.checkpoint();
+        cached_state.set_storage_at(&storage_entry, 1.into());
+        cached_state
+            .set_compiled_class_hash(&Felt252::from(3), &Felt252::from(4))
+            .unwrap();
+        cached_state.commit(inner).unwrap();
+
+        assert_eq!(
+            cached_state.cache.storage_writes[&storage_entry],
+            Felt252::from(1)
+        );
+
+        cached_state.revert_to(outer).unwrap();
+        assert!(cached_state.cache.storage_writes.is_empty());
+        assert!(cached_state
+            .cache
+            .class_hash_to_compiled_class_hash
+            .is_empty());
+
+        let checkpoint = cached_state.checkpoint();
+        cached_state.set_storage_at(&storage_entry, 2.into());
+        cached_state.commit(checkpoint).unwrap();
+        assert_eq!(
+            cached_state.cache.storage_writes[&storage_entry],
+            Felt252::from(2)
+        );
+        assert!(cached_state.journal.is_empty());
+    }
+
+    /// This test checks that a checkpoint can't be used once it was closed.
+    #[test]
+    fn closed_checkpoint_is_rejected() {
+        let mut cached_state =
+            CachedState::new(Arc::new(InMemoryStateReader::default()), HashMap::new());
+
+        let outer = cached_state.checkpoint();
+        let inner = cached_state.checkpoint();
+        cached_state.revert_to(outer).unwrap();
+
+        assert_matches!(
+            cached_state.commit(inner),
+            Err(StateError::InvalidCheckpoint)
+        );
+        assert_matches!(
+            cached_state.revert_to(outer),
+            Err(StateError::InvalidCheckpoint)
+        );
+    }
 }
@@ -0,0 +1,400 @@
+use crate::{
+    core::errors::state_errors::StateError,
+    services::api::contract_classes::compiled_class::CompiledClass,
+    state::{state_api::StateReader, state_cache::StorageEntry},
+    utils::{get_erc20_balance_var_addresses, Address, ClassHash, CompiledClassHash},
+};
+use cairo_vm::felt::Felt252;
+use getset::{Getters, MutGetters};
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{Arc, RwLock},
+};
+
+/// A [StateReader] that layers local overrides over another (usually remote) [StateReader].
+///
+/// Lookups go first to the overrides, then to a local cache and only then to the backing
+/// reader, whose answers are cached. This makes it possible to run transactions against, for
+/// example, mainnet state fetched through RPC with a few tweaks (a funded account, a patched
+/// storage slot or a replaced class) without fetching the same value twice.
+///
+/// Since it is a [StateReader], it can be used anywhere a state is expected, such as
+/// [crate::simulate_transaction], [crate::estimate_fee] or [crate::call_contract].
+#[derive(Debug, Getters, MutGetters)]
+pub struct ForkedStateReader<T: StateReader> {
+    #[getset(get = "pub")]
+    state_reader: Arc<T>,
+    #[getset(get = "pub", get_mut = "pub")]
+    address_to_class_hash: HashMap<Address, ClassHash>,
+    #[getset(get = "pub", get_mut = "pub")]
+    address_to_nonce: HashMap<Address, Felt252>,
+    #[getset(get = "pub", get_mut = "pub")]
+    address_to_storage: HashMap<StorageEntry, Felt252>,
+    #[getset(get = "pub", get_mut = "pub")]
+    class_hash_to_compiled_class: HashMap<ClassHash, CompiledClass>,
+    #[getset(get = "pub", get_mut = "pub")]
+    class_hash_to_compiled_class_hash: HashMap<ClassHash, CompiledClassHash>,
+    cache: RwLock<ForkCache>,
+}
+
+/// Values read from the backing reader.
+#[derive(Debug, Default)]
+struct ForkCache {
+    class_hashes: HashMap<Address, ClassHash>,
+    nonces: HashMap<Address, Felt252>,
+    storage: HashMap<StorageEntry, Felt252>,
+    compiled_class_hashes: HashMap<ClassHash, CompiledClassHash>,
+    compiled_classes: HashMap<ClassHash, CompiledClass>,
+}
+
+impl<T: StateReader> ForkedStateReader<T> {
+    /// Creates a new ForkedStateReader without overrides.
+    ///
+    /// # Parameters
+    /// - `state_reader` - The reader used for every value that isn't overridden.
+    pub fn new(state_reader: Arc<T>) -> Self {
+        Self {
+            state_reader,
+            address_to_class_hash: HashMap::new(),
+            address_to_nonce: HashMap::new(),
+            address_to_storage: HashMap::new(),
+            class_hash_to_compiled_class: HashMap::new(),
+            class_hash_to_compiled_class_hash: HashMap::new(),
+            cache: RwLock::new(ForkCache::default()),
+        }
+    }
+
+    /// Overrides the balance of `account_address` in the ERC20 contract at
+    /// `fee_token_address`.
+    ///
+    /// # Parameters
+    /// - `fee_token_address` - The address of the ERC20 contract.
+    /// - `account_address` - The address whose balance is overridden.
+    /// - `balance` - The new balance, as a (low, high) pair of 128-bit felts.
+    pub fn set_erc20_balance(
+        &mut self,
+        fee_token_address: &Address,
+        account_address: &Address,
+        balance: (Felt252, Felt252),
+    ) -> Result<(), StateError> {
+        let (low_key, high_key) = get_erc20_balance_var_addresses(account_address)?;
+        self.address_to_storage
+            .insert((fee_token_address.clone(), low_key), balance.0);
+        self.address_to_storage
+            .insert((fee_token_address.clone(), high_key), balance.1);
+        Ok(())
+    }
+
+    /// Replaces the class of the contract at `contract_address`.
+    ///
+    /// # Parameters
+    /// - `contract_address` - The address of the contract.
+    /// - `class_hash` - The hash of the new class.
+    /// - `compiled_class` - The new class. For Cairo 1 classes, it is stored under the class
+    ///   hash itself, so no compiled class hash is needed.
+    pub fn replace_class(
+        &mut self,
+        contract_address: Address,
+        class_hash: ClassHash,
+        compiled_class: CompiledClass,
+    ) {
+        self.address_to_class_hash
+            .insert(contract_address, class_hash);
+        self.class_hash_to_compiled_class
+            .insert(class_hash, compiled_class);
+    }
+
+    /// Drops every value cached from the backing reader.
+    pub fn clear_cache(&self) -> Result<(), StateError> {
+        *self.write_cache()? = ForkCache::default();
+        Ok(())
+    }
+
+    /// Returns true if any value is overridden.
+    pub fn has_overrides(&self) -> bool {
+        !(self.address_to_class_hash.is_empty()
+            && self.address_to_nonce.is_empty()
+            && self.address_to_storage.is_empty()
+            && self.class_hash_to_compiled_class.is_empty()
+            && self.class_hash_to_compiled_class_hash.is_empty())
+    }
+
+    /// Returns the ERC20 balance of `account_address`, taking the overrides into account.
+    pub fn get_erc20_balance(
+        &self,
+        fee_token_address: &Address,
+        account_address: &Address,
+    ) -> Result<(Felt252, Felt252), StateError> {
+        let (low_key, high_key) = get_erc20_balance_var_addresses(account_address)?;
+        let low = self.get_storage_at(&(fee_token_address.clone(), low_key))?;
+        let high = self.get_storage_at(&(fee_token_address.clone(), high_key))?;
+        Ok((low, high))
+    }
+
+    fn read_cache(&self) -> Result<std::sync::RwLockReadGuard<ForkCache>, StateError> {
+        self.cache
+            .read()
+            .map_err(|_| StateError::CustomError("Fork cache lock poisoned".to_string()))
+    }
+
+    fn write_cache(&self) -> Result<std::sync::RwLockWriteGuard<ForkCache>, StateError> {
+        self.cache
+            .write()
+            .map_err(|_| StateError::CustomError("Fork cache lock poisoned".to_string()))
+    }
+
+    /// Looks `key` up in the overrides, then in the cache and finally in the backing reader,
+    /// caching what the backing reader returns.
+    fn read_through<K, V>(
+        &self,
+        key: &K,
+        overrides: &HashMap<K, V>,
+        cached: impl Fn(&ForkCache) -> &HashMap<K, V>,
+        cached_mut: impl Fn(&mut ForkCache) -> &mut HashMap<K, V>,
+        fetch: impl FnOnce(&T, &K) -> Result<V, StateError>,
+    ) -> Result<V, StateError>
+    where
+        K: Eq + Hash + Clone,
+        V: Clone,
+    {
+        if let Some(value) = overrides.get(key) {
+            return Ok(value.clone());
+        }
+        if let Some(value) = cached(&*self.read_cache()?).get(key) {
+            return Ok(value.clone());
+        }
+
+        let value = fetch(&self.state_reader, key)?;
+        cached_mut(&mut *self.write_cache()?).insert(key.clone(), value.clone());
+        Ok(value)
+    }
+}
+
+impl<T: StateReader> StateReader for ForkedStateReader<T> {
+    fn get_class_hash_at(&self, contract_address: &Address) -> Result<ClassHash, StateError> {
+        self.read_through(
+            contract_address,
+            &self.address_to_class_hash,
+            |cache| &cache.class_hashes,
+            |cache| &mut cache.class_hashes,
+            |state_reader, address| state_reader.get_class_hash_at(address),
+        )
+    }
+
+    fn get_nonce_at(&self, contract_address: &Address) -> Result<Felt252, StateError> {
+        self.read_through(
+            contract_address,
+            &self.address_to_nonce,
+            |cache| &cache.nonces,
+            |cache| &mut cache.nonces,
+            |state_reader, address| state_reader.get_nonce_at(address),
+        )
+    }
+
+    fn get_storage_at(&self, storage_entry: &StorageEntry) -> Result<Felt252, StateError> {
+        self.read_through(
+            storage_entry,
+            &self.address_to_storage,
+            |cache| &cache.storage,
+            |cache| &mut cache.storage,
+            |state_reader, storage_entry| state_reader.get_storage_at(storage_entry),
+        )
+    }
+
+    fn get_compiled_class_hash(
+        &self,
+        class_hash: &ClassHash,
+    ) -> Result<CompiledClassHash, StateError> {
+        // A class overridden by its class hash doesn't need a compiled class hash.
+        if self.class_hash_to_compiled_class.contains_key(class_hash)
+            && !self
+                .class_hash_to_compiled_class_hash
+                .contains_key(class_hash)
+        {
+            return Ok(*class_hash);
+        }
+        self.read_through(
+            class_hash,
+            &self.class_hash_to_compiled_class_hash,
+            |cache| &cache.compiled_class_hashes,
+            |cache| &mut cache.compiled_class_hashes,
+            |state_reader, class_hash| state_reader.get_compiled_class_hash(class_hash),
+        )
+    }
+
+    fn get_contract_class(&self, class_hash: &ClassHash) -> Result<CompiledClass, StateError> {
+        if let Some(compiled_class) = self.class_hash_to_compiled_class.get(class_hash) {
+            return Ok(compiled_class.clone());
+        }
+        // The class may have been overridden under its compiled class hash.
+        if let Some(compiled_class) = self
+            .class_hash_to_compiled_class_hash
+            .get(class_hash)
+            .and_then(|compiled_class_hash| {
+                self.class_hash_to_compiled_class.get(compiled_class_hash)
+            })
+        {
+            return Ok(compiled_class.clone());
+        }
+        if let Some(compiled_class) = self.read_cache()?.compiled_classes.get(class_hash) {
+            return Ok(compiled_class.clone());
+        }
+
+        let compiled_class = self.state_reader.get_contract_class(class_hash)?;
+        self.write_cache()?
+            .compiled_classes
+            .insert(*class_hash, compiled_class.clone());
+        Ok(compiled_class)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        services::api::contract_classes::deprecated_contract_class::ContractClass,
+        state::in_memory_state_reader::InMemoryStateReader,
+    };
+    use num_traits::Zero;
+
+    /// A reader that counts how many times it was queried.
+    #[derive(Debug, Default)]
+    struct CountingStateReader {
+        state_reader: InMemoryStateReader,
+        reads: RwLock<usize>,
+    }
+
+    impl CountingStateReader {
+        fn count(&self) {
+            *self.reads.write().unwrap() += 1;
+        }
+    }
+
+    impl StateReader for CountingStateReader {
+        fn get_class_hash_at(&self, contract_address: &Address) -> Result<ClassHash, StateError> {
+            self.count();
+            self.state_reader.get_class_hash_at(contract_address)
+        }
+
+        fn get_nonce_at(&self, contract_address: &Address) -> Result<Felt252, StateError> {
+            self.count();
+            self.state_reader.get_nonce_at(contract_address)
+        }
+
+        fn get_storage_at(&self, storage_entry: &StorageEntry) -> Result<Felt252, StateError> {
+            self.count();
+            self.state_reader.get_storage_at(storage_entry)
+        }
+
+        fn get_compiled_class_hash(
+            &self,
+            class_hash: &ClassHash,
+        ) -> Result<CompiledClassHash, StateError> {
+            self.count();
+            self.state_reader.get_compiled_class_hash(class_hash)
+        }
+
+        fn get_contract_class(&self, class_hash: &ClassHash) -> Result<CompiledClass, StateError> {
+            self.count();
+            self.state_reader.get_contract_class(class_hash)
+        }
+    }
+
+    #[test]
+    fn overrides_take_precedence_over_the_backing_reader() {
+        let address = Address(1.into());
+        let storage_entry = (address.clone(), [1; 32]);
+
+        let mut backing = CountingStateReader::default();
+        backing
+            .state_reader
+            .address_to_nonce_mut()
+            .insert(address.clone(), 5.into());
+        backing
+            .state_reader
+            .address_to_storage_mut()
+            .insert(storage_entry.clone(), 6.into());
+
+        let mut state_reader = ForkedStateReader::new(Arc::new(backing));
+        state_reader
+            .address_to_storage_mut()
+            .insert(storage_entry.clone(), 7.into());
+
+        assert_eq!(state_reader.get_nonce_at(&address).unwrap(), 5.into());
+        assert_eq!(
+            state_reader.get_storage_at(&storage_entry).unwrap(),
+            7.into()
+        );
+        assert_eq!(*state_reader.state_reader().reads.read().unwrap(), 1);
+        assert!(state_reader.has_overrides());
+    }
+
+    #[test]
+    fn backing_reader_values_are_cached() {
+        let address = Address(1.into());
+        let mut backing = CountingStateReader::default();
+        backing
+            .state_reader
+            .address_to_class_hash_mut()
+            .insert(address.clone(), [2; 32]);
+
+        let state_reader = ForkedStateReader::new(Arc::new(backing));
+
+        for _ in 0..3 {
+            assert_eq!(state_reader.get_class_hash_at(&address).unwrap(), [2; 32]);
+        }
+        assert_eq!(*state_reader.state_reader().reads.read().unwrap(), 1);
+
+        state_reader.clear_cache().unwrap();
+        state_reader.get_class_hash_at(&address).unwrap();
+        assert_eq!(*state_reader.state_reader().reads.read().unwrap(), 2);
+    }
+
+    #[test]
+    fn replaced_class_is_returned_for_the_contract() {
+        let address = Address(1.into());
+        let contract_class =
+            ContractClass::from_path("starknet_programs/raw_contract_classes/class_with_abi.json")
+                .unwrap();
+        let compiled_class = CompiledClass::Deprecated(Arc::new(contract_class));
+
+        let mut state_reader = ForkedStateReader::new(Arc::new(InMemoryStateReader::default()));
+        state_reader.replace_class(address.clone(), [3; 32], compiled_class.clone());
+
+        let class_hash = state_reader.get_class_hash_at(&address).unwrap();
+        assert_eq!(class_hash, [3; 32]);
+        assert_eq!(
+            state_reader.get_contract_class(&class_hash).unwrap(),
+            compiled_class
+        );
+    }
+
+    #[test]
+    fn set_erc20_balance_overrides_both_halves() {
+        let fee_token_address = Address(0x1001.into());
+        let account_address = Address(0x101.into());
+
+        let mut state_reader = ForkedStateReader::new(Arc::new(InMemoryStateReader::default()));
+        assert!(state_reader
+            .get_erc20_balance(&fee_token_address, &account_address)
+            .unwrap()
+            .0
+            .is_zero());
+
+        state_reader
+            .set_erc20_balance(
+                &fee_token_address,
+                &account_address,
+                (1000.into(), 1.into()),
+            )
+            .unwrap();
+
+        assert_eq!(
+            state_reader
+                .get_erc20_balance(&fee_token_address, &account_address)
+                .unwrap(),
+            (1000.into(), 1.into())
+        );
+    }
+}
@@ -1,26 +1,50 @@
 pub mod cached_state;
 pub(crate) mod contract_storage_state;
+pub mod forked_state_reader;
 pub mod in_memory_state_reader;
+pub mod persistent_state_reader;
+pub mod recording_state_reader;
+pub mod shared_class_cache;
 pub mod state_api;
 pub mod state_cache;
+pub(crate) mod versioned_state;
 
 use crate::{
     core::errors::state_errors::StateError,
+    definitions::block_context::FeeType,
     services::api::contract_classes::compiled_class::CompiledClass,
     utils::{
-        get_keys, subtract_mappings, to_cache_state_storage_mapping, to_state_diff_storage_mapping,
+        felt_from_hex, felt_to_hash, felt_to_hex, hash_from_hex, hash_to_hex, subtract_mappings,
+        to_cache_state_storage_mapping, to_state_diff_storage_mapping, CompiledClassHash,
     },
 };
 use cairo_vm::{felt::Felt252, vm::runners::cairo_runner::ExecutionResources};
 use getset::Getters;
-use std::{collections::HashMap, sync::Arc};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use crate::{
     transaction::error::TransactionError,
     utils::{Address, ClassHash},
 };
 
-use self::{cached_state::CachedState, state_api::StateReader};
+use self::{
+    cached_state::{CachedState, UNINITIALIZED_CLASS_HASH},
+    state_api::StateReader,
+};
+
+/// How the state diffs of a block are published on L1.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum L1DataAvailabilityMode {
+    /// In the calldata of the L1 transaction, paid with L1 gas.
+    #[default]
+    Calldata,
+    /// In blobs, paid with L1 data gas.
+    Blob,
+}
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct BlockInfo {
@@ -30,6 +54,14 @@ pub struct BlockInfo {
     pub block_timestamp: u64,
     /// L1 gas price (in Wei) measured at the beginning of the last block creation attempt.
     pub gas_price: u128,
+    /// L1 gas price (in Fri), charged for the fees paid in STRK.
+    pub strk_gas_price: u128,
+    /// L1 data gas price (in Wei), charged for the data published in blobs.
+    pub data_gas_price: u128,
+    /// L1 data gas price (in Fri), charged for the data published in blobs.
+    pub strk_data_gas_price: u128,
+    /// How the state diff of this block is published on L1.
+    pub l1_da_mode: L1DataAvailabilityMode,
     /// The sequencer address of this block.
     pub sequencer_address: Address,
 }
@@ -40,10 +72,30 @@ impl BlockInfo {
             block_number: 0, // To do: In cairo-lang, this value is set to -1
             block_timestamp: 0,
             gas_price: 0,
+            strk_gas_price: 0,
+            data_gas_price: 0,
+            strk_data_gas_price: 0,
+            l1_da_mode: L1DataAvailabilityMode::Calldata,
             sequencer_address,
         }
     }
 
+    /// Returns the price of L1 gas in the token the fees of the given type are paid with.
+    pub const fn gas_price_by_type(&self, fee_type: FeeType) -> u128 {
+        match fee_type {
+            FeeType::Eth => self.gas_price,
+            FeeType::Strk => self.strk_gas_price,
+        }
+    }
+
+    /// Returns the price of L1 data gas in the token the fees of the given type are paid with.
+    pub const fn data_gas_price_by_type(&self, fee_type: FeeType) -> u128 {
+        match fee_type {
+            FeeType::Eth => self.data_gas_price,
+            FeeType::Strk => self.strk_data_gas_price,
+        }
+    }
+
     pub const fn validate_legal_progress(
         &self,
         next_block_info: &BlockInfo,
@@ -66,6 +118,10 @@ impl Default for BlockInfo {
             block_number: 0,
             block_timestamp: 0,
             gas_price: 0,
+            strk_gas_price: 0,
+            data_gas_price: 0,
+            strk_data_gas_price: 0,
+            l1_da_mode: L1DataAvailabilityMode::Calldata,
             sequencer_address: Address(0.into()),
         }
     }
@@ -103,13 +159,26 @@ impl ExecutionResourcesManager {
     }
 }
 
+/// The changes made to the state by one or more transactions.
+///
+/// It serializes to (and deserializes from) the state diff of a Starknet state update, with
+/// `storage_diffs`, `nonces`, `deployed_contracts`, `declared_classes`,
+/// `deprecated_declared_classes` and `replaced_classes`. The compiled classes themselves are not
+/// part of that format, so `class_hash_to_compiled_class` is left empty when deserializing.
 #[derive(Default, Clone, PartialEq, Eq, Debug, Getters)]
 #[getset(get = "pub")]
 pub struct StateDiff {
+    /// The class hashes of both the deployed contracts and the ones whose class was replaced.
     pub(crate) address_to_class_hash: HashMap<Address, ClassHash>,
     pub(crate) address_to_nonce: HashMap<Address, Felt252>,
     pub(crate) class_hash_to_compiled_class: HashMap<ClassHash, CompiledClass>,
     pub(crate) storage_updates: HashMap<Address, HashMap<Felt252, Felt252>>,
+    /// The Cairo 1 classes declared, with their compiled class hashes.
+    pub(crate) class_hash_to_compiled_class_hash: HashMap<ClassHash, CompiledClassHash>,
+    pub(crate) deprecated_declared_classes: HashSet<ClassHash>,
+    /// The addresses in `address_to_class_hash` that were already deployed, and had their class
+    /// replaced.
+    pub(crate) replaced_classes: HashSet<Address>,
 }
 
 impl StateDiff {
@@ -118,12 +187,18 @@ impl StateDiff {
         address_to_nonce: HashMap<Address, Felt252>,
         class_hash_to_compiled_class: HashMap<ClassHash, CompiledClass>,
         storage_updates: HashMap<Address, HashMap<Felt252, Felt252>>,
+        class_hash_to_compiled_class_hash: HashMap<ClassHash, CompiledClassHash>,
+        deprecated_declared_classes: HashSet<ClassHash>,
+        replaced_classes: HashSet<Address>,
     ) -> Self {
         StateDiff {
             address_to_class_hash,
             address_to_nonce,
             class_hash_to_compiled_class,
             storage_updates,
+            class_hash_to_compiled_class_hash,
+            deprecated_declared_classes,
+            replaced_classes,
         }
     }
 
@@ -131,7 +206,17 @@ impl StateDiff {
     where
         T: StateReader,
     {
-        let state_cache = cached_state.cache().to_owned();
+        Self::from_cached_state_ref(&cached_state)
+    }
+
+    /// Like [StateDiff::from_cached_state], without consuming the cached state.
+    pub(crate) fn from_cached_state_ref<T>(
+        cached_state: &CachedState<T>,
+    ) -> Result<Self, StateError>
+    where
+        T: StateReader,
+    {
+        let state_cache = cached_state.cache();
 
         let substracted_maps = subtract_mappings(
             state_cache.storage_writes.clone(),
@@ -152,14 +237,31 @@ impl StateDiff {
 
         let address_to_class_hash = subtract_mappings(
             state_cache.class_hash_writes.clone(),
-            state_cache.class_hash_initial_values,
+            state_cache.class_hash_initial_values.clone(),
         );
 
+        // A contract whose class hash was set while it already had one had its class replaced.
+        let mut replaced_classes = HashSet::new();
+        for address in address_to_class_hash.keys() {
+            let initial_class_hash = match state_cache.class_hash_initial_values.get(address) {
+                Some(class_hash) => *class_hash,
+                None => cached_state.state_reader.get_class_hash_at(address)?,
+            };
+            if initial_class_hash != *UNINITIALIZED_CLASS_HASH {
+                replaced_classes.insert(address.clone());
+            }
+        }
+
         Ok(StateDiff {
             address_to_class_hash,
             address_to_nonce,
             class_hash_to_compiled_class,
             storage_updates,
+            class_hash_to_compiled_class_hash: state_cache
+                .class_hash_to_compiled_class_hash
+                .clone(),
+            deprecated_declared_classes: state_cache.deprecated_declared_classes.clone(),
+            replaced_classes,
         })
     }
 
@@ -176,40 +278,77 @@ impl StateDiff {
             &self.address_to_nonce,
             &cache_storage_mapping,
         )?;
+        cache_state
+            .cache_mut()
+            .class_hash_to_compiled_class_hash
+            .extend(self.class_hash_to_compiled_class_hash.clone());
+        cache_state
+            .cache_mut()
+            .deprecated_declared_classes
+            .extend(self.deprecated_declared_classes.iter().copied());
         Ok(cache_state)
     }
 
-    pub fn squash(&mut self, other: StateDiff) -> Self {
+    /// Returns the contracts deployed, with their class hashes.
+    pub fn deployed_contracts(&self) -> impl Iterator<Item = (&Address, &ClassHash)> {
         self.address_to_class_hash
-            .extend(other.address_to_class_hash);
-        let address_to_class_hash = self.address_to_class_hash.clone();
+            .iter()
+            .filter(|(address, _)| !self.replaced_classes.contains(*address))
+    }
 
-        self.address_to_nonce.extend(other.address_to_nonce);
-        let address_to_nonce = self.address_to_nonce.clone();
+    /// Returns the contracts whose class was replaced, with their new class hashes.
+    pub fn replaced_contracts(&self) -> impl Iterator<Item = (&Address, &ClassHash)> {
+        self.address_to_class_hash
+            .iter()
+            .filter(|(address, _)| self.replaced_classes.contains(*address))
+    }
 
-        self.class_hash_to_compiled_class
-            .extend(other.class_hash_to_compiled_class);
-        let class_hash_to_compiled_class = self.class_hash_to_compiled_class.clone();
+    pub fn squash(&mut self, other: StateDiff) -> Self {
+        *self = self.compose(&other);
+        self.clone()
+    }
 
-        let mut storage_updates = HashMap::new();
+    /// Returns the diff of applying `self` and then `other`.
+    pub fn compose(&self, other: &StateDiff) -> StateDiff {
+        // A contract deployed by the first diff is still a deployment after the second one
+        // replaces its class, and a contract deployed by the second diff is a deployment.
+        let replaced_classes = self
+            .replaced_classes
+            .iter()
+            .filter(|address| {
+                !other.address_to_class_hash.contains_key(*address)
+                    || other.replaced_classes.contains(*address)
+            })
+            .chain(
+                other
+                    .replaced_classes
+                    .iter()
+                    .filter(|address| !self.address_to_class_hash.contains_key(*address)),
+            )
+            .cloned()
+            .collect();
 
-        let addresses: Vec<Address> =
-            get_keys(self.storage_updates.clone(), other.storage_updates.clone());
+        let mut address_to_class_hash = self.address_to_class_hash.clone();
+        address_to_class_hash.extend(other.address_to_class_hash.clone());
 
-        for address in addresses {
-            let default: HashMap<Felt252, Felt252> = HashMap::new();
-            let mut map_a = self
-                .storage_updates
-                .get(&address)
-                .unwrap_or(&default)
-                .to_owned();
-            let map_b = other
-                .storage_updates
-                .get(&address)
-                .unwrap_or(&default)
-                .to_owned();
-            map_a.extend(map_b);
-            storage_updates.insert(address, map_a.clone());
+        let mut address_to_nonce = self.address_to_nonce.clone();
+        address_to_nonce.extend(other.address_to_nonce.clone());
+
+        let mut class_hash_to_compiled_class = self.class_hash_to_compiled_class.clone();
+        class_hash_to_compiled_class.extend(other.class_hash_to_compiled_class.clone());
+
+        let mut class_hash_to_compiled_class_hash = self.class_hash_to_compiled_class_hash.clone();
+        class_hash_to_compiled_class_hash.extend(other.class_hash_to_compiled_class_hash.clone());
+
+        let mut deprecated_declared_classes = self.deprecated_declared_classes.clone();
+        deprecated_declared_classes.extend(other.deprecated_declared_classes.iter().copied());
+
+        let mut storage_updates = self.storage_updates.clone();
+        for (address, updates) in &other.storage_updates {
+            storage_updates
+                .entry(address.clone())
+                .or_default()
+                .extend(updates.clone());
         }
 
         StateDiff {
@@ -217,7 +356,309 @@ impl StateDiff {
             address_to_nonce,
             class_hash_to_compiled_class,
             storage_updates,
+            class_hash_to_compiled_class_hash,
+            deprecated_declared_classes,
+            replaced_classes,
+        }
+    }
+
+    /// Returns the diff without the writes that leave a value as it is in the state read by
+    /// `state_reader`, which must be the state the diff applies to.
+    ///
+    /// Applying the result to that state has the same effect as applying `self`.
+    pub fn squash_no_ops<T: StateReader>(&self, state_reader: &T) -> Result<StateDiff, StateError> {
+        let mut state_diff = self.clone();
+
+        for (address, class_hash) in &self.address_to_class_hash {
+            if state_reader.get_class_hash_at(address)? == *class_hash {
+                state_diff.address_to_class_hash.remove(address);
+                state_diff.replaced_classes.remove(address);
+            }
+        }
+
+        for (address, nonce) in &self.address_to_nonce {
+            if state_reader.get_nonce_at(address)? == *nonce {
+                state_diff.address_to_nonce.remove(address);
+            }
+        }
+
+        for (address, updates) in &self.storage_updates {
+            for (key, value) in updates {
+                if state_reader.get_storage_at(&(address.clone(), felt_to_hash(key)))? == *value {
+                    if let Some(updates) = state_diff.storage_updates.get_mut(address) {
+                        updates.remove(key);
+                    }
+                }
+            }
+        }
+        state_diff
+            .storage_updates
+            .retain(|_, updates| !updates.is_empty());
+
+        for (class_hash, compiled_class_hash) in &self.class_hash_to_compiled_class_hash {
+            if read_compiled_class_hash(state_reader, class_hash)? == Some(*compiled_class_hash) {
+                state_diff
+                    .class_hash_to_compiled_class_hash
+                    .remove(class_hash);
+            }
+        }
+
+        Ok(state_diff)
+    }
+
+    /// Returns the diff that undoes `self`, taking the previous values from `state_reader`,
+    /// which must be the state the diff applies to.
+    ///
+    /// Applying `self` and then its inverse leaves the storage, nonces and class hashes as they
+    /// were. Class declarations can't be undone, so the inverse only restores the compiled class
+    /// hashes of the classes that were already declared, and keeps the new ones declared.
+    ///
+    /// The state update format has no way to remove a deployed contract, so undoing a
+    /// deployment is expressed as replacing the contract's class with the zero class hash
+    /// ([UNINITIALIZED_CLASS_HASH]), which is what an address without a contract reads as.
+    pub fn inverse<T: StateReader>(&self, state_reader: &T) -> Result<StateDiff, StateError> {
+        let mut state_diff = StateDiff::default();
+
+        for (address, class_hash) in &self.address_to_class_hash {
+            state_diff
+                .address_to_class_hash
+                .insert(address.clone(), state_reader.get_class_hash_at(address)?);
+            // Once the diff is applied the contract is deployed, so restoring its previous
+            // class hash replaces its class.
+            if class_hash != UNINITIALIZED_CLASS_HASH {
+                state_diff.replaced_classes.insert(address.clone());
+            }
+        }
+
+        for address in self.address_to_nonce.keys() {
+            state_diff
+                .address_to_nonce
+                .insert(address.clone(), state_reader.get_nonce_at(address)?);
+        }
+
+        for (address, updates) in &self.storage_updates {
+            let mut previous_values = HashMap::new();
+            for key in updates.keys() {
+                previous_values.insert(
+                    key.clone(),
+                    state_reader.get_storage_at(&(address.clone(), felt_to_hash(key)))?,
+                );
+            }
+            state_diff
+                .storage_updates
+                .insert(address.clone(), previous_values);
+        }
+
+        for class_hash in self.class_hash_to_compiled_class_hash.keys() {
+            if let Some(compiled_class_hash) = read_compiled_class_hash(state_reader, class_hash)? {
+                state_diff
+                    .class_hash_to_compiled_class_hash
+                    .insert(*class_hash, compiled_class_hash);
+            }
         }
+
+        Ok(state_diff)
+    }
+}
+
+/// Reads the compiled class hash of a class, returning `None` if the class isn't declared.
+fn read_compiled_class_hash<T: StateReader>(
+    state_reader: &T,
+    class_hash: &ClassHash,
+) -> Result<Option<CompiledClassHash>, StateError> {
+    match state_reader.get_compiled_class_hash(class_hash) {
+        Ok(compiled_class_hash) => Ok(Some(compiled_class_hash)),
+        Err(StateError::NoneCompiledHash(_) | StateError::NoneCompiledClass(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializedStateDiff {
+    #[serde(default)]
+    storage_diffs: Vec<SerializedStorageDiff>,
+    #[serde(default)]
+    nonces: Vec<SerializedNonce>,
+    #[serde(default)]
+    deployed_contracts: Vec<SerializedDeployedContract>,
+    #[serde(default)]
+    declared_classes: Vec<SerializedDeclaredClass>,
+    #[serde(default)]
+    deprecated_declared_classes: Vec<String>,
+    #[serde(default)]
+    replaced_classes: Vec<SerializedReplacedClass>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializedStorageDiff {
+    address: String,
+    storage_entries: Vec<SerializedStorageEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializedStorageEntry {
+    key: String,
+    value: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializedNonce {
+    contract_address: String,
+    nonce: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializedDeployedContract {
+    address: String,
+    class_hash: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializedDeclaredClass {
+    class_hash: String,
+    compiled_class_hash: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializedReplacedClass {
+    contract_address: String,
+    class_hash: String,
+}
+
+impl Serialize for StateDiff {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Entries are sorted, so that the same diff always serializes the same way.
+        let mut storage_diffs: Vec<_> = self
+            .storage_updates
+            .iter()
+            .map(|(address, updates)| {
+                let mut updates: Vec<_> = updates.iter().collect();
+                updates.sort_by_key(|(key, _)| key.to_be_bytes());
+                (address, updates)
+            })
+            .collect();
+        storage_diffs.sort_by_key(|(address, _)| address.0.to_be_bytes());
+
+        let mut nonces: Vec<_> = self.address_to_nonce.iter().collect();
+        nonces.sort_by_key(|(address, _)| address.0.to_be_bytes());
+
+        let mut deployed_contracts: Vec<_> = self.deployed_contracts().collect();
+        deployed_contracts.sort_by_key(|(address, _)| address.0.to_be_bytes());
+
+        let mut replaced_contracts: Vec<_> = self.replaced_contracts().collect();
+        replaced_contracts.sort_by_key(|(address, _)| address.0.to_be_bytes());
+
+        let mut declared_classes: Vec<_> = self.class_hash_to_compiled_class_hash.iter().collect();
+        declared_classes.sort();
+
+        let mut deprecated_declared_classes: Vec<_> =
+            self.deprecated_declared_classes.iter().collect();
+        deprecated_declared_classes.sort();
+
+        SerializedStateDiff {
+            storage_diffs: storage_diffs
+                .into_iter()
+                .map(|(address, updates)| SerializedStorageDiff {
+                    address: felt_to_hex(&address.0),
+                    storage_entries: updates
+                        .into_iter()
+                        .map(|(key, value)| SerializedStorageEntry {
+                            key: felt_to_hex(key),
+                            value: felt_to_hex(value),
+                        })
+                        .collect(),
+                })
+                .collect(),
+            nonces: nonces
+                .into_iter()
+                .map(|(address, nonce)| SerializedNonce {
+                    contract_address: felt_to_hex(&address.0),
+                    nonce: felt_to_hex(nonce),
+                })
+                .collect(),
+            deployed_contracts: deployed_contracts
+                .into_iter()
+                .map(|(address, class_hash)| SerializedDeployedContract {
+                    address: felt_to_hex(&address.0),
+                    class_hash: hash_to_hex(class_hash),
+                })
+                .collect(),
+            declared_classes: declared_classes
+                .into_iter()
+                .map(
+                    |(class_hash, compiled_class_hash)| SerializedDeclaredClass {
+                        class_hash: hash_to_hex(class_hash),
+                        compiled_class_hash: hash_to_hex(compiled_class_hash),
+                    },
+                )
+                .collect(),
+            deprecated_declared_classes: deprecated_declared_classes
+                .into_iter()
+                .map(hash_to_hex)
+                .collect(),
+            replaced_classes: replaced_contracts
+                .into_iter()
+                .map(|(address, class_hash)| SerializedReplacedClass {
+                    contract_address: felt_to_hex(&address.0),
+                    class_hash: hash_to_hex(class_hash),
+                })
+                .collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for StateDiff {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let serialized = SerializedStateDiff::deserialize(deserializer)?;
+        let parse_felt = |value: &str| {
+            felt_from_hex(value).ok_or_else(|| D::Error::custom(format!("invalid felt: {value}")))
+        };
+        let parse_hash = |value: &str| {
+            hash_from_hex(value).ok_or_else(|| D::Error::custom(format!("invalid hash: {value}")))
+        };
+
+        let mut state_diff = StateDiff::default();
+        for storage_diff in serialized.storage_diffs {
+            let updates = state_diff
+                .storage_updates
+                .entry(Address(parse_felt(&storage_diff.address)?))
+                .or_default();
+            for entry in storage_diff.storage_entries {
+                updates.insert(parse_felt(&entry.key)?, parse_felt(&entry.value)?);
+            }
+        }
+        for nonce in serialized.nonces {
+            state_diff.address_to_nonce.insert(
+                Address(parse_felt(&nonce.contract_address)?),
+                parse_felt(&nonce.nonce)?,
+            );
+        }
+        for contract in serialized.deployed_contracts {
+            state_diff.address_to_class_hash.insert(
+                Address(parse_felt(&contract.address)?),
+                parse_hash(&contract.class_hash)?,
+            );
+        }
+        for class in serialized.declared_classes {
+            state_diff.class_hash_to_compiled_class_hash.insert(
+                parse_hash(&class.class_hash)?,
+                parse_hash(&class.compiled_class_hash)?,
+            );
+        }
+        for class_hash in serialized.deprecated_declared_classes {
+            state_diff
+                .deprecated_declared_classes
+                .insert(parse_hash(&class_hash)?);
+        }
+        for replaced in serialized.replaced_classes {
+            let address = Address(parse_felt(&replaced.contract_address)?);
+            state_diff
+                .address_to_class_hash
+                .insert(address.clone(), parse_hash(&replaced.class_hash)?);
+            state_diff.replaced_classes.insert(address);
+        }
+        Ok(state_diff)
     }
 }
 
@@ -235,17 +676,20 @@ fn test_validate_legal_progress() {
 
 #[cfg(test)]
 mod test {
-    use std::{collections::HashMap, sync::Arc};
+    use std::{
+        collections::{HashMap, HashSet},
+        sync::Arc,
+    };
 
     use super::StateDiff;
     use crate::{
         state::in_memory_state_reader::InMemoryStateReader,
         state::{
-            cached_state::CachedState,
-            state_api::StateReader,
+            cached_state::{CachedState, UNINITIALIZED_CLASS_HASH},
+            state_api::{State, StateReader},
             state_cache::{StateCache, StorageEntry},
         },
-        utils::Address,
+        utils::{felt_to_hash, Address},
     };
     use cairo_vm::felt::Felt252;
 
@@ -381,6 +825,7 @@ mod test {
             HashMap::new(),
             storage_writes,
             HashMap::new(),
+            HashSet::new(),
         );
         let cached_state =
             CachedState::new_for_testing(Arc::new(state_reader), cache, HashMap::new());
@@ -391,4 +836,313 @@ mod test {
 
         assert_eq!(diff, diff_squashed);
     }
+
+    #[test]
+    fn state_diff_from_cached_state_tells_deployed_and_replaced_contracts_apart() {
+        let deployed_address = Address(1.into());
+        let replaced_address = Address(2.into());
+
+        let mut state_reader = InMemoryStateReader::default();
+        state_reader
+            .address_to_class_hash
+            .insert(replaced_address.clone(), [1; 32]);
+
+        let mut cached_state = CachedState::new(Arc::new(state_reader), HashMap::new());
+        cached_state
+            .deploy_contract(deployed_address.clone(), [2; 32])
+            .unwrap();
+        cached_state
+            .set_class_hash_at(replaced_address.clone(), [3; 32])
+            .unwrap();
+
+        let diff = StateDiff::from_cached_state(cached_state).unwrap();
+
+        assert_eq!(
+            diff.deployed_contracts().collect::<Vec<_>>(),
+            vec![(&deployed_address, &[2; 32])]
+        );
+        assert_eq!(
+            diff.replaced_contracts().collect::<Vec<_>>(),
+            vec![(&replaced_address, &[3; 32])]
+        );
+    }
+
+    #[test]
+    fn state_diff_serializes_as_state_update() {
+        let class_hash = |value: u64| felt_to_hash(&value.into());
+        let state_diff = StateDiff::new(
+            HashMap::from([
+                (Address(1.into()), class_hash(2)),
+                (Address(3.into()), class_hash(4)),
+            ]),
+            HashMap::from([(Address(1.into()), Felt252::new(5))]),
+            HashMap::new(),
+            HashMap::from([(
+                Address(6.into()),
+                HashMap::from([(Felt252::new(7), Felt252::new(8))]),
+            )]),
+            HashMap::from([(class_hash(9), class_hash(10))]),
+            HashSet::from([class_hash(11)]),
+            HashSet::from([Address(3.into())]),
+        );
+
+        let json = serde_json::to_value(&state_diff).unwrap();
+        assert_eq!(
+            json["storage_diffs"],
+            serde_json::json!([{
+                "address": "0x6",
+                "storage_entries": [{ "key": "0x7", "value": "0x8" }]
+            }])
+        );
+        assert_eq!(
+            json["nonces"],
+            serde_json::json!([{ "contract_address": "0x1", "nonce": "0x5" }])
+        );
+        assert_eq!(
+            json["deployed_contracts"],
+            serde_json::json!([{ "address": "0x1", "class_hash": "0x2" }])
+        );
+        assert_eq!(
+            json["replaced_classes"],
+            serde_json::json!([{ "contract_address": "0x3", "class_hash": "0x4" }])
+        );
+        assert_eq!(
+            json["declared_classes"],
+            serde_json::json!([{ "class_hash": "0x9", "compiled_class_hash": "0xa" }])
+        );
+        assert_eq!(
+            json["deprecated_declared_classes"],
+            serde_json::json!(["0xb"])
+        );
+
+        let deserialized: StateDiff = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized, state_diff);
+    }
+
+    #[test]
+    fn state_diff_squash_keeps_deployments() {
+        let address = Address(1.into());
+        let mut deployed = StateDiff::default();
+        deployed
+            .address_to_class_hash
+            .insert(address.clone(), [1; 32]);
+        let mut replaced = StateDiff::default();
+        replaced
+            .address_to_class_hash
+            .insert(address.clone(), [2; 32]);
+        replaced.replaced_classes.insert(address.clone());
+
+        let squashed = deployed.squash(replaced.clone());
+        assert_eq!(
+            squashed.deployed_contracts().collect::<Vec<_>>(),
+            vec![(&address, &[2; 32])]
+        );
+
+        let squashed = replaced.clone().squash(replaced.clone());
+        assert_eq!(squashed, replaced);
+    }
+
+    /// A small deterministic generator, so that the diff properties below are checked over
+    /// many diffs whose values often collide with the ones in the state.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn below(&mut self, bound: u64) -> u64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 33) % bound
+        }
+    }
+
+    const ADDRESSES: u64 = 4;
+    const KEYS: u64 = 3;
+    const CLASSES: u64 = 3;
+
+    fn random_class_hash(rng: &mut Lcg) -> [u8; 32] {
+        felt_to_hash(&rng.below(CLASSES).into())
+    }
+
+    fn random_state_reader(rng: &mut Lcg) -> InMemoryStateReader {
+        let mut state_reader = InMemoryStateReader::default();
+        for address in 1..=ADDRESSES {
+            let address = Address(address.into());
+            state_reader
+                .address_to_class_hash
+                .insert(address.clone(), random_class_hash(rng));
+            state_reader
+                .address_to_nonce
+                .insert(address.clone(), rng.below(3).into());
+            for key in 0..KEYS {
+                state_reader.address_to_storage.insert(
+                    (address.clone(), felt_to_hash(&key.into())),
+                    rng.below(3).into(),
+                );
+            }
+        }
+        // Only some of the classes are declared.
+        state_reader
+            .class_hash_to_compiled_class_hash
+            .insert(felt_to_hash(&1.into()), random_class_hash(rng));
+        state_reader
+    }
+
+    fn random_diff(rng: &mut Lcg) -> StateDiff {
+        let mut state_diff = StateDiff::default();
+        for address in 1..=ADDRESSES {
+            let address = Address(address.into());
+            if rng.below(2) == 0 {
+                state_diff
+                    .address_to_class_hash
+                    .insert(address.clone(), random_class_hash(rng));
+            }
+            if rng.below(2) == 0 {
+                state_diff
+                    .address_to_nonce
+                    .insert(address.clone(), rng.below(3).into());
+            }
+            for key in 0..KEYS {
+                if rng.below(2) == 0 {
+                    state_diff
+                        .storage_updates
+                        .entry(address.clone())
+                        .or_default()
+                        .insert(key.into(), rng.below(3).into());
+                }
+            }
+        }
+        for class_hash in 0..CLASSES {
+            if rng.below(3) == 0 {
+                state_diff
+                    .class_hash_to_compiled_class_hash
+                    .insert(felt_to_hash(&class_hash.into()), random_class_hash(rng));
+            }
+        }
+        state_diff
+    }
+
+    fn apply<T: StateReader>(state: &mut CachedState<T>, state_diff: &StateDiff) {
+        state.apply_state_update(state_diff).unwrap();
+    }
+
+    /// Reads every value the generated diffs can touch.
+    fn read_all<T: StateReader>(state: &T) -> Vec<String> {
+        let mut values = Vec::new();
+        for address in 1..=ADDRESSES {
+            let address = Address(address.into());
+            values.push(format!("{:?}", state.get_class_hash_at(&address)));
+            values.push(format!("{:?}", state.get_nonce_at(&address)));
+            for key in 0..KEYS {
+                values.push(format!(
+                    "{:?}",
+                    state.get_storage_at(&(address.clone(), felt_to_hash(&key.into())))
+                ));
+            }
+        }
+        values
+    }
+
+    #[test]
+    fn state_diff_inverse_restores_the_state() {
+        let mut rng = Lcg(1);
+        for _ in 0..100 {
+            let state_reader = random_state_reader(&mut rng);
+            let state_diff = random_diff(&mut rng);
+            let inverse = state_diff.inverse(&state_reader).unwrap();
+
+            let mut state = CachedState::new(Arc::new(state_reader.clone()), HashMap::new());
+            apply(&mut state, &state_diff);
+            apply(&mut state, &inverse);
+
+            assert_eq!(read_all(&state), read_all(&state_reader));
+            let declared_class = felt_to_hash(&1.into());
+            assert_eq!(
+                StateReader::get_compiled_class_hash(&state, &declared_class).unwrap(),
+                state_reader
+                    .get_compiled_class_hash(&declared_class)
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn state_diff_inverse_undoes_deployments_with_the_zero_class_hash() {
+        let address = Address(1.into());
+        let mut deployed = StateDiff::default();
+        deployed
+            .address_to_class_hash
+            .insert(address.clone(), felt_to_hash(&2.into()));
+
+        let state_reader = InMemoryStateReader::default();
+        let inverse = deployed.inverse(&state_reader).unwrap();
+        assert_eq!(inverse.deployed_contracts().count(), 0);
+        assert_eq!(
+            inverse.address_to_class_hash,
+            HashMap::from([(address.clone(), *UNINITIALIZED_CLASS_HASH)])
+        );
+        assert_eq!(inverse.replaced_classes, HashSet::from([address.clone()]));
+        assert_eq!(
+            serde_json::to_value(&inverse).unwrap()["replaced_classes"],
+            serde_json::json!([{ "contract_address": "0x1", "class_hash": "0x0" }])
+        );
+
+        let mut state = CachedState::new(Arc::new(state_reader), HashMap::new());
+        apply(&mut state, &deployed);
+        apply(&mut state, &inverse);
+        assert_eq!(
+            StateReader::get_class_hash_at(&state, &address).unwrap(),
+            *UNINITIALIZED_CLASS_HASH
+        );
+    }
+
+    #[test]
+    fn state_diff_compose_applies_both_diffs() {
+        let mut rng = Lcg(2);
+        for _ in 0..100 {
+            let state_reader = Arc::new(random_state_reader(&mut rng));
+            let first = random_diff(&mut rng);
+            let second = random_diff(&mut rng);
+
+            let mut in_sequence = CachedState::new(state_reader.clone(), HashMap::new());
+            apply(&mut in_sequence, &first);
+            apply(&mut in_sequence, &second);
+
+            let mut composed = CachedState::new(state_reader, HashMap::new());
+            apply(&mut composed, &first.compose(&second));
+
+            assert_eq!(read_all(&in_sequence), read_all(&composed));
+            assert_eq!(
+                in_sequence.cache().class_hash_to_compiled_class_hash,
+                composed.cache().class_hash_to_compiled_class_hash
+            );
+        }
+    }
+
+    #[test]
+    fn state_diff_squash_no_ops_keeps_the_effect() {
+        let mut rng = Lcg(3);
+        for _ in 0..100 {
+            let state_reader = Arc::new(random_state_reader(&mut rng));
+            let state_diff = random_diff(&mut rng);
+            let squashed = state_diff.squash_no_ops(state_reader.as_ref()).unwrap();
+
+            // Nothing left in the squashed diff is a no-op...
+            assert_eq!(
+                squashed.squash_no_ops(state_reader.as_ref()).unwrap(),
+                squashed
+            );
+            for (address, nonce) in squashed.address_to_nonce() {
+                assert_ne!(&state_reader.get_nonce_at(address).unwrap(), nonce);
+            }
+
+            // ...and applying it has the same effect as applying the whole diff.
+            let mut applied = CachedState::new(state_reader.clone(), HashMap::new());
+            apply(&mut applied, &state_diff);
+            let mut applied_squashed = CachedState::new(state_reader, HashMap::new());
+            apply(&mut applied_squashed, &squashed);
+
+            assert_eq!(read_all(&applied), read_all(&applied_squashed));
+        }
+    }
 }
@@ -0,0 +1,757 @@
+use crate::{
+    core::errors::state_errors::StateError,
+    services::api::contract_classes::{
+        compiled_class::CompiledClass, deprecated_contract_class::ContractClass,
+    },
+    state::{
+        cached_state::{CachedState, ContractClassCache, UNINITIALIZED_CLASS_HASH},
+        state_api::StateReader,
+        state_cache::StorageEntry,
+        StateDiff,
+    },
+    utils::{
+        felt_from_hex, felt_to_hex, hash_from_hex, hash_to_hex, Address, ClassHash,
+        CompiledClassHash,
+    },
+};
+use cairo_lang_starknet::casm_contract_class::CasmContractClass;
+use cairo_vm::felt::Felt252;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+const SNAPSHOT_FILE: &str = "snapshot.json";
+const JOURNAL_FILE: &str = "journal.jsonl";
+const CLASSES_DIR: &str = "classes";
+
+/// A [StateReader] that keeps its data on disk.
+///
+/// The state lives in a directory containing:
+/// - `snapshot.json`: the full state as of the last compaction.
+/// - `journal.jsonl`: one line per committed [StateDiff] since that compaction.
+/// - `classes/`: one file per stored compiled class.
+///
+/// Every commit writes its classes first and then appends a single line to the journal,
+/// which is synced before returning. A crash in the middle of a commit leaves, at most,
+/// a truncated last line in the journal; it is discarded when reopening, so the reader
+/// always reopens to the state left by the last successful commit.
+///
+/// Since commits take `&self`, the reader can be shared through the `Arc` held by a
+/// [CachedState] and updated once the cached state's changes are final.
+#[derive(Debug)]
+pub struct PersistentStateReader {
+    path: PathBuf,
+    state: RwLock<PersistedState>,
+    journal: Mutex<File>,
+    loaded_classes: RwLock<ContractClassCache>,
+}
+
+/// The in-memory view of the persisted state.
+#[derive(Debug, Default)]
+struct PersistedState {
+    address_to_class_hash: HashMap<Address, ClassHash>,
+    address_to_nonce: HashMap<Address, Felt252>,
+    address_to_storage: HashMap<StorageEntry, Felt252>,
+    class_hash_to_compiled_class_hash: HashMap<ClassHash, CompiledClassHash>,
+    stored_classes: HashSet<ClassHash>,
+}
+
+/// The on-disk representation of a (partial) state, used both for the snapshot and for each
+/// journal entry. Felts and hashes are stored as hex strings, see [felt_to_hex] and
+/// [hash_to_hex].
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StateRecord {
+    #[serde(default)]
+    address_to_class_hash: HashMap<String, String>,
+    #[serde(default)]
+    address_to_nonce: HashMap<String, String>,
+    #[serde(default)]
+    storage_updates: HashMap<String, HashMap<String, String>>,
+    #[serde(default)]
+    class_hash_to_compiled_class_hash: HashMap<String, String>,
+    #[serde(default)]
+    stored_classes: Vec<String>,
+}
+
+/// The on-disk representation of a compiled class.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum StoredClass {
+    Casm(CasmContractClass),
+    Deprecated {
+        hinted_class_hash: String,
+        program_json: String,
+    },
+}
+
+impl PersistentStateReader {
+    /// Opens the state stored in the directory `path`, creating an empty one if it
+    /// doesn't exist yet.
+    ///
+    /// # Parameters
+    /// - `path` - The directory where the state is stored.
+    ///
+    /// # Errors
+    /// - [StateError::Io] - If the directory can't be read or created.
+    /// - [StateError::CorruptedPersistentState] - If the stored data can't be parsed.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, StateError> {
+        let path = path.as_ref().to_path_buf();
+        fs::create_dir_all(path.join(CLASSES_DIR))?;
+
+        let mut state = PersistedState::default();
+
+        let snapshot_path = path.join(SNAPSHOT_FILE);
+        if snapshot_path.exists() {
+            let snapshot: StateRecord = serde_json::from_slice(&fs::read(&snapshot_path)?)
+                .map_err(|e| StateError::CorruptedPersistentState(e.to_string()))?;
+            state.apply_record(snapshot)?;
+        }
+
+        let mut journal = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path.join(JOURNAL_FILE))?;
+
+        let mut contents = Vec::new();
+        journal.read_to_end(&mut contents)?;
+
+        // Only newline-terminated lines were fully written. Anything after the last newline
+        // belongs to a commit that didn't finish, so it is dropped.
+        let complete_len = contents
+            .iter()
+            .rposition(|b| *b == b'\n')
+            .map(|pos| pos + 1)
+            .unwrap_or(0);
+        if complete_len < contents.len() {
+            journal.set_len(complete_len as u64)?;
+            journal.sync_all()?;
+        }
+
+        for line in contents[..complete_len]
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+        {
+            let record: StateRecord = serde_json::from_slice(line)
+                .map_err(|e| StateError::CorruptedPersistentState(e.to_string()))?;
+            state.apply_record(record)?;
+        }
+
+        Ok(Self {
+            path,
+            state: RwLock::new(state),
+            journal: Mutex::new(journal),
+            loaded_classes: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Returns the directory where the state is stored.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Durably applies a [StateDiff] (for example, one obtained with
+    /// [StateDiff::from_cached_state]) to the stored state.
+    ///
+    /// Casm classes are stored as they are. Cairo 0 classes can't be serialized back into
+    /// their program JSON, so they have to be stored beforehand with
+    /// [PersistentStateReader::store_deprecated_class].
+    ///
+    /// # Errors
+    /// - [StateError::MissingDeprecatedClassProgram] - If the diff contains a Cairo 0 class
+    ///   that wasn't stored.
+    /// - [StateError::Io] - If writing to disk fails. In that case the stored state is left
+    ///   unchanged.
+    pub fn commit(&self, state_diff: &StateDiff) -> Result<(), StateError> {
+        self.commit_with_classes(state_diff, &state_diff.class_hash_to_compiled_class)
+    }
+
+    /// Durably applies the changes made in a [CachedState] to the stored state.
+    ///
+    /// Besides the [StateDiff] obtained from the cached state, this also stores the classes
+    /// declared through it, which are not part of the diff.
+    pub fn commit_cached_state<T: StateReader>(
+        &self,
+        cached_state: &CachedState<T>,
+    ) -> Result<(), StateError> {
+        let mut diff_state = CachedState::new(cached_state.state_reader.clone(), HashMap::new());
+        diff_state.cache = cached_state.cache.clone();
+        let state_diff = StateDiff::from_cached_state(diff_state)?;
+
+        let mut classes = state_diff.class_hash_to_compiled_class.clone();
+        {
+            let state = self.read_state()?;
+            classes.extend(
+                cached_state
+                    .contract_classes()
+                    .iter()
+                    .filter(|(class_hash, _)| !state.stored_classes.contains(*class_hash))
+                    .map(|(class_hash, class)| (*class_hash, class.clone())),
+            );
+        }
+
+        self.commit_with_classes(&state_diff, &classes)
+    }
+
+    /// Stores a Cairo 0 class from the JSON of its compiled program.
+    ///
+    /// # Parameters
+    /// - `class_hash` - The hash of the class.
+    /// - `program_json` - The JSON of the compiled Cairo 0 program.
+    pub fn store_deprecated_class(
+        &self,
+        class_hash: &ClassHash,
+        program_json: &str,
+    ) -> Result<(), StateError> {
+        let contract_class = ContractClass::from_str(program_json)
+            .map_err(|e| StateError::CustomError(e.to_string()))?;
+
+        let stored_class = StoredClass::Deprecated {
+            hinted_class_hash: felt_to_hex(contract_class.hinted_class_hash()),
+            program_json: program_json.to_string(),
+        };
+        self.write_class_file(class_hash, &stored_class)?;
+
+        let record = StateRecord {
+            stored_classes: vec![hash_to_hex(class_hash)],
+            ..Default::default()
+        };
+        let _journal = self.append_record(&record)?;
+
+        self.write_state()?.stored_classes.insert(*class_hash);
+        self.write_loaded_classes()?.insert(
+            *class_hash,
+            CompiledClass::Deprecated(Arc::new(contract_class)),
+        );
+        Ok(())
+    }
+
+    /// Rewrites the snapshot with the current state and empties the journal.
+    ///
+    /// The new snapshot replaces the old one atomically, so a crash at any point leaves
+    /// either the old snapshot with the full journal or the new one.
+    pub fn compact(&self) -> Result<(), StateError> {
+        let journal = self
+            .journal
+            .lock()
+            .map_err(|_| StateError::CustomError("Journal lock poisoned".to_string()))?;
+
+        let record = self.read_state()?.to_record();
+        let contents = serde_json::to_vec(&record)
+            .map_err(|e| StateError::CorruptedPersistentState(e.to_string()))?;
+        write_atomically(&self.path, &self.path.join(SNAPSHOT_FILE), &contents)?;
+
+        // Replaying the journal over the new snapshot is idempotent, so a crash before this
+        // point only means the journal is replayed once more.
+        journal.set_len(0)?;
+        journal.sync_all()?;
+        Ok(())
+    }
+
+    fn commit_with_classes(
+        &self,
+        state_diff: &StateDiff,
+        classes: &HashMap<ClassHash, CompiledClass>,
+    ) -> Result<(), StateError> {
+        let mut new_classes = Vec::new();
+        {
+            let state = self.read_state()?;
+            for (class_hash, class) in classes {
+                if state.stored_classes.contains(class_hash) {
+                    continue;
+                }
+                match class {
+                    CompiledClass::Casm(casm_class) => {
+                        self.write_class_file(
+                            class_hash,
+                            &StoredClass::Casm(casm_class.as_ref().clone()),
+                        )?;
+                        new_classes.push(*class_hash);
+                    }
+                    CompiledClass::Deprecated(_) => {
+                        return Err(StateError::MissingDeprecatedClassProgram(*class_hash))
+                    }
+                }
+            }
+        }
+
+        let record = StateRecord {
+            address_to_class_hash: state_diff
+                .address_to_class_hash
+                .iter()
+                .map(|(address, class_hash)| (felt_to_hex(&address.0), hash_to_hex(class_hash)))
+                .collect(),
+            address_to_nonce: state_diff
+                .address_to_nonce
+                .iter()
+                .map(|(address, nonce)| (felt_to_hex(&address.0), felt_to_hex(nonce)))
+                .collect(),
+            storage_updates: state_diff
+                .storage_updates
+                .iter()
+                .map(|(address, updates)| {
+                    (
+                        felt_to_hex(&address.0),
+                        updates
+                            .iter()
+                            .map(|(key, value)| (felt_to_hex(key), felt_to_hex(value)))
+                            .collect(),
+                    )
+                })
+                .collect(),
+            class_hash_to_compiled_class_hash: state_diff
+                .class_hash_to_compiled_class_hash
+                .iter()
+                .map(|(class_hash, compiled_class_hash)| {
+                    (hash_to_hex(class_hash), hash_to_hex(compiled_class_hash))
+                })
+                .collect(),
+            stored_classes: new_classes.iter().map(hash_to_hex).collect(),
+        };
+        let _journal = self.append_record(&record)?;
+
+        self.write_state()?.apply_record(record)?;
+        let mut loaded_classes = self.write_loaded_classes()?;
+        for class_hash in new_classes {
+            if let Some(class) = classes.get(&class_hash) {
+                loaded_classes.insert(class_hash, class.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends a record to the journal, returning the journal's guard so that the caller can
+    /// update the in-memory state before a compaction gets to see it.
+    fn append_record(&self, record: &StateRecord) -> Result<MutexGuard<File>, StateError> {
+        let mut line = serde_json::to_vec(record)
+            .map_err(|e| StateError::CorruptedPersistentState(e.to_string()))?;
+        line.push(b'\n');
+
+        let mut journal = self
+            .journal
+            .lock()
+            .map_err(|_| StateError::CustomError("Journal lock poisoned".to_string()))?;
+        journal.write_all(&line)?;
+        journal.sync_data()?;
+        Ok(journal)
+    }
+
+    fn write_class_file(
+        &self,
+        class_hash: &ClassHash,
+        stored_class: &StoredClass,
+    ) -> Result<(), StateError> {
+        let contents = serde_json::to_vec(stored_class)
+            .map_err(|e| StateError::CorruptedPersistentState(e.to_string()))?;
+        let classes_dir = self.path.join(CLASSES_DIR);
+        write_atomically(
+            &classes_dir,
+            &class_file_path(&self.path, class_hash),
+            &contents,
+        )
+    }
+
+    fn load_class(&self, class_hash: &ClassHash) -> Result<Option<CompiledClass>, StateError> {
+        if let Some(class) = self.read_loaded_classes()?.get(class_hash) {
+            return Ok(Some(class.clone()));
+        }
+        if !self.read_state()?.stored_classes.contains(class_hash) {
+            return Ok(None);
+        }
+
+        let contents = fs::read(class_file_path(&self.path, class_hash))?;
+        let stored_class: StoredClass = serde_json::from_slice(&contents)
+            .map_err(|e| StateError::CorruptedPersistentState(e.to_string()))?;
+        let class = match stored_class {
+            StoredClass::Casm(casm_class) => CompiledClass::Casm(Arc::new(casm_class)),
+            StoredClass::Deprecated {
+                hinted_class_hash,
+                program_json,
+            } => {
+                let hinted_class_hash = parse_felt(&hinted_class_hash)?;
+                CompiledClass::Deprecated(Arc::new(
+                    ContractClass::from_program_json_and_class_hash(
+                        &program_json,
+                        hinted_class_hash,
+                    )?,
+                ))
+            }
+        };
+
+        self.write_loaded_classes()?
+            .insert(*class_hash, class.clone());
+        Ok(Some(class))
+    }
+
+    fn read_state(&self) -> Result<RwLockReadGuard<PersistedState>, StateError> {
+        self.state
+            .read()
+            .map_err(|_| StateError::CustomError("State lock poisoned".to_string()))
+    }
+
+    fn write_state(&self) -> Result<RwLockWriteGuard<PersistedState>, StateError> {
+        self.state
+            .write()
+            .map_err(|_| StateError::CustomError("State lock poisoned".to_string()))
+    }
+
+    fn read_loaded_classes(&self) -> Result<RwLockReadGuard<ContractClassCache>, StateError> {
+        self.loaded_classes
+            .read()
+            .map_err(|_| StateError::CustomError("Class cache lock poisoned".to_string()))
+    }
+
+    fn write_loaded_classes(&self) -> Result<RwLockWriteGuard<ContractClassCache>, StateError> {
+        self.loaded_classes
+            .write()
+            .map_err(|_| StateError::CustomError("Class cache lock poisoned".to_string()))
+    }
+}
+
+impl PersistedState {
+    fn apply_record(&mut self, record: StateRecord) -> Result<(), StateError> {
+        for (address, class_hash) in record.address_to_class_hash {
+            self.address_to_class_hash
+                .insert(Address(parse_felt(&address)?), parse_hash(&class_hash)?);
+        }
+        for (address, nonce) in record.address_to_nonce {
+            self.address_to_nonce
+                .insert(Address(parse_felt(&address)?), parse_felt(&nonce)?);
+        }
+        for (address, updates) in record.storage_updates {
+            let address = Address(parse_felt(&address)?);
+            for (key, value) in updates {
+                self.address_to_storage
+                    .insert((address.clone(), parse_hash(&key)?), parse_felt(&value)?);
+            }
+        }
+        for (class_hash, compiled_class_hash) in record.class_hash_to_compiled_class_hash {
+            self.class_hash_to_compiled_class_hash
+                .insert(parse_hash(&class_hash)?, parse_hash(&compiled_class_hash)?);
+        }
+        for class_hash in record.stored_classes {
+            self.stored_classes.insert(parse_hash(&class_hash)?);
+        }
+        Ok(())
+    }
+
+    fn to_record(&self) -> StateRecord {
+        let mut storage_updates: HashMap<String, HashMap<String, String>> = HashMap::new();
+        for ((address, key), value) in &self.address_to_storage {
+            storage_updates
+                .entry(felt_to_hex(&address.0))
+                .or_default()
+                .insert(hash_to_hex(key), felt_to_hex(value));
+        }
+
+        StateRecord {
+            address_to_class_hash: self
+                .address_to_class_hash
+                .iter()
+                .map(|(address, class_hash)| (felt_to_hex(&address.0), hash_to_hex(class_hash)))
+                .collect(),
+            address_to_nonce: self
+                .address_to_nonce
+                .iter()
+                .map(|(address, nonce)| (felt_to_hex(&address.0), felt_to_hex(nonce)))
+                .collect(),
+            storage_updates,
+            class_hash_to_compiled_class_hash: self
+                .class_hash_to_compiled_class_hash
+                .iter()
+                .map(|(class_hash, compiled_class_hash)| {
+                    (hash_to_hex(class_hash), hash_to_hex(compiled_class_hash))
+                })
+                .collect(),
+            stored_classes: self.stored_classes.iter().map(hash_to_hex).collect(),
+        }
+    }
+}
+
+impl StateReader for PersistentStateReader {
+    fn get_class_hash_at(&self, contract_address: &Address) -> Result<ClassHash, StateError> {
+        Ok(self
+            .read_state()?
+            .address_to_class_hash
+            .get(contract_address)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn get_nonce_at(&self, contract_address: &Address) -> Result<Felt252, StateError> {
+        Ok(self
+            .read_state()?
+            .address_to_nonce
+            .get(contract_address)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn get_storage_at(&self, storage_entry: &StorageEntry) -> Result<Felt252, StateError> {
+        Ok(self
+            .read_state()?
+            .address_to_storage
+            .get(storage_entry)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn get_compiled_class_hash(
+        &self,
+        class_hash: &ClassHash,
+    ) -> Result<CompiledClassHash, StateError> {
+        self.read_state()?
+            .class_hash_to_compiled_class_hash
+            .get(class_hash)
+            .ok_or(StateError::NoneCompiledHash(*class_hash))
+            .copied()
+    }
+
+    fn get_contract_class(&self, class_hash: &ClassHash) -> Result<CompiledClass, StateError> {
+        // Deprecated contract classes dont have a compiled_class_hash, we dont need to fetch it
+        if let Some(compiled_class) = self.load_class(class_hash)? {
+            return Ok(compiled_class);
+        }
+
+        let compiled_class_hash = self.get_compiled_class_hash(class_hash)?;
+        if compiled_class_hash == *UNINITIALIZED_CLASS_HASH {
+            return Err(StateError::MissingCasmClass(compiled_class_hash));
+        }
+        self.load_class(&compiled_class_hash)?
+            .ok_or(StateError::NoneCompiledClass(compiled_class_hash))
+    }
+}
+
+fn class_file_path(path: &Path, class_hash: &ClassHash) -> PathBuf {
+    path.join(CLASSES_DIR)
+        .join(format!("{}.json", hex::encode(class_hash)))
+}
+
+/// Writes `contents` to `file_path` so that readers either see the old file or the new one.
+fn write_atomically(dir: &Path, file_path: &Path, contents: &[u8]) -> Result<(), StateError> {
+    let tmp_path = file_path.with_extension("tmp");
+    {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp_path, file_path)?;
+
+    // Make the rename itself durable.
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+
+    Ok(())
+}
+
+fn parse_felt(value: &str) -> Result<Felt252, StateError> {
+    felt_from_hex(value)
+        .ok_or_else(|| StateError::CorruptedPersistentState(format!("Invalid felt: {value}")))
+}
+
+fn parse_hash(value: &str) -> Result<ClassHash, StateError> {
+    hash_from_hex(value)
+        .ok_or_else(|| StateError::CorruptedPersistentState(format!("Invalid hash: {value}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{state::state_api::State, utils::felt_to_hash};
+    use num_traits::Zero;
+
+    fn temp_state_dir(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("starknet_in_rust_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        path
+    }
+
+    #[test]
+    fn empty_state_returns_defaults() {
+        let path = temp_state_dir("empty_state_returns_defaults");
+        let state_reader = PersistentStateReader::open(&path).unwrap();
+
+        let address = Address(1.into());
+        assert_eq!(state_reader.get_class_hash_at(&address).unwrap(), [0; 32]);
+        assert!(state_reader.get_nonce_at(&address).unwrap().is_zero());
+        assert!(state_reader
+            .get_storage_at(&(address, [1; 32]))
+            .unwrap()
+            .is_zero());
+        assert_matches!(
+            state_reader.get_compiled_class_hash(&[1; 32]),
+            Err(StateError::NoneCompiledHash(_))
+        );
+
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn committed_state_diff_survives_reopening() {
+        let path = temp_state_dir("committed_state_diff_survives_reopening");
+        let state_reader = Arc::new(PersistentStateReader::open(&path).unwrap());
+
+        let address = Address(5.into());
+        let storage_entry = (address.clone(), felt_to_hash(&7.into()));
+
+        let mut cached_state = CachedState::new(state_reader.clone(), HashMap::new());
+        cached_state
+            .set_class_hash_at(address.clone(), [3; 32])
+            .unwrap();
+        cached_state.increment_nonce(&address).unwrap();
+        cached_state.set_storage_at(&storage_entry, 11.into());
+        cached_state
+            .set_compiled_class_hash(&Felt252::from(3), &Felt252::from(4))
+            .unwrap();
+
+        state_reader.commit_cached_state(&cached_state).unwrap();
+        drop(cached_state);
+        drop(state_reader);
+
+        let state_reader = PersistentStateReader::open(&path).unwrap();
+        assert_eq!(state_reader.get_class_hash_at(&address).unwrap(), [3; 32]);
+        assert_eq!(state_reader.get_nonce_at(&address).unwrap(), 1.into());
+        assert_eq!(
+            state_reader.get_storage_at(&storage_entry).unwrap(),
+            11.into()
+        );
+        assert_eq!(
+            state_reader.get_compiled_class_hash(&[3; 32]).unwrap(),
+            felt_to_hash(&4.into())
+        );
+
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn truncated_journal_entry_is_discarded() {
+        let path = temp_state_dir("truncated_journal_entry_is_discarded");
+        let address = Address(5.into());
+        {
+            let state_reader = PersistentStateReader::open(&path).unwrap();
+            let state_diff = StateDiff::new(
+                HashMap::new(),
+                HashMap::from([(address.clone(), 1.into())]),
+                HashMap::new(),
+                HashMap::new(),
+                HashMap::new(),
+                HashSet::new(),
+                HashSet::new(),
+            );
+            state_reader.commit(&state_diff).unwrap();
+        }
+
+        // Simulate a crash in the middle of a second commit.
+        let mut journal = OpenOptions::new()
+            .append(true)
+            .open(path.join(JOURNAL_FILE))
+            .unwrap();
+        journal.write_all(b"{\"address_to_nonce\":{\"5\":").unwrap();
+        drop(journal);
+
+        let state_reader = PersistentStateReader::open(&path).unwrap();
+        assert_eq!(state_reader.get_nonce_at(&address).unwrap(), 1.into());
+
+        // The reader keeps working after recovering.
+        let state_diff = StateDiff::new(
+            HashMap::new(),
+            HashMap::from([(address.clone(), 2.into())]),
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+            HashSet::new(),
+            HashSet::new(),
+        );
+        state_reader.commit(&state_diff).unwrap();
+        drop(state_reader);
+
+        let state_reader = PersistentStateReader::open(&path).unwrap();
+        assert_eq!(state_reader.get_nonce_at(&address).unwrap(), 2.into());
+
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn compact_keeps_the_state() {
+        let path = temp_state_dir("compact_keeps_the_state");
+        let address = Address(9.into());
+        {
+            let state_reader = PersistentStateReader::open(&path).unwrap();
+            let state_diff = StateDiff::new(
+                HashMap::from([(address.clone(), [1; 32])]),
+                HashMap::new(),
+                HashMap::new(),
+                HashMap::from([(address.clone(), HashMap::from([(2.into(), 3.into())]))]),
+                HashMap::new(),
+                HashSet::new(),
+                HashSet::new(),
+            );
+            state_reader.commit(&state_diff).unwrap();
+            state_reader.compact().unwrap();
+        }
+
+        assert!(fs::read(path.join(JOURNAL_FILE)).unwrap().is_empty());
+
+        let state_reader = PersistentStateReader::open(&path).unwrap();
+        assert_eq!(state_reader.get_class_hash_at(&address).unwrap(), [1; 32]);
+        assert_eq!(
+            state_reader
+                .get_storage_at(&(address, felt_to_hash(&2.into())))
+                .unwrap(),
+            3.into()
+        );
+
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn deprecated_classes_must_be_stored_with_their_program() {
+        let path = temp_state_dir("deprecated_classes_must_be_stored_with_their_program");
+        let program_json = fs::read_to_string(
+            "starknet_programs/raw_contract_classes/0x4479c3b883b34f1eafa5065418225d78a11ee7957c371e1b285e4b77afc6dad.json",
+        ).unwrap();
+        let contract_class = ContractClass::from_str(&program_json).unwrap();
+        let class_hash = [2; 32];
+
+        let state_reader = PersistentStateReader::open(&path).unwrap();
+        let state_diff = StateDiff::new(
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::from([(
+                class_hash,
+                CompiledClass::Deprecated(Arc::new(contract_class.clone())),
+            )]),
+            HashMap::new(),
+            HashMap::new(),
+            HashSet::new(),
+            HashSet::new(),
+        );
+        assert_matches!(
+            state_reader.commit(&state_diff),
+            Err(StateError::MissingDeprecatedClassProgram(_))
+        );
+
+        state_reader
+            .store_deprecated_class(&class_hash, &program_json)
+            .unwrap();
+        state_reader.commit(&state_diff).unwrap();
+        drop(state_reader);
+
+        let state_reader = PersistentStateReader::open(&path).unwrap();
+        assert_eq!(
+            state_reader.get_contract_class(&class_hash).unwrap(),
+            CompiledClass::Deprecated(Arc::new(contract_class))
+        );
+
+        fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -0,0 +1,303 @@
+use crate::{
+    core::errors::state_errors::StateError,
+    services::api::contract_classes::compiled_class::CompiledClass,
+    state::{
+        in_memory_state_reader::InMemoryStateReader, state_api::StateReader,
+        state_cache::StorageEntry,
+    },
+    utils::{
+        felt_from_hex, felt_to_hex, hash_from_hex, hash_to_hex, Address, ClassHash,
+        CompiledClassHash,
+    },
+};
+use cairo_vm::felt::Felt252;
+use getset::Getters;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+/// A [StateReader] that records every value read from the reader it wraps.
+///
+/// The recorded values are exactly the state a transaction touched, so they can be saved
+/// with [RecordingStateReader::save_log] and later loaded as an [InMemoryStateReader] with
+/// [StateReadLog::load], to run the same transaction again without access to the original
+/// state (for example, without network access when the original reader is an RPC one).
+///
+/// Cairo 0 classes can only be saved if they kept the JSON of their program, see
+/// [ContractClass::from_str_with_program_json](crate::services::api::contract_classes::deprecated_contract_class::ContractClass::from_str_with_program_json).
+#[derive(Debug, Getters)]
+pub struct RecordingStateReader<T: StateReader> {
+    #[getset(get = "pub")]
+    state_reader: Arc<T>,
+    log: Mutex<StateReadLog>,
+}
+
+/// The values read through a [RecordingStateReader].
+#[derive(Clone, Debug, Default, Eq, Getters, PartialEq)]
+#[getset(get = "pub")]
+pub struct StateReadLog {
+    address_to_class_hash: HashMap<Address, ClassHash>,
+    address_to_nonce: HashMap<Address, Felt252>,
+    address_to_storage: HashMap<StorageEntry, Felt252>,
+    class_hash_to_compiled_class: HashMap<ClassHash, CompiledClass>,
+    class_hash_to_compiled_class_hash: HashMap<ClassHash, CompiledClassHash>,
+}
+
+/// Serialized form of a [StateReadLog]. Felts and hashes are stored as hex strings, see
+/// [felt_to_hex] and [hash_to_hex].
+#[derive(Serialize, Deserialize)]
+struct SerializedStateReadLog {
+    address_to_class_hash: HashMap<String, String>,
+    address_to_nonce: HashMap<String, String>,
+    address_to_storage: HashMap<String, HashMap<String, String>>,
+    class_hash_to_compiled_class: HashMap<String, CompiledClass>,
+    class_hash_to_compiled_class_hash: HashMap<String, String>,
+}
+
+impl<T: StateReader> RecordingStateReader<T> {
+    /// Creates a new RecordingStateReader with an empty log.
+    ///
+    /// # Parameters
+    /// - `state_reader` - The reader whose values are recorded.
+    pub fn new(state_reader: Arc<T>) -> Self {
+        Self {
+            state_reader,
+            log: Mutex::new(StateReadLog::default()),
+        }
+    }
+
+    /// Returns a copy of the values recorded so far.
+    pub fn log(&self) -> Result<StateReadLog, StateError> {
+        Ok(self.lock_log()?.clone())
+    }
+
+    /// Writes the values recorded so far to the file at `path`, as JSON.
+    pub fn save_log<P: AsRef<Path>>(&self, path: P) -> Result<(), StateError> {
+        self.lock_log()?.save(path)
+    }
+
+    fn lock_log(&self) -> Result<std::sync::MutexGuard<StateReadLog>, StateError> {
+        self.log
+            .lock()
+            .map_err(|_| StateError::CustomError("State read log lock poisoned".to_string()))
+    }
+}
+
+impl<T: StateReader> StateReader for RecordingStateReader<T> {
+    fn get_class_hash_at(&self, contract_address: &Address) -> Result<ClassHash, StateError> {
+        let class_hash = self.state_reader.get_class_hash_at(contract_address)?;
+        self.lock_log()?
+            .address_to_class_hash
+            .insert(contract_address.clone(), class_hash);
+        Ok(class_hash)
+    }
+
+    fn get_nonce_at(&self, contract_address: &Address) -> Result<Felt252, StateError> {
+        let nonce = self.state_reader.get_nonce_at(contract_address)?;
+        self.lock_log()?
+            .address_to_nonce
+            .insert(contract_address.clone(), nonce.clone());
+        Ok(nonce)
+    }
+
+    fn get_storage_at(&self, storage_entry: &StorageEntry) -> Result<Felt252, StateError> {
+        let value = self.state_reader.get_storage_at(storage_entry)?;
+        self.lock_log()?
+            .address_to_storage
+            .insert(storage_entry.clone(), value.clone());
+        Ok(value)
+    }
+
+    fn get_compiled_class_hash(
+        &self,
+        class_hash: &ClassHash,
+    ) -> Result<CompiledClassHash, StateError> {
+        let compiled_class_hash = self.state_reader.get_compiled_class_hash(class_hash)?;
+        self.lock_log()?
+            .class_hash_to_compiled_class_hash
+            .insert(*class_hash, compiled_class_hash);
+        Ok(compiled_class_hash)
+    }
+
+    fn get_contract_class(&self, class_hash: &ClassHash) -> Result<CompiledClass, StateError> {
+        let compiled_class = self.state_reader.get_contract_class(class_hash)?;
+        self.lock_log()?
+            .class_hash_to_compiled_class
+            .insert(*class_hash, compiled_class.clone());
+        Ok(compiled_class)
+    }
+}
+
+impl StateReadLog {
+    /// Reads a log previously written with [StateReadLog::save] or
+    /// [RecordingStateReader::save_log].
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, StateError> {
+        let serialized: SerializedStateReadLog = serde_json::from_slice(&fs::read(path)?)
+            .map_err(|e| StateError::CustomError(e.to_string()))?;
+
+        let mut log = StateReadLog::default();
+        for (address, class_hash) in serialized.address_to_class_hash {
+            log.address_to_class_hash
+                .insert(Address(parse_felt(&address)?), parse_hash(&class_hash)?);
+        }
+        for (address, nonce) in serialized.address_to_nonce {
+            log.address_to_nonce
+                .insert(Address(parse_felt(&address)?), parse_felt(&nonce)?);
+        }
+        for (address, storage) in serialized.address_to_storage {
+            let address = Address(parse_felt(&address)?);
+            for (key, value) in storage {
+                log.address_to_storage
+                    .insert((address.clone(), parse_hash(&key)?), parse_felt(&value)?);
+            }
+        }
+        for (class_hash, compiled_class) in serialized.class_hash_to_compiled_class {
+            log.class_hash_to_compiled_class
+                .insert(parse_hash(&class_hash)?, compiled_class);
+        }
+        for (class_hash, compiled_class_hash) in serialized.class_hash_to_compiled_class_hash {
+            log.class_hash_to_compiled_class_hash
+                .insert(parse_hash(&class_hash)?, parse_hash(&compiled_class_hash)?);
+        }
+        Ok(log)
+    }
+
+    /// Writes the log to the file at `path`, as JSON.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), StateError> {
+        let mut address_to_storage: HashMap<String, HashMap<String, String>> = HashMap::new();
+        for ((address, key), value) in &self.address_to_storage {
+            address_to_storage
+                .entry(felt_to_hex(&address.0))
+                .or_default()
+                .insert(hash_to_hex(key), felt_to_hex(value));
+        }
+
+        let serialized = SerializedStateReadLog {
+            address_to_class_hash: self
+                .address_to_class_hash
+                .iter()
+                .map(|(address, class_hash)| (felt_to_hex(&address.0), hash_to_hex(class_hash)))
+                .collect(),
+            address_to_nonce: self
+                .address_to_nonce
+                .iter()
+                .map(|(address, nonce)| (felt_to_hex(&address.0), felt_to_hex(nonce)))
+                .collect(),
+            address_to_storage,
+            class_hash_to_compiled_class: self
+                .class_hash_to_compiled_class
+                .iter()
+                .map(|(class_hash, compiled_class)| {
+                    (hash_to_hex(class_hash), compiled_class.clone())
+                })
+                .collect(),
+            class_hash_to_compiled_class_hash: self
+                .class_hash_to_compiled_class_hash
+                .iter()
+                .map(|(class_hash, compiled_class_hash)| {
+                    (hash_to_hex(class_hash), hash_to_hex(compiled_class_hash))
+                })
+                .collect(),
+        };
+
+        let contents = serde_json::to_vec_pretty(&serialized)
+            .map_err(|e| StateError::CustomError(e.to_string()))?;
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /// Builds an [InMemoryStateReader] holding exactly the recorded values.
+    pub fn into_state_reader(self) -> InMemoryStateReader {
+        InMemoryStateReader::new(
+            self.address_to_class_hash,
+            self.address_to_nonce,
+            self.address_to_storage,
+            self.class_hash_to_compiled_class,
+            self.class_hash_to_compiled_class_hash,
+        )
+    }
+}
+
+fn parse_felt(value: &str) -> Result<Felt252, StateError> {
+    felt_from_hex(value).ok_or_else(|| StateError::CustomError(format!("Invalid felt: {value}")))
+}
+
+fn parse_hash(value: &str) -> Result<[u8; 32], StateError> {
+    hash_from_hex(value).ok_or_else(|| StateError::CustomError(format!("Invalid hash: {value}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        services::api::contract_classes::deprecated_contract_class::ContractClass,
+        state::cached_state::CachedState, utils::felt_to_hash,
+    };
+
+    #[test]
+    fn recorded_reads_rehydrate_as_in_memory_state_reader() {
+        let address = Address(0x100.into());
+        let storage_entry = (address.clone(), felt_to_hash(&7.into()));
+        let class_hash = [1; 32];
+        let contract_class = ContractClass::from_str_with_program_json(
+            &fs::read_to_string("starknet_programs/raw_contract_classes/class_with_abi.json")
+                .unwrap(),
+        )
+        .unwrap();
+
+        let mut state_reader = InMemoryStateReader::default();
+        state_reader
+            .address_to_class_hash_mut()
+            .insert(address.clone(), class_hash);
+        state_reader
+            .address_to_nonce_mut()
+            .insert(address.clone(), 3.into());
+        state_reader
+            .address_to_storage_mut()
+            .insert(storage_entry.clone(), 11.into());
+        state_reader
+            .address_to_storage_mut()
+            .insert((address.clone(), [8; 32]), 12.into());
+        state_reader.class_hash_to_compiled_class_mut().insert(
+            class_hash,
+            CompiledClass::Deprecated(Arc::new(contract_class)),
+        );
+
+        let recorder = Arc::new(RecordingStateReader::new(Arc::new(state_reader.clone())));
+        let state = CachedState::new(recorder.clone(), HashMap::new());
+        state.get_class_hash_at(&address).unwrap();
+        state.get_nonce_at(&address).unwrap();
+        state.get_storage_at(&storage_entry).unwrap();
+        state.get_contract_class(&class_hash).unwrap();
+
+        let path = std::env::temp_dir().join(format!(
+            "starknet_in_rust_state_read_log_{}.json",
+            std::process::id()
+        ));
+        recorder.save_log(&path).unwrap();
+        let log = StateReadLog::load(&path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(log, recorder.log().unwrap());
+
+        // Only the values that were read are part of the fixture.
+        let fixture = log.into_state_reader();
+        assert_eq!(fixture.address_to_storage.len(), 1);
+        assert_eq!(
+            fixture.get_storage_at(&storage_entry).unwrap(),
+            state_reader.get_storage_at(&storage_entry).unwrap()
+        );
+        assert_eq!(
+            fixture.get_nonce_at(&address).unwrap(),
+            state_reader.get_nonce_at(&address).unwrap()
+        );
+        assert_eq!(
+            fixture.get_contract_class(&class_hash).unwrap(),
+            state_reader.get_contract_class(&class_hash).unwrap()
+        );
+    }
+}
@@ -0,0 +1,341 @@
+use crate::{
+    core::errors::state_errors::StateError,
+    services::api::contract_classes::compiled_class::CompiledClass, utils::ClassHash,
+};
+use cairo_vm::types::relocatable::MaybeRelocatable;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, mem,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard,
+    },
+};
+
+/// A bounded cache of contract classes that can be shared between threads and between many
+/// [CachedState](super::cached_state::CachedState)s, so that a class is fetched from the state
+/// reader and parsed only once.
+///
+/// When the cache is over its limits, the least recently used classes are evicted.
+///
+/// Classes are only cached as the state reader returns them, so a cache should only be shared
+/// between states whose readers agree on the classes they have (for example, states over the
+/// same reader, or over readers of the same chain).
+pub struct SharedClassCache {
+    max_entries: Option<usize>,
+    max_bytes: Option<usize>,
+    classes: Mutex<LruClasses>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+/// A snapshot of the usage of a [SharedClassCache].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ClassCacheStats {
+    /// Lookups that found the class in the cache.
+    pub hits: u64,
+    /// Lookups that had to fetch the class.
+    pub misses: u64,
+    /// Classes removed to keep the cache within its limits.
+    pub evictions: u64,
+    /// Classes currently in the cache.
+    pub entries: usize,
+    /// Estimated size of the classes currently in the cache.
+    pub bytes: usize,
+}
+
+#[derive(Default)]
+struct LruClasses {
+    entries: HashMap<ClassHash, CacheEntry>,
+    /// Class hashes by the tick of their last use, oldest first.
+    recency: BTreeMap<u64, ClassHash>,
+    tick: u64,
+    bytes: usize,
+}
+
+struct CacheEntry {
+    class: CompiledClass,
+    size: usize,
+    last_used: u64,
+}
+
+impl SharedClassCache {
+    /// Creates a cache holding at most `max_entries` classes.
+    pub fn new(max_entries: usize) -> Self {
+        Self::with_limits(Some(max_entries), None)
+    }
+
+    /// Creates a cache holding classes of an estimated total size of at most `max_bytes`.
+    pub fn with_max_bytes(max_bytes: usize) -> Self {
+        Self::with_limits(None, Some(max_bytes))
+    }
+
+    /// Creates a cache with the given limits. A cache without limits never evicts classes.
+    ///
+    /// # Parameters
+    /// - `max_entries` - The maximum amount of classes in the cache.
+    /// - `max_bytes` - The maximum estimated total size of the classes in the cache.
+    pub fn with_limits(max_entries: Option<usize>, max_bytes: Option<usize>) -> Self {
+        Self {
+            max_entries,
+            max_bytes,
+            classes: Mutex::new(LruClasses::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the class cached under `class_hash`, marking it as recently used.
+    pub fn get(&self, class_hash: &ClassHash) -> Option<CompiledClass> {
+        let class = self.lock_classes().get(class_hash);
+        match class {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+        class
+    }
+
+    /// Caches `class` under `class_hash`, evicting the least recently used classes if the
+    /// cache goes over its limits.
+    pub fn insert(&self, class_hash: ClassHash, class: CompiledClass) {
+        let size = estimated_size(&class);
+        let mut classes = self.lock_classes();
+        classes.insert(class_hash, class, size);
+
+        let mut evictions = 0;
+        while !classes.entries.is_empty()
+            && (self
+                .max_entries
+                .is_some_and(|max| classes.entries.len() > max)
+                || self.max_bytes.is_some_and(|max| classes.bytes > max))
+        {
+            classes.evict_least_recently_used();
+            evictions += 1;
+        }
+        self.evictions.fetch_add(evictions, Ordering::Relaxed);
+    }
+
+    /// Returns the class cached under `class_hash`, or fetches it with `fetch` and caches it.
+    ///
+    /// The cache isn't locked while fetching, so concurrent misses of the same class may
+    /// fetch it more than once.
+    pub fn get_or_fetch<F>(
+        &self,
+        class_hash: &ClassHash,
+        fetch: F,
+    ) -> Result<CompiledClass, StateError>
+    where
+        F: FnOnce() -> Result<CompiledClass, StateError>,
+    {
+        if let Some(class) = self.get(class_hash) {
+            return Ok(class);
+        }
+        let class = fetch()?;
+        self.insert(*class_hash, class.clone());
+        Ok(class)
+    }
+
+    /// Removes every class from the cache. The statistics are kept.
+    pub fn clear(&self) {
+        *self.lock_classes() = LruClasses::default();
+    }
+
+    /// Returns the amount of classes in the cache.
+    pub fn len(&self) -> usize {
+        self.lock_classes().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the current usage statistics of the cache.
+    pub fn stats(&self) -> ClassCacheStats {
+        let classes = self.lock_classes();
+        ClassCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries: classes.entries.len(),
+            bytes: classes.bytes,
+        }
+    }
+
+    fn lock_classes(&self) -> MutexGuard<LruClasses> {
+        // The cache is consistent after every operation, so a panic while it was locked
+        // doesn't leave it in a state that can't be used.
+        self.classes
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl fmt::Debug for SharedClassCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedClassCache")
+            .field("max_entries", &self.max_entries)
+            .field("max_bytes", &self.max_bytes)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+/// Two caches are equal only if they are the same cache, so that states sharing a cache can
+/// still be compared.
+impl PartialEq for SharedClassCache {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for SharedClassCache {}
+
+impl LruClasses {
+    fn get(&mut self, class_hash: &ClassHash) -> Option<CompiledClass> {
+        let tick = self.next_tick();
+        let entry = self.entries.get_mut(class_hash)?;
+        self.recency.remove(&entry.last_used);
+        self.recency.insert(tick, *class_hash);
+        entry.last_used = tick;
+        Some(entry.class.clone())
+    }
+
+    fn insert(&mut self, class_hash: ClassHash, class: CompiledClass, size: usize) {
+        let tick = self.next_tick();
+        if let Some(previous) = self.entries.insert(
+            class_hash,
+            CacheEntry {
+                class,
+                size,
+                last_used: tick,
+            },
+        ) {
+            self.recency.remove(&previous.last_used);
+            self.bytes -= previous.size;
+        }
+        self.recency.insert(tick, class_hash);
+        self.bytes += size;
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let oldest = self.recency.keys().next().copied();
+        if let Some(class_hash) = oldest.and_then(|tick| self.recency.remove(&tick)) {
+            if let Some(entry) = self.entries.remove(&class_hash) {
+                self.bytes -= entry.size;
+            }
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+/// Estimates the memory taken by a class, from the size of its serialized form for Cairo 1
+/// classes and from the size of its program's bytecode for Cairo 0 ones.
+fn estimated_size(class: &CompiledClass) -> usize {
+    match class {
+        CompiledClass::Casm(casm_class) => serde_json::to_vec(casm_class.as_ref())
+            .map(|serialized| serialized.len())
+            .unwrap_or_default(),
+        CompiledClass::Deprecated(contract_class) => {
+            contract_class.program().data_len() * mem::size_of::<MaybeRelocatable>()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::api::contract_classes::deprecated_contract_class::ContractClass;
+    use std::{sync::Arc, thread};
+
+    fn deprecated_class() -> CompiledClass {
+        CompiledClass::Deprecated(Arc::new(
+            ContractClass::from_path("starknet_programs/raw_contract_classes/class_with_abi.json")
+                .unwrap(),
+        ))
+    }
+
+    #[test]
+    fn least_recently_used_class_is_evicted() {
+        let class_cache = SharedClassCache::new(2);
+        let class = deprecated_class();
+
+        class_cache.insert([1; 32], class.clone());
+        class_cache.insert([2; 32], class.clone());
+        // Using the first class makes the second one the least recently used.
+        assert!(class_cache.get(&[1; 32]).is_some());
+        class_cache.insert([3; 32], class);
+
+        assert!(class_cache.get(&[1; 32]).is_some());
+        assert!(class_cache.get(&[2; 32]).is_none());
+        assert!(class_cache.get(&[3; 32]).is_some());
+        assert_eq!(
+            class_cache.stats(),
+            ClassCacheStats {
+                hits: 3,
+                misses: 1,
+                evictions: 1,
+                entries: 2,
+                bytes: 2 * estimated_size(&deprecated_class()),
+            }
+        );
+    }
+
+    #[test]
+    fn classes_are_evicted_to_stay_within_max_bytes() {
+        let class = deprecated_class();
+        let size = estimated_size(&class);
+        assert!(size > 0);
+
+        let class_cache = SharedClassCache::with_max_bytes(2 * size + size / 2);
+        for i in 0..4 {
+            class_cache.insert([i; 32], class.clone());
+        }
+
+        let stats = class_cache.stats();
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.bytes, 2 * size);
+        assert_eq!(stats.evictions, 2);
+        assert!(class_cache.get(&[3; 32]).is_some());
+        assert!(class_cache.get(&[0; 32]).is_none());
+    }
+
+    #[test]
+    fn get_or_fetch_only_fetches_on_misses() {
+        let class_cache = Arc::new(SharedClassCache::new(10));
+        let class = deprecated_class();
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let class_cache = class_cache.clone();
+                let class = class.clone();
+                thread::spawn(move || {
+                    for i in 0..10u8 {
+                        class_cache
+                            .get_or_fetch(&[i % 2; 32], || Ok(class.clone()))
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let stats = class_cache.stats();
+        assert_eq!(stats.hits + stats.misses, 40);
+        assert!(stats.misses >= 2);
+        assert_eq!(stats.entries, 2);
+
+        assert_matches!(
+            class_cache.get_or_fetch(&[5; 32], || Err(StateError::MissingCasmClass([5; 32]))),
+            Err(StateError::MissingCasmClass(_))
+        );
+        assert!(class_cache.get(&[5; 32]).is_none());
+    }
+}
@@ -25,6 +25,35 @@ pub trait StateReader {
     ) -> Result<CompiledClassHash, StateError>;
 }
 
+/// Reads through a reference, e.g. to execute a transaction on a [CachedState] over another
+/// state without taking ownership of it.
+///
+/// [CachedState]: super::cached_state::CachedState
+impl<T: StateReader + ?Sized> StateReader for &T {
+    fn get_contract_class(&self, class_hash: &ClassHash) -> Result<CompiledClass, StateError> {
+        (**self).get_contract_class(class_hash)
+    }
+
+    fn get_class_hash_at(&self, contract_address: &Address) -> Result<ClassHash, StateError> {
+        (**self).get_class_hash_at(contract_address)
+    }
+
+    fn get_nonce_at(&self, contract_address: &Address) -> Result<Felt252, StateError> {
+        (**self).get_nonce_at(contract_address)
+    }
+
+    fn get_storage_at(&self, storage_entry: &StorageEntry) -> Result<Felt252, StateError> {
+        (**self).get_storage_at(storage_entry)
+    }
+
+    fn get_compiled_class_hash(
+        &self,
+        class_hash: &ClassHash,
+    ) -> Result<CompiledClassHash, StateError> {
+        (**self).get_compiled_class_hash(class_hash)
+    }
+}
+
 pub trait State {
     fn set_contract_class(
         &mut self,
@@ -53,6 +82,11 @@ pub trait State {
         class_hash: &Felt252,
         compiled_class_hash: &Felt252,
     ) -> Result<(), StateError>;
+
+    /// Records a Cairo 0 class as declared. Cairo 0 classes have no compiled class hash, so
+    /// their declarations are tracked separately.
+    fn declare_deprecated_class(&mut self, class_hash: &ClassHash) -> Result<(), StateError>;
+
     fn apply_state_update(&mut self, sate_updates: &StateDiff) -> Result<(), StateError>;
 
     /// Counts the amount of modified contracts and the updates to the storage
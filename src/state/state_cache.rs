@@ -35,6 +35,8 @@ pub struct StateCache {
     pub(crate) storage_writes: HashMap<StorageEntry, Felt252>,
     #[get_mut = "pub"]
     pub(crate) class_hash_to_compiled_class_hash: HashMap<ClassHash, CompiledClassHash>,
+    #[getset(get = "pub", get_mut = "pub")]
+    pub(crate) deprecated_declared_classes: HashSet<ClassHash>,
 }
 
 impl StateCache {
@@ -51,6 +53,7 @@ impl StateCache {
         nonce_writes: HashMap<Address, Felt252>,
         storage_writes: HashMap<StorageEntry, Felt252>,
         class_hash_to_compiled_class_hash: HashMap<ClassHash, ClassHash>,
+        deprecated_declared_classes: HashSet<ClassHash>,
     ) -> Self {
         Self {
             class_hash_initial_values,
@@ -62,6 +65,7 @@ impl StateCache {
             nonce_writes,
             storage_writes,
             class_hash_to_compiled_class_hash,
+            deprecated_declared_classes,
         }
     }
 
@@ -77,6 +81,7 @@ impl StateCache {
             nonce_writes: HashMap::new(),
             storage_writes: HashMap::new(),
             class_hash_to_compiled_class_hash: HashMap::new(),
+            deprecated_declared_classes: HashSet::new(),
         }
     }
 
@@ -92,6 +97,7 @@ impl StateCache {
         nonce_writes: HashMap<Address, Felt252>,
         storage_writes: HashMap<(Address, [u8; 32]), Felt252>,
         class_hash_to_compiled_class_hash: HashMap<ClassHash, ClassHash>,
+        deprecated_declared_classes: HashSet<ClassHash>,
     ) -> Self {
         Self {
             class_hash_initial_values,
@@ -103,6 +109,7 @@ impl StateCache {
             nonce_writes,
             storage_writes,
             class_hash_to_compiled_class_hash,
+            deprecated_declared_classes,
         }
     }
 
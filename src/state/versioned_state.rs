@@ -0,0 +1,326 @@
+use super::{state_api::StateReader, state_cache::StorageEntry};
+use crate::{
+    core::errors::state_errors::StateError,
+    execution::block::TransactionWrites,
+    services::api::contract_classes::compiled_class::CompiledClass,
+    utils::{to_cache_state_storage_mapping, Address, ClassHash, CompiledClassHash},
+};
+use cairo_vm::felt::Felt252;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+/// A location of the state that transactions of a block read and write.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum StateKey {
+    ClassHash(Address),
+    Nonce(Address),
+    Storage(StorageEntry),
+    CompiledClassHash(ClassHash),
+    ContractClass(ClassHash),
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum StateValue {
+    ClassHash(ClassHash),
+    Felt(Felt252),
+    CompiledClassHash(CompiledClassHash),
+    ContractClass(CompiledClass),
+}
+
+/// The execution of a transaction that wrote a value: the index of the transaction in the block
+/// and its incarnation, i.e. how many times it was executed before.
+pub(crate) type Version = (usize, usize);
+
+/// The state of a block whose transactions are executed concurrently. It keeps, for every
+/// location, the value written by each transaction, so that a transaction reads the value
+/// written by the closest transaction before it, or the value of the base state if there is
+/// none.
+pub(crate) struct VersionedState<S: StateReader> {
+    base: S,
+    data: RwLock<VersionedData>,
+}
+
+#[derive(Default)]
+struct VersionedData {
+    values: HashMap<StateKey, BTreeMap<usize, (usize, StateValue)>>,
+    /// The locations written by the last execution of each transaction.
+    written_keys: HashMap<usize, HashSet<StateKey>>,
+}
+
+impl<S: StateReader> VersionedState<S> {
+    pub(crate) fn new(base: S) -> Self {
+        Self {
+            base,
+            data: RwLock::new(VersionedData::default()),
+        }
+    }
+
+    /// Returns the value of `key` as seen by the transaction at `tx_index`, with the version
+    /// that wrote it, or `None` if no transaction before it wrote the location.
+    pub(crate) fn read(
+        &self,
+        key: &StateKey,
+        tx_index: usize,
+    ) -> Result<Option<(Version, StateValue)>, StateError> {
+        Ok(self
+            .read_data()?
+            .values
+            .get(key)
+            .and_then(|versions| versions.range(..tx_index).next_back())
+            .map(|(index, (incarnation, value))| ((*index, *incarnation), value.clone())))
+    }
+
+    /// Returns the version of `key` seen by the transaction at `tx_index`.
+    pub(crate) fn version_of(
+        &self,
+        key: &StateKey,
+        tx_index: usize,
+    ) -> Result<Option<Version>, StateError> {
+        Ok(self
+            .read_data()?
+            .values
+            .get(key)
+            .and_then(|versions| versions.range(..tx_index).next_back())
+            .map(|(index, (incarnation, _))| (*index, *incarnation)))
+    }
+
+    /// Replaces the values written by a previous execution of the transaction at `tx_index`
+    /// with `writes`.
+    pub(crate) fn publish(
+        &self,
+        tx_index: usize,
+        incarnation: usize,
+        writes: Option<&TransactionWrites>,
+    ) -> Result<(), StateError> {
+        let new_values = writes.map(write_set).unwrap_or_default();
+        let mut data = self.write_data()?;
+        let new_keys: HashSet<StateKey> = new_values.iter().map(|(key, _)| key.clone()).collect();
+        for stale_key in data
+            .written_keys
+            .remove(&tx_index)
+            .unwrap_or_default()
+            .difference(&new_keys)
+        {
+            if let Some(versions) = data.values.get_mut(stale_key) {
+                versions.remove(&tx_index);
+            }
+        }
+        for (key, value) in new_values {
+            data.values
+                .entry(key)
+                .or_default()
+                .insert(tx_index, (incarnation, value));
+        }
+        data.written_keys.insert(tx_index, new_keys);
+        Ok(())
+    }
+
+    fn read_data(&self) -> Result<RwLockReadGuard<VersionedData>, StateError> {
+        self.data
+            .read()
+            .map_err(|_| StateError::CustomError("Versioned state lock poisoned".to_string()))
+    }
+
+    fn write_data(&self) -> Result<RwLockWriteGuard<VersionedData>, StateError> {
+        self.data
+            .write()
+            .map_err(|_| StateError::CustomError("Versioned state lock poisoned".to_string()))
+    }
+}
+
+/// The locations written by a transaction, with their values.
+fn write_set(writes: &TransactionWrites) -> Vec<(StateKey, StateValue)> {
+    let state_diff = &writes.state_diff;
+    let class_hashes = state_diff
+        .address_to_class_hash
+        .iter()
+        .map(|(address, class_hash)| {
+            (
+                StateKey::ClassHash(address.clone()),
+                StateValue::ClassHash(*class_hash),
+            )
+        });
+    let nonces = state_diff.address_to_nonce.iter().map(|(address, nonce)| {
+        (
+            StateKey::Nonce(address.clone()),
+            StateValue::Felt(nonce.clone()),
+        )
+    });
+    let storage = to_cache_state_storage_mapping(&state_diff.storage_updates)
+        .into_iter()
+        .map(|(storage_entry, value)| (StateKey::Storage(storage_entry), StateValue::Felt(value)));
+    let compiled_class_hashes = state_diff.class_hash_to_compiled_class_hash.iter().map(
+        |(class_hash, compiled_class_hash)| {
+            (
+                StateKey::CompiledClassHash(*class_hash),
+                StateValue::CompiledClassHash(*compiled_class_hash),
+            )
+        },
+    );
+    let contract_classes = writes
+        .contract_classes
+        .iter()
+        .map(|(class_hash, contract_class)| {
+            (
+                StateKey::ContractClass(*class_hash),
+                StateValue::ContractClass(contract_class.clone()),
+            )
+        });
+
+    class_hashes
+        .chain(nonces)
+        .chain(storage)
+        .chain(compiled_class_hashes)
+        .chain(contract_classes)
+        .collect()
+}
+
+/// A [StateReader] over a [VersionedState], for an execution of the transaction at `tx_index`.
+/// It records the version of every location read, so that the execution can be validated later.
+pub(crate) struct VersionedStateReader<'a, S: StateReader> {
+    state: &'a VersionedState<S>,
+    tx_index: usize,
+    reads: RefCell<Vec<(StateKey, Option<Version>)>>,
+}
+
+impl<'a, S: StateReader> VersionedStateReader<'a, S> {
+    pub(crate) fn new(state: &'a VersionedState<S>, tx_index: usize) -> Self {
+        Self {
+            state,
+            tx_index,
+            reads: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Returns the locations read, with the version read of each one (`None` for the base
+    /// state).
+    pub(crate) fn into_reads(self) -> Vec<(StateKey, Option<Version>)> {
+        self.reads.into_inner()
+    }
+
+    fn read(&self, key: StateKey) -> Result<Option<StateValue>, StateError> {
+        let read = self.state.read(&key, self.tx_index)?;
+        let (version, value) = match read {
+            Some((version, value)) => (Some(version), Some(value)),
+            None => (None, None),
+        };
+        self.reads.borrow_mut().push((key, version));
+        Ok(value)
+    }
+}
+
+impl<S: StateReader> StateReader for VersionedStateReader<'_, S> {
+    fn get_contract_class(&self, class_hash: &ClassHash) -> Result<CompiledClass, StateError> {
+        if let Some(StateValue::ContractClass(contract_class)) =
+            self.read(StateKey::ContractClass(*class_hash))?
+        {
+            return Ok(contract_class);
+        }
+        // Casm classes are declared by their compiled class hash.
+        if let Some(StateValue::CompiledClassHash(compiled_class_hash)) =
+            self.read(StateKey::CompiledClassHash(*class_hash))?
+        {
+            if let Some(StateValue::ContractClass(contract_class)) =
+                self.read(StateKey::ContractClass(compiled_class_hash))?
+            {
+                return Ok(contract_class);
+            }
+        }
+        self.state.base.get_contract_class(class_hash)
+    }
+
+    fn get_class_hash_at(&self, contract_address: &Address) -> Result<ClassHash, StateError> {
+        match self.read(StateKey::ClassHash(contract_address.clone()))? {
+            Some(StateValue::ClassHash(class_hash)) => Ok(class_hash),
+            _ => self.state.base.get_class_hash_at(contract_address),
+        }
+    }
+
+    fn get_nonce_at(&self, contract_address: &Address) -> Result<Felt252, StateError> {
+        match self.read(StateKey::Nonce(contract_address.clone()))? {
+            Some(StateValue::Felt(nonce)) => Ok(nonce),
+            _ => self.state.base.get_nonce_at(contract_address),
+        }
+    }
+
+    fn get_storage_at(&self, storage_entry: &StorageEntry) -> Result<Felt252, StateError> {
+        match self.read(StateKey::Storage(storage_entry.clone()))? {
+            Some(StateValue::Felt(value)) => Ok(value),
+            _ => self.state.base.get_storage_at(storage_entry),
+        }
+    }
+
+    fn get_compiled_class_hash(
+        &self,
+        class_hash: &ClassHash,
+    ) -> Result<CompiledClassHash, StateError> {
+        match self.read(StateKey::CompiledClassHash(*class_hash))? {
+            Some(StateValue::CompiledClassHash(compiled_class_hash)) => Ok(compiled_class_hash),
+            _ => self.state.base.get_compiled_class_hash(class_hash),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        state::{in_memory_state_reader::InMemoryStateReader, StateDiff},
+        utils::felt_to_hash,
+    };
+
+    fn storage_writes(address: u64, value: u64) -> TransactionWrites {
+        let mut state_diff = StateDiff::default();
+        state_diff.storage_updates.insert(
+            Address(address.into()),
+            HashMap::from([(Felt252::from(1), Felt252::from(value))]),
+        );
+        TransactionWrites {
+            state_diff,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn transactions_read_the_closest_write_before_them() {
+        let mut base = InMemoryStateReader::default();
+        let storage_entry = (Address(5.into()), felt_to_hash(&1.into()));
+        base.address_to_storage_mut()
+            .insert(storage_entry.clone(), 7.into());
+        let state = VersionedState::new(base);
+
+        state.publish(1, 0, Some(&storage_writes(5, 10))).unwrap();
+        state.publish(3, 0, Some(&storage_writes(5, 30))).unwrap();
+
+        let values: Vec<Felt252> = (0..5)
+            .map(|tx_index| {
+                VersionedStateReader::new(&state, tx_index)
+                    .get_storage_at(&storage_entry)
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec![7.into(), 7.into(), 10.into(), 10.into(), 30.into()]
+        );
+
+        let reader = VersionedStateReader::new(&state, 2);
+        reader.get_storage_at(&storage_entry).unwrap();
+        assert_eq!(
+            reader.into_reads(),
+            vec![(StateKey::Storage(storage_entry.clone()), Some((1, 0)))]
+        );
+
+        // A new execution of a transaction replaces its previous writes.
+        state.publish(1, 1, None).unwrap();
+        assert_eq!(
+            state
+                .version_of(&StateKey::Storage(storage_entry), 2)
+                .unwrap(),
+            None
+        );
+    }
+}
@@ -20,8 +20,8 @@ use super::{
     },
     syscall_response::{CallContractResponse, FailureReason, ResponseBody},
 };
+use crate::core::block_hash::starknet_block_hash::block_hash_storage_entry;
 use crate::definitions::block_context::BlockContext;
-use crate::definitions::constants::BLOCK_HASH_CONTRACT_ADDRESS;
 use crate::execution::execution_entry_point::ExecutionResult;
 use crate::services::api::contract_classes::compiled_class::CompiledClass;
 use crate::state::cached_state::CachedState;
@@ -86,35 +86,6 @@ lazy_static! {
 
             map
     };
-
-    // TODO: There is no reason why this could not be in the syscall enum itself AFAICT
-    // Taken from starkware/starknet/constants.py in cairo-lang
-    // See further documentation on cairo_programs/constants.cairo
-    /// Maps syscall name to gas costs
-    static ref SYSCALL_GAS_COST: HashMap<&'static str, u128> = {
-        let mut map = HashMap::new();
-
-        map.insert("initial", 100_000_000 * STEP);
-        map.insert("entry_point_initial_budget", 100 * STEP);
-
-        map.insert("entry_point", map["entry_point_initial_budget"] + 500 * STEP);
-        map.insert("fee_transfer", map["entry_point"] + 100 * STEP);
-        map.insert("transaction", 2 * map["entry_point"] + map["fee_transfer"] + 100 * STEP);
-
-        map.insert("call_contract", SYSCALL_BASE + 10 * STEP + map["entry_point"]);
-        map.insert("deploy", SYSCALL_BASE + 200 * STEP + map["entry_point"]);
-        map.insert("get_execution_info", SYSCALL_BASE + 10 * STEP);
-        map.insert("library_call", map["call_contract"]);
-        map.insert("replace_class", SYSCALL_BASE + 50 * STEP);
-        map.insert("storage_read", SYSCALL_BASE + 50 * STEP);
-        map.insert("storage_write", SYSCALL_BASE + 50 * STEP);
-        map.insert("emit_event", SYSCALL_BASE + 10 * STEP);
-        map.insert("send_message_to_l1", SYSCALL_BASE + 50 * STEP);
-        map.insert("get_block_timestamp", 0);
-        map.insert("keccak", 0);
-
-        map
-    };
 }
 
 #[derive(Debug)]
@@ -410,9 +381,11 @@ impl<'a, S: StateReader> BusinessLogicSyscallHandler<'a, S> {
         let request = self.read_and_validate_syscall_request(vm, &mut syscall_ptr, syscall_name)?;
 
         // Check and reduce gas (after validating the syscall selector for consistency wth the OS).
-        let required_gas = SYSCALL_GAS_COST
-            .get(syscall_name)
-            .map(|&x| x.saturating_sub(SYSCALL_BASE))
+        let required_gas = self
+            .block_context
+            .versioned_constants()
+            .syscall_gas_cost(syscall_name)
+            .map(|gas_cost| gas_cost.saturating_sub(SYSCALL_BASE))
             .ok_or(SyscallHandlerError::SelectorDoesNotHaveAssociatedGas(
                 selector.to_string(),
             ))?;
@@ -479,7 +452,11 @@ impl<'a, S: StateReader> BusinessLogicSyscallHandler<'a, S> {
         let block_number = request.block_number;
         let current_block_number = self.block_context.block_info.block_number;
 
-        if block_number > current_block_number - 10 {
+        // Only the hashes of blocks at least 10 blocks older than the current one can be read.
+        let is_in_range = current_block_number
+            .checked_sub(10)
+            .map_or(false, |last_block_number| block_number <= last_block_number);
+        if !is_in_range {
             let out_of_range_felt = Felt252::from_bytes_be("Block number out of range".as_bytes());
             let retdata_start =
                 self.allocate_segment(vm, vec![MaybeRelocatable::from(out_of_range_felt)])?;
@@ -499,10 +476,9 @@ impl<'a, S: StateReader> BusinessLogicSyscallHandler<'a, S> {
         let block_hash = if block_number < V_0_12_0_FIRST_BLOCK {
             Felt252::zero()
         } else {
-            self.starknet_storage_state.state.get_storage_at(&(
-                BLOCK_HASH_CONTRACT_ADDRESS.clone(),
-                Felt252::new(block_number).to_be_bytes(),
-            ))?
+            self.starknet_storage_state
+                .state
+                .get_storage_at(&block_hash_storage_entry(block_number))?
         };
 
         Ok(SyscallResponse {
@@ -641,6 +617,36 @@ impl<'a, S: StateReader> BusinessLogicSyscallHandler<'a, S> {
         }
         let signature_end = res_segment;
 
+        // Transactions before version 3 have empty resource bounds, paymaster data and account
+        // deployment data, and zero tip and data availability modes.
+        let v3_fields = tx_info.v3_fields.clone().unwrap_or_default();
+        let resource_bounds_start = res_segment;
+        if tx_info.v3_fields.is_some() {
+            for (resource, bounds) in v3_fields.resource_bounds() {
+                vm.insert_value(res_segment, Felt252::from_bytes_be(resource))?;
+                res_segment = (res_segment + 1)?;
+                vm.insert_value::<Felt252>(res_segment, bounds.max_amount.into())?;
+                res_segment = (res_segment + 1)?;
+                vm.insert_value::<Felt252>(res_segment, bounds.max_price_per_unit.into())?;
+                res_segment = (res_segment + 1)?;
+            }
+        }
+        let resource_bounds_end = res_segment;
+
+        let paymaster_data_start = res_segment;
+        for data in v3_fields.paymaster_data.iter() {
+            vm.insert_value(res_segment, data)?;
+            res_segment = (res_segment + 1)?;
+        }
+        let paymaster_data_end = res_segment;
+
+        let account_deployment_data_start = res_segment;
+        for data in v3_fields.account_deployment_data.iter() {
+            vm.insert_value(res_segment, data)?;
+            res_segment = (res_segment + 1)?;
+        }
+        let account_deployment_data_end = res_segment;
+
         let tx_info_ptr = res_segment;
         vm.insert_value::<Felt252>(res_segment, tx_info.version.clone())?;
         res_segment = (res_segment + 1)?;
@@ -661,6 +667,24 @@ impl<'a, S: StateReader> BusinessLogicSyscallHandler<'a, S> {
         res_segment = (res_segment + 1)?;
         vm.insert_value::<Felt252>(res_segment, tx_info.nonce.clone())?;
         res_segment = (res_segment + 1)?;
+        vm.insert_value(res_segment, resource_bounds_start)?;
+        res_segment = (res_segment + 1)?;
+        vm.insert_value(res_segment, resource_bounds_end)?;
+        res_segment = (res_segment + 1)?;
+        vm.insert_value::<Felt252>(res_segment, v3_fields.tip.into())?;
+        res_segment = (res_segment + 1)?;
+        vm.insert_value(res_segment, paymaster_data_start)?;
+        res_segment = (res_segment + 1)?;
+        vm.insert_value(res_segment, paymaster_data_end)?;
+        res_segment = (res_segment + 1)?;
+        vm.insert_value::<Felt252>(res_segment, v3_fields.nonce_data_availability_mode.into())?;
+        res_segment = (res_segment + 1)?;
+        vm.insert_value::<Felt252>(res_segment, v3_fields.fee_data_availability_mode.into())?;
+        res_segment = (res_segment + 1)?;
+        vm.insert_value(res_segment, account_deployment_data_start)?;
+        res_segment = (res_segment + 1)?;
+        vm.insert_value(res_segment, account_deployment_data_end)?;
+        res_segment = (res_segment + 1)?;
 
         let block_info_ptr = res_segment;
         vm.insert_value::<Felt252>(res_segment, block_info.block_number.into())?;
@@ -1021,3 +1045,70 @@ impl<'a, S: StateReader> BusinessLogicSyscallHandler<'a, S> {
         }))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        core::block_hash::starknet_block_hash::store_block_hash,
+        state::in_memory_state_reader::InMemoryStateReader,
+    };
+    use coverage_helper::test;
+    use std::sync::Arc;
+
+    fn get_block_hash(
+        state: &mut CachedState<InMemoryStateReader>,
+        current_block_number: u64,
+        block_number: u64,
+    ) -> SyscallResponse {
+        let block_info = BlockInfo {
+            block_number: current_block_number,
+            ..Default::default()
+        };
+        let mut syscall_handler =
+            BusinessLogicSyscallHandler::new_for_testing(block_info, Address(0.into()), state);
+
+        syscall_handler
+            .get_block_hash(
+                &mut VirtualMachine::new(false),
+                GetBlockHashRequest { block_number },
+                1000,
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn get_block_hash_reads_stored_block_hashes() {
+        let mut state = CachedState::new(Arc::new(InMemoryStateReader::default()), HashMap::new());
+        store_block_hash(&mut state, 5, 42.into());
+
+        let response = get_block_hash(&mut state, 15, 5);
+        assert_eq!(response.gas, 1000);
+        assert!(matches!(
+            response.body,
+            Some(ResponseBody::GetBlockHash(GetBlockHashResponse { block_hash }))
+                if block_hash == Felt252::new(42)
+        ));
+    }
+
+    #[test]
+    fn get_block_hash_fails_for_the_last_ten_blocks() {
+        let mut state = CachedState::new(Arc::new(InMemoryStateReader::default()), HashMap::new());
+        store_block_hash(&mut state, 6, 42.into());
+
+        assert!(matches!(
+            get_block_hash(&mut state, 15, 6).body,
+            Some(ResponseBody::Failure(_))
+        ));
+    }
+
+    #[test]
+    fn get_block_hash_fails_in_the_first_ten_blocks() {
+        let mut state = CachedState::new(Arc::new(InMemoryStateReader::default()), HashMap::new());
+
+        assert!(matches!(
+            get_block_hash(&mut state, 3, 0).body,
+            Some(ResponseBody::Failure(_))
+        ));
+    }
+}
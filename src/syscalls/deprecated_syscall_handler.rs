@@ -501,6 +501,7 @@ mod tests {
             nonce: 263.into(),
             n_sent_messages: 52,
             _n_steps: 100000,
+            v3_fields: None,
         };
         syscall_handler_hint_processor
             .syscall_handler
@@ -825,6 +826,7 @@ mod tests {
             nonce: 263.into(),
             n_sent_messages: 52,
             _n_steps: 10000,
+            v3_fields: None,
         };
         syscall_handler_hint_processor
             .syscall_handler
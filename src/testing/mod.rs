@@ -12,7 +12,7 @@ use num_traits::Zero;
 use crate::{
     definitions::{
         block_context::{BlockContext, StarknetChainId, StarknetOsConfig},
-        constants::DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS,
+        constants::{DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS, EXECUTE_ENTRY_POINT_SELECTOR},
     },
     services::api::contract_classes::{
         compiled_class::CompiledClass, deprecated_contract_class::ContractClass,
@@ -21,7 +21,8 @@ use crate::{
         cached_state::CachedState, in_memory_state_reader::InMemoryStateReader,
         state_cache::StorageEntry, BlockInfo,
     },
-    utils::{felt_to_hash, Address, ClassHash},
+    transaction::{InvokeFunction, Transaction},
+    utils::{calculate_sn_keccak, felt_to_hash, Address, ClassHash},
 };
 
 pub const ACCOUNT_CONTRACT_PATH: &str = "starknet_programs/account_without_validation.json";
@@ -68,14 +69,18 @@ pub fn new_starknet_block_context_for_testing() -> BlockContext {
         StarknetOsConfig::new(
             StarknetChainId::TestNet.to_felt(),
             TEST_ERC20_CONTRACT_ADDRESS.clone(),
-            1,
+            TEST_ERC20_CONTRACT_ADDRESS.clone(),
         ),
         0,
         0,
         DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS.clone(),
         1_000_000,
-        0,
-        BlockInfo::empty(TEST_SEQUENCER_ADDRESS.clone()),
+        1_000_000,
+        BlockInfo {
+            gas_price: 1,
+            strk_gas_price: 1,
+            ..BlockInfo::empty(TEST_SEQUENCER_ADDRESS.clone())
+        },
         HashMap::default(),
         true,
     )
@@ -163,3 +168,31 @@ pub fn create_account_tx_test_state(
 
     Ok((block_context, cached_state))
 }
+
+/// Returns a version 1 invoke, sent by the account of [create_account_tx_test_state], that calls
+/// the given entry point of the test contract with a single argument.
+pub fn create_test_contract_invoke(
+    entry_point: &[u8],
+    argument: Felt252,
+    nonce: u64,
+    max_fee: u128,
+) -> Transaction {
+    Transaction::InvokeFunction(
+        InvokeFunction::new(
+            TEST_ACCOUNT_CONTRACT_ADDRESS.clone(),
+            EXECUTE_ENTRY_POINT_SELECTOR.clone(),
+            max_fee,
+            1.into(),
+            vec![
+                TEST_CONTRACT_ADDRESS.0.clone(),
+                Felt252::from_bytes_be(&calculate_sn_keccak(entry_point)),
+                1.into(),
+                argument,
+            ],
+            vec![],
+            StarknetChainId::TestNet.to_felt(),
+            Some(nonce.into()),
+        )
+        .unwrap(),
+    )
+}
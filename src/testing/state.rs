@@ -329,12 +329,14 @@ mod tests {
     use crate::{
         core::contract_address::compute_deprecated_class_hash,
         definitions::{
-            constants::CONSTRUCTOR_ENTRY_POINT_SELECTOR, transaction_type::TransactionType,
+            constants::{CONSTRUCTOR_ENTRY_POINT_SELECTOR, DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS},
+            transaction_type::TransactionType,
         },
-        execution::{CallType, OrderedL2ToL1Message},
+        execution::{gas_usage::L1GasUsage, CallType, OrderedL2ToL1Message},
         hash_utils::calculate_contract_address,
         services::api::contract_classes::compiled_class::CompiledClass,
         state::state_cache::StorageEntry,
+        transaction::fee::FeeBreakdown,
         utils::{calculate_sn_keccak, felt_to_hash},
     };
 
@@ -381,6 +383,21 @@ mod tests {
             actual_fee: 0,
             actual_resources,
             tx_type: Some(TransactionType::Deploy),
+            // The deployment runs no Cairo code, so it's only charged for its state diff.
+            fee_breakdown: Some(FeeBreakdown {
+                cairo_resources_l1_gas: DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS
+                    .keys()
+                    .map(|resource| (resource.clone(), 0.0))
+                    .collect(),
+                bottleneck_resource: None,
+                l1_gas_usage: L1GasUsage {
+                    storage_diffs: 1224,
+                    contract_deployments: 1224,
+                    ..Default::default()
+                },
+                os_resources: ExecutionResources::default(),
+                total_l1_gas: 2448,
+            }),
         };
 
         // check result is correct
@@ -610,6 +627,31 @@ mod tests {
             }),
             actual_resources,
             tx_type: Some(TransactionType::InvokeFunction),
+            fee_breakdown: Some(FeeBreakdown {
+                cairo_resources_l1_gas: DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS
+                    .keys()
+                    .map(|resource| (resource.clone(), 0.0))
+                    .chain([
+                        ("n_steps".to_string(), 3457.0 * 0.01),
+                        ("range_check_builtin".to_string(), 80.0 * 0.16),
+                        ("pedersen_builtin".to_string(), 16.0 * 0.32),
+                    ])
+                    .collect(),
+                bottleneck_resource: Some("n_steps".to_string()),
+                l1_gas_usage: L1GasUsage {
+                    storage_diffs: 2448,
+                    ..Default::default()
+                },
+                os_resources: ExecutionResources {
+                    n_steps: 3363,
+                    n_memory_holes: 0,
+                    builtin_instance_counter: HashMap::from([
+                        ("pedersen_builtin".to_string(), 16),
+                        ("range_check_builtin".to_string(), 80),
+                    ]),
+                },
+                total_l1_gas: 2483,
+            }),
             ..Default::default()
         };
 
@@ -0,0 +1,667 @@
+use super::{
+    error::TransactionError,
+    invoke_function::{convert_invoke_v0, convert_invoke_v1},
+    Declare, DeclareV2, Deploy, DeployAccount, InvokeFunction, L1Handler, ResourceBounds,
+    Transaction, V3TransactionFields,
+};
+use crate::{
+    core::contract_address::compute_sierra_class_hash,
+    services::api::contract_classes::deprecated_contract_class::ContractClass, utils::Address,
+};
+use cairo_lang_starknet::casm_contract_class::CasmContractClass;
+use cairo_lang_starknet::contract_class::ContractClass as SierraContractClass;
+use cairo_vm::felt::Felt252;
+use num_traits::ToPrimitive;
+use serde::de::DeserializeOwned;
+use starknet_api::{
+    core::ContractAddress,
+    hash::StarkFelt,
+    transaction::{
+        DeclareTransaction, DeployAccountTransaction, DeployTransaction, InvokeTransaction,
+        L1HandlerTransaction, Transaction as StarknetApiTransaction, TransactionHash,
+        TransactionVersion,
+    },
+};
+
+/// The class a declare or a deploy transaction needs to be executed. Neither the transactions of
+/// the Starknet API nor the ones of the JSON-RPC carry it, only its hash.
+#[derive(Clone, Debug)]
+pub enum TransactionClass {
+    /// A Cairo 0 class, declared by version 0 and 1 declares and deployed by deploys.
+    Deprecated(ContractClass),
+    /// A Cairo 1 class, declared by version 2 declares, with its compiled class if it's known.
+    Sierra(Box<SierraContractClass>, Option<CasmContractClass>),
+}
+
+/// A transaction of the Starknet API, with what's needed to turn it into a [Transaction].
+///
+/// The conversion recomputes the hash of the transaction, and checks it against the given one
+/// when there is one.
+#[derive(Clone, Debug)]
+pub struct ApiTransaction {
+    pub transaction: StarknetApiTransaction,
+    /// The chain the transaction was sent to, which is part of its hash.
+    pub chain_id: Felt252,
+    /// The expected hash of the transaction.
+    pub transaction_hash: Option<Felt252>,
+    /// The class declared or deployed by the transaction.
+    pub class: Option<TransactionClass>,
+    /// The fee paid on L1 by L1 handler transactions.
+    pub paid_fee_on_l1: Option<Felt252>,
+    /// The fields of version 3 transactions, which the Starknet API doesn't have yet. When they
+    /// are set, the invoke, declare or deploy account transaction is converted into a version 3
+    /// one, with the rest of its fields.
+    pub v3_fields: Option<V3TransactionFields>,
+}
+
+impl ApiTransaction {
+    pub fn new(transaction: StarknetApiTransaction, chain_id: Felt252) -> Self {
+        ApiTransaction {
+            transaction,
+            chain_id,
+            transaction_hash: None,
+            class: None,
+            paid_fee_on_l1: None,
+            v3_fields: None,
+        }
+    }
+
+    /// Parses a transaction object of the JSON-RPC, as returned by
+    /// `starknet_getTransactionByHash`. Its `transaction_hash`, if present, is the expected hash
+    /// of the transaction.
+    ///
+    /// ## Parameters
+    /// - json: The transaction object.
+    /// - chain_id: The chain the transaction was sent to.
+    pub fn from_rpc_json(
+        json: &serde_json::Value,
+        chain_id: Felt252,
+    ) -> Result<Self, TransactionError> {
+        let tx_type = json["type"].as_str().ok_or_else(|| {
+            TransactionError::InvalidRpcTransaction("missing transaction type".to_string())
+        })?;
+        let version: TransactionVersion = from_json(&json["version"])?;
+        let version = stark_felt_to_felt(&version.0);
+
+        let transaction = match (tx_type, version.to_u8()) {
+            ("DECLARE", Some(0)) => {
+                // Version 0 declares have no nonce in the JSON-RPC.
+                let mut json = json.clone();
+                if json.get("nonce").is_none() {
+                    json["nonce"] = serde_json::Value::String("0x0".to_string());
+                }
+                StarknetApiTransaction::Declare(DeclareTransaction::V0(from_json(&json)?))
+            }
+            ("DECLARE", Some(1)) => {
+                StarknetApiTransaction::Declare(DeclareTransaction::V1(from_json(json)?))
+            }
+            ("DECLARE", Some(2)) => {
+                StarknetApiTransaction::Declare(DeclareTransaction::V2(from_json(json)?))
+            }
+            ("DECLARE", Some(3)) => StarknetApiTransaction::Declare(DeclareTransaction::V2(
+                from_json(&with_zero_max_fee(json))?,
+            )),
+            ("DEPLOY", Some(0)) => StarknetApiTransaction::Deploy(from_json(json)?),
+            ("DEPLOY_ACCOUNT", Some(1)) => StarknetApiTransaction::DeployAccount(from_json(json)?),
+            ("DEPLOY_ACCOUNT", Some(3)) => {
+                StarknetApiTransaction::DeployAccount(from_json(&with_zero_max_fee(json))?)
+            }
+            ("INVOKE", Some(0)) => {
+                StarknetApiTransaction::Invoke(InvokeTransaction::V0(from_json(json)?))
+            }
+            ("INVOKE", Some(1)) => {
+                StarknetApiTransaction::Invoke(InvokeTransaction::V1(from_json(json)?))
+            }
+            ("INVOKE", Some(3)) => StarknetApiTransaction::Invoke(InvokeTransaction::V1(
+                from_json(&with_zero_max_fee(json))?,
+            )),
+            ("L1_HANDLER", Some(0)) => StarknetApiTransaction::L1Handler(from_json(json)?),
+            ("DECLARE" | "DEPLOY" | "DEPLOY_ACCOUNT" | "INVOKE" | "L1_HANDLER", _) => {
+                return Err(TransactionError::UnsupportedVersion(format!(
+                    "{tx_type} transaction of version {version}"
+                )))
+            }
+            _ => {
+                return Err(TransactionError::InvalidRpcTransaction(format!(
+                    "unknown transaction type {tx_type}"
+                )))
+            }
+        };
+
+        let transaction_hash = match json.get("transaction_hash") {
+            Some(hash) => {
+                let hash: TransactionHash = from_json(hash)?;
+                Some(stark_felt_to_felt(&hash.0))
+            }
+            None => None,
+        };
+
+        let v3_fields = match version.to_u8() {
+            Some(3) => Some(v3_fields_from_rpc_json(json)?),
+            _ => None,
+        };
+
+        Ok(ApiTransaction {
+            transaction_hash,
+            v3_fields,
+            ..ApiTransaction::new(transaction, chain_id)
+        })
+    }
+
+    /// Sets the expected hash of the transaction.
+    pub fn with_transaction_hash(mut self, transaction_hash: Felt252) -> Self {
+        self.transaction_hash = Some(transaction_hash);
+        self
+    }
+
+    /// Sets the class declared or deployed by the transaction.
+    pub fn with_class(mut self, class: TransactionClass) -> Self {
+        self.class = Some(class);
+        self
+    }
+
+    /// Sets the fee paid on L1 by an L1 handler transaction.
+    pub fn with_paid_fee_on_l1(mut self, paid_fee_on_l1: Felt252) -> Self {
+        self.paid_fee_on_l1 = Some(paid_fee_on_l1);
+        self
+    }
+
+    /// Sets the fields of a version 3 transaction.
+    pub fn with_v3_fields(mut self, v3_fields: V3TransactionFields) -> Self {
+        self.v3_fields = Some(v3_fields);
+        self
+    }
+}
+
+impl TryFrom<ApiTransaction> for Transaction {
+    type Error = TransactionError;
+
+    fn try_from(value: ApiTransaction) -> Result<Self, TransactionError> {
+        let ApiTransaction {
+            transaction,
+            chain_id,
+            transaction_hash,
+            class,
+            paid_fee_on_l1,
+            v3_fields,
+        } = value;
+
+        let tx = match transaction {
+            StarknetApiTransaction::Declare(DeclareTransaction::V0(tx)) => {
+                convert_declare(tx, 0.into(), class, chain_id)?
+            }
+            StarknetApiTransaction::Declare(DeclareTransaction::V1(tx)) => {
+                convert_declare(tx, 1.into(), class, chain_id)?
+            }
+            StarknetApiTransaction::Declare(DeclareTransaction::V2(tx)) => {
+                let (sierra_contract_class, casm_contract_class) = match class {
+                    Some(TransactionClass::Sierra(sierra, casm)) => (sierra, casm),
+                    _ => return Err(TransactionError::MissingTransactionClass("Sierra")),
+                };
+                let declare = match v3_fields {
+                    Some(v3_fields) => DeclareV2::new_v3(
+                        &sierra_contract_class,
+                        casm_contract_class,
+                        stark_felt_to_felt(&tx.compiled_class_hash.0),
+                        chain_id,
+                        contract_address_to_address(&tx.sender_address),
+                        stark_felts_to_felts(&tx.signature.0),
+                        stark_felt_to_felt(&tx.nonce.0),
+                        v3_fields,
+                    )?,
+                    None => DeclareV2::new_with_sierra_class_hash(
+                        &sierra_contract_class,
+                        compute_sierra_class_hash(&sierra_contract_class)?,
+                        casm_contract_class,
+                        stark_felt_to_felt(&tx.compiled_class_hash.0),
+                        chain_id,
+                        contract_address_to_address(&tx.sender_address),
+                        tx.max_fee.0,
+                        2.into(),
+                        stark_felts_to_felts(&tx.signature.0),
+                        stark_felt_to_felt(&tx.nonce.0),
+                    )?,
+                };
+                if declare.sierra_class_hash != stark_felt_to_felt(&tx.class_hash.0) {
+                    return Err(TransactionError::NotEqualClassHash);
+                }
+                Transaction::DeclareV2(Box::new(declare))
+            }
+            StarknetApiTransaction::Deploy(tx) => convert_deploy(tx, class, chain_id)?,
+            StarknetApiTransaction::DeployAccount(tx) => {
+                convert_deploy_account(tx, v3_fields, chain_id)?
+            }
+            StarknetApiTransaction::Invoke(InvokeTransaction::V0(tx)) => {
+                Transaction::InvokeFunction(convert_invoke_v0(tx, chain_id)?)
+            }
+            StarknetApiTransaction::Invoke(InvokeTransaction::V1(tx)) => match v3_fields {
+                Some(v3_fields) => Transaction::InvokeFunction(InvokeFunction::new_v3(
+                    contract_address_to_address(&tx.sender_address),
+                    stark_felts_to_felts(&tx.calldata.0),
+                    stark_felts_to_felts(&tx.signature.0),
+                    chain_id,
+                    stark_felt_to_felt(&tx.nonce.0),
+                    v3_fields,
+                )?),
+                None => Transaction::InvokeFunction(convert_invoke_v1(tx, chain_id)?),
+            },
+            StarknetApiTransaction::L1Handler(tx) => {
+                convert_l1_handler(tx, paid_fee_on_l1, chain_id)?
+            }
+        };
+
+        match transaction_hash {
+            Some(expected) if expected != tx.hash_value() => Err(
+                TransactionError::TransactionHashMismatch(expected, tx.hash_value()),
+            ),
+            _ => Ok(tx),
+        }
+    }
+}
+
+fn convert_declare(
+    tx: starknet_api::transaction::DeclareTransactionV0V1,
+    version: Felt252,
+    class: Option<TransactionClass>,
+    chain_id: Felt252,
+) -> Result<Transaction, TransactionError> {
+    let contract_class = match class {
+        Some(TransactionClass::Deprecated(contract_class)) => contract_class,
+        _ => return Err(TransactionError::MissingTransactionClass("Cairo 0")),
+    };
+    let declare = Declare::new(
+        contract_class,
+        chain_id,
+        contract_address_to_address(&tx.sender_address),
+        tx.max_fee.0,
+        version,
+        stark_felts_to_felts(&tx.signature.0),
+        stark_felt_to_felt(&tx.nonce.0),
+    )?;
+    if declare.class_hash.as_slice() != tx.class_hash.0.bytes() {
+        return Err(TransactionError::NotEqualClassHash);
+    }
+    Ok(Transaction::Declare(declare))
+}
+
+fn convert_deploy(
+    tx: DeployTransaction,
+    class: Option<TransactionClass>,
+    chain_id: Felt252,
+) -> Result<Transaction, TransactionError> {
+    let contract_class = match class {
+        Some(TransactionClass::Deprecated(contract_class)) => contract_class,
+        _ => return Err(TransactionError::MissingTransactionClass("Cairo 0")),
+    };
+    let deploy = Deploy::new(
+        stark_felt_to_felt(&tx.contract_address_salt.0),
+        contract_class,
+        stark_felts_to_felts(&tx.constructor_calldata.0),
+        chain_id,
+        stark_felt_to_felt(&tx.version.0),
+    )?;
+    if deploy.contract_hash.as_slice() != tx.class_hash.0.bytes() {
+        return Err(TransactionError::NotEqualClassHash);
+    }
+    Ok(Transaction::Deploy(deploy))
+}
+
+fn convert_deploy_account(
+    tx: DeployAccountTransaction,
+    v3_fields: Option<V3TransactionFields>,
+    chain_id: Felt252,
+) -> Result<Transaction, TransactionError> {
+    let class_hash = tx
+        .class_hash
+        .0
+        .bytes()
+        .try_into()
+        .map_err(|_| TransactionError::FailToReadClassHash)?;
+    if let Some(v3_fields) = v3_fields {
+        return Ok(Transaction::DeployAccount(DeployAccount::new_v3(
+            class_hash,
+            stark_felt_to_felt(&tx.nonce.0),
+            stark_felts_to_felts(&tx.constructor_calldata.0),
+            stark_felts_to_felts(&tx.signature.0),
+            stark_felt_to_felt(&tx.contract_address_salt.0),
+            chain_id,
+            v3_fields,
+        )?));
+    }
+    Ok(Transaction::DeployAccount(DeployAccount::new(
+        class_hash,
+        tx.max_fee.0,
+        stark_felt_to_felt(&tx.version.0),
+        stark_felt_to_felt(&tx.nonce.0),
+        stark_felts_to_felts(&tx.constructor_calldata.0),
+        stark_felts_to_felts(&tx.signature.0),
+        stark_felt_to_felt(&tx.contract_address_salt.0),
+        chain_id,
+    )?))
+}
+
+fn convert_l1_handler(
+    tx: L1HandlerTransaction,
+    paid_fee_on_l1: Option<Felt252>,
+    chain_id: Felt252,
+) -> Result<Transaction, TransactionError> {
+    Ok(Transaction::L1Handler(L1Handler::new(
+        contract_address_to_address(&tx.contract_address),
+        stark_felt_to_felt(&tx.entry_point_selector.0),
+        stark_felts_to_felts(&tx.calldata.0),
+        stark_felt_to_felt(&tx.nonce.0),
+        chain_id,
+        paid_fee_on_l1,
+    )?))
+}
+
+/// Version 3 transactions have resource bounds instead of a max fee, which the transactions of
+/// the Starknet API require.
+fn with_zero_max_fee(json: &serde_json::Value) -> serde_json::Value {
+    let mut json = json.clone();
+    json["max_fee"] = serde_json::Value::String("0x0".to_string());
+    json
+}
+
+/// Parses the fields that version 3 transactions add to the previous versions.
+fn v3_fields_from_rpc_json(
+    json: &serde_json::Value,
+) -> Result<V3TransactionFields, TransactionError> {
+    let resource_bounds = |resource: &str| -> Result<ResourceBounds, TransactionError> {
+        let bounds = &json["resource_bounds"][resource];
+        let max_amount = felt_from_json(&bounds["max_amount"])?;
+        let max_price_per_unit = felt_from_json(&bounds["max_price_per_unit"])?;
+        Ok(ResourceBounds {
+            max_amount: max_amount.to_u64().ok_or_else(|| {
+                TransactionError::InvalidRpcTransaction(format!(
+                    "max amount of {resource} out of range"
+                ))
+            })?,
+            max_price_per_unit: max_price_per_unit.to_u128().ok_or_else(|| {
+                TransactionError::InvalidRpcTransaction(format!(
+                    "max price per unit of {resource} out of range"
+                ))
+            })?,
+        })
+    };
+    let felts = |field: &str| -> Result<Vec<Felt252>, TransactionError> {
+        // Deploy accounts have no account deployment data.
+        match json.get(field) {
+            Some(felts) => Ok(stark_felts_to_felts(&from_json::<Vec<StarkFelt>>(felts)?)),
+            None => Ok(Vec::new()),
+        }
+    };
+
+    Ok(V3TransactionFields {
+        l1_resource_bounds: resource_bounds("l1_gas")?,
+        l2_resource_bounds: resource_bounds("l2_gas")?,
+        tip: felt_from_json(&json["tip"])?.to_u64().ok_or_else(|| {
+            TransactionError::InvalidRpcTransaction("tip out of range".to_string())
+        })?,
+        paymaster_data: felts("paymaster_data")?,
+        nonce_data_availability_mode: from_json(&json["nonce_data_availability_mode"])?,
+        fee_data_availability_mode: from_json(&json["fee_data_availability_mode"])?,
+        account_deployment_data: felts("account_deployment_data")?,
+    })
+}
+
+fn felt_from_json(json: &serde_json::Value) -> Result<Felt252, TransactionError> {
+    Ok(stark_felt_to_felt(&from_json(json)?))
+}
+
+fn from_json<T: DeserializeOwned>(json: &serde_json::Value) -> Result<T, TransactionError> {
+    serde_json::from_value(json.clone())
+        .map_err(|err| TransactionError::InvalidRpcTransaction(err.to_string()))
+}
+
+fn stark_felt_to_felt(felt: &StarkFelt) -> Felt252 {
+    Felt252::from_bytes_be(felt.bytes())
+}
+
+fn stark_felts_to_felts(felts: &[StarkFelt]) -> Vec<Felt252> {
+    felts.iter().map(stark_felt_to_felt).collect()
+}
+
+fn contract_address_to_address(address: &ContractAddress) -> Address {
+    Address(stark_felt_to_felt(address.0.key()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definitions::block_context::StarknetChainId;
+    use cairo_vm::felt::felt_str;
+    use coverage_helper::test;
+    use serde_json::json;
+
+    // https://starkscan.co/tx/0x05b6cf416d56e7c7c519b44e6d06a41657ff6c6a3f2629044fac395e6d200ac4
+    fn invoke_v1_json() -> serde_json::Value {
+        json!({
+            "type": "INVOKE",
+            "version": "0x1",
+            "transaction_hash": "0x5b6cf416d56e7c7c519b44e6d06a41657ff6c6a3f2629044fac395e6d200ac4",
+            "sender_address": "0xc4658311841a69ce121543af332622bc243cf5593fc4aaf822481c7b7f183d",
+            "max_fee": "0x2c90b3981000",
+            "signature": [
+                "0x18315db8eb360a82ea11f302d6a6a35a11b9df1dc220ec1376c4d4604770dd4",
+                "0x5e8642259ac8e99c84cdf88c17385698150eb11dccfb3036ecc2b97c0903d27"
+            ],
+            "nonce": "0x16",
+            "calldata": [
+                "0x1",
+                "0x454f0bd015e730e5adbb4f080b075fdbf55654ff41ee336203aa2e1ac4d4309",
+                "0x32a99297e1d12a9b91d4f90d5dd4b160d93c84a9e3b4daa916fec14ec852e05",
+                "0x0",
+                "0x2",
+                "0x2",
+                "0x383538353434346334616431626237363933663435643237376236313461663",
+                "0x393762666334373463313762393535303530383563613961323435643965666"
+            ]
+        })
+    }
+
+    #[test]
+    fn invoke_from_rpc_json_verifies_the_hash() {
+        let chain_id = StarknetChainId::MainNet.to_felt();
+        let tx = ApiTransaction::from_rpc_json(&invoke_v1_json(), chain_id.clone()).unwrap();
+        let tx = Transaction::try_from(tx).unwrap();
+        assert_matches!(tx, Transaction::InvokeFunction(_));
+        assert_eq!(
+            tx.hash_value(),
+            felt_str!(
+                "5b6cf416d56e7c7c519b44e6d06a41657ff6c6a3f2629044fac395e6d200ac4",
+                16
+            )
+        );
+
+        let tx = ApiTransaction::from_rpc_json(&invoke_v1_json(), chain_id)
+            .unwrap()
+            .with_transaction_hash(1.into());
+        assert_matches!(
+            Transaction::try_from(tx),
+            Err(TransactionError::TransactionHashMismatch(_, _))
+        );
+    }
+
+    // https://starkscan.co/tx/0x1d4735f4ba73a67be2f648d9b21cab3783383b8c229566b46b027c46012219
+    fn invoke_v3_json() -> serde_json::Value {
+        json!({
+            "type": "INVOKE",
+            "version": "0x3",
+            "transaction_hash": "0x1d4735f4ba73a67be2f648d9b21cab3783383b8c229566b46b027c46012219",
+            "sender_address": "0x69c0f9bcd79697bdceaf7748e3ff8f34aa39e4063ce44896af664c0c96f6c10",
+            "signature": [
+                "0x1",
+                "0x1c3a868705399362140bf5ae33f955f1c946a8f836d4c052f2aff397bcfe80a",
+                "0x78163ce5979e2bc8a944ba353a63c194ca4f63d393bbaaa95857daa9223e93c"
+            ],
+            "nonce": "0x9d",
+            "calldata": [
+                "0x1",
+                "0x4c0a5193d58f74fbace4b74dcf65481e734ed1714121bdc571da345540efa05",
+                "0x3943907ef0ef6f9d2e2408b05e520a66daaf74293dbf665e5a20b117676170e",
+                "0x2",
+                "0x49d36570d4e46f48e99674bd3fcc84644ddd6b96f7c741b1562b82f9e004dc7",
+                "0x16345785d8a0000"
+            ],
+            "resource_bounds": {
+                "l1_gas": { "max_amount": "0xa9e", "max_price_per_unit": "0x7f2a1ad4f2f1" },
+                "l2_gas": { "max_amount": "0x0", "max_price_per_unit": "0x0" }
+            },
+            "tip": "0x0",
+            "paymaster_data": [],
+            "account_deployment_data": [],
+            "nonce_data_availability_mode": "L1",
+            "fee_data_availability_mode": "L1"
+        })
+    }
+
+    #[test]
+    fn invoke_v3_from_rpc_json_verifies_the_hash() {
+        let tx =
+            ApiTransaction::from_rpc_json(&invoke_v3_json(), StarknetChainId::MainNet.to_felt())
+                .unwrap();
+        assert_eq!(
+            tx.v3_fields,
+            Some(V3TransactionFields {
+                l1_resource_bounds: ResourceBounds {
+                    max_amount: 0xa9e,
+                    max_price_per_unit: 0x7f2a1ad4f2f1,
+                },
+                ..Default::default()
+            })
+        );
+
+        let tx = Transaction::try_from(tx).unwrap();
+        assert_matches!(&tx, Transaction::InvokeFunction(invoke) if invoke.v3_fields().is_some());
+        assert_eq!(
+            tx.hash_value(),
+            felt_str!(
+                "1d4735f4ba73a67be2f648d9b21cab3783383b8c229566b46b027c46012219",
+                16
+            )
+        );
+    }
+
+    // https://starkscan.co/tx/0x25cbbc1e197e1e6dd8886b4b359c2dbe26245a282547624d7e54e7c3e2cf15
+    #[test]
+    fn deploy_account_v3_from_rpc_json_verifies_the_hash() {
+        let json = json!({
+            "type": "DEPLOY_ACCOUNT",
+            "version": "0x3",
+            "transaction_hash": "0x25cbbc1e197e1e6dd8886b4b359c2dbe26245a282547624d7e54e7c3e2cf15",
+            "class_hash": "0x13bfe114fb1cf405bfc3a7f8dbe2d91db146c17521d40dcf57e16d6b59fa8e6",
+            "contract_address_salt": "0x1f218cfa725d1679ff028c53d094a765dfd1de632787cab37ffe10f3915dcc7",
+            "constructor_calldata": [
+                "0x1f218cfa725d1679ff028c53d094a765dfd1de632787cab37ffe10f3915dcc7"
+            ],
+            "signature": [
+                "0x516550aaa957b0f931b1f9347304bb557e29036b5056c0b97b047778c1c8fec",
+                "0x782d87c2df96241acb2d88260e6ae2f18ad36fd68bd1036cfb2166a30bb9e14"
+            ],
+            "nonce": "0x0",
+            "resource_bounds": {
+                "l1_gas": { "max_amount": "0x4c", "max_price_per_unit": "0x8ab967606cb9" },
+                "l2_gas": { "max_amount": "0x0", "max_price_per_unit": "0x0" }
+            },
+            "tip": "0x0",
+            "paymaster_data": [],
+            "nonce_data_availability_mode": "L1",
+            "fee_data_availability_mode": "L1"
+        });
+
+        let tx = Transaction::try_from(
+            ApiTransaction::from_rpc_json(&json, StarknetChainId::MainNet.to_felt()).unwrap(),
+        )
+        .unwrap();
+        assert_matches!(tx, Transaction::DeployAccount(_));
+        assert_eq!(
+            tx.hash_value(),
+            felt_str!(
+                "25cbbc1e197e1e6dd8886b4b359c2dbe26245a282547624d7e54e7c3e2cf15",
+                16
+            )
+        );
+    }
+
+    // https://starkscan.co/tx/0x439e12f67962c353182d72b4af12c3f11eaba4b36e552aebcdcd6db66971bdb
+    #[test]
+    fn l1_handler_from_rpc_json_verifies_the_hash() {
+        let json = json!({
+            "type": "L1_HANDLER",
+            "version": "0x0",
+            "transaction_hash": "0x439e12f67962c353182d72b4af12c3f11eaba4b36e552aebcdcd6db66971bdb",
+            "nonce": "0x18e94d",
+            "contract_address": "0x73314940630fd6dcda0d772d4c972c4e0a9946bef9dabf4ef84eda8ef542b82",
+            "entry_point_selector": "0x1b64b1b3b690b43b9b514fb81377518f4039cd3e4f4914d8a6bdf01d679fb19",
+            "calldata": [
+                "0xae0ee0a63a2ce6baeeffe56e7714fb4efe48d419",
+                "0x455448",
+                "0xc27947400e26e534e677afc2e9b2ec1bab14fc89",
+                "0x4af4754baf89f1b8b449215a8ea7ce558824a33a5393eaa3829658549f2bfa2",
+                "0x9184e72a000",
+                "0x0"
+            ]
+        });
+
+        let tx = Transaction::try_from(
+            ApiTransaction::from_rpc_json(&json, StarknetChainId::MainNet.to_felt())
+                .unwrap()
+                .with_paid_fee_on_l1(10.into()),
+        )
+        .unwrap();
+        assert_matches!(tx, Transaction::L1Handler(_));
+        assert_eq!(
+            tx.hash_value(),
+            felt_str!(
+                "439e12f67962c353182d72b4af12c3f11eaba4b36e552aebcdcd6db66971bdb",
+                16
+            )
+        );
+    }
+
+    #[test]
+    fn unsupported_transactions_are_rejected() {
+        let chain_id = StarknetChainId::MainNet.to_felt();
+
+        let mut json = invoke_v1_json();
+        json["version"] = json!("0x2");
+        assert_matches!(
+            ApiTransaction::from_rpc_json(&json, chain_id.clone()),
+            Err(TransactionError::UnsupportedVersion(_))
+        );
+
+        json["type"] = json!("UNKNOWN");
+        assert_matches!(
+            ApiTransaction::from_rpc_json(&json, chain_id.clone()),
+            Err(TransactionError::InvalidRpcTransaction(_))
+        );
+
+        let json = json!({
+            "type": "DECLARE",
+            "version": "0x1",
+            "class_hash": "0x1",
+            "sender_address": "0x2",
+            "max_fee": "0x0",
+            "signature": [],
+            "nonce": "0x0"
+        });
+        let tx = ApiTransaction::from_rpc_json(&json, chain_id.clone()).unwrap();
+        assert_matches!(
+            Transaction::try_from(tx),
+            Err(TransactionError::MissingTransactionClass(_))
+        );
+
+        // Version 3 declares need the Sierra class too.
+        let mut json = invoke_v3_json();
+        json["type"] = json!("DECLARE");
+        json["class_hash"] = json!("0x1");
+        json["compiled_class_hash"] = json!("0x2");
+        let tx = ApiTransaction::from_rpc_json(&json, chain_id).unwrap();
+        assert!(tx.v3_fields.is_some());
+        assert_matches!(
+            Transaction::try_from(tx),
+            Err(TransactionError::MissingTransactionClass(_))
+        );
+    }
+}
@@ -8,7 +8,8 @@ use crate::{
         transaction_hash::calculate_declare_transaction_hash,
     },
     definitions::{
-        block_context::BlockContext, constants::VALIDATE_DECLARE_ENTRY_POINT_SELECTOR,
+        block_context::{BlockContext, FeeType},
+        constants::VALIDATE_DECLARE_ENTRY_POINT_SELECTOR,
         transaction_type::TransactionType,
     },
     execution::{
@@ -169,16 +170,20 @@ impl Declare {
             self.run_validate_entrypoint(state, &mut resources_manager, block_context)?
         };
         let changes = state.count_actual_storage_changes(Some((
-            &block_context.starknet_os_config.fee_token_address,
+            block_context
+                .starknet_os_config
+                .fee_token_address_by_type(FeeType::from_version(&self.version)),
             &self.sender_address,
         )))?;
-        let actual_resources = calculate_tx_resources(
+        let (actual_resources, fee_breakdown) = calculate_tx_resources(
             resources_manager,
             &vec![validate_info.clone()],
             TransactionType::Declare,
+            0,
             changes,
             None,
             0,
+            block_context,
         )
         .map_err(|_| TransactionError::ResourcesCalculation)?;
 
@@ -188,7 +193,8 @@ impl Declare {
             None,
             actual_resources,
             Some(self.tx_type),
-        ))
+        )
+        .with_fee_breakdown(fee_breakdown))
     }
 
     // ~~~~~~~~~~~~~~~~~~~~~~~~~~~
@@ -284,12 +290,14 @@ impl Declare {
             self.max_fee,
             &mut tx_execution_context,
             self.skip_fee_transfer,
+            tx_exec_info.revert_error.is_some(),
         )?;
 
         state.set_contract_class(
             &self.class_hash,
             &CompiledClass::Deprecated(Arc::new(self.contract_class.clone())),
         )?;
+        state.declare_deprecated_class(&self.class_hash)?;
 
         tx_exec_info.set_fee_info(actual_fee, fee_transfer_info);
 
@@ -337,15 +345,18 @@ mod tests {
     use crate::{
         definitions::{
             block_context::{BlockContext, StarknetChainId},
-            constants::VALIDATE_DECLARE_ENTRY_POINT_SELECTOR,
+            constants::{
+                DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS, VALIDATE_DECLARE_ENTRY_POINT_SELECTOR,
+            },
             transaction_type::TransactionType,
         },
-        execution::CallType,
+        execution::{gas_usage::L1GasUsage, CallType},
         services::api::contract_classes::{
             compiled_class::CompiledClass, deprecated_contract_class::ContractClass,
         },
         state::cached_state::CachedState,
         state::in_memory_state_reader::InMemoryStateReader,
+        transaction::fee::FeeBreakdown,
         utils::{felt_to_hash, Address},
     };
 
@@ -451,17 +462,40 @@ mod tests {
             actual_fee: 0,
             actual_resources,
             tx_type: Some(TransactionType::Declare),
+            fee_breakdown: Some(FeeBreakdown {
+                cairo_resources_l1_gas: DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS
+                    .keys()
+                    .map(|resource| (resource.clone(), 0.0))
+                    .chain([
+                        ("n_steps".to_string(), 2715.0 * 0.01),
+                        ("range_check_builtin".to_string(), 63.0 * 0.16),
+                        ("pedersen_builtin".to_string(), 15.0 * 0.32),
+                    ])
+                    .collect(),
+                bottleneck_resource: Some("n_steps".to_string()),
+                l1_gas_usage: L1GasUsage {
+                    storage_diffs: 1224,
+                    ..Default::default()
+                },
+                os_resources: ExecutionResources {
+                    n_steps: 2703,
+                    n_memory_holes: 0,
+                    builtin_instance_counter: HashMap::from([
+                        ("pedersen_builtin".to_string(), 15),
+                        ("range_check_builtin".to_string(), 63),
+                    ]),
+                },
+                total_l1_gas: 1252,
+            }),
         };
 
         // ---------------------
         //      Comparison
         // ---------------------
-        assert_eq!(
-            internal_declare
-                .apply(&mut state, &BlockContext::default())
-                .unwrap(),
-            transaction_exec_info
-        );
+        let tx_info = internal_declare
+            .apply(&mut state, &BlockContext::default())
+            .unwrap();
+        assert_eq!(tx_info, transaction_exec_info);
     }
 
     #[test]
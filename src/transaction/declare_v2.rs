@@ -8,10 +8,14 @@ use crate::services::api::contract_classes::deprecated_contract_class::EntryPoin
 use crate::services::api::contract_classes::compiled_class::CompiledClass;
 use crate::state::cached_state::CachedState;
 use crate::{
-    core::transaction_hash::calculate_declare_v2_transaction_hash,
+    core::transaction_hash::{
+        calculate_declare_v2_transaction_hash, calculate_declare_v3_transaction_hash,
+    },
     definitions::{
-        block_context::BlockContext,
-        constants::{INITIAL_GAS_COST, VALIDATE_DECLARE_ENTRY_POINT_SELECTOR},
+        block_context::{BlockContext, FeeType},
+        constants::{
+            INITIAL_GAS_COST, V3_TRANSACTION_VERSION, VALIDATE_DECLARE_ENTRY_POINT_SELECTOR,
+        },
         transaction_type::TransactionType,
     },
     execution::{
@@ -20,7 +24,10 @@ use crate::{
     },
     state::state_api::{State, StateReader},
     state::ExecutionResourcesManager,
-    transaction::{error::TransactionError, invoke_function::verify_no_calls_to_other_contracts},
+    transaction::{
+        error::TransactionError, invoke_function::verify_no_calls_to_other_contracts,
+        V3TransactionFields,
+    },
     utils::{calculate_tx_resources, Address},
 };
 use cairo_lang_starknet::casm_contract_class::CasmContractClass;
@@ -49,6 +56,8 @@ pub struct DeclareV2 {
     pub skip_validate: bool,
     pub skip_execute: bool,
     pub skip_fee_transfer: bool,
+    /// The fields of version 3 transactions, or `None` for the previous versions.
+    pub v3_fields: Option<V3TransactionFields>,
 }
 
 impl DeclareV2 {
@@ -147,6 +156,7 @@ impl DeclareV2 {
             skip_execute: false,
             skip_validate: false,
             skip_fee_transfer: false,
+            v3_fields: None,
         };
 
         verify_version(
@@ -247,6 +257,56 @@ impl DeclareV2 {
         )
     }
 
+    /// Creates a version 3 [DeclareV2], which pays its fee in STRK within the resource bounds
+    /// of `v3_fields`.
+    /// ## Parameters:
+    /// - sierra_contract_class: The sierra contract class of the contract to declare
+    /// - casm_contract_class: The casm contract class of the contract to declare. This is optional.
+    /// - compiled_class_hash: the class hash of the contract compiled with Cairo1 or newer.
+    /// - chain_id: Id of the network where is going to be declare, those can be: Mainnet, Testnet.
+    /// - sender_address: The address of the account declaring the contract.
+    /// - signature: Array of felts with the signatures of the contract.
+    /// - nonce: The nonce of the contract.
+    /// - v3_fields: The resource bounds, tip, paymaster and data availability fields.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_v3(
+        sierra_contract_class: &SierraContractClass,
+        casm_contract_class: Option<CasmContractClass>,
+        compiled_class_hash: Felt252,
+        chain_id: Felt252,
+        sender_address: Address,
+        signature: Vec<Felt252>,
+        nonce: Felt252,
+        v3_fields: V3TransactionFields,
+    ) -> Result<Self, TransactionError> {
+        let sierra_class_hash = compute_sierra_class_hash(sierra_contract_class)?;
+
+        let hash_value = calculate_declare_v3_transaction_hash(
+            V3_TRANSACTION_VERSION.clone(),
+            sierra_class_hash.clone(),
+            compiled_class_hash.clone(),
+            &sender_address,
+            chain_id,
+            nonce.clone(),
+            &v3_fields,
+        )?;
+
+        let mut declare = Self::new_with_sierra_class_hash_and_tx_hash(
+            sierra_contract_class,
+            sierra_class_hash,
+            casm_contract_class,
+            compiled_class_hash,
+            sender_address,
+            v3_fields.max_fee(),
+            V3_TRANSACTION_VERSION.clone(),
+            signature,
+            nonce,
+            hash_value,
+        )?;
+        declare.v3_fields = Some(v3_fields);
+        Ok(declare)
+    }
+
     // ~~~~~~~~~~~~~~~~~~~~~~~~~~~
     //  Account Functions
     // ~~~~~~~~~~~~~~~~~~~~~~~~~~~
@@ -264,6 +324,7 @@ impl DeclareV2 {
             n_steps,
             self.version.clone(),
         )
+        .with_v3_fields(self.v3_fields.clone())
     }
 
     /// returns the calldata with which the contract is executed
@@ -321,16 +382,20 @@ impl DeclareV2 {
         };
 
         let storage_changes = state.count_actual_storage_changes(Some((
-            &block_context.starknet_os_config.fee_token_address,
+            block_context
+                .starknet_os_config
+                .fee_token_address_by_type(FeeType::from_version(&self.version)),
             &self.sender_address,
         )))?;
-        let actual_resources = calculate_tx_resources(
+        let (actual_resources, fee_breakdown) = calculate_tx_resources(
             resources_manager,
             &[execution_result.call_info.clone()],
             self.tx_type,
+            0,
             storage_changes,
             None,
             execution_result.n_reverted_steps,
+            block_context,
         )?;
 
         let mut tx_execution_context =
@@ -342,16 +407,18 @@ impl DeclareV2 {
             self.max_fee,
             &mut tx_execution_context,
             self.skip_fee_transfer,
+            execution_result.revert_error.is_some(),
         )?;
         self.compile_and_store_casm_class(state)?;
 
         let mut tx_exec_info = TransactionExecutionInfo::new_without_fee_info(
             execution_result.call_info,
             None,
-            None,
+            execution_result.revert_error,
             actual_resources,
             Some(self.tx_type),
-        );
+        )
+        .with_fee_breakdown(fee_breakdown);
         tx_exec_info.set_fee_info(actual_fee, fee_transfer_info);
 
         Ok(tx_exec_info)
@@ -385,7 +452,7 @@ impl DeclareV2 {
         Ok(())
     }
 
-    fn run_validate_entrypoint<S: StateReader>(
+    pub(crate) fn run_validate_entrypoint<S: StateReader>(
         &self,
         mut remaining_gas: u128,
         state: &mut CachedState<S>,
@@ -449,6 +516,13 @@ impl DeclareV2 {
             } else {
                 self.max_fee
             },
+            v3_fields: if ignore_max_fee {
+                self.v3_fields
+                    .as_ref()
+                    .map(V3TransactionFields::with_unbounded_l1_gas)
+            } else {
+                self.v3_fields.clone()
+            },
             ..self.clone()
         };
 
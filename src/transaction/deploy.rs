@@ -156,7 +156,7 @@ impl Deploy {
 
         if self.constructor_entry_points_empty(self.contract_class.clone())? {
             // Contract has no constructors
-            Ok(self.handle_empty_constructor(state)?)
+            Ok(self.handle_empty_constructor(state, block_context)?)
         } else {
             self.invoke_constructor(state, block_context)
         }
@@ -165,9 +165,11 @@ impl Deploy {
     /// Executes the contract without constructor
     /// ## Parameters
     /// - state: A state that implements the [`State`] and [`StateReader`] traits.
+    /// - block_context: The block's execution context.
     pub fn handle_empty_constructor<S: State + StateReader>(
         &self,
         state: &mut S,
+        block_context: &BlockContext,
     ) -> Result<TransactionExecutionInfo, TransactionError> {
         if !self.constructor_calldata.is_empty() {
             return Err(TransactionError::EmptyConstructorCalldata);
@@ -183,13 +185,15 @@ impl Deploy {
         let resources_manager = ExecutionResourcesManager::default();
 
         let changes = state.count_actual_storage_changes(None)?;
-        let actual_resources = calculate_tx_resources(
+        let (actual_resources, fee_breakdown) = calculate_tx_resources(
             resources_manager,
             &[Some(call_info.clone())],
             self.tx_type,
+            self.constructor_calldata.len(),
             changes,
             None,
             0,
+            block_context,
         )?;
 
         Ok(TransactionExecutionInfo::new_without_fee_info(
@@ -198,7 +202,8 @@ impl Deploy {
             None,
             actual_resources,
             Some(self.tx_type),
-        ))
+        )
+        .with_fee_breakdown(fee_breakdown))
     }
 
     /// Execute the contract using its constructor
@@ -242,17 +247,19 @@ impl Deploy {
             &mut resources_manager,
             &mut tx_execution_context,
             true,
-            block_context.validate_max_n_steps,
+            block_context.invoke_tx_max_n_steps,
         )?;
 
         let changes = state.count_actual_storage_changes(None)?;
-        let actual_resources = calculate_tx_resources(
+        let (actual_resources, fee_breakdown) = calculate_tx_resources(
             resources_manager,
             &[call_info.clone()],
             self.tx_type,
+            self.constructor_calldata.len(),
             changes,
             None,
             n_reverted_steps,
+            block_context,
         )?;
 
         Ok(TransactionExecutionInfo::new_without_fee_info(
@@ -261,7 +268,8 @@ impl Deploy {
             revert_error,
             actual_resources,
             Some(self.tx_type),
-        ))
+        )
+        .with_fee_breakdown(fee_breakdown))
     }
 
     /// Calculates actual fee used by the transaction using the execution
@@ -441,6 +449,7 @@ mod tests {
             program: contract_class.program,
             entry_points_by_type: HashMap::new(),
             abi: None,
+            program_json: None,
         };
 
         // Should fail when compouting the hash due to a failed contract class
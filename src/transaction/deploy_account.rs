@@ -7,12 +7,14 @@ use crate::state::cached_state::CachedState;
 use crate::{
     core::{
         errors::state_errors::StateError,
-        transaction_hash::calculate_deploy_account_transaction_hash,
+        transaction_hash::{
+            calculate_deploy_account_transaction_hash, calculate_deploy_account_v3_transaction_hash,
+        },
     },
     definitions::{
-        block_context::BlockContext,
+        block_context::{BlockContext, FeeType},
         constants::{
-            CONSTRUCTOR_ENTRY_POINT_SELECTOR, INITIAL_GAS_COST,
+            CONSTRUCTOR_ENTRY_POINT_SELECTOR, INITIAL_GAS_COST, V3_TRANSACTION_VERSION,
             VALIDATE_DEPLOY_ENTRY_POINT_SELECTOR,
         },
         transaction_type::TransactionType,
@@ -28,7 +30,7 @@ use crate::{
     state::state_api::{State, StateReader},
     state::ExecutionResourcesManager,
     syscalls::syscall_handler_errors::SyscallHandlerError,
-    transaction::error::TransactionError,
+    transaction::{error::TransactionError, V3TransactionFields},
     utils::{calculate_tx_resources, Address, ClassHash},
 };
 use cairo_vm::felt::Felt252;
@@ -51,8 +53,11 @@ pub struct DeployAccount {
     class_hash: ClassHash,
     #[getset(get = "pub")]
     constructor_calldata: Vec<Felt252>,
+    #[getset(get = "pub")]
     version: Felt252,
+    #[getset(get = "pub")]
     nonce: Felt252,
+    #[getset(get = "pub")]
     max_fee: u128,
     #[getset(get = "pub")]
     hash_value: Felt252,
@@ -61,6 +66,9 @@ pub struct DeployAccount {
     skip_validate: bool,
     skip_execute: bool,
     skip_fee_transfer: bool,
+    /// The fields of version 3 transactions, or `None` for the previous versions.
+    #[getset(get = "pub")]
+    v3_fields: Option<V3TransactionFields>,
 }
 
 impl DeployAccount {
@@ -106,6 +114,7 @@ impl DeployAccount {
             skip_execute: false,
             skip_validate: false,
             skip_fee_transfer: false,
+            v3_fields: None,
         })
     }
 
@@ -140,9 +149,54 @@ impl DeployAccount {
             skip_execute: false,
             skip_validate: false,
             skip_fee_transfer: false,
+            v3_fields: None,
         })
     }
 
+    /// Creates a version 3 [DeployAccount], which pays its fee in STRK within the resource
+    /// bounds of `v3_fields`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_v3(
+        class_hash: ClassHash,
+        nonce: Felt252,
+        constructor_calldata: Vec<Felt252>,
+        signature: Vec<Felt252>,
+        contract_address_salt: Felt252,
+        chain_id: Felt252,
+        v3_fields: V3TransactionFields,
+    ) -> Result<Self, SyscallHandlerError> {
+        let contract_address = Address(calculate_contract_address(
+            &contract_address_salt,
+            &Felt252::from_bytes_be(&class_hash),
+            &constructor_calldata,
+            Address(Felt252::zero()),
+        )?);
+
+        let hash_value = calculate_deploy_account_v3_transaction_hash(
+            V3_TRANSACTION_VERSION.clone(),
+            &contract_address,
+            Felt252::from_bytes_be(&class_hash),
+            &constructor_calldata,
+            contract_address_salt.clone(),
+            chain_id,
+            nonce.clone(),
+            &v3_fields,
+        )?;
+
+        let mut tx = Self::new_with_tx_hash(
+            class_hash,
+            v3_fields.max_fee(),
+            V3_TRANSACTION_VERSION.clone(),
+            nonce,
+            constructor_calldata,
+            signature,
+            contract_address_salt,
+            hash_value,
+        )?;
+        tx.v3_fields = Some(v3_fields);
+        Ok(tx)
+    }
+
     pub fn get_state_selector(&self, _block_context: BlockContext) -> StateSelector {
         StateSelector {
             contract_addresses: vec![self.contract_address.clone()],
@@ -167,6 +221,7 @@ impl DeployAccount {
             self.max_fee,
             &mut tx_execution_context,
             self.skip_fee_transfer,
+            tx_info.revert_error.is_some(),
         )?;
 
         tx_info.set_fee_info(actual_fee, fee_transfer_info);
@@ -209,16 +264,20 @@ impl DeployAccount {
             self.run_validate_entrypoint(state, &mut resources_manager, block_context)?
         };
 
-        let actual_resources = calculate_tx_resources(
+        let (actual_resources, fee_breakdown) = calculate_tx_resources(
             resources_manager,
             &[Some(constructor_call_info.clone()), validate_info.clone()],
             TransactionType::DeployAccount,
+            self.constructor_calldata.len(),
             state.count_actual_storage_changes(Some((
-                &block_context.starknet_os_config.fee_token_address,
+                block_context
+                    .starknet_os_config
+                    .fee_token_address_by_type(FeeType::from_version(&self.version)),
                 &self.contract_address,
             )))?,
             None,
             0,
+            block_context,
         )
         .map_err::<TransactionError, _>(|_| TransactionError::ResourcesCalculation)?;
 
@@ -228,7 +287,8 @@ impl DeployAccount {
             None,
             actual_resources,
             Some(TransactionType::DeployAccount),
-        ))
+        )
+        .with_fee_breakdown(fee_breakdown))
     }
 
     pub fn handle_constructor<S: StateReader>(
@@ -315,6 +375,7 @@ impl DeployAccount {
             n_steps,
             self.version.clone(),
         )
+        .with_v3_fields(self.v3_fields.clone())
     }
 
     pub fn run_validate_entrypoint<S: StateReader>(
@@ -379,6 +440,13 @@ impl DeployAccount {
             } else {
                 self.max_fee
             },
+            v3_fields: if ignore_max_fee {
+                self.v3_fields
+                    .as_ref()
+                    .map(V3TransactionFields::with_unbounded_l1_gas)
+            } else {
+                self.v3_fields.clone()
+            },
             ..self.clone()
         };
 
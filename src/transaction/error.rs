@@ -8,6 +8,7 @@ use crate::{
     syscalls::syscall_handler_errors::SyscallHandlerError,
     utils::ClassHash,
 };
+use cairo_vm::felt::Felt252;
 use cairo_vm::{
     types::{
         errors::{math_errors::MathError, program_errors::ProgramError},
@@ -18,6 +19,7 @@ use cairo_vm::{
         trace_errors::TraceError, vm_errors::VirtualMachineError,
     },
 };
+use num_bigint::BigUint;
 use starknet::core::types::FromByteArrayError;
 use thiserror::Error;
 
@@ -41,6 +43,14 @@ pub enum TransactionError {
     InvalidTransactionNonce(String, String),
     #[error("Actual fee exceeds max fee. Actual: {0}, Max: {1}")]
     ActualFeeExceedsMaxFee(u128, u128),
+    #[error("Transaction execution reverted: {0}")]
+    ExecutionReverted(String),
+    #[error("Transaction exceeds the {0} limit of a block")]
+    TransactionTooLargeForBlock(String),
+    #[error("L1 gas usage exceeds the max amount of L1 gas. Actual: {0}, Max: {1}")]
+    L1GasExceedsMaxAmount(u128, u64),
+    #[error("Max L1 gas price is lower than the block's L1 gas price. Max: {0}, Block: {1}")]
+    MaxL1GasPriceTooLow(u128, u128),
     #[error("Fee transfer failure: {0}")]
     FeeTransferError(Box<TransactionError>),
     #[error("{0}")]
@@ -145,6 +155,37 @@ pub enum TransactionError {
     UnsupportedVersion(String),
     #[error("Invalid compiled class, expected class hash: {0}, but received: {1}")]
     InvalidCompiledClassHash(String, String),
+    #[error("Invalid JSON-RPC transaction: {0}")]
+    InvalidRpcTransaction(String),
+    #[error("Converting the transaction requires its {0} class")]
+    MissingTransactionClass(&'static str),
+    #[error("Transaction hash mismatch. Expected: {0}, computed: {1}")]
+    TransactionHashMismatch(Felt252, Felt252),
+    #[error("Transactions of type {0:?} have no RPC trace")]
+    NoRpcTrace(Option<TransactionType>),
     #[error(transparent)]
     FromByteArrayError(#[from] FromByteArrayError),
+    #[error("Execution exceeded the limit of {0} steps")]
+    StepsLimitExceeded(u64),
+}
+
+/// The reason a transaction is rejected before being admitted to the mempool.
+#[derive(Debug, Error)]
+pub enum TransactionRejection {
+    #[error("Transactions of type {0:?} aren't sent by accounts")]
+    NotAnAccountTransaction(TransactionType),
+    #[error("Unsupported version {0}")]
+    UnsupportedVersion(Felt252),
+    #[error("Invalid transaction nonce. Expected at least: {expected}, got: {actual}")]
+    NonceTooOld { expected: Felt252, actual: Felt252 },
+    #[error("Invalid transaction nonce. Expected at most: {max}, got: {actual}")]
+    NonceTooFarAhead { max: Felt252, actual: Felt252 },
+    #[error("Fee token balance {balance} doesn't cover the max fee {max_fee}")]
+    InsufficientBalance { balance: BigUint, max_fee: u128 },
+    #[error("Validation took at least {n_steps} steps, more than the limit of {max_n_steps}")]
+    ValidateStepsExceeded { n_steps: usize, max_n_steps: u64 },
+    #[error("Validation failed: {0}")]
+    ValidationFailure(String),
+    #[error(transparent)]
+    State(#[from] StateError),
 }
@@ -1,12 +1,13 @@
 use super::error::TransactionError;
 use crate::definitions::constants::{FEE_FACTOR, QUERY_VERSION_BASE};
 use crate::execution::execution_entry_point::ExecutionResult;
+use crate::execution::gas_usage::L1GasUsage;
 use crate::execution::CallType;
 use crate::services::api::contract_classes::deprecated_contract_class::EntryPointType;
 use crate::state::cached_state::CachedState;
 use crate::{
     definitions::{
-        block_context::BlockContext,
+        block_context::{BlockContext, FeeType},
         constants::{INITIAL_GAS_COST, TRANSFER_ENTRY_POINT_SELECTOR},
     },
     execution::{
@@ -14,8 +15,9 @@ use crate::{
     },
     state::state_api::StateReader,
     state::ExecutionResourcesManager,
+    transaction::V3TransactionFields,
 };
-use cairo_vm::felt::Felt252;
+use cairo_vm::{felt::Felt252, vm::runners::cairo_runner::ExecutionResources};
 use num_traits::{ToPrimitive, Zero};
 use std::cmp::min;
 use std::collections::HashMap;
@@ -23,7 +25,62 @@ use std::collections::HashMap;
 // second element is the actual fee that the transaction uses
 pub type FeeInfo = (Option<CallInfo>, u128);
 
-/// Transfers the amount actual_fee from the caller account to the sequencer.
+/// How the L1 gas charged to a transaction was calculated from the resources it used.
+///
+/// The L1 data gas of the state diffs published in blobs is charged apart, see
+/// [L1GasUsage::l1_data_gas].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FeeBreakdown {
+    /// The L1 gas of each Cairo resource with a fee weight, i.e. its usage times its weight.
+    pub cairo_resources_l1_gas: HashMap<String, f64>,
+    /// The Cairo resource with the most L1 gas, which is the only one charged. It's `None` when
+    /// no weighted resource was used.
+    pub bottleneck_resource: Option<String>,
+    /// The L1 gas and L1 data gas used by the transaction besides its Cairo resources.
+    pub l1_gas_usage: L1GasUsage,
+    /// The resources added by the OS to run the transaction and its syscalls, which are part
+    /// of the Cairo resources.
+    pub os_resources: ExecutionResources,
+    /// The L1 gas charged: the L1 gas of the bottleneck resource plus the L1 gas of
+    /// `l1_gas_usage`, rounded up.
+    pub total_l1_gas: u128,
+}
+
+impl FeeBreakdown {
+    /// Breaks down the fee of a transaction.
+    ///
+    /// ## Parameters
+    /// - resources: The resources used by the transaction.
+    /// - l1_gas_usage: The L1 gas and L1 data gas used by the transaction besides its Cairo resources.
+    /// - os_resources: The resources added by the OS to run the transaction.
+    /// - block_context: The context holding the fee weights of the Cairo resources.
+    pub(crate) fn new(
+        resources: &HashMap<String, usize>,
+        l1_gas_usage: L1GasUsage,
+        os_resources: ExecutionResources,
+        block_context: &BlockContext,
+    ) -> Self {
+        let cairo_resources_l1_gas =
+            weighted_cairo_usage(resources, &block_context.cairo_resource_fee_weights);
+        let bottleneck = bottleneck(&cairo_resources_l1_gas);
+        let bottleneck_resource = bottleneck.map(|(resource, _)| resource.clone());
+        let total_l1_gas = total_l1_gas(
+            l1_gas_usage.l1_gas(),
+            bottleneck.map_or(0.0_f64, |(_, l1_gas)| l1_gas),
+        );
+
+        FeeBreakdown {
+            cairo_resources_l1_gas,
+            bottleneck_resource,
+            l1_gas_usage,
+            os_resources,
+            total_l1_gas,
+        }
+    }
+}
+
+/// Transfers the amount actual_fee from the caller account to the sequencer, in the fee token
+/// of the transaction version.
 /// Returns the resulting CallInfo of the transfer call.
 pub(crate) fn execute_fee_transfer<S: StateReader>(
     state: &mut CachedState<S>,
@@ -38,7 +95,11 @@ pub(crate) fn execute_fee_transfer<S: StateReader>(
         ));
     }
 
-    let fee_token_address = block_context.starknet_os_config.fee_token_address.clone();
+    let fee_type = FeeType::from_version(&tx_execution_context.version);
+    let fee_token_address = block_context
+        .starknet_os_config
+        .fee_token_address_by_type(fee_type)
+        .clone();
 
     let calldata = [
         block_context.block_info.sequencer_address.0.clone(),
@@ -74,13 +135,28 @@ pub(crate) fn execute_fee_transfer<S: StateReader>(
 }
 
 // ----------------------------------------------------------------------------------------
-/// Calculates the fee of a transaction given its execution resources.
+/// Calculates the fee of a transaction given its execution resources, in the token of the
+/// given fee type.
 /// We add the l1_gas_usage (which may include, for example, the direct cost of L2-to-L1
-/// messages) to the gas consumed by Cairo resource and multiply by the L1 gas price.
+/// messages) to the gas consumed by Cairo resource and multiply by the L1 gas price. The
+/// l1_data_gas_usage of the state diffs published in blobs is added at the L1 data gas price.
 
 pub fn calculate_tx_fee(
     resources: &HashMap<String, usize>,
-    gas_price: u128,
+    fee_type: FeeType,
+    block_context: &BlockContext,
+) -> Result<u128, TransactionError> {
+    let gas_price = block_context.block_info.gas_price_by_type(fee_type);
+    let data_gas_price = block_context.block_info.data_gas_price_by_type(fee_type);
+    Ok(
+        calculate_tx_l1_gas_usage(resources, block_context)? * gas_price
+            + calculate_tx_l1_data_gas_usage(resources) * data_gas_price,
+    )
+}
+
+/// Calculates the total L1 gas used by a transaction given its execution resources.
+pub(crate) fn calculate_tx_l1_gas_usage(
+    resources: &HashMap<String, usize>,
     block_context: &BlockContext,
 ) -> Result<u128, TransactionError> {
     let gas_usage = resources
@@ -89,9 +165,63 @@ pub fn calculate_tx_fee(
         .to_owned();
 
     let l1_gas_by_cairo_usage = calculate_l1_gas_by_cairo_usage(block_context, resources)?;
-    let total_l1_gas_usage = gas_usage.to_f64().unwrap() + l1_gas_by_cairo_usage;
 
-    Ok(total_l1_gas_usage.ceil() as u128 * gas_price)
+    Ok(total_l1_gas(gas_usage, l1_gas_by_cairo_usage))
+}
+
+/// Returns the L1 gas charged for a transaction: its L1 gas usage plus the L1 gas of its Cairo
+/// resources, rounded up.
+fn total_l1_gas(l1_gas_usage: usize, l1_gas_by_cairo_usage: f64) -> u128 {
+    (l1_gas_usage.to_f64().unwrap() + l1_gas_by_cairo_usage).ceil() as u128
+}
+
+/// Returns the L1 data gas used by a transaction given its execution resources. Only the
+/// transactions of blocks that publish their state diffs in blobs use L1 data gas.
+pub(crate) fn calculate_tx_l1_data_gas_usage(resources: &HashMap<String, usize>) -> u128 {
+    resources
+        .get("l1_data_gas_usage")
+        .copied()
+        .unwrap_or_default() as u128
+}
+
+/// Checks that a version 3 transaction can pay for the L1 gas it used within its resource
+/// bounds.
+///
+/// Version 3 transactions have no bounds for the L1 data gas, so it's bounded as L1 gas: it's
+/// counted as the L1 gas that costs the same in STRK, rounded up. When the price of L1 gas is
+/// zero, each unit of L1 data gas counts as one unit of L1 gas.
+fn check_resource_bounds(
+    v3_fields: &V3TransactionFields,
+    resources: &HashMap<String, usize>,
+    block_context: &BlockContext,
+) -> Result<(), TransactionError> {
+    let l1_bounds = &v3_fields.l1_resource_bounds;
+    let gas_price = block_context.block_info.gas_price_by_type(FeeType::Strk);
+    if l1_bounds.max_price_per_unit < gas_price {
+        return Err(TransactionError::MaxL1GasPriceTooLow(
+            l1_bounds.max_price_per_unit,
+            gas_price,
+        ));
+    }
+
+    let l1_data_gas_usage = calculate_tx_l1_data_gas_usage(resources);
+    let l1_data_gas_as_l1_gas = match gas_price {
+        0 => l1_data_gas_usage,
+        _ => {
+            let data_gas_price = block_context
+                .block_info
+                .data_gas_price_by_type(FeeType::Strk);
+            (l1_data_gas_usage * data_gas_price + gas_price - 1) / gas_price
+        }
+    };
+    let l1_gas_usage = calculate_tx_l1_gas_usage(resources, block_context)? + l1_data_gas_as_l1_gas;
+    if l1_gas_usage > l1_bounds.max_amount as u128 {
+        return Err(TransactionError::L1GasExceedsMaxAmount(
+            l1_gas_usage,
+            l1_bounds.max_amount,
+        ));
+    }
+    Ok(())
 }
 
 // ----------------------------------------------------------------------------------------
@@ -103,30 +233,57 @@ pub(crate) fn calculate_l1_gas_by_cairo_usage(
     block_context: &BlockContext,
     cairo_resource_usage: &HashMap<String, usize>,
 ) -> Result<f64, TransactionError> {
-    if !cairo_resource_usage
-        .keys()
-        .all(|k| k == "l1_gas_usage" || block_context.cairo_resource_fee_weights.contains_key(k))
-    {
+    if !cairo_resource_usage.keys().all(|k| {
+        k == "l1_gas_usage"
+            || k == "l1_data_gas_usage"
+            || block_context.cairo_resource_fee_weights.contains_key(k)
+    }) {
         return Err(TransactionError::ResourcesError);
     }
 
     // Convert Cairo usage to L1 gas usage.
-    Ok(max_of_keys(
+    let cairo_resources_l1_gas = weighted_cairo_usage(
         cairo_resource_usage,
         &block_context.cairo_resource_fee_weights,
-    ))
+    );
+    Ok(bottleneck(&cairo_resources_l1_gas).map_or(0.0_f64, |(_, l1_gas)| l1_gas))
 }
 
-fn max_of_keys(cairo_rsc: &HashMap<String, usize>, weights: &HashMap<String, f64>) -> f64 {
-    let mut max = 0.0_f64;
-    for (k, v) in weights {
-        let val = cairo_rsc.get(k).unwrap_or(&0).to_f64().unwrap_or(0.0_f64);
-        max = f64::max(max, val * v);
-    }
-    max
+/// Returns the L1 gas of each Cairo resource with a fee weight, i.e. its usage times its weight.
+fn weighted_cairo_usage(
+    cairo_rsc: &HashMap<String, usize>,
+    weights: &HashMap<String, f64>,
+) -> HashMap<String, f64> {
+    weights
+        .iter()
+        .map(|(resource, weight)| {
+            let usage = cairo_rsc.get(resource).copied().unwrap_or(0);
+            (resource.clone(), usage.to_f64().unwrap_or(0.0_f64) * weight)
+        })
+        .collect()
+}
+
+/// Returns the Cairo resource with the most L1 gas, which is the only one charged, with its L1
+/// gas. Ties are broken by name, so that the bottleneck doesn't depend on the map order.
+fn bottleneck(cairo_resources_l1_gas: &HashMap<String, f64>) -> Option<(&String, f64)> {
+    cairo_resources_l1_gas
+        .iter()
+        .filter(|(_, l1_gas)| **l1_gas > 0.0)
+        .max_by(|(resource_a, l1_gas_a), (resource_b, l1_gas_b)| {
+            l1_gas_a
+                .total_cmp(l1_gas_b)
+                .then_with(|| resource_b.cmp(resource_a))
+        })
+        .map(|(resource, l1_gas)| (resource, *l1_gas))
 }
 
-/// Calculates and charges the actual fee.
+/// Calculates and charges the actual fee, in ETH or STRK depending on the transaction version.
+///
+/// Version 3 transactions must also stay within the L1 gas bounds of their execution context,
+/// and their `max_fee` is the most those bounds allow them to pay.
+///
+/// The execution of a reverted transaction can't be undone, so its fee is capped at `max_fee`
+/// instead of failing when it exceeds it.
 ///
 /// # Parameters:
 /// - `state`: A [CachedState].
@@ -135,6 +292,7 @@ fn max_of_keys(cairo_rsc: &HashMap<String, usize>, weights: &HashMap<String, f64
 /// - `max_fee`: The maximum fee that the transaction is allowed to charge.
 /// - `tx_execution_context`: The transaction's execution context.
 /// - `skip_fee_transfer`: Whether to skip the fee transfer.
+/// - `reverted`: Whether the execution of the transaction was reverted.
 ///
 pub fn charge_fee<S: StateReader>(
     state: &mut CachedState<S>,
@@ -143,19 +301,22 @@ pub fn charge_fee<S: StateReader>(
     max_fee: u128,
     tx_execution_context: &mut TransactionExecutionContext,
     skip_fee_transfer: bool,
+    reverted: bool,
 ) -> Result<FeeInfo, TransactionError> {
+    if let Some(v3_fields) = tx_execution_context.v3_fields() {
+        match check_resource_bounds(v3_fields, resources, block_context) {
+            Err(TransactionError::L1GasExceedsMaxAmount(..)) if reverted => {}
+            result => result?,
+        }
+    }
     if max_fee.is_zero() {
         return Ok((None, 0));
     }
 
-    let actual_fee = calculate_tx_fee(
-        resources,
-        block_context.starknet_os_config.gas_price,
-        block_context,
-    )?;
+    let fee_type = FeeType::from_version(&tx_execution_context.version);
+    let actual_fee = calculate_tx_fee(resources, fee_type, block_context)?;
 
-    if actual_fee > max_fee {
-        // TODO: Charge max_fee
+    if actual_fee > max_fee && !reverted {
         return Err(TransactionError::ActualFeeExceedsMaxFee(
             actual_fee, max_fee,
         ));
@@ -166,7 +327,7 @@ pub fn charge_fee<S: StateReader>(
     {
         min(actual_fee, max_fee) * FEE_FACTOR
     } else {
-        actual_fee
+        min(actual_fee, max_fee)
     };
 
     let fee_transfer_info = if skip_fee_transfer {
@@ -188,10 +349,14 @@ mod tests {
     use std::{collections::HashMap, sync::Arc};
 
     use crate::{
-        definitions::block_context::BlockContext,
+        definitions::block_context::{BlockContext, FeeType},
         execution::TransactionExecutionContext,
         state::{cached_state::CachedState, in_memory_state_reader::InMemoryStateReader},
-        transaction::{error::TransactionError, fee::charge_fee},
+        transaction::{
+            error::TransactionError,
+            fee::{calculate_tx_fee, charge_fee},
+            ResourceBounds, V3TransactionFields,
+        },
     };
 
     #[test]
@@ -199,7 +364,7 @@ mod tests {
         let mut state = CachedState::new(Arc::new(InMemoryStateReader::default()), HashMap::new());
         let mut tx_execution_context = TransactionExecutionContext::default();
         let mut block_context = BlockContext::default();
-        block_context.starknet_os_config.gas_price = 1;
+        block_context.block_info.gas_price = 1;
         let resources = HashMap::from([
             ("l1_gas_usage".to_string(), 200_usize),
             ("pedersen_builtin".to_string(), 10000_usize),
@@ -214,6 +379,7 @@ mod tests {
             max_fee,
             &mut tx_execution_context,
             skip_fee_transfer,
+            false,
         )
         .unwrap_err();
 
@@ -228,7 +394,7 @@ mod tests {
             ..Default::default()
         };
         let mut block_context = BlockContext::default();
-        block_context.starknet_os_config.gas_price = 1;
+        block_context.block_info.gas_price = 1;
         let resources = HashMap::from([
             ("l1_gas_usage".to_string(), 200_usize),
             ("pedersen_builtin".to_string(), 10000_usize),
@@ -243,9 +409,118 @@ mod tests {
             max_fee,
             &mut tx_execution_context,
             skip_fee_transfer,
+            false,
         )
         .unwrap_err();
 
         assert_matches!(result, TransactionError::ActualFeeExceedsMaxFee(_, _));
     }
+
+    #[test]
+    fn test_charge_fee_v3_checks_the_l1_gas_bounds() {
+        let mut state = CachedState::new(Arc::new(InMemoryStateReader::default()), HashMap::new());
+        let mut block_context = BlockContext::default();
+        block_context.block_info.gas_price = 1;
+        block_context.block_info.strk_gas_price = 10;
+        let resources = HashMap::from([("l1_gas_usage".to_string(), 200_usize)]);
+        let v3_fields = |max_amount, max_price_per_unit| V3TransactionFields {
+            l1_resource_bounds: ResourceBounds {
+                max_amount,
+                max_price_per_unit,
+            },
+            ..Default::default()
+        };
+        let mut charge = |v3_fields: V3TransactionFields| {
+            let max_fee = v3_fields.max_fee();
+            let mut tx_execution_context = TransactionExecutionContext {
+                version: 3.into(),
+                ..Default::default()
+            }
+            .with_v3_fields(Some(v3_fields));
+            charge_fee(
+                &mut state,
+                &resources,
+                &block_context,
+                max_fee,
+                &mut tx_execution_context,
+                true,
+                false,
+            )
+        };
+
+        assert_matches!(
+            charge(v3_fields(1000, 9)),
+            Err(TransactionError::MaxL1GasPriceTooLow(9, 10))
+        );
+        assert_matches!(
+            charge(v3_fields(199, 20)),
+            Err(TransactionError::L1GasExceedsMaxAmount(200, 199))
+        );
+        assert_matches!(charge(v3_fields(200, 20)), Ok((None, 2000)));
+    }
+
+    #[test]
+    fn test_charge_fee_v3_bounds_the_l1_data_gas_as_l1_gas() {
+        let mut state = CachedState::new(Arc::new(InMemoryStateReader::default()), HashMap::new());
+        let mut block_context = BlockContext::default();
+        block_context.block_info.strk_gas_price = 10;
+        block_context.block_info.strk_data_gas_price = 5;
+        let resources = HashMap::from([
+            ("l1_gas_usage".to_string(), 200_usize),
+            ("l1_data_gas_usage".to_string(), 63_usize),
+        ]);
+        let mut charge = |max_amount| {
+            let v3_fields = V3TransactionFields {
+                l1_resource_bounds: ResourceBounds {
+                    max_amount,
+                    max_price_per_unit: 20,
+                },
+                ..Default::default()
+            };
+            let max_fee = v3_fields.max_fee();
+            let mut tx_execution_context = TransactionExecutionContext {
+                version: 3.into(),
+                ..Default::default()
+            }
+            .with_v3_fields(Some(v3_fields));
+            charge_fee(
+                &mut state,
+                &resources,
+                &block_context,
+                max_fee,
+                &mut tx_execution_context,
+                true,
+                false,
+            )
+        };
+
+        // The 63 units of L1 data gas cost as much as 31.5 units of L1 gas.
+        assert_matches!(
+            charge(231),
+            Err(TransactionError::L1GasExceedsMaxAmount(232, 231))
+        );
+        assert_matches!(charge(232), Ok((None, 2315)));
+    }
+
+    #[test]
+    fn test_calculate_tx_fee_charges_the_l1_data_gas_at_its_price() {
+        let mut block_context = BlockContext::default();
+        block_context.block_info.gas_price = 3;
+        block_context.block_info.strk_gas_price = 30;
+        block_context.block_info.data_gas_price = 2;
+        block_context.block_info.strk_data_gas_price = 20;
+        let resources = HashMap::from([
+            ("l1_gas_usage".to_string(), 200_usize),
+            ("l1_data_gas_usage".to_string(), 64_usize),
+        ]);
+
+        assert_eq!(
+            calculate_tx_fee(&resources, FeeType::Eth, &block_context).unwrap(),
+            200 * 3 + 64 * 2
+        );
+        assert_eq!(
+            calculate_tx_fee(&resources, FeeType::Strk, &block_context).unwrap(),
+            200 * 30 + 64 * 20
+        );
+    }
 }
@@ -1,9 +1,13 @@
 use crate::{
-    core::transaction_hash::{calculate_transaction_hash_common, TransactionHashPrefix},
+    core::transaction_hash::{
+        calculate_invoke_v3_transaction_hash, calculate_transaction_hash_common,
+        TransactionHashPrefix,
+    },
     definitions::{
-        block_context::{BlockContext, StarknetChainId},
+        block_context::{BlockContext, FeeType, StarknetChainId},
         constants::{
-            EXECUTE_ENTRY_POINT_SELECTOR, QUERY_VERSION_BASE, VALIDATE_ENTRY_POINT_SELECTOR,
+            EXECUTE_ENTRY_POINT_SELECTOR, QUERY_VERSION_BASE, V3_TRANSACTION_VERSION,
+            VALIDATE_ENTRY_POINT_SELECTOR,
         },
         transaction_type::TransactionType,
     },
@@ -13,7 +17,7 @@ use crate::{
     },
     state::state_api::{State, StateReader},
     state::{cached_state::CachedState, ExecutionResourcesManager},
-    transaction::error::TransactionError,
+    transaction::{error::TransactionError, V3TransactionFields},
     utils::{calculate_tx_resources, Address},
 };
 
@@ -34,18 +38,24 @@ pub struct InvokeFunction {
     entry_point_type: EntryPointType,
     calldata: Vec<Felt252>,
     tx_type: TransactionType,
+    #[getset(get = "pub")]
     version: Felt252,
     validate_entry_point_selector: Felt252,
     #[getset(get = "pub")]
     hash_value: Felt252,
     #[getset(get = "pub")]
     signature: Vec<Felt252>,
+    #[getset(get = "pub")]
     max_fee: u128,
+    #[getset(get = "pub")]
     nonce: Option<Felt252>,
     skip_validation: bool,
     skip_execute: bool,
     skip_fee_transfer: bool,
     skip_nonce_check: bool,
+    /// The fields of version 3 transactions, or `None` for the previous versions.
+    #[getset(get = "pub")]
+    v3_fields: Option<V3TransactionFields>,
 }
 
 impl InvokeFunction {
@@ -117,17 +127,81 @@ impl InvokeFunction {
             skip_execute: false,
             skip_fee_transfer: false,
             skip_nonce_check: false,
+            v3_fields: None,
         })
     }
 
+    /// Creates a version 3 [InvokeFunction], which pays its fee in STRK within the resource
+    /// bounds of `v3_fields`.
+    /// ## Parameters
+    /// - sender_address: The address of the account sending the transaction.
+    /// - calldata: The calldata of the `__execute__` entry point of the account.
+    /// - signature: The signature of the transaction.
+    /// - chain_id: The id of the network the transaction is sent to.
+    /// - nonce: The nonce of the account.
+    /// - v3_fields: The resource bounds, tip, paymaster and data availability fields.
+    pub fn new_v3(
+        sender_address: Address,
+        calldata: Vec<Felt252>,
+        signature: Vec<Felt252>,
+        chain_id: Felt252,
+        nonce: Felt252,
+        v3_fields: V3TransactionFields,
+    ) -> Result<Self, TransactionError> {
+        let hash_value = calculate_invoke_v3_transaction_hash(
+            V3_TRANSACTION_VERSION.clone(),
+            &sender_address,
+            &calldata,
+            chain_id,
+            nonce.clone(),
+            &v3_fields,
+        )?;
+
+        InvokeFunction::new_v3_with_tx_hash(
+            sender_address,
+            calldata,
+            signature,
+            nonce,
+            v3_fields,
+            hash_value,
+        )
+    }
+
+    /// Creates a version 3 [InvokeFunction] with a precomputed transaction hash.
+    pub fn new_v3_with_tx_hash(
+        sender_address: Address,
+        calldata: Vec<Felt252>,
+        signature: Vec<Felt252>,
+        nonce: Felt252,
+        v3_fields: V3TransactionFields,
+        hash_value: Felt252,
+    ) -> Result<Self, TransactionError> {
+        let mut tx = InvokeFunction::new_with_tx_hash(
+            sender_address,
+            EXECUTE_ENTRY_POINT_SELECTOR.clone(),
+            v3_fields.max_fee(),
+            V3_TRANSACTION_VERSION.clone(),
+            calldata,
+            signature,
+            Some(nonce),
+            hash_value,
+        )?;
+        tx.v3_fields = Some(v3_fields);
+        Ok(tx)
+    }
+
     /// Creates a `InvokeFunction` from a starknet api `InvokeTransaction`.
     pub fn from_invoke_transaction(
         tx: starknet_api::transaction::InvokeTransaction,
         chain_id: StarknetChainId,
     ) -> Result<Self, TransactionError> {
         match tx {
-            starknet_api::transaction::InvokeTransaction::V0(v0) => convert_invoke_v0(v0, chain_id),
-            starknet_api::transaction::InvokeTransaction::V1(v1) => convert_invoke_v1(v1, chain_id),
+            starknet_api::transaction::InvokeTransaction::V0(v0) => {
+                convert_invoke_v0(v0, chain_id.to_felt())
+            }
+            starknet_api::transaction::InvokeTransaction::V1(v1) => {
+                convert_invoke_v1(v1, chain_id.to_felt())
+            }
         }
     }
 
@@ -147,7 +221,8 @@ impl InvokeFunction {
             },
             n_steps,
             self.version.clone(),
-        ))
+        )
+        .with_v3_fields(self.v3_fields.clone()))
     }
 
     /// Execute the validation entrypoint of the contract and returns the call info.
@@ -257,16 +332,20 @@ impl InvokeFunction {
             )?
         };
         let changes = state.count_actual_storage_changes(Some((
-            &block_context.starknet_os_config.fee_token_address,
+            block_context
+                .starknet_os_config
+                .fee_token_address_by_type(FeeType::from_version(&self.version)),
             &self.contract_address,
         )))?;
-        let actual_resources = calculate_tx_resources(
+        let (actual_resources, fee_breakdown) = calculate_tx_resources(
             resources_manager,
             &vec![call_info.clone(), validate_info.clone()],
             self.tx_type,
+            self.calldata.len(),
             changes,
             None,
             n_reverted_steps,
+            block_context,
         )?;
         let transaction_execution_info = TransactionExecutionInfo::new_without_fee_info(
             validate_info,
@@ -274,7 +353,8 @@ impl InvokeFunction {
             revert_error,
             actual_resources,
             Some(self.tx_type),
-        );
+        )
+        .with_fee_breakdown(fee_breakdown);
         Ok(transaction_execution_info)
     }
 
@@ -304,6 +384,7 @@ impl InvokeFunction {
             self.max_fee,
             &mut tx_execution_context,
             self.skip_fee_transfer,
+            tx_exec_info.revert_error.is_some(),
         )?;
 
         tx_exec_info.set_fee_info(actual_fee, fee_transfer_info);
@@ -357,6 +438,13 @@ impl InvokeFunction {
             } else {
                 self.max_fee
             },
+            v3_fields: if ignore_max_fee {
+                self.v3_fields
+                    .as_ref()
+                    .map(V3TransactionFields::with_unbounded_l1_gas)
+            } else {
+                self.v3_fields.clone()
+            },
             ..self.clone()
         };
 
@@ -415,9 +503,9 @@ pub(crate) fn preprocess_invoke_function_fields(
 //      Try from starknet api
 // ----------------------------------
 
-fn convert_invoke_v0(
+pub(crate) fn convert_invoke_v0(
     value: starknet_api::transaction::InvokeTransactionV0,
-    chain_id: StarknetChainId,
+    chain_id: Felt252,
 ) -> Result<InvokeFunction, TransactionError> {
     let contract_address = Address(Felt252::from_bytes_be(
         value.contract_address.0.key().bytes(),
@@ -447,14 +535,14 @@ fn convert_invoke_v0(
         Felt252::new(0),
         calldata,
         signature,
-        chain_id.to_felt(),
+        chain_id,
         nonce,
     )
 }
 
-fn convert_invoke_v1(
+pub(crate) fn convert_invoke_v1(
     value: starknet_api::transaction::InvokeTransactionV1,
-    chain_id: StarknetChainId,
+    chain_id: Felt252,
 ) -> Result<InvokeFunction, TransactionError> {
     let contract_address = Address(Felt252::from_bytes_be(value.sender_address.0.key().bytes()));
     let max_fee = value.max_fee.0;
@@ -482,7 +570,7 @@ fn convert_invoke_v1(
         Felt252::new(1),
         calldata,
         signature,
-        chain_id.to_felt(),
+        chain_id,
         Some(nonce),
     )
 }
@@ -499,7 +587,7 @@ mod tests {
         utils::calculate_sn_keccak,
     };
     use cairo_lang_starknet::casm_contract_class::CasmContractClass;
-    use num_traits::Num;
+    use num_traits::{Num, One};
     use starknet_api::{
         core::{ContractAddress, Nonce, PatriciaKey},
         hash::{StarkFelt, StarkHash},
@@ -595,6 +683,7 @@ mod tests {
             skip_execute: false,
             skip_fee_transfer: false,
             skip_nonce_check: false,
+            v3_fields: None,
         };
 
         // Instantiate CachedState
@@ -667,6 +756,7 @@ mod tests {
             skip_execute: false,
             skip_fee_transfer: false,
             skip_nonce_check: false,
+            v3_fields: None,
         };
 
         // Instantiate CachedState
@@ -718,7 +808,7 @@ mod tests {
     }
 
     #[test]
-    fn test_apply_invoke_entrypoint_not_found_should_fail() {
+    fn test_apply_invoke_entrypoint_not_found_should_revert() {
         let internal_invoke_function = InvokeFunction {
             contract_address: Address(0.into()),
             entry_point_selector: (*EXECUTE_ENTRY_POINT_SELECTOR).clone(),
@@ -735,6 +825,7 @@ mod tests {
             skip_execute: false,
             skip_fee_transfer: false,
             skip_nonce_check: false,
+            v3_fields: None,
         };
 
         // Instantiate CachedState
@@ -765,13 +856,14 @@ mod tests {
             )
             .unwrap();
 
-        let expected_error =
-            internal_invoke_function.apply(&mut state, &BlockContext::default(), 0);
+        let result = internal_invoke_function
+            .apply(&mut state, &BlockContext::default(), 0)
+            .unwrap();
 
-        assert!(expected_error.is_err());
-        assert_matches!(
-            expected_error.unwrap_err(),
-            TransactionError::EntryPointNotFound
+        assert!(result.call_info.is_none());
+        assert_eq!(
+            result.revert_error,
+            Some(TransactionError::EntryPointNotFound.to_string())
         );
     }
 
@@ -797,6 +889,7 @@ mod tests {
             skip_execute: false,
             skip_fee_transfer: false,
             skip_nonce_check: false,
+            v3_fields: None,
         };
 
         // Instantiate CachedState
@@ -865,6 +958,7 @@ mod tests {
             skip_execute: false,
             skip_fee_transfer: false,
             skip_nonce_check: false,
+            v3_fields: None,
         };
 
         // Instantiate CachedState
@@ -943,6 +1037,7 @@ mod tests {
             skip_execute: false,
             skip_fee_transfer: false,
             skip_nonce_check: false,
+            v3_fields: None,
         };
 
         let mut state = CachedState::new(Arc::new(state_reader), HashMap::new());
@@ -987,6 +1082,7 @@ mod tests {
             skip_execute: false,
             skip_fee_transfer: true,
             skip_nonce_check: false,
+            v3_fields: None,
         };
 
         // Instantiate CachedState
@@ -1018,7 +1114,7 @@ mod tests {
             .unwrap();
 
         let mut block_context = BlockContext::default();
-        block_context.starknet_os_config.gas_price = 1;
+        block_context.block_info.gas_price = 1;
 
         let tx = internal_invoke_function
             .execute(&mut state, &block_context, 0)
@@ -1048,6 +1144,7 @@ mod tests {
             skip_execute: false,
             skip_fee_transfer: false,
             skip_nonce_check: false,
+            v3_fields: None,
         };
 
         // Instantiate CachedState
@@ -1114,6 +1211,7 @@ mod tests {
             skip_execute: false,
             skip_fee_transfer: false,
             skip_nonce_check: false,
+            v3_fields: None,
         };
 
         // Instantiate CachedState
@@ -1258,6 +1356,7 @@ mod tests {
             skip_execute: false,
             skip_fee_transfer: true,
             skip_nonce_check: false,
+            v3_fields: None,
         };
 
         let mut state_reader = InMemoryStateReader::default();
@@ -1314,4 +1413,188 @@ mod tests {
                 .class_hash_to_compiled_class_hash
         );
     }
+
+    #[test]
+    fn test_reverted_cairo_0_transaction_charges_consumed_steps() {
+        let max_fee = 5;
+        let internal_invoke_function = InvokeFunction {
+            contract_address: Address(0.into()),
+            entry_point_selector: Felt252::from_bytes_be(&calculate_sn_keccak(b"fib")),
+            entry_point_type: EntryPointType::External,
+            // fib takes three arguments, so the execution fails after running a few steps.
+            calldata: vec![1.into(), 1.into()],
+            tx_type: TransactionType::InvokeFunction,
+            version: 1.into(),
+            validate_entry_point_selector: 0.into(),
+            hash_value: 0.into(),
+            signature: Vec::new(),
+            max_fee,
+            nonce: Some(0.into()),
+            skip_validation: true,
+            skip_execute: false,
+            skip_fee_transfer: true,
+            skip_nonce_check: false,
+            v3_fields: None,
+        };
+
+        let mut state_reader = InMemoryStateReader::default();
+        let class_hash = [1; 32];
+        let contract_class = ContractClass::from_path("starknet_programs/fibonacci.json").unwrap();
+        let contract_address = Address(0.into());
+
+        state_reader
+            .address_to_class_hash_mut()
+            .insert(contract_address.clone(), class_hash);
+        state_reader
+            .address_to_nonce
+            .insert(contract_address.clone(), Felt252::zero());
+
+        let mut state = CachedState::new(Arc::new(state_reader), HashMap::new());
+        state
+            .set_contract_class(
+                &class_hash,
+                &CompiledClass::Deprecated(Arc::new(contract_class)),
+            )
+            .unwrap();
+
+        let mut block_context = BlockContext::default();
+        block_context.block_info.gas_price = 1;
+
+        let not_executed = InvokeFunction {
+            skip_execute: true,
+            ..internal_invoke_function.clone()
+        }
+        .apply(&mut state.clone(), &block_context, 0)
+        .unwrap();
+        let result = internal_invoke_function
+            .execute(&mut state, &block_context, 0)
+            .unwrap();
+
+        assert!(result.call_info.is_none());
+        assert!(result.revert_error.is_some());
+        assert!(result.actual_resources["n_steps"] > not_executed.actual_resources["n_steps"]);
+        // The fee exceeds the max fee, which is charged instead.
+        assert_eq!(result.actual_fee, max_fee);
+        // The nonce is bumped even though the execution was reverted.
+        assert_eq!(
+            state.get_nonce_at(&contract_address).unwrap(),
+            Felt252::one()
+        );
+    }
+
+    #[test]
+    fn test_transaction_exceeding_the_step_limit_is_stopped_and_reverted() {
+        let internal_invoke_function = InvokeFunction {
+            contract_address: Address(0.into()),
+            entry_point_selector: Felt252::from_bytes_be(&calculate_sn_keccak(b"fib")),
+            entry_point_type: EntryPointType::External,
+            // Computing the millionth Fibonacci number takes millions of steps.
+            calldata: vec![1.into(), 1.into(), 1_000_000.into()],
+            tx_type: TransactionType::InvokeFunction,
+            version: 1.into(),
+            validate_entry_point_selector: 0.into(),
+            hash_value: 0.into(),
+            signature: Vec::new(),
+            max_fee: 0,
+            nonce: Some(0.into()),
+            skip_validation: true,
+            skip_execute: false,
+            skip_fee_transfer: true,
+            skip_nonce_check: false,
+            v3_fields: None,
+        };
+
+        let mut state_reader = InMemoryStateReader::default();
+        let class_hash = [1; 32];
+        let contract_class = ContractClass::from_path("starknet_programs/fibonacci.json").unwrap();
+        let contract_address = Address(0.into());
+
+        state_reader
+            .address_to_class_hash_mut()
+            .insert(contract_address.clone(), class_hash);
+        state_reader
+            .address_to_nonce
+            .insert(contract_address, Felt252::zero());
+
+        let mut state = CachedState::new(Arc::new(state_reader), HashMap::new());
+        state
+            .set_contract_class(
+                &class_hash,
+                &CompiledClass::Deprecated(Arc::new(contract_class)),
+            )
+            .unwrap();
+
+        let mut block_context = BlockContext::default();
+        block_context.invoke_tx_max_n_steps = 1000;
+
+        let not_executed = InvokeFunction {
+            skip_execute: true,
+            ..internal_invoke_function.clone()
+        }
+        .apply(&mut state.clone(), &block_context, 0)
+        .unwrap();
+        let result = internal_invoke_function
+            .apply(&mut state, &block_context, 0)
+            .unwrap();
+
+        assert!(result.call_info.is_none());
+        assert_eq!(
+            result.revert_error,
+            Some(TransactionError::StepsLimitExceeded(1000).to_string())
+        );
+        // The execution is charged exactly the steps it was allowed to run.
+        assert_eq!(
+            result.actual_resources["n_steps"],
+            not_executed.actual_resources["n_steps"] + 1000
+        );
+    }
+
+    #[test]
+    fn invoke_v3_commits_to_its_resource_bounds() {
+        let v3_fields = V3TransactionFields {
+            l1_resource_bounds: crate::transaction::ResourceBounds {
+                max_amount: 1000,
+                max_price_per_unit: 5,
+            },
+            tip: 1,
+            ..Default::default()
+        };
+        let invoke = InvokeFunction::new_v3(
+            Address(1111.into()),
+            vec![1.into(), 2.into()],
+            vec![3.into()],
+            StarknetChainId::TestNet.to_felt(),
+            7.into(),
+            v3_fields.clone(),
+        )
+        .unwrap();
+
+        assert_eq!(invoke.version, 3.into());
+        assert_eq!(invoke.max_fee, 5000);
+        assert_eq!(invoke.entry_point_selector, *EXECUTE_ENTRY_POINT_SELECTOR);
+        assert_eq!(
+            invoke.hash_value,
+            calculate_invoke_v3_transaction_hash(
+                3.into(),
+                &Address(1111.into()),
+                &[1.into(), 2.into()],
+                StarknetChainId::TestNet.to_felt(),
+                7.into(),
+                &v3_fields,
+            )
+            .unwrap()
+        );
+
+        let tx_execution_context = invoke.get_execution_context(10).unwrap();
+        assert_eq!(tx_execution_context.v3_fields(), &Some(v3_fields));
+        assert_eq!(tx_execution_context.nonce(), &Felt252::from(7));
+
+        // Simulations that ignore the max fee also ignore the L1 gas bounds.
+        match invoke.create_for_simulation(false, false, false, true, false) {
+            Transaction::InvokeFunction(simulated) => {
+                assert_eq!(simulated.v3_fields().as_ref().unwrap().max_fee(), u128::MAX);
+            }
+            _ => unreachable!(),
+        }
+    }
 }
@@ -10,7 +10,8 @@ use num_traits::Zero;
 use crate::{
     core::transaction_hash::{calculate_transaction_hash_common, TransactionHashPrefix},
     definitions::{
-        block_context::BlockContext, constants::L1_HANDLER_VERSION,
+        block_context::{BlockContext, FeeType},
+        constants::L1_HANDLER_VERSION,
         transaction_type::TransactionType,
     },
     execution::{
@@ -129,13 +130,15 @@ impl L1Handler {
         };
 
         let changes = state.count_actual_storage_changes(None)?;
-        let actual_resources = calculate_tx_resources(
+        let (actual_resources, fee_breakdown) = calculate_tx_resources(
             resources_manager,
             &[call_info.clone()],
             TransactionType::L1Handler,
+            self.calldata.len(),
             changes,
             Some(self.get_payload_size()),
             n_reverted_steps,
+            block_context,
         )?;
 
         // Enforce L1 fees.
@@ -144,11 +147,8 @@ impl L1Handler {
             // L1 handler fee is enforced, and paid_fee_on_l1 is None; If this is the case,
             // the transaction is an old transaction.
             if let Some(paid_fee) = self.paid_fee_on_l1.clone() {
-                let required_fee = calculate_tx_fee(
-                    &actual_resources,
-                    block_context.starknet_os_config.gas_price,
-                    block_context,
-                )?;
+                let required_fee =
+                    calculate_tx_fee(&actual_resources, FeeType::Eth, block_context)?;
                 // For now, assert only that any amount of fee was paid.
                 if paid_fee.is_zero() {
                     return Err(TransactionError::FeeError(format!(
@@ -164,7 +164,8 @@ impl L1Handler {
             revert_error,
             actual_resources,
             Some(TransactionType::L1Handler),
-        ))
+        )
+        .with_fee_breakdown(fee_breakdown))
     }
 
     /// Returns the payload size of the corresponding L1-to-L2 message.
@@ -221,14 +222,17 @@ mod test {
     use num_traits::{Num, Zero};
 
     use crate::{
-        definitions::{block_context::BlockContext, transaction_type::TransactionType},
-        execution::{CallInfo, TransactionExecutionInfo},
+        definitions::{
+            block_context::BlockContext, constants::DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS,
+            transaction_type::TransactionType,
+        },
+        execution::{gas_usage::L1GasUsage, CallInfo, TransactionExecutionInfo},
         services::api::contract_classes::deprecated_contract_class::ContractClass,
         state::{
             cached_state::CachedState, in_memory_state_reader::InMemoryStateReader,
             state_api::State,
         },
-        transaction::l1_handler::L1Handler,
+        transaction::{fee::FeeBreakdown, l1_handler::L1Handler},
         utils::Address,
     };
 
@@ -281,7 +285,7 @@ mod test {
             .unwrap();
 
         let mut block_context = BlockContext::default();
-        block_context.starknet_os_config.gas_price = 1;
+        block_context.block_info.gas_price = 1;
 
         let tx_exec = l1_handler
             .execute(&mut state, &block_context, 100000)
@@ -342,6 +346,33 @@ mod test {
                 ("l1_gas_usage".to_string(), 18471),
             ]),
             tx_type: Some(TransactionType::L1Handler),
+            fee_breakdown: Some(FeeBreakdown {
+                cairo_resources_l1_gas: DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS
+                    .keys()
+                    .map(|resource| (resource.clone(), 0.0))
+                    .chain([
+                        ("n_steps".to_string(), 1319.0 * 0.01),
+                        ("pedersen_builtin".to_string(), 13.0 * 0.32),
+                        ("range_check_builtin".to_string(), 23.0 * 0.16),
+                    ])
+                    .collect(),
+                bottleneck_resource: Some("n_steps".to_string()),
+                l1_gas_usage: L1GasUsage {
+                    storage_diffs: 2448,
+                    l1_handler_payload: 16023,
+                    ..Default::default()
+                },
+                // The resources of the L1 handler transaction, a storage read and a storage write.
+                os_resources: ExecutionResources {
+                    n_steps: 1158,
+                    n_memory_holes: 0,
+                    builtin_instance_counter: HashMap::from([
+                        ("range_check_builtin".to_string(), 17),
+                        ("pedersen_builtin".to_string(), 11),
+                    ]),
+                },
+                total_l1_gas: 18485,
+            }),
         }
     }
 }
@@ -1,3 +1,4 @@
+pub mod conversions;
 pub mod declare;
 pub mod declare_v2;
 pub mod deploy;
@@ -6,23 +7,37 @@ pub mod error;
 pub mod fee;
 pub mod invoke_function;
 pub mod l1_handler;
+pub mod resource_bounds;
 mod verify_version;
 
+pub use conversions::{ApiTransaction, TransactionClass};
 pub use declare::Declare;
 pub use declare_v2::DeclareV2;
 pub use deploy::Deploy;
 pub use deploy_account::DeployAccount;
 pub use invoke_function::InvokeFunction;
 pub use l1_handler::L1Handler;
+pub use resource_bounds::{DataAvailabilityMode, ResourceBounds, V3TransactionFields};
 pub use verify_version::verify_version;
 
 use crate::{
-    definitions::block_context::BlockContext,
-    execution::TransactionExecutionInfo,
-    state::{cached_state::CachedState, state_api::StateReader},
-    utils::Address,
+    definitions::{
+        block_context::{BlockContext, FeeType},
+        constants::{INITIAL_GAS_COST, L1_HANDLER_VERSION, QUERY_VERSION_BASE, SUPPORTED_VERSIONS},
+        transaction_type::TransactionType,
+    },
+    execution::{CallInfo, TransactionExecutionInfo},
+    state::{
+        cached_state::CachedState,
+        state_api::{State, StateReader},
+        ExecutionResourcesManager,
+    },
+    utils::{get_erc20_balance_var_addresses, Address},
 };
-use error::TransactionError;
+use cairo_vm::felt::Felt252;
+use error::{TransactionError, TransactionRejection};
+use num_bigint::BigUint;
+use num_traits::Zero;
 
 /// Represents a transaction inside the starknet network.
 /// The transaction are actions that may modified the state of the network.
@@ -61,6 +76,41 @@ impl Transaction {
         }
     }
 
+    /// returns the hash of the transaction.
+    pub fn hash_value(&self) -> Felt252 {
+        match self {
+            Transaction::Deploy(tx) => tx.hash_value.clone(),
+            Transaction::InvokeFunction(tx) => tx.hash_value().clone(),
+            Transaction::Declare(tx) => tx.hash_value.clone(),
+            Transaction::DeclareV2(tx) => tx.hash_value.clone(),
+            Transaction::DeployAccount(tx) => tx.hash_value().clone(),
+            Transaction::L1Handler(tx) => tx.hash_value().clone(),
+        }
+    }
+
+    /// returns the version of the transaction.
+    pub fn version(&self) -> Felt252 {
+        match self {
+            Transaction::Deploy(tx) => tx.version.clone(),
+            Transaction::InvokeFunction(tx) => tx.version().clone(),
+            Transaction::Declare(tx) => tx.version.clone(),
+            Transaction::DeclareV2(tx) => tx.version.clone(),
+            Transaction::DeployAccount(tx) => tx.version().clone(),
+            Transaction::L1Handler(_) => L1_HANDLER_VERSION.into(),
+        }
+    }
+
+    /// returns the type of the transaction.
+    pub fn tx_type(&self) -> TransactionType {
+        match self {
+            Transaction::Deploy(_) => TransactionType::Deploy,
+            Transaction::InvokeFunction(_) => TransactionType::InvokeFunction,
+            Transaction::Declare(_) | Transaction::DeclareV2(_) => TransactionType::Declare,
+            Transaction::DeployAccount(_) => TransactionType::DeployAccount,
+            Transaction::L1Handler(_) => TransactionType::L1Handler,
+        }
+    }
+
     /// execute the transaction in cairo-vm and returns a TransactionExecutionInfo structure.
     ///## Parameters:
     ///- state: a structure that implements State and StateReader traits.
@@ -81,6 +131,147 @@ impl Transaction {
             Transaction::L1Handler(tx) => tx.execute(state, block_context, remaining_gas),
         }
     }
+
+    /// Checks whether an account transaction can be admitted to the mempool, without executing
+    /// it: its version must be supported, its nonce can be ahead of the account nonce by at most
+    /// `max_nonce_gap`, the fee token balance of the sender must cover its max fee, and its
+    /// validation entry point must succeed within `validate_max_n_steps`.
+    /// Returns the call info of the validation.
+    ///## Parameters:
+    ///- state: a cached state, whose changes should be discarded afterwards.
+    ///- block_context: The block context the transaction would be executed in.
+    ///- max_nonce_gap: How far ahead of the account nonce the nonce of the transaction can be.
+    pub fn validate<S: StateReader>(
+        &self,
+        state: &mut CachedState<S>,
+        block_context: &BlockContext,
+        max_nonce_gap: u64,
+    ) -> Result<Option<CallInfo>, TransactionRejection> {
+        let (sender_address, version, nonce, max_fee) = match self {
+            Transaction::Declare(tx) => {
+                (&tx.sender_address, &tx.version, Some(&tx.nonce), tx.max_fee)
+            }
+            Transaction::DeclareV2(tx) => {
+                (&tx.sender_address, &tx.version, Some(&tx.nonce), tx.max_fee)
+            }
+            Transaction::DeployAccount(tx) => (
+                tx.contract_address(),
+                tx.version(),
+                Some(tx.nonce()),
+                *tx.max_fee(),
+            ),
+            Transaction::InvokeFunction(tx) => (
+                tx.contract_address(),
+                tx.version(),
+                tx.nonce().as_ref(),
+                *tx.max_fee(),
+            ),
+            Transaction::Deploy(_) | Transaction::L1Handler(_) => {
+                return Err(TransactionRejection::NotAnAccountTransaction(
+                    self.tx_type(),
+                ))
+            }
+        };
+
+        if !SUPPORTED_VERSIONS.contains(version) {
+            return Err(TransactionRejection::UnsupportedVersion(version.clone()));
+        }
+
+        // Transactions of version 0 don't bump the nonce of their sender.
+        let is_version_0 = version.is_zero() || *version == *QUERY_VERSION_BASE;
+        if let Some(nonce) = nonce.filter(|_| !is_version_0) {
+            let account_nonce = state.get_nonce_at(sender_address)?;
+            if *nonce < account_nonce {
+                return Err(TransactionRejection::NonceTooOld {
+                    expected: account_nonce,
+                    actual: nonce.clone(),
+                });
+            }
+            let max_nonce = &account_nonce + &Felt252::from(max_nonce_gap);
+            if *nonce > max_nonce {
+                return Err(TransactionRejection::NonceTooFarAhead {
+                    max: max_nonce,
+                    actual: nonce.clone(),
+                });
+            }
+        }
+
+        let fee_token_address = block_context
+            .starknet_os_config
+            .fee_token_address_by_type(FeeType::from_version(version));
+        let (low_key, high_key) = get_erc20_balance_var_addresses(sender_address)
+            .map_err(|e| TransactionRejection::State(e.into()))?;
+        let balance = state
+            .get_storage_at(&(fee_token_address.clone(), low_key))?
+            .to_biguint()
+            + (state
+                .get_storage_at(&(fee_token_address.clone(), high_key))?
+                .to_biguint()
+                << 128);
+        if balance < BigUint::from(max_fee) {
+            return Err(TransactionRejection::InsufficientBalance { balance, max_fee });
+        }
+
+        // A validation that never ends is stopped with this error once it runs out of steps.
+        let steps_limit_exceeded =
+            TransactionError::StepsLimitExceeded(block_context.validate_max_n_steps);
+        let mut resources_manager = ExecutionResourcesManager::default();
+        let validate_info = match self {
+            Transaction::Declare(tx) => {
+                tx.run_validate_entrypoint(state, &mut resources_manager, block_context)
+            }
+            Transaction::DeclareV2(tx) => tx
+                .run_validate_entrypoint(
+                    INITIAL_GAS_COST,
+                    state,
+                    &mut resources_manager,
+                    block_context,
+                )
+                .and_then(
+                    |(execution_result, _)| match execution_result.revert_error {
+                        // Validation is reverted instead of failing, so the only trace of running
+                        // out of steps is its revert error.
+                        Some(revert_error) if revert_error == steps_limit_exceeded.to_string() => {
+                            Err(steps_limit_exceeded)
+                        }
+                        Some(revert_error) => Err(TransactionError::CustomError(revert_error)),
+                        None => Ok(execution_result.call_info),
+                    },
+                ),
+            Transaction::DeployAccount(tx) => {
+                // The account is validated as if it were already deployed.
+                state.deploy_contract(tx.contract_address().clone(), *tx.class_hash())?;
+                tx.run_validate_entrypoint(state, &mut resources_manager, block_context)
+            }
+            Transaction::InvokeFunction(tx) => {
+                tx.run_validate_entrypoint(state, &mut resources_manager, block_context)
+            }
+            Transaction::Deploy(_) | Transaction::L1Handler(_) => Ok(None),
+        }
+        .map_err(|e| match e {
+            // The validation ran out of steps before it was done.
+            TransactionError::StepsLimitExceeded(max_n_steps) => {
+                TransactionRejection::ValidateStepsExceeded {
+                    n_steps: max_n_steps as usize + 1,
+                    max_n_steps,
+                }
+            }
+            e => TransactionRejection::ValidationFailure(e.to_string()),
+        })?;
+
+        if let Some(call_info) = &validate_info {
+            let n_steps = call_info.execution_resources.n_steps;
+            if n_steps as u64 > block_context.validate_max_n_steps {
+                return Err(TransactionRejection::ValidateStepsExceeded {
+                    n_steps,
+                    max_n_steps: block_context.validate_max_n_steps,
+                });
+            }
+        }
+
+        Ok(validate_info)
+    }
+
     /// It creates a new transaction structure modificating the skip flags. It is meant to be used only to run a simulation
     ///## Parameters:
     ///- skip_validate: the transaction will not be verified.
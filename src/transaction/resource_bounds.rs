@@ -0,0 +1,112 @@
+use cairo_vm::felt::Felt252;
+use serde::{Deserialize, Serialize};
+
+/// The maximum amount of a resource a transaction may use, and the maximum price it is willing
+/// to pay for each unit of it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceBounds {
+    pub max_amount: u64,
+    pub max_price_per_unit: u128,
+}
+
+impl ResourceBounds {
+    /// Returns the most a transaction can pay for this resource.
+    pub fn max_fee(&self) -> u128 {
+        (self.max_amount as u128).saturating_mul(self.max_price_per_unit)
+    }
+}
+
+/// Where the data of the nonce or the fee of a transaction is made available.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DataAvailabilityMode {
+    #[default]
+    L1,
+    L2,
+}
+
+impl From<DataAvailabilityMode> for u32 {
+    fn from(mode: DataAvailabilityMode) -> Self {
+        match mode {
+            DataAvailabilityMode::L1 => 0,
+            DataAvailabilityMode::L2 => 1,
+        }
+    }
+}
+
+impl From<DataAvailabilityMode> for Felt252 {
+    fn from(mode: DataAvailabilityMode) -> Self {
+        u32::from(mode).into()
+    }
+}
+
+/// The fields of version 3 transactions. They replace the `max_fee` of the previous versions,
+/// whose fees are paid in ETH, with resource bounds, as fees are paid in STRK.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct V3TransactionFields {
+    /// The bounds of the L1 gas used by the transaction.
+    pub l1_resource_bounds: ResourceBounds,
+    /// The bounds of the L2 gas used by the transaction.
+    pub l2_resource_bounds: ResourceBounds,
+    /// The tip paid to the sequencer on top of the fee. It's part of the transaction hash and
+    /// it's given to contracts in the execution info, but, as in Starknet, it isn't charged yet:
+    /// the fee only covers the L1 gas used.
+    pub tip: u64,
+    /// Data for a paymaster to pay the fee of the transaction.
+    pub paymaster_data: Vec<Felt252>,
+    pub nonce_data_availability_mode: DataAvailabilityMode,
+    pub fee_data_availability_mode: DataAvailabilityMode,
+    /// Data used to deploy the account sending the transaction. It's always empty for
+    /// `DeployAccount` transactions.
+    pub account_deployment_data: Vec<Felt252>,
+}
+
+impl V3TransactionFields {
+    /// Returns the most the transaction can pay for the L1 gas it uses, which takes the place of
+    /// the `max_fee` of the previous transaction versions.
+    pub fn max_fee(&self) -> u128 {
+        self.l1_resource_bounds.max_fee()
+    }
+
+    /// Returns the fields with L1 gas bounds that never limit the transaction, for simulations
+    /// that ignore the max fee.
+    pub(crate) fn with_unbounded_l1_gas(&self) -> Self {
+        Self {
+            l1_resource_bounds: ResourceBounds {
+                max_amount: u64::MAX,
+                max_price_per_unit: u128::MAX,
+            },
+            ..self.clone()
+        }
+    }
+
+    /// Returns the resource bounds as they are given to contracts and hashed: the short string
+    /// name of each resource with its bounds.
+    pub(crate) fn resource_bounds(&self) -> [(&'static [u8], ResourceBounds); 2] {
+        [
+            (b"L1_GAS", self.l1_resource_bounds),
+            (b"L2_GAS", self.l2_resource_bounds),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_fee_is_bounded_by_l1_gas() {
+        let fields = V3TransactionFields {
+            l1_resource_bounds: ResourceBounds {
+                max_amount: 10,
+                max_price_per_unit: 7,
+            },
+            l2_resource_bounds: ResourceBounds {
+                max_amount: 100,
+                max_price_per_unit: 100,
+            },
+            ..Default::default()
+        };
+        assert_eq!(fields.max_fee(), 70);
+        assert_eq!(fields.with_unbounded_l1_gas().max_fee(), u128::MAX);
+    }
+}
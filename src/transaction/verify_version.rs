@@ -67,8 +67,18 @@ mod test {
     }
 
     #[test]
-    fn version_3_should_fail() {
+    fn version_3_should_return_ok() {
         let version = 3.into();
+        let max_fee = 70;
+        let nonce = 4.into();
+        let signature = vec![6.into()];
+        let result = verify_version(&version, max_fee, &nonce, &signature);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn version_4_should_fail() {
+        let version = 4.into();
         let max_fee = 0;
         let nonce = 0.into();
         let signature = vec![];
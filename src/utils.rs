@@ -2,14 +2,17 @@ use crate::core::errors::hash_errors::HashError;
 use crate::services::api::contract_classes::deprecated_contract_class::EntryPointType;
 use crate::state::state_api::State;
 use crate::{
-    definitions::transaction_type::TransactionType,
+    definitions::{block_context::BlockContext, transaction_type::TransactionType},
     execution::{
-        gas_usage::calculate_tx_gas_usage, os_usage::get_additional_os_resources, CallInfo,
+        gas_usage::calculate_tx_gas_usage_breakdown, os_usage::get_additional_os_resources,
+        CallInfo,
     },
     state::ExecutionResourcesManager,
-    state::{cached_state::UNINITIALIZED_CLASS_HASH, state_cache::StorageEntry},
+    state::{
+        cached_state::UNINITIALIZED_CLASS_HASH, state_cache::StorageEntry, L1DataAvailabilityMode,
+    },
     syscalls::syscall_handler_errors::SyscallHandlerError,
-    transaction::error::TransactionError,
+    transaction::{error::TransactionError, fee::FeeBreakdown},
 };
 use cairo_vm::vm::runners::builtin_runner::SEGMENT_ARENA_BUILTIN_NAME;
 use cairo_vm::{
@@ -99,13 +102,13 @@ pub fn get_felt_range(
     get_integer_range(vm, start_addr, size)
 }
 
-pub fn felt_to_field_element(value: &Felt252) -> Result<FieldElement, SyscallHandlerError> {
-    FieldElement::from_dec_str(&value.to_str_radix(10))
-        .map_err(|e| SyscallHandlerError::HashError(HashError::FailedToComputeHash(e.to_string())))
+pub fn felt_to_field_element(value: &Felt252) -> Result<FieldElement, HashError> {
+    FieldElement::from_bytes_be(&value.to_be_bytes())
+        .map_err(|_| HashError::FailedToComputeHash(format!("{value} is not a field element")))
 }
 
-pub fn field_element_to_felt(felt: &FieldElement) -> Felt252 {
-    Felt252::from_bytes_be(&felt.to_bytes_be())
+pub fn field_element_to_felt(value: &FieldElement) -> Felt252 {
+    Felt252::from_bytes_be(&value.to_bytes_be())
 }
 
 pub fn felt_to_hash(value: &Felt252) -> ClassHash {
@@ -117,6 +120,42 @@ pub fn felt_to_hash(value: &Felt252) -> ClassHash {
     output
 }
 
+/// Returns the hexadecimal representation of a felt, prefixed with `0x` and without leading
+/// zeros, as felts are represented in the JSON-RPC.
+pub fn felt_to_hex(felt: &Felt252) -> String {
+    format!("0x{}", felt.to_str_radix(16))
+}
+
+/// Returns the hexadecimal representations of some felts, as given by [felt_to_hex].
+pub fn felts_to_hex(felts: &[Felt252]) -> Vec<String> {
+    felts.iter().map(felt_to_hex).collect()
+}
+
+/// Returns the hexadecimal representation of a hash, in the same format as [felt_to_hex]. The
+/// hash isn't converted to a felt, so hashes above the field prime are kept as they are.
+pub fn hash_to_hex(hash: &[u8; 32]) -> String {
+    let hex = hex::encode(hash);
+    match hex.trim_start_matches('0') {
+        "" => "0x0".to_string(),
+        digits => format!("0x{digits}"),
+    }
+}
+
+/// Parses a hexadecimal felt, with or without the `0x` prefix.
+pub fn felt_from_hex(value: &str) -> Option<Felt252> {
+    Felt252::from_str_radix(value.strip_prefix("0x").unwrap_or(value), 16).ok()
+}
+
+/// Parses a hexadecimal hash, with or without the `0x` prefix and the leading zeros. It's the
+/// inverse of [hash_to_hex].
+pub fn hash_from_hex(value: &str) -> Option<[u8; 32]> {
+    let digits = value.strip_prefix("0x").unwrap_or(value);
+    if digits.is_empty() || digits.len() > 64 {
+        return None;
+    }
+    hex::decode(format!("{digits:0>64}")).ok()?.try_into().ok()
+}
+
 pub fn string_to_hash(class_string: &String) -> ClassHash {
     let parsed_felt = Felt252::from_str_radix(
         if &class_string[..2] == "0x" {
@@ -165,14 +204,17 @@ pub fn get_call_n_deployments(call_info: &CallInfo) -> usize {
         })
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn calculate_tx_resources(
     resources_manager: ExecutionResourcesManager,
     call_info: &[Option<CallInfo>],
     tx_type: TransactionType,
+    calldata_length: usize,
     storage_changes: (usize, usize),
     l1_handler_payload_size: Option<usize>,
     n_reverted_steps: usize,
-) -> Result<HashMap<String, usize>, TransactionError> {
+    block_context: &BlockContext,
+) -> Result<(HashMap<String, usize>, FeeBreakdown), TransactionError> {
     let (n_modified_contracts, n_storage_changes) = storage_changes;
 
     let non_optional_calls: Vec<CallInfo> = call_info.iter().flatten().cloned().collect();
@@ -184,19 +226,25 @@ pub fn calculate_tx_resources(
         l2_to_l1_messages.extend(call_info.get_sorted_l2_to_l1_messages()?)
     }
 
-    let l1_gas_usage = calculate_tx_gas_usage(
-        l2_to_l1_messages,
+    let l1_gas_usage = calculate_tx_gas_usage_breakdown(
+        &l2_to_l1_messages,
         n_modified_contracts,
         n_storage_changes,
         l1_handler_payload_size,
         n_deployments,
+        block_context.block_info.l1_da_mode,
     );
 
     let cairo_usage = resources_manager.cairo_usage.clone();
     let tx_syscall_counter = resources_manager.syscall_counter;
 
     // Add additional Cairo resources needed for the OS to run the transaction.
-    let additional_resources = get_additional_os_resources(tx_syscall_counter, &tx_type)?;
+    let additional_resources = get_additional_os_resources(
+        tx_syscall_counter,
+        &tx_type,
+        calldata_length,
+        block_context.versioned_constants().os_resources(),
+    )?;
     let new_resources = &cairo_usage + &additional_resources;
     let mut filtered_builtins = new_resources.filter_unused_builtins();
 
@@ -208,7 +256,10 @@ pub fn calculate_tx_resources(
             .unwrap_or(0);
 
     let mut resources: HashMap<String, usize> = HashMap::new();
-    resources.insert("l1_gas_usage".to_string(), l1_gas_usage);
+    resources.insert("l1_gas_usage".to_string(), l1_gas_usage.l1_gas());
+    if l1_gas_usage.l1_da_mode == L1DataAvailabilityMode::Blob {
+        resources.insert("l1_data_gas_usage".to_string(), l1_gas_usage.l1_data_gas());
+    }
     resources.insert(
         "n_steps".to_string(),
         n_steps + filtered_builtins.n_memory_holes,
@@ -217,7 +268,13 @@ pub fn calculate_tx_resources(
         resources.insert(builtin, value);
     }
 
-    Ok(resources)
+    let fee_breakdown = FeeBreakdown::new(
+        &resources,
+        l1_gas_usage,
+        additional_resources,
+        block_context,
+    );
+    Ok((resources, fee_breakdown))
 }
 
 /// Returns a mapping containing key-value pairs from a that are not included in b (if
@@ -733,4 +790,22 @@ mod test {
             ],
         );
     }
+
+    #[test]
+    fn test_felt_and_hash_hex_conversions() {
+        assert_eq!(felt_to_hex(&Felt252::zero()), "0x0");
+        assert_eq!(felt_to_hex(&257.into()), "0x101");
+        assert_eq!(hash_to_hex(&[0; 32]), "0x0");
+        assert_eq!(hash_to_hex(&felt_to_hash(&257.into())), "0x101");
+        // Hashes above the field prime are kept as they are.
+        assert_eq!(hash_to_hex(&[0xff; 32]), format!("0x{}", "ff".repeat(32)));
+
+        assert_eq!(felt_from_hex("0x101"), Some(257.into()));
+        assert_eq!(felt_from_hex("101"), Some(257.into()));
+        assert_eq!(felt_from_hex("0xz"), None);
+        assert_eq!(hash_from_hex("0x101"), Some(felt_to_hash(&257.into())));
+        assert_eq!(hash_from_hex(&hash_to_hex(&[0xff; 32])), Some([0xff; 32]));
+        assert_eq!(hash_from_hex(&"1".repeat(65)), None);
+        assert_eq!(hash_from_hex("0x"), None);
+    }
 }
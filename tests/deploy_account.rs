@@ -7,15 +7,16 @@ use num_traits::Zero;
 use starknet_in_rust::{
     core::contract_address::compute_deprecated_class_hash,
     definitions::{
-        block_context::StarknetChainId, constants::CONSTRUCTOR_ENTRY_POINT_SELECTOR,
+        block_context::StarknetChainId,
+        constants::{CONSTRUCTOR_ENTRY_POINT_SELECTOR, DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS},
         transaction_type::TransactionType,
     },
-    execution::{CallInfo, CallType, TransactionExecutionInfo},
+    execution::{gas_usage::L1GasUsage, CallInfo, CallType, TransactionExecutionInfo},
     hash_utils::calculate_contract_address,
     services::api::contract_classes::deprecated_contract_class::ContractClass,
     state::in_memory_state_reader::InMemoryStateReader,
     state::{cached_state::CachedState, state_api::State},
-    transaction::DeployAccount,
+    transaction::{fee::FeeBreakdown, DeployAccount},
     utils::Address,
     CasmContractClass,
 };
@@ -110,7 +111,33 @@ fn internal_deploy_account() {
             .map(|(k, v)| (k.to_string(), v))
             .collect(),
             Some(TransactionType::DeployAccount),
-        ),
+        )
+        .with_fee_breakdown(FeeBreakdown {
+            cairo_resources_l1_gas: DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS
+                .keys()
+                .map(|resource| (resource.clone(), 0.0))
+                .chain([
+                    ("n_steps".to_string(), 3612.0 * 0.01),
+                    ("pedersen_builtin".to_string(), 23.0 * 0.32),
+                    ("range_check_builtin".to_string(), 83.0 * 0.16),
+                ])
+                .collect(),
+            bottleneck_resource: Some("n_steps".to_string()),
+            l1_gas_usage: L1GasUsage {
+                storage_diffs: 2448,
+                contract_deployments: 1224,
+                ..Default::default()
+            },
+            os_resources: ExecutionResources {
+                n_steps: 3612,
+                n_memory_holes: 0,
+                builtin_instance_counter: HashMap::from([
+                    ("pedersen_builtin".to_string(), 23),
+                    ("range_check_builtin".to_string(), 83),
+                ]),
+            },
+            total_l1_gas: 3709,
+        }),
     );
 }
 
@@ -270,6 +297,32 @@ fn internal_deploy_account_cairo1() {
             .map(|(k, v)| (k.to_string(), v))
             .collect(),
             Some(TransactionType::DeployAccount),
-        ),
+        )
+        .with_fee_breakdown(FeeBreakdown {
+            cairo_resources_l1_gas: DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS
+                .keys()
+                .map(|resource| (resource.clone(), 0.0))
+                .chain([
+                    ("n_steps".to_string(), n_steps as f64 * 0.01),
+                    ("pedersen_builtin".to_string(), 23.0 * 0.32),
+                    ("range_check_builtin".to_string(), 87.0 * 0.16),
+                ])
+                .collect(),
+            bottleneck_resource: Some("n_steps".to_string()),
+            l1_gas_usage: L1GasUsage {
+                storage_diffs: 3672,
+                contract_deployments: 1224,
+                ..Default::default()
+            },
+            os_resources: ExecutionResources {
+                n_steps: 3687,
+                n_memory_holes: 0,
+                builtin_instance_counter: HashMap::from([
+                    ("pedersen_builtin".to_string(), 23),
+                    ("range_check_builtin".to_string(), 83),
+                ]),
+            },
+            total_l1_gas: 4936,
+        }),
     );
 }
@@ -3,12 +3,7 @@ use assert_matches::assert_matches;
 use cairo_lang_starknet::contract_class::ContractClass as SierraContractClass;
 use cairo_vm::felt::{felt_str, Felt252};
 use cairo_vm::vm::runners::builtin_runner::{HASH_BUILTIN_NAME, RANGE_CHECK_BUILTIN_NAME};
-use cairo_vm::vm::{
-    errors::{
-        cairo_run_errors::CairoRunError, vm_errors::VirtualMachineError, vm_exception::VmException,
-    },
-    runners::cairo_runner::ExecutionResources,
-};
+use cairo_vm::vm::runners::cairo_runner::ExecutionResources;
 use lazy_static::lazy_static;
 use num_bigint::BigUint;
 use num_traits::{FromPrimitive, Num, One, Zero};
@@ -24,13 +19,13 @@ use starknet_in_rust::execution::TransactionExecutionContext;
 use starknet_in_rust::services::api::contract_classes::compiled_class::CompiledClass;
 use starknet_in_rust::services::api::contract_classes::deprecated_contract_class::ContractClass;
 use starknet_in_rust::state::ExecutionResourcesManager;
-use starknet_in_rust::transaction::fee::calculate_tx_fee;
+use starknet_in_rust::transaction::fee::{calculate_tx_fee, FeeBreakdown};
 use starknet_in_rust::transaction::{DeclareV2, Deploy};
 use starknet_in_rust::CasmContractClass;
 use starknet_in_rust::EntryPointType;
 use starknet_in_rust::{
     definitions::{
-        block_context::{BlockContext, StarknetChainId, StarknetOsConfig},
+        block_context::{BlockContext, FeeType, StarknetChainId, StarknetOsConfig},
         constants::{
             CONSTRUCTOR_ENTRY_POINT_SELECTOR, EXECUTE_ENTRY_POINT_SELECTOR, TRANSACTION_VERSION,
             TRANSFER_ENTRY_POINT_SELECTOR, TRANSFER_EVENT_SELECTOR,
@@ -38,7 +33,9 @@ use starknet_in_rust::{
         },
         transaction_type::TransactionType,
     },
-    execution::{CallInfo, CallType, OrderedEvent, TransactionExecutionInfo},
+    execution::{
+        gas_usage::L1GasUsage, CallInfo, CallType, OrderedEvent, TransactionExecutionInfo,
+    },
     state::in_memory_state_reader::InMemoryStateReader,
     state::{
         cached_state::CachedState,
@@ -105,14 +102,18 @@ pub fn new_starknet_block_context_for_testing() -> BlockContext {
         StarknetOsConfig::new(
             StarknetChainId::TestNet.to_felt(),
             TEST_ERC20_CONTRACT_ADDRESS.clone(),
-            *GAS_PRICE,
+            TEST_ERC20_CONTRACT_ADDRESS.clone(),
         ),
         0,
         0,
         DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS.clone(),
         1_000_000,
-        0,
-        BlockInfo::empty(TEST_SEQUENCER_ADDRESS.clone()),
+        1_000_000,
+        BlockInfo {
+            gas_price: *GAS_PRICE,
+            strk_gas_price: *GAS_PRICE,
+            ..BlockInfo::empty(TEST_SEQUENCER_ADDRESS.clone())
+        },
         HashMap::default(),
         true,
     )
@@ -337,6 +338,7 @@ fn state_cache_after_invoke_tx(fee: u128) -> StateCache {
         nonce_writes,
         storage_writes,
         compiled_class_hash,
+        HashSet::new(),
     )
 }
 
@@ -779,6 +781,7 @@ fn declarev2_tx() -> DeclareV2 {
         skip_execute: false,
         skip_fee_transfer: false,
         skip_validate: false,
+        v3_fields: None,
     }
 }
 
@@ -939,7 +942,7 @@ fn test_declare_tx() {
         ("pedersen_builtin".to_string(), 15),
         ("l1_gas_usage".to_string(), 2448),
     ]);
-    let fee = calculate_tx_fee(&resources, *GAS_PRICE, &block_context).unwrap();
+    let fee = calculate_tx_fee(&resources, FeeType::Eth, &block_context).unwrap();
 
     let expected_execution_info = TransactionExecutionInfo::new(
         Some(CallInfo {
@@ -961,7 +964,32 @@ fn test_declare_tx() {
         fee,
         resources,
         Some(TransactionType::Declare),
-    );
+    )
+    .with_fee_breakdown(FeeBreakdown {
+        cairo_resources_l1_gas: DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS
+            .keys()
+            .map(|resource| (resource.clone(), 0.0))
+            .chain([
+                ("n_steps".to_string(), 2715.0 * 0.01),
+                ("range_check_builtin".to_string(), 63.0 * 0.16),
+                ("pedersen_builtin".to_string(), 15.0 * 0.32),
+            ])
+            .collect(),
+        bottleneck_resource: Some("n_steps".to_string()),
+        l1_gas_usage: L1GasUsage {
+            storage_diffs: 2448,
+            ..Default::default()
+        },
+        os_resources: ExecutionResources {
+            n_steps: 2703,
+            n_memory_holes: 0,
+            builtin_instance_counter: HashMap::from([
+                ("pedersen_builtin".to_string(), 15),
+                ("range_check_builtin".to_string(), 63),
+            ]),
+        },
+        total_l1_gas: 2476,
+    });
 
     assert_eq!(result, expected_execution_info);
 }
@@ -1027,7 +1055,7 @@ fn test_declarev2_tx() {
         ("pedersen_builtin".to_string(), 15),
         ("l1_gas_usage".to_string(), 1224),
     ]);
-    let fee = calculate_tx_fee(&resources, *GAS_PRICE, &block_context).unwrap();
+    let fee = calculate_tx_fee(&resources, FeeType::Eth, &block_context).unwrap();
 
     let contract_hash;
     #[cfg(not(feature = "cairo_1_tests"))]
@@ -1058,7 +1086,32 @@ fn test_declarev2_tx() {
         fee,
         resources,
         Some(TransactionType::Declare),
-    );
+    )
+    .with_fee_breakdown(FeeBreakdown {
+        cairo_resources_l1_gas: DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS
+            .keys()
+            .map(|resource| (resource.clone(), 0.0))
+            .chain([
+                ("n_steps".to_string(), 2715.0 * 0.01),
+                ("range_check_builtin".to_string(), 63.0 * 0.16),
+                ("pedersen_builtin".to_string(), 15.0 * 0.32),
+            ])
+            .collect(),
+        bottleneck_resource: Some("n_steps".to_string()),
+        l1_gas_usage: L1GasUsage {
+            storage_diffs: 1224,
+            ..Default::default()
+        },
+        os_resources: ExecutionResources {
+            n_steps: 2703,
+            n_memory_holes: 0,
+            builtin_instance_counter: HashMap::from([
+                ("pedersen_builtin".to_string(), 15),
+                ("range_check_builtin".to_string(), 63),
+            ]),
+        },
+        total_l1_gas: 1252,
+    });
 
     assert_eq!(result, expected_execution_info);
 }
@@ -1246,7 +1299,7 @@ fn expected_transaction_execution_info(block_context: &BlockContext) -> Transact
         ("l1_gas_usage".to_string(), 2448),
         ("range_check_builtin".to_string(), 101),
     ]);
-    let fee = calculate_tx_fee(&resources, *GAS_PRICE, block_context).unwrap();
+    let fee = calculate_tx_fee(&resources, FeeType::Eth, block_context).unwrap();
     TransactionExecutionInfo::new(
         Some(expected_validate_call_info_2()),
         Some(expected_execute_call_info()),
@@ -1256,6 +1309,31 @@ fn expected_transaction_execution_info(block_context: &BlockContext) -> Transact
         resources,
         Some(TransactionType::InvokeFunction),
     )
+    .with_fee_breakdown(FeeBreakdown {
+        cairo_resources_l1_gas: DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS
+            .keys()
+            .map(|resource| (resource.clone(), 0.0))
+            .chain([
+                ("n_steps".to_string(), 4135.0 * 0.01),
+                ("pedersen_builtin".to_string(), 16.0 * 0.32),
+                ("range_check_builtin".to_string(), 101.0 * 0.16),
+            ])
+            .collect(),
+        bottleneck_resource: Some("n_steps".to_string()),
+        l1_gas_usage: L1GasUsage {
+            storage_diffs: 2448,
+            ..Default::default()
+        },
+        os_resources: ExecutionResources {
+            n_steps: 4053,
+            n_memory_holes: 0,
+            builtin_instance_counter: HashMap::from([
+                ("pedersen_builtin".to_string(), 16),
+                ("range_check_builtin".to_string(), 99),
+            ]),
+        },
+        total_l1_gas: 2490,
+    })
 }
 
 fn expected_fib_transaction_execution_info(
@@ -1276,7 +1354,7 @@ fn expected_fib_transaction_execution_info(
         ("pedersen_builtin".to_string(), 16),
         ("range_check_builtin".to_string(), 104),
     ]);
-    let fee = calculate_tx_fee(&resources, *GAS_PRICE, block_context).unwrap();
+    let fee = calculate_tx_fee(&resources, FeeType::Eth, block_context).unwrap();
     TransactionExecutionInfo::new(
         Some(expected_fib_validate_call_info_2()),
         Some(expected_fib_execute_call_info()),
@@ -1286,6 +1364,31 @@ fn expected_fib_transaction_execution_info(
         resources,
         Some(TransactionType::InvokeFunction),
     )
+    .with_fee_breakdown(FeeBreakdown {
+        cairo_resources_l1_gas: DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS
+            .keys()
+            .map(|resource| (resource.clone(), 0.0))
+            .chain([
+                ("n_steps".to_string(), n_steps as f64 * 0.01),
+                ("pedersen_builtin".to_string(), 16.0 * 0.32),
+                ("range_check_builtin".to_string(), 104.0 * 0.16),
+            ])
+            .collect(),
+        bottleneck_resource: Some("n_steps".to_string()),
+        l1_gas_usage: L1GasUsage {
+            storage_diffs: 4896,
+            ..Default::default()
+        },
+        os_resources: ExecutionResources {
+            n_steps: 4053,
+            n_memory_holes: 0,
+            builtin_instance_counter: HashMap::from([
+                ("pedersen_builtin".to_string(), 16),
+                ("range_check_builtin".to_string(), 99),
+            ]),
+        },
+        total_l1_gas: 4939,
+    })
 }
 
 #[test]
@@ -1528,7 +1631,7 @@ fn test_deploy_account() {
         ("l1_gas_usage".to_string(), 3672),
     ]);
 
-    let fee = calculate_tx_fee(&resources, *GAS_PRICE, &block_context).unwrap();
+    let fee = calculate_tx_fee(&resources, FeeType::Eth, &block_context).unwrap();
 
     assert_eq!(fee, expected_fee);
 
@@ -1542,7 +1645,33 @@ fn test_deploy_account() {
         // Default::default(),
         resources,
         TransactionType::DeployAccount.into(),
-    );
+    )
+    .with_fee_breakdown(FeeBreakdown {
+        cairo_resources_l1_gas: DEFAULT_CAIRO_RESOURCE_FEE_WEIGHTS
+            .keys()
+            .map(|resource| (resource.clone(), 0.0))
+            .chain([
+                ("n_steps".to_string(), 3625.0 * 0.01),
+                ("range_check_builtin".to_string(), 83.0 * 0.16),
+                ("pedersen_builtin".to_string(), 23.0 * 0.32),
+            ])
+            .collect(),
+        bottleneck_resource: Some("n_steps".to_string()),
+        l1_gas_usage: L1GasUsage {
+            storage_diffs: 2448,
+            contract_deployments: 1224,
+            ..Default::default()
+        },
+        os_resources: ExecutionResources {
+            n_steps: 3612,
+            n_memory_holes: 0,
+            builtin_instance_counter: HashMap::from([
+                ("pedersen_builtin".to_string(), 23),
+                ("range_check_builtin".to_string(), 83),
+            ]),
+        },
+        total_l1_gas: 3709,
+    });
     assert_eq!(tx_info, expected_execution_info);
 
     let nonce_from_state = state
@@ -1885,7 +2014,8 @@ fn test_state_for_declare_tx() {
                     INITIAL_BALANCE.clone() - &fee,
                 ),
             ]),
-            HashMap::new()
+            HashMap::new(),
+            HashSet::from([declare_tx.class_hash])
         ),
     );
 
@@ -1923,17 +2053,20 @@ fn test_invoke_tx_wrong_call_data() {
     let invoke_tx = invoke_tx(calldata);
 
     // Execute transaction
-    let result = invoke_tx.execute(state, starknet_general_context, 0);
+    let result = invoke_tx
+        .execute(state, starknet_general_context, 0)
+        .unwrap();
 
-    // Assert error
+    // The execution is reverted, but the nonce is bumped and the fee charged.
+    assert!(result.call_info.is_none());
     assert_matches!(
-        result,
-        Err(TransactionError::CairoRunner(CairoRunError::VmException(
-            VmException {
-                inner_exc: VirtualMachineError::DiffAssertValues(..),
-                ..
-            }
-        )))
+        result.revert_error,
+        Some(error) if error.contains("An ASSERT_EQ instruction failed")
+    );
+    assert!(result.fee_transfer_info.is_some());
+    assert_eq!(
+        state.get_nonce_at(&TEST_ACCOUNT_CONTRACT_ADDRESS).unwrap(),
+        Felt252::one()
     );
 }
 
@@ -1962,10 +2095,16 @@ fn test_invoke_tx_wrong_entrypoint() {
     .unwrap();
 
     // Execute transaction
-    let result = invoke_tx.execute(state, starknet_general_context, 0);
+    let result = invoke_tx
+        .execute(state, starknet_general_context, 0)
+        .unwrap();
 
-    // Assert error
-    assert_matches!(result, Err(TransactionError::EntryPointNotFound));
+    // The execution is reverted
+    assert!(result.call_info.is_none());
+    assert_eq!(
+        result.revert_error,
+        Some(TransactionError::EntryPointNotFound.to_string())
+    );
 }
 
 #[test]
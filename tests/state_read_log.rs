@@ -0,0 +1,72 @@
+use cairo_vm::felt::Felt252;
+use num_traits::Zero;
+use starknet_in_rust::{
+    definitions::{block_context::BlockContext, constants::TRANSACTION_VERSION},
+    execution::{
+        execution_entry_point::ExecutionEntryPoint, CallType, TransactionExecutionContext,
+    },
+    state::{
+        cached_state::CachedState, recording_state_reader::StateReadLog, ExecutionResourcesManager,
+    },
+    utils::{calculate_sn_keccak, Address},
+    EntryPointType,
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// Replays a call to `getPublicKey` on an account, from the state it read when it was recorded
+/// with a `RecordingStateReader`.
+#[test]
+fn replay_recorded_call_offline() {
+    let state_reader = StateReadLog::load("tests/fixtures/account_get_public_key_reads.json")
+        .unwrap()
+        .into_state_reader();
+    let mut state = CachedState::new(Arc::new(state_reader), HashMap::new());
+
+    let account_address = Address(0x100.into());
+    let entry_point = ExecutionEntryPoint::new(
+        account_address.clone(),
+        vec![],
+        Felt252::from_bytes_be(&calculate_sn_keccak(b"getPublicKey")),
+        Address(0.into()),
+        EntryPointType::External,
+        Some(CallType::Call),
+        None,
+        0,
+    );
+
+    let block_context = BlockContext::default();
+    let mut tx_execution_context = TransactionExecutionContext::new(
+        Address(0.into()),
+        Felt252::zero(),
+        Vec::new(),
+        0,
+        10.into(),
+        block_context.invoke_tx_max_n_steps(),
+        TRANSACTION_VERSION.clone(),
+    );
+
+    let call_info = entry_point
+        .execute(
+            &mut state,
+            &block_context,
+            &mut ExecutionResourcesManager::default(),
+            &mut tx_execution_context,
+            false,
+            block_context.invoke_tx_max_n_steps(),
+        )
+        .unwrap()
+        .call_info
+        .unwrap();
+
+    assert_eq!(
+        call_info.retdata,
+        vec![Felt252::new(0x1f2e3d4c5b6a7988_u64)]
+    );
+    assert_eq!(
+        call_info
+            .accessed_storage_keys
+            .into_iter()
+            .collect::<Vec<_>>(),
+        vec![calculate_sn_keccak(b"Account_public_key")]
+    );
+}